@@ -7,7 +7,7 @@ import type { ScanResult, ScanProgress } from "../types";
 vi.mock("../api", () => ({
   debugLog: vi.fn(),
   findFiles: vi.fn(() =>
-    Promise.resolve({ items: [], nextOffset: null })
+    Promise.resolve({ items: [], nextCursor: null })
   ),
 }));
 
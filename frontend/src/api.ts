@@ -1,6 +1,29 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import type { ScanDirectoryResponse, ScanProgress, FileSearchResult, FolderSizesReady } from "./types";
+import type {
+  ScanDirectoryResponse,
+  ScanCancelled,
+  ScanError,
+  Finding,
+  OwnerKind,
+  OwnerUsage,
+  ScanProgress,
+  FileSearchResult,
+  FolderSizesReady,
+  DuplicateReport,
+  DuplicateFolderReport,
+  DedupeMethod,
+  DedupeReport,
+  ScanInfo,
+  DiffReport,
+  DeltaTreeNode,
+  SizeSeries,
+  SizeMetric,
+  DeviceUsage,
+  ExclusionRule,
+  ScannerName,
+  SymlinkPolicy,
+} from "./types";
 import type { DiskTreeNode } from "./utils/diskTree";
 
 export const scanDirectoryWithHelper = (): Promise<ScanDirectoryResponse> =>
@@ -9,12 +32,14 @@ export const scanDirectoryWithHelper = (): Promise<ScanDirectoryResponse> =>
 export const buildDiskTreeCached = (
   startPath: string,
   maxChildrenPerNode: number,
-  maxDepth: number
+  maxDepth: number,
+  sizeMetric?: SizeMetric
 ): Promise<DiskTreeNode | null> =>
   invoke("build_disk_tree_cached", {
     startPath,
     maxChildrenPerNode,
     maxDepth,
+    sizeMetric: sizeMetric ?? null,
   });
 
 export const debugLog = (message: string): void => {
@@ -28,16 +53,44 @@ export const debugLogStats = (message: string): void => {
   invoke("debug_log_stats", { message }).catch(() => {});
 };
 
-export const scanDirectory = (): Promise<ScanDirectoryResponse> => {
+export const scanDirectory = (
+  oneFileSystem?: boolean,
+  scanner?: ScannerName,
+  resumable?: boolean,
+  incremental?: boolean,
+  symlinks?: SymlinkPolicy,
+): Promise<ScanDirectoryResponse> => {
   const stack = new Error().stack ?? "(no stack)";
   console.error(`[scanDirectory] invoke called.\n${stack}`);
   debugLog(`scanDirectory invoked stack=${stack.split("\n").slice(0, 5).join(" | ")}`);
-  return invoke("scan_directory", {});
+  return invoke("scan_directory", {
+    oneFileSystem: oneFileSystem ?? null,
+    scanner: scanner ?? null,
+    resumable: resumable ?? null,
+    incremental: incremental ?? null,
+    symlinks: symlinks ?? null,
+  });
 };
 
 export const getScanStatus = (): Promise<boolean> =>
   invoke("get_scan_status", {});
 
+export const cancelScan = (keepPartial?: boolean): Promise<boolean> =>
+  invoke("cancel_scan", { keepPartial: keepPartial ?? null });
+
+export const pauseScan = (): Promise<boolean> =>
+  invoke("pause_scan", {});
+
+export const resumeScan = (): Promise<boolean> =>
+  invoke("resume_scan", {});
+
+export const onScanCancelled = (callback: (event: ScanCancelled) => void) => {
+  const unlisten = listen<ScanCancelled>("scan-cancelled", (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+};
+
 export const onScanComplete = (callback: (response: ScanDirectoryResponse) => void) => {
   const unlisten = listen<ScanDirectoryResponse>("scan-complete", (event) => {
     callback(event.payload);
@@ -85,22 +138,144 @@ export const listCachedTreeDepths = (
 
 export type FindFilesResponse = {
   items: FileSearchResult[];
-  nextOffset: number | null;
+  /** Pass back to `findFiles` for the next page; absent on the last one. */
+  nextCursor?: string | null;
 };
 
+/** How the search text is matched: names containing it, names ranked by fuzzy match, full paths containing it, or names matching it as a glob or regex. */
+export type SearchMode = "exact" | "fuzzy" | "path" | "glob" | "regex";
+
+/** What results are ordered by: match quality (by name in exact, glob and regex mode), name, size or modification time. */
+export type SortKey = "relevance" | "name" | "size" | "modified";
+
+/** Omitted: largest and newest first for size and time, from the start otherwise. */
+export type SortDirection = "asc" | "desc";
+
 export const findFiles = (
   query: string,
   extensions: string,
   category: string,
-  useFuzzy: boolean,
+  mode: SearchMode,
+  sort: SortKey,
+  direction?: SortDirection,
   limit?: number,
-  offset?: number
+  cursor?: string | null
 ): Promise<FindFilesResponse> =>
   invoke("find_files", {
     query,
     extensions: extensions.trim().length > 0 ? extensions.trim() : null,
     category: category.trim() !== "" && category !== "all" ? category : null,
     limit: limit ?? 500,
-    useFuzzy,
-    offset: offset ?? 0,
+    mode,
+    sort,
+    direction: direction ?? null,
+    cursor: cursor ?? null,
   });
+
+export const findDuplicates = (minSize?: number): Promise<DuplicateReport> =>
+  invoke("find_duplicates", { minSize: minSize ?? null });
+
+export const findSimilarImages = (maxDistance?: number): Promise<DuplicateReport> =>
+  invoke("find_similar_images", { maxDistance: maxDistance ?? null });
+
+export const findDuplicateFolders = (minSize?: number): Promise<DuplicateFolderReport> =>
+  invoke("find_duplicate_folders", { minSize: minSize ?? null });
+
+export const dedupeFiles = (
+  keep: string,
+  copies: string[],
+  method: DedupeMethod,
+  dryRun: boolean
+): Promise<DedupeReport> =>
+  invoke("dedupe_files", { keep, copies, method, dryRun });
+
+export const listScans = (): Promise<ScanInfo[]> => invoke("list_scans");
+
+export const deleteScan = (scanId: number): Promise<boolean> =>
+  invoke("delete_scan", { scanId });
+
+export const buildSnapshotTree = (
+  scanId: number,
+  startPath: string,
+  maxChildrenPerNode: number,
+  maxDepth: number
+): Promise<DiskTreeNode | null> =>
+  invoke("build_snapshot_tree", { scanId, startPath, maxChildrenPerNode, maxDepth });
+
+export const diffScans = (
+  oldScanId: number,
+  newScanId?: number,
+  options?: {
+    minFileSize?: number;
+    limit?: number;
+    treeMaxChildren?: number;
+    treeMaxDepth?: number;
+  }
+): Promise<DiffReport> =>
+  invoke("diff_scans", {
+    oldScanId,
+    newScanId: newScanId ?? null,
+    minFileSize: options?.minFileSize ?? null,
+    limit: options?.limit ?? null,
+    treeMaxChildren: options?.treeMaxChildren ?? null,
+    treeMaxDepth: options?.treeMaxDepth ?? null,
+  });
+
+export const buildDeltaTree = (
+  oldScanId: number,
+  newScanId: number | undefined,
+  startPath: string,
+  maxChildrenPerNode: number,
+  maxDepth: number
+): Promise<DeltaTreeNode | null> =>
+  invoke("build_delta_tree", {
+    oldScanId,
+    newScanId: newScanId ?? null,
+    startPath,
+    maxChildrenPerNode,
+    maxDepth,
+  });
+
+export const getSizeSeries = (path: string, capacityBytes?: number): Promise<SizeSeries> =>
+  invoke("get_size_series", { path, capacityBytes: capacityBytes ?? null });
+
+export const getScanErrors = (limit?: number): Promise<ScanError[]> =>
+  invoke("get_scan_errors", { limit: limit ?? null });
+
+export const getFindings = (): Promise<Finding[]> =>
+  invoke("get_findings", {});
+
+export const getOwnerUsage = (
+  ownerKind?: OwnerKind,
+  sizeMetric?: SizeMetric,
+): Promise<OwnerUsage[]> =>
+  invoke("get_owner_usage", { ownerKind: ownerKind ?? null, sizeMetric: sizeMetric ?? null });
+
+export const getOwnerFolders = (
+  id: number,
+  ownerKind?: OwnerKind,
+  limit?: number,
+  sizeMetric?: SizeMetric,
+): Promise<[string, number][]> =>
+  invoke("get_owner_folders", {
+    id,
+    ownerKind: ownerKind ?? null,
+    limit: limit ?? null,
+    sizeMetric: sizeMetric ?? null,
+  });
+
+export const getDeviceUsage = (): Promise<DeviceUsage[]> =>
+  invoke("get_device_usage", {});
+
+export const getExclusionRules = (): Promise<ExclusionRule[]> =>
+  invoke("get_exclusion_rules", {});
+
+export const setExclusionRules = (rules: ExclusionRule[]): Promise<void> =>
+  invoke("set_exclusion_rules", { rules });
+
+export const onDuplicatesProgress = (callback: (progress: ScanProgress) => void) => {
+  const unlisten = listen<ScanProgress>("duplicates-progress", (event) => {
+    callback(event.payload);
+  });
+  return unlisten;
+};
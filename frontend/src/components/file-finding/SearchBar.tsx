@@ -1,11 +1,29 @@
 import { Search, Sparkles } from "lucide-react";
 import { motion } from "framer-motion";
+import type { SearchMode, SortKey } from "../../api";
+
+const SEARCH_MODES: { mode: SearchMode; icon: string; label: string; title: string }[] = [
+  { mode: "exact", icon: "🔍", label: "Exact", title: "Names containing the text" },
+  { mode: "fuzzy", icon: "✨", label: "Fuzzy", title: "Names ranked by how closely they match" },
+  { mode: "path", icon: "📁", label: "Path", title: "Full paths containing the text, e.g. photos/2019" },
+  { mode: "glob", icon: "✳️", label: "Glob", title: "Names matching a glob, e.g. *.tar.gz" },
+  { mode: "regex", icon: "🧩", label: "Regex", title: "Names containing a regex match, e.g. IMG_\\d{4}\\.jpg" },
+];
+
+const SORT_KEYS: { sort: SortKey; label: string }[] = [
+  { sort: "relevance", label: "Best match" },
+  { sort: "name", label: "Name" },
+  { sort: "size", label: "Largest" },
+  { sort: "modified", label: "Newest" },
+];
 
 type SearchBarProps = {
   query: string;
   onQueryChange: (q: string) => void;
-  useFuzzySearch: boolean;
-  onFuzzySearchChange: (v: boolean) => void;
+  searchMode: SearchMode;
+  onSearchModeChange: (mode: SearchMode) => void;
+  sort: SortKey;
+  onSortChange: (sort: SortKey) => void;
   extensionFilter: string;
   onExtensionFilterChange: (ext: string) => void;
   disabled?: boolean;
@@ -14,8 +32,10 @@ type SearchBarProps = {
 const SearchBar = ({
   query,
   onQueryChange,
-  useFuzzySearch,
-  onFuzzySearchChange,
+  searchMode,
+  onSearchModeChange,
+  sort,
+  onSortChange,
   extensionFilter,
   onExtensionFilterChange,
   disabled = false,
@@ -34,6 +54,7 @@ const SearchBar = ({
           value={query}
           onChange={(e) => onQueryChange(e.target.value)}
           placeholder="Search your cozy file collection..."
+          title="Filters: size:>2GB, ext:mp4,mkv, category:video, modified:>1y, path:~/Downloads, kind:folder; -term negates, a OR b matches either"
           disabled={disabled}
           autoFocus
           className="flex-1 min-w-0 bg-transparent border-none outline-none text-foreground placeholder:text-muted-foreground py-3 font-body text-base disabled:opacity-60"
@@ -46,35 +67,42 @@ const SearchBar = ({
       </div>
 
       <div className="flex items-center gap-1 pr-1 shrink-0">
-        <button
-          type="button"
-          onClick={() => onFuzzySearchChange(false)}
-          disabled={disabled}
-          className={`px-3 py-2 rounded-xl text-sm font-medium transition-all duration-300 disabled:opacity-60 cursor-pointer ${
-            !useFuzzySearch
-              ? "bg-primary text-primary-foreground shadow-cozy"
-              : "text-muted-foreground hover:bg-muted hover:text-foreground"
-          }`}
-        >
-          <span className="mr-1">🔍</span>
-          Exact
-        </button>
-        <button
-          type="button"
-          onClick={() => onFuzzySearchChange(true)}
-          disabled={disabled}
-          className={`px-3 py-2 rounded-xl text-sm font-medium transition-all duration-300 disabled:opacity-60 cursor-pointer ${
-            useFuzzySearch
-              ? "bg-primary text-primary-foreground shadow-cozy"
-              : "text-muted-foreground hover:bg-muted hover:text-foreground"
-          }`}
-        >
-          <span className="mr-1">✨</span>
-          Fuzzy
-        </button>
+        {SEARCH_MODES.map(({ mode, icon, label, title }) => (
+          <button
+            key={mode}
+            type="button"
+            onClick={() => onSearchModeChange(mode)}
+            disabled={disabled}
+            title={title}
+            className={`px-3 py-2 rounded-xl text-sm font-medium transition-all duration-300 disabled:opacity-60 cursor-pointer ${
+              searchMode === mode
+                ? "bg-primary text-primary-foreground shadow-cozy"
+                : "text-muted-foreground hover:bg-muted hover:text-foreground"
+            }`}
+          >
+            <span className="mr-1">{icon}</span>
+            {label}
+          </button>
+        ))}
       </div>
     </div>
 
+    <div className="glass rounded-2xl px-4 flex items-center gap-2 shrink-0">
+      <span className="text-sm text-muted-foreground">sort:</span>
+      <select
+        value={sort}
+        onChange={(e) => onSortChange(e.target.value as SortKey)}
+        disabled={disabled}
+        className="bg-transparent border-none outline-none text-foreground text-sm cursor-pointer disabled:opacity-60"
+      >
+        {SORT_KEYS.map(({ sort: key, label }) => (
+          <option key={key} value={key}>
+            {label}
+          </option>
+        ))}
+      </select>
+    </div>
+
     <div className="glass rounded-2xl px-4 flex items-center gap-2 shrink-0">
       <span className="text-sm text-muted-foreground">ext:</span>
       <input
@@ -7,7 +7,7 @@ export type FileEntry = {
   mtime?: number;
 };
 
-export type SearchItemKind = "file" | "folder";
+export type SearchItemKind = "file" | "folder" | "symlink" | "collapsed";
 
 export type ScanProgress = {
   files_count: number;
@@ -21,12 +21,44 @@ export type ScanResult = {
   folder_sizes: Record<string, number>;
   files_count?: number;
   folders_count?: number;
+  errors_count?: number;
 };
 
 export type ScanDirectoryResponse = {
   roots: string[];
   files_count: number;
   folders_count: number;
+  errors_count: number;
+};
+
+export type ScanErrorKind = "permission_denied" | "not_found" | "loop" | "other";
+
+export type ScanError = {
+  path: string;
+  kind: ScanErrorKind;
+  errno: number | null;
+  message: string;
+};
+
+export type FindingKind = "broken_symlink" | "setuid" | "setgid" | "world_writable";
+
+export type OwnerKind = "user" | "group";
+
+export type OwnerUsage = {
+  id: number;
+  name: string | null;
+  files_count: number;
+  size: number;
+};
+
+export type Finding = {
+  kind: FindingKind;
+  path: string;
+  detail: string;
+};
+
+export type ScanCancelled = {
+  partialScanId: number | null;
 };
 
 export type FolderSizesReady = {
@@ -37,6 +69,150 @@ export type FileSearchResult = {
   kind: SearchItemKind;
   path: string;
   size: number;
+  allocated_size?: number;
   file_key?: FileKey;
 };
 
+export type SizeMetric = "apparent" | "allocated";
+
+
+export type DuplicateKind = "exact" | "similar";
+
+export type DuplicateGroup = {
+  kind: DuplicateKind;
+  hash: string;
+  size: number;
+  files: FileEntry[];
+  wasted_bytes: number;
+};
+
+export type DuplicateReport = {
+  groups: DuplicateGroup[];
+  total_wasted_bytes: number;
+  files_hashed: number;
+  bytes_hashed: number;
+  cache_hits: number;
+};
+
+export type DuplicateFolderGroup = {
+  fingerprint: string;
+  size: number;
+  file_count: number;
+  folders: string[];
+  wasted_bytes: number;
+};
+
+export type DuplicateFolderReport = {
+  groups: DuplicateFolderGroup[];
+  total_wasted_bytes: number;
+  files_hashed: number;
+  bytes_hashed: number;
+  cache_hits: number;
+};
+
+export type DedupeMethod = "hardlink" | "reflink";
+
+export type DedupedFile = {
+  path: string;
+  file_key: FileKey;
+  mtime?: number;
+  bytes_saved: number;
+};
+
+export type DedupeReport = {
+  method: DedupeMethod;
+  dry_run: boolean;
+  kept: string;
+  replaced: DedupedFile[];
+  skipped: { path: string; reason: string }[];
+  bytes_saved: number;
+};
+
+export type ScanInfo = {
+  id: number;
+  update_id: number;
+  created_at: number;
+  roots: string[];
+  files_count: number;
+  folders_count: number;
+  total_size: number;
+  complete: boolean;
+};
+
+export type ChangeKind = "added" | "removed" | "grown" | "shrunk" | "unchanged";
+
+export type PathDelta = {
+  path: string;
+  kind: "File" | "Folder";
+  change: ChangeKind;
+  old_size: number;
+  new_size: number;
+  delta: number;
+};
+
+export type DeltaTreeNode = {
+  path: string;
+  name: string;
+  change: ChangeKind;
+  old_size: number;
+  new_size: number;
+  delta: number;
+  children?: DeltaTreeNode[];
+};
+
+export type DiffReport = {
+  old_total: number;
+  new_total: number;
+  total_delta: number;
+  folders: PathDelta[];
+  top_growth: PathDelta[];
+  top_shrinkage: PathDelta[];
+  new_files_count: number;
+  new_files_bytes: number;
+  new_files: PathDelta[];
+  removed_files_count: number;
+  removed_files_bytes: number;
+  removed_files: PathDelta[];
+  tree: DeltaTreeNode[];
+};
+
+export type SizePoint = {
+  timestamp: number;
+  size: number;
+};
+
+export type SizeTrend = {
+  bytes_per_day: number;
+  base_timestamp: number;
+  base_size: number;
+};
+
+export type SizeSeries = {
+  path: string;
+  points: SizePoint[];
+  trend: SizeTrend | null;
+  full_at: number | null;
+};
+
+export type DeviceUsage = {
+  device: number;
+  root: string;
+  mount_point: string | null;
+  fs_type: string | null;
+  source: string | null;
+  files_count: number;
+  size: number;
+  allocated_size: number;
+};
+
+export type ScannerName = "ignore" | "jwalk" | "walkdir" | "lolcate";
+
+export type SymlinkPolicy = "skip" | "record" | "follow";
+
+export type ExclusionRule = {
+  pattern: string;
+  kind: "glob" | "prefix";
+  action: "exclude" | "collapse";
+  min_size?: number;
+  older_than_days?: number;
+};
@@ -1,6 +1,6 @@
 import { useEffect, useMemo, useRef, useState } from "react";
 import { motion } from "framer-motion";
-import { debugLog, findFiles } from "../api";
+import { debugLog, findFiles, type SearchMode, type SortKey } from "../api";
 import { Button } from "../components/ui/button";
 import { IndexingControls } from "../components/file-finding/IndexingControls";
 import SearchBar from "../components/file-finding/SearchBar";
@@ -36,9 +36,10 @@ export const FileFindingView = ({ result, loading, error, progress, scanPhaseSta
   const [searchQuery, setSearchQuery] = useState("");
   const [searchExtensions, setSearchExtensions] = useState("");
   const [searchCategory, setSearchCategory] = useState<FileCategory>("all");
-  const [useFuzzySearch, setUseFuzzySearch] = useState(false);
+  const [searchMode, setSearchMode] = useState<SearchMode>("exact");
+  const [searchSort, setSearchSort] = useState<SortKey>("relevance");
   const [searchResults, setSearchResults] = useState<FileSearchResult[]>([]);
-  const [searchNextOffset, setSearchNextOffset] = useState<number | null>(null);
+  const [searchNextCursor, setSearchNextCursor] = useState<string | null>(null);
   const [searchLoading, setSearchLoading] = useState(false);
   const [searchLoadingMore, setSearchLoadingMore] = useState(false);
   const [searchError, setSearchError] = useState<string | null>(null);
@@ -46,7 +47,8 @@ export const FileFindingView = ({ result, loading, error, progress, scanPhaseSta
     query: string;
     extensions: string;
     category: FileCategory;
-    fuzzy: boolean;
+    mode: SearchMode;
+    sort: SortKey;
   } | null>(null);
   const searchGenRef = useRef(0);
 
@@ -89,34 +91,36 @@ export const FileFindingView = ({ result, loading, error, progress, scanPhaseSta
         query: searchQuery,
         extensions,
         category: searchCategory,
-        fuzzy: useFuzzySearch,
+        mode: searchMode,
+        sort: searchSort,
       };
       if (
         lastQueryRef.current &&
         lastQueryRef.current.query === queryKey.query &&
         lastQueryRef.current.extensions === queryKey.extensions &&
         lastQueryRef.current.category === queryKey.category &&
-        lastQueryRef.current.fuzzy === queryKey.fuzzy
+        lastQueryRef.current.mode === queryKey.mode &&
+        lastQueryRef.current.sort === queryKey.sort
       ) {
         return;
       }
       const gen = ++searchGenRef.current;
       setSearchLoading(true);
       setSearchError(null);
-      findFiles(searchQuery, extensions, searchCategory, useFuzzySearch, PAGE_SIZE, 0)
+      findFiles(searchQuery, extensions, searchCategory, searchMode, searchSort, undefined, PAGE_SIZE)
         .then((response) => {
           if (gen !== searchGenRef.current) return;
           lastQueryRef.current = queryKey;
           setSearchResults(response.items);
-          setSearchNextOffset(response.nextOffset);
+          setSearchNextCursor(response.nextCursor ?? null);
         })
         .catch((e) => { if (gen === searchGenRef.current) setSearchError(e instanceof Error ? e.message : String(e)); })
         .finally(() => { if (gen === searchGenRef.current) setSearchLoading(false); });
     }, 16);
     return () => window.clearTimeout(timeoutId);
-  }, [activeTab, canSearch, searchQuery, searchExtensions, searchCategory, useFuzzySearch]);
+  }, [activeTab, canSearch, searchQuery, searchExtensions, searchCategory, searchMode, searchSort]);
 
-  const canLoadMore = searchNextOffset != null;
+  const canLoadMore = searchNextCursor != null;
 
   const handleLoadMore = async () => {
     if (!canSearch || result === null || !canLoadMore || searchLoadingMore) return;
@@ -126,12 +130,14 @@ export const FileFindingView = ({ result, loading, error, progress, scanPhaseSta
         searchQuery,
         getEffectiveExtensions(),
         searchCategory,
-        useFuzzySearch,
+        searchMode,
+        searchSort,
+        undefined,
         PAGE_SIZE,
-        searchNextOffset ?? 0
+        searchNextCursor
       );
       setSearchResults((prev) => [...prev, ...response.items]);
-      setSearchNextOffset(response.nextOffset);
+      setSearchNextCursor(response.nextCursor ?? null);
     } catch (e) {
       setSearchError(e instanceof Error ? e.message : String(e));
     } finally {
@@ -223,8 +229,10 @@ export const FileFindingView = ({ result, loading, error, progress, scanPhaseSta
                 <SearchBar
                   query={searchQuery}
                   onQueryChange={setSearchQuery}
-                  useFuzzySearch={useFuzzySearch}
-                  onFuzzySearchChange={setUseFuzzySearch}
+                  searchMode={searchMode}
+                  onSearchModeChange={setSearchMode}
+                  sort={searchSort}
+                  onSortChange={setSearchSort}
                   extensionFilter={searchExtensions}
                   onExtensionFilterChange={setSearchExtensions}
                   disabled={!canSearch}
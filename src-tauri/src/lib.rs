@@ -1,18 +1,40 @@
-use cutest_disk_tree::{db, DiskObject, DiskObjectKind};
+use cutest_disk_tree::{db, DiskObject, DiskObjectKind, SymlinkEntry};
 use cutest_disk_tree::core::indexing::compressed_text_index::{
-    build_index as cti_build_index, find_files as cti_find_files,
-    compressed_text_index_exists, write_scan_metadata, read_scan_metadata,
+    build_index as cti_build_index, CompressedTextBackend, compressed_text_index_exists, write_scan_metadata, read_scan_metadata,
     read_scan_result_from_compressed_text_index,
 };
 use cutest_disk_tree::core::indexing::ngram::{
-    build_index as trigram_build_index, find_files as trigram_find_files, TrigramIndex,
+    build_index as trigram_build_index, TrigramIndex,
 };
+use cutest_disk_tree::core::duplicates::{
+    find_duplicates_with_cache, DuplicateOptions, DuplicateReport,
+};
+use cutest_disk_tree::core::duplicates::dedupe::{
+    self, DedupeMethod, DedupeOptions, DedupeReport,
+};
+use cutest_disk_tree::core::duplicates::similar_images::{
+    find_similar_images_with_progress, SimilarImageOptions,
+};
+use cutest_disk_tree::core::duplicates::folders::{
+    find_duplicate_folders_with_cache, DuplicateFolderReport,
+};
+use cutest_disk_tree::core::diff::{self as scan_diff, DeltaTreeNode, DiffOptions, DiffReport};
 use cutest_disk_tree::core::file_updating::{IndexWatcher, IndexReconciler};
+use cutest_disk_tree::core::trend::{select_tracked_folders, size_series, SizeSeries, TrackingOptions};
+use cutest_disk_tree::core::scanning::mounts::{list_mounts, usage_by_device, DeviceUsage};
+use cutest_disk_tree::core::scanning::checkpoint::{scan_resumable, DoneFolder};
+use cutest_disk_tree::core::scanning::exclusions::{is_collapsed_entry, ExclusionRule, ExclusionRules};
+use cutest_disk_tree::core::findings::Finding;
+use cutest_disk_tree::core::owners::{OwnerKind, OwnerUsage};
+use cutest_disk_tree::core::scanning::{scanner_by_name, ScanCollector, ScanControl, ScanError, ScanOptions, SymlinkPolicy, SCANNER_NAMES};
 use cutest_disk_tree::core::indexing::suffix::{
-    SuffixIndex, build_index as suffix_build_index, find_files as suffix_find_files,
+    SuffixIndex, SuffixSearch, build_index as suffix_build_index,
+};
+use cutest_disk_tree::core::indexing::sqlite::SqliteBackend;
+use cutest_disk_tree::core::search::{
+    Cursor, SearchBackend, SearchMode, SearchRequest, SearchResults, SortDirection, SortKey, SortOrder,
+    DEFAULT_LIMIT as DEFAULT_SEARCH_LIMIT,
 };
-use cutest_disk_tree::core::indexing::sqlite::{find_files as sqlite_find_files, SearchFilter};
-use cutest_disk_tree::core::search_category;
 use std::collections::{HashMap, HashSet};
 use suffix::SuffixTable;
 use std::sync::{Arc, Mutex};
@@ -21,52 +43,10 @@ use std::process;
 use std::time::Instant;
 use tauri::Manager;
 use tauri::Emitter;
-use nucleo::{Matcher, Config};
-use nucleo::pattern::{Pattern, CaseMatching, Normalization};
 use serde::Serialize;
 use std::io::Write;
 use sysinfo::{Pid, System};
 
-fn category_allowed(category: Option<&str>, obj: &DiskObject) -> bool {
-    let category = match category {
-        None => return true,
-        Some("all") | Some("") => return true,
-        Some(c) => c.trim(),
-    };
-    if category.is_empty() {
-        return true;
-    }
-    match category {
-        "folder" => matches!(obj.kind, DiskObjectKind::Folder),
-        "other" => {
-            if matches!(obj.kind, DiskObjectKind::Folder) {
-                return false;
-            }
-            let known = search_category::all_known_extensions();
-            match &obj.ext {
-                None => true,
-                Some(ext) => {
-                    let ext_lower = ext.trim().to_lowercase();
-                    !known.iter().any(|&e| e == ext_lower.as_str())
-                }
-            }
-        }
-        _ => {
-            if matches!(obj.kind, DiskObjectKind::Folder) {
-                return false;
-            }
-            let set = match search_category::extension_set(category) {
-                Some(s) => s,
-                None => return true,
-            };
-            obj.ext.as_ref().map(|e| {
-                let ext_lower = e.trim().to_lowercase();
-                set.iter().any(|&s| s == ext_lower.as_str())
-            }).unwrap_or(false)
-        }
-    }
-}
-
 #[derive(Clone, Serialize)]
 struct FolderSizesReady {
     folder_sizes: HashMap<String, u64>,
@@ -76,6 +56,8 @@ struct FolderSizesReady {
 struct SearchEntry {
     path: String,
     size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allocated_size: Option<u64>,
     kind: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     file_key: Option<cutest_disk_tree::FileKey>,
@@ -86,6 +68,26 @@ struct ScanDirectoryResponse {
     roots: Vec<String>,
     files_count: u64,
     folders_count: u64,
+    /// Entries the walk could not read; listed by `get_scan_errors`.
+    errors_count: u64,
+}
+
+/// What the walk of `scan_directory` found.
+struct WalkResult {
+    files: Arc<Vec<cutest_disk_tree::FileEntry>>,
+    folders: HashSet<std::path::PathBuf>,
+    roots: Vec<String>,
+    /// Folders an incremental scan read, for `db::write_incremental_scan`.
+    changed_folders: Option<Vec<DoneFolder>>,
+    errors: Vec<ScanError>,
+    symlinks: Vec<SymlinkEntry>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanCancelledEvent {
+    /// Id of the incomplete snapshot the partial result was saved as, if it was kept.
+    partial_scan_id: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -93,71 +95,30 @@ struct ScanDirectoryResponse {
 struct FindFilesResponse {
     items: Vec<SearchEntry>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    next_offset: Option<usize>,
+    next_cursor: Option<String>,
 }
 
 fn search_entry_from_disk_object(o: &DiskObject) -> SearchEntry {
     SearchEntry {
         path: o.path.clone(),
         size: o.size.or(o.recursive_size).unwrap_or(0),
+        allocated_size: o.allocated_size,
         kind: match o.kind {
             DiskObjectKind::File => "file".to_string(),
             DiskObjectKind::Folder => "folder".to_string(),
+            DiskObjectKind::Symlink => "symlink".to_string(),
+            DiskObjectKind::Collapsed => "collapsed".to_string(),
         },
         file_key: match o.kind {
             DiskObjectKind::File => Some(cutest_disk_tree::FileKey {
                 dev: o.dev.unwrap_or(0),
                 ino: o.ino.unwrap_or(0),
             }),
-            DiskObjectKind::Folder => None,
+            DiskObjectKind::Folder | DiskObjectKind::Symlink | DiskObjectKind::Collapsed => None,
         },
     }
 }
 
-fn paginate_scan<F>(
-    disk_entries: &[DiskObject],
-    start_index: usize,
-    limit: usize,
-    use_mask: bool,
-    allowed_indices: &[bool],
-    mut predicate: F,
-) -> (Vec<SearchEntry>, Option<usize>)
-where
-    F: FnMut(usize, &DiskObject) -> bool,
-{
-    if start_index >= disk_entries.len() || limit == 0 {
-        return (Vec::new(), None);
-    }
-
-    let mut items: Vec<SearchEntry> = Vec::new();
-    let mut produced = 0usize;
-    let mut last_index = None;
-
-    for (i, e) in disk_entries.iter().enumerate().skip(start_index) {
-        if use_mask && !allowed_indices[i] {
-            last_index = Some(i);
-            continue;
-        }
-        if !predicate(i, e) {
-            last_index = Some(i);
-            continue;
-        }
-        items.push(search_entry_from_disk_object(e));
-        produced += 1;
-        last_index = Some(i);
-        if produced == limit {
-            break;
-        }
-    }
-
-    let next_offset = match last_index {
-        Some(i) if produced == limit && i + 1 < disk_entries.len() => Some(i + 1),
-        _ => None,
-    };
-
-    (items, next_offset)
-}
-
 #[cfg(test)]
 mod tests;
 #[cfg(test)]
@@ -193,6 +154,10 @@ struct AppState {
     trigram_index: Arc<Mutex<TrigramIndex>>,
     phase2_cancel: Mutex<Arc<AtomicBool>>,
     is_scanning: Arc<AtomicBool>,
+    /// Pauses or stops the walk of the running scan; replaced when a scan starts.
+    scan_control: Mutex<ScanControl>,
+    /// Whether a cancelled scan is kept as an incomplete snapshot; set by `cancel_scan`.
+    keep_partial_scan: AtomicBool,
     scan_path_override: Option<String>,
     index_mode: SearchIndexMode,
     _watcher: Mutex<Option<IndexWatcher>>,
@@ -237,6 +202,9 @@ fn write_startup_config_log(state: &AppState, index_mode: SearchIndexMode) {
             if let Ok(p) = std::env::var("CUTE_DISK_TREE_SCAN_PATH") {
                 writeln!(f, "CUTE_DISK_TREE_SCAN_PATH={}", p)?;
             }
+            if let Ok(s) = std::env::var("CUTE_DISK_TREE_SCANNER") {
+                writeln!(f, "CUTE_DISK_TREE_SCANNER={}", s)?;
+            }
             writeln!(f)?;
             f.flush()
         });
@@ -245,11 +213,13 @@ fn write_startup_config_log(state: &AppState, index_mode: SearchIndexMode) {
 fn start_file_watchers(state: &AppState, roots: Vec<std::path::PathBuf>) {
     let index = Arc::clone(&state.trigram_index);
     let scan_flag = Arc::clone(&state.is_scanning);
-    match IndexWatcher::new(Arc::clone(&index), roots.clone()) {
+    let rules = Arc::new(load_exclusion_rules(state));
+    match IndexWatcher::with_rules(Arc::clone(&index), roots.clone(), Arc::clone(&rules)) {
         Ok(w) => { *state._watcher.lock().unwrap_or_else(|e| e.into_inner()) = Some(w); }
         Err(e) => { write_debug_log(state, &format!("start_file_watchers: watcher error: {:?}", e)); }
     }
-    *state._reconciler.lock().unwrap_or_else(|e| e.into_inner()) = Some(IndexReconciler::new(index, roots, scan_flag));
+    *state._reconciler.lock().unwrap_or_else(|e| e.into_inner()) =
+        Some(IndexReconciler::with_rules(index, roots, scan_flag, rules));
     write_debug_log(state, "start_file_watchers: watcher and reconciler started");
 }
 
@@ -314,7 +284,7 @@ fn make_disk_object_from_path(
             .extension()
             .and_then(|os| os.to_str())
             .map(|s| s.to_ascii_lowercase()),
-        DiskObjectKind::Folder => None,
+        DiskObjectKind::Folder | DiskObjectKind::Symlink | DiskObjectKind::Collapsed => None,
     };
     DiskObject {
         path: path_string,
@@ -326,9 +296,14 @@ fn make_disk_object_from_path(
         kind,
         size,
         recursive_size,
+        allocated_size: None,
         dev,
         ino,
         mtime,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     }
 }
 
@@ -337,8 +312,9 @@ fn build_disk_objects(
     folder_paths: &std::collections::HashSet<std::path::PathBuf>,
 ) -> Vec<DiskObject> {
     let mut objs: Vec<DiskObject> = Vec::with_capacity(files.len() + folder_paths.len());
-    for f in files {
-        objs.push(make_disk_object_from_path(
+    // Collapsed-folder summaries have nothing on disk to find.
+    for f in files.iter().filter(|f| !is_collapsed_entry(&f.path)) {
+        let mut obj = make_disk_object_from_path(
             f.path.to_string_lossy().into_owned(),
             DiskObjectKind::File,
             Some(f.size),
@@ -346,7 +322,9 @@ fn build_disk_objects(
             Some(f.file_key.dev),
             Some(f.file_key.ino),
             f.mtime,
-        ));
+        );
+        obj.allocated_size = Some(f.allocated_size);
+        objs.push(obj);
     }
     for folder in folder_paths {
         objs.push(make_disk_object_from_path(
@@ -475,6 +453,7 @@ fn activate_initial_index(
     let _ = app.emit("scan-index-ready", true);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_phase2(
     app_bg: tauri::AppHandle,
     db_path_bg: std::path::PathBuf,
@@ -483,6 +462,8 @@ fn run_phase2(
     folder_paths: std::collections::HashSet<std::path::PathBuf>,
     cancel: Arc<AtomicBool>,
     mode: SearchIndexMode,
+    changed_folders: Option<Vec<DoneFolder>>,
+    symlinks: Vec<SymlinkEntry>,
 ) {
     let state_ptr: tauri::State<AppState> = app_bg.state();
     let total_start = Instant::now();
@@ -603,6 +584,8 @@ fn run_phase2(
     }
 
     let roots_str: Vec<String> = scan_roots.iter().map(|r| r.to_string_lossy().to_string()).collect();
+    // Shared by the database write, the snapshot and the size history of this scan.
+    let update_id = chrono::Utc::now().timestamp_millis();
 
     if mode == SearchIndexMode::CompressedText {
         let app_data_dir = db_path_bg.parent().unwrap_or(&db_path_bg);
@@ -668,13 +651,17 @@ fn run_phase2(
     } else if mode == SearchIndexMode::Sqlite {
         write_debug_log(&state_ptr, "phase2 opening database");
         let _ = app_bg.emit("scan-phase-status", "saving to database...".to_string());
-        let update_id = chrono::Utc::now().timestamp_millis();
         match db::open_db(&db_path_bg) {
             Ok(conn) => {
-                if let Err(e) = db::write_scan(&conn, &files_bg, &folder_sizes, update_id) {
+                let written = match &changed_folders {
+                    Some(changed) => db::write_incremental_scan(&conn, changed, &folder_paths, update_id),
+                    None => db::write_scan_with_symlinks(&conn, &files_bg, &symlinks, &folder_sizes, update_id),
+                };
+                if let Err(e) = written {
                     write_debug_log(&state_ptr, &format!("phase2 db_write failed error={:?}", e));
                 } else {
                     write_debug_log(&state_ptr, "phase2 db_write done");
+                    prune_snapshots(&state_ptr, &conn, update_id);
                 }
             }
             Err(e) => write_debug_log(&state_ptr, &format!("phase2 db_open failed error={:?}", e)),
@@ -682,11 +669,17 @@ fn run_phase2(
     } else {
         write_debug_log(&state_ptr, "phase2 opening database");
         let _ = app_bg.emit("scan-phase-status", "saving to database...".to_string());
-        let update_id = chrono::Utc::now().timestamp_millis();
         let db_start = Instant::now();
         match db::open_db(&db_path_bg) {
             Ok(conn) => {
-                if let Err(e) = db::write_scan(&conn, &files_bg, &folder_sizes, update_id) {
+                let written = match &changed_folders {
+                    Some(changed) => db::write_incremental_scan(&conn, changed, &folder_paths, update_id),
+                    None => db::write_scan_with_symlinks(&conn, &files_bg, &symlinks, &folder_sizes, update_id),
+                };
+                if written.is_ok() {
+                    prune_snapshots(&state_ptr, &conn, update_id);
+                }
+                if let Err(e) = written {
                     write_debug_log(&state_ptr, &format!(
                         "phase2 db_write failed error={:?} ms={}", e, db_start.elapsed().as_millis()
                     ));
@@ -713,9 +706,35 @@ fn run_phase2(
         }
     }
 
+    record_size_history(&state_ptr, &db_path_bg, &scan_roots, &folder_sizes, update_id);
+
     let _ = app_bg.emit("scan-phase-status", "".to_string());
 }
 
+/// Append this scan's largest folders per depth to `size_history`, in every index mode.
+fn record_size_history(
+    state: &AppState,
+    db_path: &std::path::Path,
+    scan_roots: &[std::path::PathBuf],
+    folder_sizes: &HashMap<std::path::PathBuf, u64>,
+    update_id: i64,
+) {
+    let tracked = select_tracked_folders(scan_roots, folder_sizes, &TrackingOptions::default());
+    let written = db::open_db(db_path).and_then(|conn| db::write_size_history(&conn, update_id, &tracked));
+    match written {
+        Ok(count) => write_debug_log(state, &format!("phase2 size_history done folders={}", count)),
+        Err(e) => write_debug_log(state, &format!("phase2 size_history failed error={:?}", e)),
+    }
+}
+
+/// Drop snapshots outside the default retention policy after a scan was written.
+fn prune_snapshots(state: &AppState, conn: &rusqlite::Connection, now_ms: i64) {
+    match db::apply_retention(conn, &db::RetentionPolicy::default(), now_ms) {
+        Ok(removed) => write_debug_log(state, &format!("phase2 retention removed={}", removed)),
+        Err(e) => write_debug_log(state, &format!("phase2 retention failed error={:?}", e)),
+    }
+}
+
 /// Mirror of the types written by `mft-helper` — used only for JSON deserialisation.
 #[derive(serde::Deserialize)]
 struct MftFileEntry {
@@ -876,8 +895,10 @@ async fn scan_directory_with_helper(
             .map(|f| cutest_disk_tree::FileEntry {
                 path: std::path::PathBuf::from(&f.path),
                 size: f.size,
+                allocated_size: f.size,
                 file_key: cutest_disk_tree::FileKey { dev: f.dev, ino: f.ino },
                 mtime: f.mtime,
+                owner: None,
             })
             .collect(),
     );
@@ -892,6 +913,10 @@ async fn scan_directory_with_helper(
         files_arc.len(), all_folder_paths.len(), scan_start.elapsed().as_millis(),
     ));
 
+    if control.is_cancelled() {
+        return finish_cancelled_scan(&app, &state, scan_roots, roots_str, files_arc).await;
+    }
+
     let cancel_token = {
         let mut guard = state.phase2_cancel.lock().map_err(|e| format!("lock poisoned: {}", e))?;
         guard.store(true, Ordering::Relaxed);
@@ -911,6 +936,7 @@ async fn scan_directory_with_helper(
         roots: roots_str,
         files_count: files_arc.len() as u64,
         folders_count: all_folder_paths.len() as u64,
+        errors_count: 0,
     };
 
     let app_bg = app.clone();
@@ -919,7 +945,7 @@ async fn scan_directory_with_helper(
     let folder_paths_bg = all_folder_paths;
     let mode = state.index_mode;
     tauri::async_runtime::spawn_blocking(move || {
-        run_phase2(app_bg, db_path, roots_bg, files_bg, folder_paths_bg, cancel_token, mode);
+        run_phase2(app_bg, db_path, roots_bg, files_bg, folder_paths_bg, cancel_token, mode, None, Vec::new());
     });
 
     state.is_scanning.store(false, Ordering::SeqCst);
@@ -932,13 +958,41 @@ async fn scan_directory_with_helper(
 async fn scan_directory(
     app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
+    one_file_system: Option<bool>,
+    scanner: Option<String>,
+    resumable: Option<bool>,
+    incremental: Option<bool>,
+    symlinks: Option<String>,
 ) -> Result<ScanDirectoryResponse, String> {
     write_debug_log(&state, &format!(
-        "scan_directory called is_scanning={} scan_path_override={:?}",
+        "scan_directory called is_scanning={} scan_path_override={:?} one_file_system={:?} scanner={:?} resumable={:?} incremental={:?} symlinks={:?}",
         state.is_scanning.load(Ordering::SeqCst),
         state.scan_path_override,
+        one_file_system,
+        scanner,
+        resumable,
+        incremental,
+        symlinks,
     ));
 
+    let scanner_name = scanner
+        .or_else(|| std::env::var("CUTE_DISK_TREE_SCANNER").ok())
+        .unwrap_or_else(|| SCANNER_NAMES[0].to_string());
+    let Some(scanner) = scanner_by_name(&scanner_name) else {
+        let e = format!("Unknown scanner: {} (expected one of {})", scanner_name, SCANNER_NAMES.join(", "));
+        write_debug_log(&state, &format!("error scan_directory: {}", e));
+        return Err(e);
+    };
+    let symlink_policy = match symlinks.as_deref().map(SymlinkPolicy::by_name) {
+        None => SymlinkPolicy::default(),
+        Some(Some(policy)) => policy,
+        Some(None) => {
+            let e = format!("Unknown symlink policy (expected one of {})", SymlinkPolicy::NAMES.join(", "));
+            write_debug_log(&state, &format!("error scan_directory: {}", e));
+            return Err(e);
+        }
+    };
+
     if state.is_scanning.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
         write_debug_log(&state, "scan_directory rejected: scan already in progress");
         return Err("A scan is already in progress".to_string());
@@ -964,38 +1018,73 @@ async fn scan_directory(
     let scan_start = Instant::now();
     let roots_for_scan = scan_roots.clone();
     let scan_log_path = resolve_debug_log_path(&state);
-    let (files_arc, all_folder_paths, roots_str) = match tauri::async_runtime::spawn_blocking(move || {
+    let control = ScanControl::new();
+    *state.scan_control.lock().map_err(|e| format!("lock poisoned: {}", e))? = control.clone();
+    state.keep_partial_scan.store(false, Ordering::SeqCst);
+    let scan_options = ScanOptions {
+        one_file_system: one_file_system.unwrap_or(false),
+        exclusions: Arc::new(load_exclusion_rules(&state)),
+        control: control.clone(),
+        symlinks: symlink_policy,
+        ..ScanOptions::default()
+    };
+    let checkpoint_db = resumable.unwrap_or(false).then(|| db_path.clone());
+    let incremental_db = incremental.unwrap_or(false).then(|| db_path.clone());
+    let errors_db = db_path.clone();
+    let walk = match tauri::async_runtime::spawn_blocking(move || {
         let mut last_progress_emit: Option<Instant> = None;
-        let (files_arc, all_folders, roots_str) =
-            cutest_disk_tree::core::scanning::ignore_scanner::scan_roots_with_ignore(
-                &roots_for_scan,
-                move |p| {
-                    // Write scan-method status messages to the debug log so it's
-                    // always clear whether we used the MFT or the directory walk.
-                    if let Some(ref status) = p.status {
-                        if status.contains("MFT") || status.contains("falling back") || status.contains("directory walk") {
-                            cutest_disk_tree::logging::debug_log::write_debug_log(
-                                &scan_log_path,
-                                &format!("scan_method: {}", status),
-                            );
-                        }
-                    }
-                    let now = Instant::now();
-                    let should_emit = match last_progress_emit {
-                        None => true,
-                        Some(t) => now.duration_since(t).as_millis() >= 100,
-                    };
-                    if should_emit {
-                        last_progress_emit = Some(now);
-                        let _ = app_for_scan.emit("scan-progress", &p);
-                    }
-                },
-            );
-        Ok::<_, String>((files_arc, all_folders, roots_str))
+        let walk_log = scan_log_path.clone();
+        let progress = move |p: cutest_disk_tree::ScanProgress| {
+            // Write scan-method status messages to the debug log so it's
+            // always clear whether we used the MFT or the directory walk.
+            if let Some(ref status) = p.status {
+                if status.contains("MFT") || status.contains("falling back") || status.contains("directory walk") {
+                    cutest_disk_tree::logging::debug_log::write_debug_log(
+                        &scan_log_path,
+                        &format!("scan_method: {}", status),
+                    );
+                }
+            }
+            let now = Instant::now();
+            let should_emit = match last_progress_emit {
+                None => true,
+                Some(t) => now.duration_since(t).as_millis() >= 100,
+            };
+            if should_emit {
+                last_progress_emit = Some(now);
+                let _ = app_for_scan.emit("scan-progress", &p);
+            }
+        };
+        let walk = if let Some(db_path) = &incremental_db {
+            scan_incremental(db_path, &roots_for_scan, &scan_options, progress)?
+        } else if let Some(db_path) = &checkpoint_db {
+            scan_with_checkpoint(db_path, &roots_for_scan, &scan_options, &walk_log, progress)?
+        } else {
+            let mut collector = ScanCollector::new(progress);
+            scanner.scan(&roots_for_scan, &scan_options, &mut collector);
+            WalkResult {
+                files: Arc::new(collector.files),
+                folders: collector.folders,
+                roots: roots_for_scan.iter().map(|r| r.to_string_lossy().to_string()).collect(),
+                changed_folders: None,
+                errors: collector.errors,
+                symlinks: collector.symlinks,
+            }
+        };
+        if !scan_options.control.is_cancelled() {
+            let written = db::open_db(&errors_db).and_then(|conn| db::write_scan_errors(&conn, &walk.errors));
+            if let Err(e) = written {
+                cutest_disk_tree::logging::debug_log::write_debug_log(
+                    &walk_log,
+                    &format!("error scan_directory write_scan_errors: {}", e),
+                );
+            }
+        }
+        Ok::<_, String>(walk)
     })
     .await
     {
-        Ok(Ok(triple)) => triple,
+        Ok(Ok(result)) => result,
         Ok(Err(e)) => {
             write_debug_log(&state, &format!("error scan_directory phase1: {}", e));
             state.is_scanning.store(false, Ordering::SeqCst);
@@ -1007,11 +1096,16 @@ async fn scan_directory(
             return Err(e.to_string());
         }
     };
+    let WalkResult { files: files_arc, folders: all_folder_paths, roots: roots_str, changed_folders, errors, symlinks } = walk;
     write_debug_log(&state, &format!(
-        "scan_directory phase1_done files={} folders={} ms={}",
-        files_arc.len(), all_folder_paths.len(), scan_start.elapsed().as_millis(),
+        "scan_directory phase1_done files={} folders={} errors={} symlinks={} ms={}",
+        files_arc.len(), all_folder_paths.len(), errors.len(), symlinks.len(), scan_start.elapsed().as_millis(),
     ));
 
+    if control.is_cancelled() {
+        return finish_cancelled_scan(&app, &state, scan_roots, roots_str, files_arc).await;
+    }
+
     let cancel_token = {
         let mut guard = state.phase2_cancel.lock().map_err(|e| format!("lock poisoned: {}", e))?;
         guard.store(true, Ordering::Relaxed);
@@ -1030,6 +1124,7 @@ async fn scan_directory(
         roots: roots_str,
         files_count: files_arc.len() as u64,
         folders_count: all_folder_paths.len() as u64,
+        errors_count: errors.len() as u64,
     };
 
     let app_bg = app.clone();
@@ -1038,7 +1133,7 @@ async fn scan_directory(
     let folder_paths_bg = all_folder_paths;
     let mode = state.index_mode;
     tauri::async_runtime::spawn_blocking(move || {
-        run_phase2(app_bg, db_path, roots_bg, files_bg, folder_paths_bg, cancel_token, mode);
+        run_phase2(app_bg, db_path, roots_bg, files_bg, folder_paths_bg, cancel_token, mode, changed_folders, symlinks);
     });
 
     state.is_scanning.store(false, Ordering::SeqCst);
@@ -1047,6 +1142,146 @@ async fn scan_directory(
     Ok(response)
 }
 
+/// Walk `roots` with checkpoints in the database, continuing the interrupted scan of the same
+/// roots if there is one. The checkpoint is dropped once the walk completes.
+fn scan_with_checkpoint(
+    db_path: &std::path::Path,
+    roots: &[std::path::PathBuf],
+    options: &ScanOptions,
+    log_path: &std::path::Path,
+    progress: impl FnMut(cutest_disk_tree::ScanProgress),
+) -> Result<WalkResult, String> {
+    let log = |message: &str| cutest_disk_tree::logging::debug_log::write_debug_log(log_path, message);
+    let conn = db::open_db(db_path).map_err(|e| e.to_string())?;
+    let resume = db::load_checkpoint(&conn)
+        .map_err(|e| e.to_string())?
+        .filter(|c| c.roots == roots);
+    match &resume {
+        Some(c) => log(&format!("scan_directory resuming checkpoint folders={}", c.folders.len())),
+        None => db::start_checkpoint(&conn, roots).map_err(|e| e.to_string())?,
+    }
+    let mut collector = ScanCollector::new(progress);
+    scan_resumable(roots, options, resume.as_ref(), &mut collector, &mut |folders| {
+        if let Err(e) = db::save_checkpoint(&conn, folders) {
+            log(&format!("error scan_directory save_checkpoint: {}", e));
+        }
+    });
+    if !options.control.is_cancelled() {
+        if let Err(e) = db::clear_checkpoint(&conn) {
+            log(&format!("error scan_directory clear_checkpoint: {}", e));
+        }
+    }
+    Ok(WalkResult {
+        files: Arc::new(collector.files),
+        folders: collector.folders,
+        roots: roots.iter().map(|r| r.to_string_lossy().to_string()).collect(),
+        changed_folders: None,
+        errors: collector.errors,
+        symlinks: collector.symlinks,
+    })
+}
+
+/// Walk `roots`, reading only the folders that changed since the stored scan. Also returns the
+/// folders that were read, for `db::write_incremental_scan`.
+fn scan_incremental(
+    db_path: &std::path::Path,
+    roots: &[std::path::PathBuf],
+    options: &ScanOptions,
+    progress: impl FnMut(cutest_disk_tree::ScanProgress),
+) -> Result<WalkResult, String> {
+    let conn = db::open_db(db_path).map_err(|e| e.to_string())?;
+    let previous = db::current_scan_checkpoint(&conn).map_err(|e| e.to_string())?;
+    let mut collector = ScanCollector::new(progress);
+    let mut changed = Vec::new();
+    scan_resumable(roots, options, Some(&previous), &mut collector, &mut |f| changed.extend_from_slice(f));
+    Ok(WalkResult {
+        files: Arc::new(collector.files),
+        folders: collector.folders,
+        roots: roots.iter().map(|r| r.to_string_lossy().to_string()).collect(),
+        changed_folders: Some(changed),
+        errors: collector.errors,
+        symlinks: collector.symlinks,
+    })
+}
+
+/// End a scan whose walk was cancelled: discard what it found, or keep it as an incomplete
+/// snapshot when `cancel_scan` asked for that. The current scan stays as it was either way.
+async fn finish_cancelled_scan(
+    app: &tauri::AppHandle,
+    state: &tauri::State<'_, AppState>,
+    scan_roots: Vec<std::path::PathBuf>,
+    roots_str: Vec<String>,
+    files: Arc<Vec<cutest_disk_tree::FileEntry>>,
+) -> Result<ScanDirectoryResponse, String> {
+    let partial_scan_id = if state.keep_partial_scan.load(Ordering::SeqCst) {
+        let db_path = state.db_path.clone();
+        let saved = tauri::async_runtime::spawn_blocking(move || {
+            let mut folder_sizes: HashMap<std::path::PathBuf, u64> = HashMap::new();
+            for root in &scan_roots {
+                for (k, v) in cutest_disk_tree::compute_folder_sizes(root, &files) {
+                    *folder_sizes.entry(k).or_insert(0) += v;
+                }
+            }
+            let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+            let update_id = chrono::Utc::now().timestamp_millis();
+            db::write_partial_scan(&conn, &roots_str, &files, &folder_sizes, update_id)
+                .map_err(|e| e.to_string())
+        })
+        .await;
+        match saved {
+            Ok(Ok(id)) => Some(id),
+            Ok(Err(e)) => {
+                write_debug_log(state, &format!("error scan_directory keep_partial: {}", e));
+                None
+            }
+            Err(e) => {
+                write_debug_log(state, &format!("error scan_directory keep_partial spawn: {}", e));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    state.is_scanning.store(false, Ordering::SeqCst);
+    let _ = app.emit("scan-cancelled", &ScanCancelledEvent { partial_scan_id });
+    write_debug_log(state, &format!("scan_directory cancelled partial_scan_id={:?}", partial_scan_id));
+    Err("Scan cancelled".to_string())
+}
+
+/// Stop the walk of the running scan. With `keep_partial` what was found so far is saved as
+/// an incomplete snapshot; otherwise it is discarded. Returns false when no scan is running.
+#[tauri::command]
+fn cancel_scan(state: tauri::State<AppState>, keep_partial: Option<bool>) -> Result<bool, String> {
+    if !state.is_scanning.load(Ordering::SeqCst) {
+        return Ok(false);
+    }
+    state.keep_partial_scan.store(keep_partial.unwrap_or(false), Ordering::SeqCst);
+    state.scan_control.lock().map_err(|e| format!("lock poisoned: {}", e))?.cancel();
+    write_debug_log(&state, &format!("cancel_scan keep_partial={:?}", keep_partial));
+    Ok(true)
+}
+
+/// Pause the walk of the running scan until `resume_scan` or `cancel_scan`.
+#[tauri::command]
+fn pause_scan(state: tauri::State<AppState>) -> Result<bool, String> {
+    if !state.is_scanning.load(Ordering::SeqCst) {
+        return Ok(false);
+    }
+    state.scan_control.lock().map_err(|e| format!("lock poisoned: {}", e))?.pause();
+    write_debug_log(&state, "pause_scan");
+    Ok(true)
+}
+
+#[tauri::command]
+fn resume_scan(state: tauri::State<AppState>) -> Result<bool, String> {
+    if !state.is_scanning.load(Ordering::SeqCst) {
+        return Ok(false);
+    }
+    state.scan_control.lock().map_err(|e| format!("lock poisoned: {}", e))?.resume();
+    write_debug_log(&state, "resume_scan");
+    Ok(true)
+}
+
 #[tauri::command]
 async fn load_cached_scan(
     app: tauri::AppHandle,
@@ -1062,8 +1297,17 @@ async fn load_cached_scan(
             let metadata_path = db_path.parent()
                 .map(|p| p.join("scan-metadata.json"))
                 .unwrap_or_else(|| std::path::PathBuf::from("scan-metadata.json"));
+            let db_path = db_path.clone();
             match tauri::async_runtime::spawn_blocking(move || {
-                read_scan_metadata(&metadata_path).map_err(|e| e.to_string())
+                let mut summary = read_scan_metadata(&metadata_path).map_err(|e| e.to_string())?;
+                // The metadata file is written before errors are known; they live in the database.
+                if let Some(summary) = summary.as_mut() {
+                    summary.errors_count = db::open_db(&db_path)
+                        .and_then(|conn| db::count_scan_errors(&conn))
+                        .map(|counts| counts.iter().map(|(_, n)| n).sum())
+                        .unwrap_or(0);
+                }
+                Ok::<_, String>(summary)
             }).await {
                 Ok(Ok(s)) => s,
                 Ok(Err(e)) => return Err(e),
@@ -1083,6 +1327,11 @@ async fn load_cached_scan(
                             files_count,
                             folders_count,
                             folder_sizes: std::collections::HashMap::new(),
+                            errors_count: db::count_scan_errors(&conn)
+                                .map_err(|e| e.to_string())?
+                                .iter()
+                                .map(|(_, n)| n)
+                                .sum(),
                         })),
                         None => Ok(None),
                     }
@@ -1128,8 +1377,10 @@ async fn load_cached_scan(
                 cutest_disk_tree::FileEntry {
                     path: std::path::PathBuf::from(&f.path),
                     size: f.size,
+                    allocated_size: f.size,
                     file_key: cutest_disk_tree::FileKey { dev: f.file_key.dev, ino: f.file_key.ino },
                     mtime: f.mtime,
+                    owner: None,
                 }
             }).collect();
             let folder_paths: std::collections::HashSet<std::path::PathBuf> = scan_result.folder_sizes.keys()
@@ -1185,12 +1436,44 @@ async fn build_disk_tree_cached(
     start_path: String,
     max_children_per_node: u32,
     max_depth: u32,
+    size_metric: Option<cutest_disk_tree::SizeMetric>,
 ) -> Result<Option<cutest_disk_tree::DiskTreeNode>, String> {
-    write_debug_log(&state, &format!("build_disk_tree_cached started mode={:?} start_path={} max_depth={}", state.index_mode, start_path, max_depth));
+    let metric = size_metric.unwrap_or_default();
+    write_debug_log(&state, &format!("build_disk_tree_cached started mode={:?} start_path={} max_depth={} metric={:?}", state.index_mode, start_path, max_depth, metric));
     let db_path = state.db_path.clone();
     let path_clone = start_path.clone();
     let mode = state.index_mode;
 
+    // Cached trees and the compressed text index only hold apparent sizes.
+    if metric == cutest_disk_tree::SizeMetric::Allocated && mode != SearchIndexMode::CompressedText {
+        let start_path_for_log = start_path.clone();
+        return match tauri::async_runtime::spawn_blocking(move || {
+            let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+            Ok::<_, String>(cutest_disk_tree::build_disk_tree_from_db_with_metric(
+                &conn,
+                &path_clone,
+                max_children_per_node as usize,
+                max_depth as usize,
+                metric,
+            ))
+        })
+        .await
+        {
+            Ok(Ok(tree)) => {
+                write_debug_log(&state, &format!("build_disk_tree_cached done start_path={} metric=allocated has_tree={}", start_path_for_log, tree.is_some()));
+                Ok(tree)
+            }
+            Ok(Err(e)) => {
+                write_debug_log(&state, &format!("error build_disk_tree_cached: {}", e));
+                Err(e)
+            }
+            Err(e) => {
+                write_debug_log(&state, &format!("error build_disk_tree_cached spawn: {}", e));
+                Err(e.to_string())
+            }
+        };
+    }
+
     if mode == SearchIndexMode::CompressedText {
         let cti_path = state.db_path.parent()
             .map(|p| p.join("index.compressed-text-index.lz4"))
@@ -1332,723 +1615,738 @@ async fn build_disk_tree_cached(
     Ok(result)
 }
 
-fn resolve_compressed_text_index_path(state: &AppState) -> std::path::PathBuf {
-    state.db_path.parent()
-        .map(|p| p.join("index.compressed-text-index.lz4"))
-        .unwrap_or_else(|| std::path::PathBuf::from("index.compressed-text-index.lz4"))
-}
-
+/// Find duplicate files among the files of the last scan persisted to `index.db`.
+///
+/// Progress is reported through `duplicates-progress` events. The compressed-text mode does
+/// not persist sizes or file keys, so it always yields an empty report.
 #[tauri::command]
-fn find_files(
-    state: tauri::State<AppState>,
-    query: String,
-    extensions: Option<String>,
-    category: Option<String>,
-    limit: Option<u32>,
-    use_fuzzy: bool,
-    offset: Option<u32>,
-) -> Result<FindFilesResponse, String> {
-    match state.index_mode {
-        SearchIndexMode::CompressedText => find_files_in_compressed_text_index(&state, query, extensions, category, limit, use_fuzzy, offset),
-        SearchIndexMode::Sqlite => find_files_in_db(&state, query, extensions, category, limit, use_fuzzy, offset),
-        SearchIndexMode::InMemoryNgrams => {
-            let has_index = !state.trigram_index.lock().map_err(|e| format!("lock poisoned: {}", e))?.objects.is_empty();
-            if has_index {
-                find_files_in_ngram_index(&state, query, extensions, category, limit, use_fuzzy, offset)
-            } else {
-                find_files_in_db(&state, query, extensions, category, limit, use_fuzzy, offset)
-            }
+async fn find_duplicates(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    min_size: Option<u64>,
+) -> Result<DuplicateReport, String> {
+    write_debug_log(&state, &format!("find_duplicates started min_size={:?}", min_size));
+    let db_path = state.db_path.clone();
+    let t0 = Instant::now();
+
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        let files = db::get_file_entries(&conn).map_err(|e| e.to_string())?;
+        let mut cache = db::read_file_hashes(&conn).map_err(|e| e.to_string())?;
+        let mut options = DuplicateOptions::default();
+        if let Some(min) = min_size {
+            options.min_size = min;
         }
-        SearchIndexMode::InMemorySuffix => {
-            let has_memory_index = state.disk_objects.lock().map_err(|e| format!("lock poisoned: {}", e))?.is_some();
-            if has_memory_index {
-                find_files_in_memory(&state, query, extensions, category, limit, use_fuzzy, offset)
-            } else {
-                find_files_in_db(&state, query, extensions, category, limit, use_fuzzy, offset)
+        let mut last_progress_emit: Option<Instant> = None;
+        let report = find_duplicates_with_cache(&files, &options, &mut cache, move |p| {
+            let now = Instant::now();
+            let should_emit = match last_progress_emit {
+                None => true,
+                Some(t) => now.duration_since(t).as_millis() >= 100,
+            };
+            if should_emit {
+                last_progress_emit = Some(now);
+                let _ = app.emit("duplicates-progress", &p);
             }
+        });
+        db::write_file_hashes(&conn, &mut cache).map_err(|e| e.to_string())?;
+        Ok::<_, String>(report)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(report)) => {
+            write_debug_log(&state, &format!(
+                "find_duplicates done groups={} wasted_bytes={} files_hashed={} bytes_hashed={} cache_hits={} ms={}",
+                report.groups.len(), report.total_wasted_bytes,
+                report.files_hashed, report.bytes_hashed, report.cache_hits, t0.elapsed().as_millis(),
+            ));
+            Ok(report)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error find_duplicates: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error find_duplicates spawn: {}", e));
+            Err(e.to_string())
         }
     }
 }
 
-fn find_files_in_memory(
-    state: &tauri::State<AppState>,
-    query: String,
-    extensions: Option<String>,
-    category: Option<String>,
-    limit: Option<u32>,
-    use_fuzzy: bool,
-    offset: Option<u32>,
-) -> Result<FindFilesResponse, String> {
-    const DEFAULT_LIMIT: u32 = 500;
-    let limit = limit.unwrap_or(DEFAULT_LIMIT) as usize;
-    let start_index: usize = offset.unwrap_or(0) as usize;
-    let total_start = Instant::now();
-
-    write_debug_log(
-        state,
-        &format!(
-            ">>> find_files start query_len={} limit={}",
-            query.len(),
-            limit
-        ),
-    );
+/// Find resized or re-encoded copies of the same image among the files in `index.db`.
+///
+/// Returns the same report type as `find_duplicates`, with groups of kind `similar`.
+#[tauri::command]
+async fn find_similar_images(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    max_distance: Option<u32>,
+) -> Result<DuplicateReport, String> {
+    write_debug_log(&state, &format!("find_similar_images started max_distance={:?}", max_distance));
+    let db_path = state.db_path.clone();
+    let t0 = Instant::now();
 
-    let disk_entries_arc = {
-        let guard = state.disk_objects.lock().map_err(|e| format!("lock poisoned: {}", e))?;
-        if let Some(entries) = guard.as_ref() {
-            let t = total_start.elapsed().as_millis();
-            write_debug_log(
-                state,
-                &format!(
-                    "find_files index from_cache count={} ms={}",
-                    entries.len(),
-                    t
-                ),
-            );
-            entries.clone()
-        } else {
-            write_debug_log(
-                state,
-                "find_files no_active_index (returning empty results)",
-            );
-            return Ok(FindFilesResponse {
-                items: Vec::new(),
-                next_offset: None,
-            });
-        }
-    };
-    let disk_entries: &[DiskObject] = disk_entries_arc.as_ref();
-
-    let ext_filter_start = Instant::now();
-    let extension_set: Option<std::collections::HashSet<String>> = extensions.as_ref().and_then(|s| {
-        let cleaned: Vec<String> = s
-            .split(',')
-            .map(|x| x.trim().trim_start_matches('.').to_ascii_lowercase())
-            .filter(|x| !x.is_empty())
-            .collect();
-        if cleaned.is_empty() {
-            None
-        } else {
-            Some(cleaned.into_iter().collect())
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        let files = db::get_file_entries(&conn).map_err(|e| e.to_string())?;
+        let mut options = SimilarImageOptions::default();
+        if let Some(d) = max_distance {
+            options.max_distance = d.min(64);
         }
-    });
-
-    let (use_mask, allowed_indices, allowed_count) = if let Some(ref set) = extension_set {
-        let mut mask: Vec<bool> = Vec::with_capacity(disk_entries.len());
-        let mut count = 0usize;
-        for o in disk_entries {
-            let allowed = match o.kind {
-                DiskObjectKind::File => o
-                    .ext
-                    .as_ref()
-                    .map(|t| set.contains(t))
-                    .unwrap_or(false),
-                DiskObjectKind::Folder => false,
+        let mut last_progress_emit: Option<Instant> = None;
+        Ok::<_, String>(find_similar_images_with_progress(&files, &options, move |p| {
+            let now = Instant::now();
+            let should_emit = match last_progress_emit {
+                None => true,
+                Some(t) => now.duration_since(t).as_millis() >= 100,
             };
-            if allowed {
-                count += 1;
+            if should_emit {
+                last_progress_emit = Some(now);
+                let _ = app.emit("duplicates-progress", &p);
             }
-            mask.push(allowed);
+        }))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(report)) => {
+            write_debug_log(&state, &format!(
+                "find_similar_images done groups={} wasted_bytes={} images_decoded={} ms={}",
+                report.groups.len(), report.total_wasted_bytes, report.files_hashed, t0.elapsed().as_millis(),
+            ));
+            Ok(report)
         }
-        (true, mask, count)
-    } else {
-        let category_ref = category.as_deref();
-        if matches!(category_ref, Some(c) if !c.trim().is_empty() && c.trim() != "all") {
-            let mut mask: Vec<bool> = Vec::with_capacity(disk_entries.len());
-            let mut count = 0usize;
-            for o in disk_entries {
-                let allowed = category_allowed(category_ref.map(|s| s.trim()), o);
-                if allowed {
-                    count += 1;
-                }
-                mask.push(allowed);
-            }
-            (true, mask, count)
-        } else {
-            (false, Vec::new(), disk_entries.len())
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error find_similar_images: {}", e));
+            Err(e)
         }
-    };
+        Err(e) => {
+            write_debug_log(&state, &format!("error find_similar_images spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
+}
 
-    let ext_filter_ms = ext_filter_start.elapsed().as_millis();
-    write_debug_log(
-        state,
-        &format!(
-            "find_files after_filter allowed_count={} total_entries={} use_extensions={} category={:?} ext_filter_ms={}",
-            allowed_count, disk_entries.len(), extension_set.is_some(), category.as_deref(), ext_filter_ms
-        ),
-    );
+/// Find folders whose whole subtree is duplicated, using the last scan in `index.db`.
+///
+/// Shares the `file_hashes` cache and the `duplicates-progress` event with `find_duplicates`.
+#[tauri::command]
+async fn find_duplicate_folders(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    min_size: Option<u64>,
+) -> Result<DuplicateFolderReport, String> {
+    write_debug_log(&state, &format!("find_duplicate_folders started min_size={:?}", min_size));
+    let db_path = state.db_path.clone();
+    let t0 = Instant::now();
 
-    if start_index >= disk_entries.len() {
-        let total_ms = total_start.elapsed().as_millis();
-        write_debug_log(
-            state,
-            &format!(
-                "find_files empty_page start_index={} total_entries={} total_ms={}",
-                start_index, disk_entries.len(), total_ms
-            ),
-        );
-        return Ok(FindFilesResponse {
-            items: Vec::new(),
-            next_offset: None,
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        let files = db::get_file_entries(&conn).map_err(|e| e.to_string())?;
+        let folder_sizes: HashMap<std::path::PathBuf, u64> = db::get_folders(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(path, size)| (std::path::PathBuf::from(path), size))
+            .collect();
+        let mut cache = db::read_file_hashes(&conn).map_err(|e| e.to_string())?;
+        let mut options = DuplicateOptions::default();
+        if let Some(min) = min_size {
+            options.min_size = min;
+        }
+        let mut last_progress_emit: Option<Instant> = None;
+        let report = find_duplicate_folders_with_cache(&files, &folder_sizes, &options, &mut cache, move |p| {
+            let now = Instant::now();
+            let should_emit = match last_progress_emit {
+                None => true,
+                Some(t) => now.duration_since(t).as_millis() >= 100,
+            };
+            if should_emit {
+                last_progress_emit = Some(now);
+                let _ = app.emit("duplicates-progress", &p);
+            }
         });
-    }
+        db::write_file_hashes(&conn, &mut cache).map_err(|e| e.to_string())?;
+        Ok::<_, String>(report)
+    })
+    .await;
 
-    if query.trim().is_empty() {
-        let collect_start = Instant::now();
-        let (items, next_offset) =
-            paginate_scan(disk_entries, start_index, limit, use_mask, &allowed_indices, |_i, _e| {
-                true
-            });
-        let collect_ms = collect_start.elapsed().as_millis();
-        let total_ms = total_start.elapsed().as_millis();
-        write_debug_log(
-            state,
-            &format!(
-                "find_files done empty_query count={} start_index={} next_offset={:?} collect_take_ms={} total_ms={}",
-                items.len(), start_index, next_offset, collect_ms, total_ms
-            ),
-        );
-        return Ok(FindFilesResponse {
-            items,
-            next_offset,
-        });
+    match result {
+        Ok(Ok(report)) => {
+            write_debug_log(&state, &format!(
+                "find_duplicate_folders done groups={} wasted_bytes={} files_hashed={} cache_hits={} ms={}",
+                report.groups.len(), report.total_wasted_bytes,
+                report.files_hashed, report.cache_hits, t0.elapsed().as_millis(),
+            ));
+            Ok(report)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error find_duplicate_folders: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error find_duplicate_folders spawn: {}", e));
+            Err(e.to_string())
+        }
     }
+}
 
-    let q_trimmed = query.trim();
-    let q = q_trimmed.to_lowercase();
-    let q_len = q_trimmed.chars().count();
-
-    let suffix_search_start = Instant::now();
-    let candidate_set_opt = {
-        let guard = state.name_reverse_index.lock().map_err(|e| format!("lock poisoned: {}", e))?;
-        guard
-            .as_ref()
-            .and_then(|idx| suffix_find_files(idx.as_ref(), &q))
-    };
-    let suffix_search_ms = suffix_search_start.elapsed().as_millis();
-    write_debug_log(state, &format!(
-        "find_files suffix_search q_len={} candidates={} ms={}",
-        q_len,
-        candidate_set_opt.as_ref().map(|s| s.len()).unwrap_or(0),
-        suffix_search_ms,
+/// Replace duplicate copies with hard links or reflinks of `keep`.
+///
+/// Every copy is verified byte-for-byte first. Unless `dry_run` is set, the new dev/ino of each
+/// replaced file is written to the in-memory trigram index and `disk_objects`, so search and
+/// duplicate results reflect the change without a rescan.
+#[tauri::command]
+async fn dedupe_files(
+    state: tauri::State<'_, AppState>,
+    keep: String,
+    copies: Vec<String>,
+    method: DedupeMethod,
+    dry_run: bool,
+) -> Result<DedupeReport, String> {
+    write_debug_log(&state, &format!(
+        "dedupe_files started keep={} copies={} method={:?} dry_run={}",
+        keep, copies.len(), method, dry_run,
     ));
-
-    if !use_fuzzy || q_len < 3 {
-        let build_start = Instant::now();
-        let (items, next_offset) = paginate_scan(
-            disk_entries,
-            start_index,
-            limit,
-            use_mask,
-            &allowed_indices,
-            |i, e| {
-                if let Some(cs) = &candidate_set_opt {
-                    if !cs.contains(&i) {
-                        return false;
-                    }
-                }
-                e.name.to_ascii_lowercase().contains(&q)
-            },
-        );
-        let build_ms = build_start.elapsed().as_millis();
-        let total_ms = total_start.elapsed().as_millis();
-        let label = if !use_fuzzy {
-            "substring_no_fuzzy_done"
-        } else {
-            "short_query_fuzzy"
-        };
-        write_debug_log(
-            state,
-            &format!(
-                "find_files {} q_len={} count={} start_index={} next_offset={:?} results_build_ms={} total_ms={}",
-                label, q_len, items.len(), start_index, next_offset, build_ms, total_ms,
-            ),
-        );
-        return Ok(FindFilesResponse {
-            items,
-            next_offset,
-        });
-    } else {
-        let nucleo_start = Instant::now();
-        let mut filtered: Vec<(usize, &DiskObject)> = Vec::new();
-        for (i, e) in disk_entries.iter().enumerate() {
-            if use_mask && !allowed_indices[i] {
-                continue;
-            }
-            if let Some(cs) = &candidate_set_opt {
-                if !cs.contains(&i) {
-                    continue;
+    let db_path = state.db_path.clone();
+    let trigram_index = Arc::clone(&state.trigram_index);
+
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let copies: Vec<std::path::PathBuf> = copies.into_iter().map(std::path::PathBuf::from).collect();
+        let options = DedupeOptions { method, dry_run };
+        let report = dedupe::dedupe_files(std::path::Path::new(&keep), &copies, &options)
+            .map_err(|e| e.to_string())?;
+        if !report.dry_run && !report.replaced.is_empty() {
+            {
+                let mut index = trigram_index.lock().map_err(|e| format!("lock poisoned: {}", e))?;
+                for done in &report.replaced {
+                    index.update_file_identity(&done.path, done.file_key, done.mtime);
                 }
             }
-            filtered.push((i, e));
-        }
-        let labels: Vec<&str> = filtered.iter().map(|(_, e)| e.name.as_str()).collect();
-        let mut matcher = Matcher::new(Config::DEFAULT);
-        let pattern =
-            Pattern::parse(&query, CaseMatching::Smart, Normalization::Smart);
-        let mut scored = pattern.match_list(labels.iter().copied(), &mut matcher);
-        scored.sort_by(|a, b| b.1.cmp(&a.1));
-        let scored_len = scored.len();
-        let nucleo_ms = nucleo_start.elapsed().as_millis();
-        let select_start = Instant::now();
-
-        let label_to_filtered: HashMap<*const str, usize> = filtered
-            .iter()
-            .enumerate()
-            .map(|(fi, (_, e))| (e.name.as_str() as *const str, fi))
-            .collect();
-
-        let mut items: Vec<SearchEntry> = Vec::new();
-        let mut seen_filtered_indices: HashSet<usize> = HashSet::new();
-        for (label, _score) in scored.into_iter().take(limit) {
-            let label_ptr = label as *const str;
-            if let Some(&fi) = label_to_filtered.get(&label_ptr) {
-                if seen_filtered_indices.insert(fi) {
-                    items.push(search_entry_from_disk_object(filtered[fi].1));
-                }
+            let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+            for done in &report.replaced {
+                db::update_file_identity(&conn, &done.path, done.file_key, done.mtime)
+                    .map_err(|e| e.to_string())?;
             }
         }
+        Ok::<_, String>(report)
+    })
+    .await;
 
-        let select_ms = select_start.elapsed().as_millis();
-        let total_ms = total_start.elapsed().as_millis();
-        write_debug_log(
-            state,
-            &format!(
-                "find_files nucleo_done q_len={} scored={} taken={} nucleo_ms={} select_build_ms={} total_ms={}",
-                q_len, scored_len, items.len(), nucleo_ms, select_ms, total_ms,
-            ),
-        );
-        return Ok(FindFilesResponse {
-            items,
-            next_offset: None,
-        });
+    match result {
+        Ok(Ok(report)) => {
+            write_debug_log(&state, &format!(
+                "dedupe_files done replaced={} skipped={} bytes_saved={} dry_run={}",
+                report.replaced.len(), report.skipped.len(), report.bytes_saved, report.dry_run,
+            ));
+            Ok(report)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error dedupe_files: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error dedupe_files spawn: {}", e));
+            Err(e.to_string())
+        }
     }
 }
 
-fn nucleo_rank_objects(query: &str, candidates: Vec<&DiskObject>, limit: usize) -> Vec<SearchEntry> {
-    let labels: Vec<&str> = candidates.iter().map(|o| o.name.as_str()).collect();
-    let mut matcher = Matcher::new(Config::DEFAULT);
-    let pattern = Pattern::parse(query, CaseMatching::Smart, Normalization::Smart);
-    let mut scored = pattern.match_list(labels.iter().copied(), &mut matcher);
-    scored.sort_by(|a, b| b.1.cmp(&a.1));
-    let label_to_idx: HashMap<*const str, usize> = candidates
-        .iter()
-        .enumerate()
-        .map(|(i, o)| (o.name.as_str() as *const str, i))
-        .collect();
-    let mut seen: HashSet<usize> = HashSet::new();
-    scored
-        .into_iter()
-        .take(limit)
-        .filter_map(|(label, _)| {
-            let ptr = label as *const str;
-            label_to_idx.get(&ptr).and_then(|&i| {
-                if seen.insert(i) {
-                    Some(search_entry_from_disk_object(candidates[i]))
-                } else {
-                    None
-                }
-            })
-        })
-        .collect()
+/// Stored scan snapshots, newest first.
+#[tauri::command]
+async fn list_scans(state: tauri::State<'_, AppState>) -> Result<Vec<db::ScanInfo>, String> {
+    let db_path = state.db_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        db::list_scans(&conn).map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(scans)) => Ok(scans),
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error list_scans: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error list_scans spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
 }
 
-fn find_files_in_ngram_index(
-    state: &tauri::State<AppState>,
-    query: String,
-    extensions: Option<String>,
-    category: Option<String>,
-    limit: Option<u32>,
-    use_fuzzy: bool,
-    offset: Option<u32>,
-) -> Result<FindFilesResponse, String> {
-    const DEFAULT_LIMIT: u32 = 500;
-    let limit = limit.unwrap_or(DEFAULT_LIMIT) as usize;
-    let offset = offset.unwrap_or(0) as usize;
-    let q_trimmed = query.trim();
-    let q_len = q_trimmed.chars().count();
-    let apply_fuzzy = use_fuzzy && !q_trimmed.is_empty() && q_len >= 3;
-    let total_start = Instant::now();
+#[tauri::command]
+async fn delete_scan(state: tauri::State<'_, AppState>, scan_id: i64) -> Result<bool, String> {
+    write_debug_log(&state, &format!("delete_scan started scan_id={}", scan_id));
+    let db_path = state.db_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        db::delete_scan(&conn, scan_id).map_err(|e| e.to_string())
+    })
+    .await;
 
-    let guard = state.trigram_index.lock().map_err(|e| format!("lock poisoned: {}", e))?;
-    let index_arc = &*guard;
-    if index_arc.objects.is_empty() {
-        return Ok(FindFilesResponse { items: vec![], next_offset: None });
+    match result {
+        Ok(Ok(deleted)) => {
+            write_debug_log(&state, &format!("delete_scan done scan_id={} deleted={}", scan_id, deleted));
+            Ok(deleted)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error delete_scan: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error delete_scan spawn: {}", e));
+            Err(e.to_string())
+        }
     }
+}
 
-    let has_filter = extensions.as_ref().map_or(false, |s| !s.trim().is_empty())
-        || category.as_deref().map_or(false, |c| !c.trim().is_empty() && c.trim() != "all");
+/// Tree of a previous snapshot, read through a read-only connection. Never cached.
+#[tauri::command]
+async fn build_snapshot_tree(
+    state: tauri::State<'_, AppState>,
+    scan_id: i64,
+    start_path: String,
+    max_children_per_node: u32,
+    max_depth: u32,
+) -> Result<Option<cutest_disk_tree::DiskTreeNode>, String> {
+    write_debug_log(&state, &format!(
+        "build_snapshot_tree started scan_id={} start_path={} max_depth={}",
+        scan_id, start_path, max_depth,
+    ));
+    let db_path = state.db_path.clone();
+    let t0 = Instant::now();
 
-    let extension_set: Option<std::collections::HashSet<String>> = extensions.as_ref().and_then(|s| {
-        let cleaned: Vec<String> = s.split(',')
-            .map(|x| x.trim().trim_start_matches('.').to_ascii_lowercase())
-            .filter(|x| !x.is_empty())
-            .collect();
-        if cleaned.is_empty() { None } else { Some(cleaned.into_iter().collect()) }
-    });
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let Some(snapshot) = db::open_snapshot(&db_path, scan_id).map_err(|e| e.to_string())? else {
+            return Ok(None);
+        };
+        let scan = snapshot.scan_result().map_err(|e| e.to_string())?;
+        Ok::<_, String>(cutest_disk_tree::build_disk_tree(
+            &scan,
+            &start_path,
+            max_children_per_node as usize,
+            max_depth as usize,
+        ))
+    })
+    .await;
 
-    let passes_filter = |o: &DiskObject| -> bool {
-        if !has_filter { return true; }
-        if let Some(ref set) = extension_set {
-            match o.kind {
-                DiskObjectKind::File => o.ext.as_ref().map(|e| set.contains(e)).unwrap_or(false),
-                DiskObjectKind::Folder => false,
-            }
-        } else {
-            category_allowed(category.as_deref(), o)
+    match result {
+        Ok(Ok(tree)) => {
+            write_debug_log(&state, &format!(
+                "build_snapshot_tree done found={} ms={}",
+                tree.is_some(), t0.elapsed().as_millis(),
+            ));
+            Ok(tree)
         }
-    };
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error build_snapshot_tree: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error build_snapshot_tree spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
+}
 
-    // For empty queries with a filter, scan all objects directly with correct pagination.
-    // The trigram index empty-query path only returns a bounded window by object position,
-    // which misses matching files that happen to sit beyond that window.
-    let filter_start = Instant::now();
-    let (items, next_offset) = if query.trim().is_empty() && has_filter {
-        let objects = &index_arc.objects;
-        let mut collected: Vec<SearchEntry> = Vec::new();
-        let mut next_off: Option<usize> = None;
-        let mut i = offset;
-        while i < objects.len() {
-            if passes_filter(&objects[i]) {
-                collected.push(search_entry_from_disk_object(&objects[i]));
-                if collected.len() == limit {
-                    if i + 1 < objects.len() {
-                        next_off = Some(i + 1);
-                    }
-                    break;
-                }
-            }
-            i += 1;
-        }
-        (collected, next_off)
-    } else if has_filter {
-        // Non-empty query with a category/extension filter.
-        //
-        // Strategy: intersect two trigram-index result sets instead of overfetching
-        // query results and post-filtering (which yields too few hits when the category
-        // is sparsely represented among the top-N query matches).
-        //
-        // For categories with known extensions we search the trigram index for each
-        // ".ext" suffix to get every file that belongs to the category, then intersect
-        // with the query matches.  For "folder" / "other" (no known extensions) we fall
-        // back to the overfetch approach since there's nothing extension-specific to
-        // search for.
-        let searchable_exts: Option<Vec<String>> = if let Some(ref set) = extension_set {
-            // Manual extension filter supplied by the caller
-            Some(set.iter().cloned().collect())
-        } else if let Some(cat) = category.as_deref().map(|c| c.trim()) {
-            search_category::extension_set(cat)
-                .map(|exts| exts.iter().map(|&e| e.to_string()).collect())
-        } else {
-            None
-        };
+/// Scan to compare: a stored snapshot, or the current scan when `scan_id` is `None`.
+fn load_scan_for_diff(
+    db_path: &std::path::Path,
+    scan_id: Option<i64>,
+) -> Result<cutest_disk_tree::ScanResult, String> {
+    match scan_id {
+        Some(id) => db::open_snapshot(db_path, id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("no snapshot with id {}", id))?
+            .scan_result()
+            .map_err(|e| e.to_string()),
+        None => {
+            let conn = db::open_db(db_path).map_err(|e| e.to_string())?;
+            db::get_scan_result(&conn)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "no scan in database".to_string())
+        }
+    }
+}
 
-        let search_start = Instant::now();
-        if let Some(exts) = searchable_exts {
-            // Step 1: collect all object indices whose filename contains ".{ext}"
-            let mut cat_set: HashSet<u32> = HashSet::new();
-            for ext in &exts {
-                let ext_query = format!(".{}", ext);
-                let (ext_matches, _) = trigram_find_files(
-                    &index_arc,
-                    &ext_query,
-                    &SearchFilter::None,
-                    usize::MAX,
-                    0,
-                );
-                cat_set.extend(ext_matches);
-            }
+/// Compare snapshot `old_scan_id` with `new_scan_id` (the current scan when `None`).
+#[tauri::command]
+async fn diff_scans(
+    state: tauri::State<'_, AppState>,
+    old_scan_id: i64,
+    new_scan_id: Option<i64>,
+    min_file_size: Option<u64>,
+    limit: Option<usize>,
+    tree_max_children: Option<usize>,
+    tree_max_depth: Option<usize>,
+) -> Result<DiffReport, String> {
+    write_debug_log(&state, &format!(
+        "diff_scans started old={} new={:?}", old_scan_id, new_scan_id,
+    ));
+    let db_path = state.db_path.clone();
+    let t0 = Instant::now();
 
-            if apply_fuzzy {
-                // Fuzzy: skip trigram query filter — nucleo scores all ext-matching candidates.
-                let search_ms = search_start.elapsed().as_millis();
-                write_debug_log(state, &format!(
-                    "find_files_in_ngram_index ext_search mode=fuzzy query={:?} cat_candidates={} ms={}",
-                    query, cat_set.len(), search_ms,
-                ));
-                let mut cat_sorted: Vec<u32> = cat_set.into_iter().collect();
-                cat_sorted.sort_unstable();
-                let candidates: Vec<&DiskObject> = cat_sorted.iter()
-                    .map(|&i| &index_arc.objects[i as usize])
-                    .collect();
-                let items = nucleo_rank_objects(&query, candidates, limit);
-                (items, None)
-            } else {
-                // Exact: Step 2 — intersect with trigram results for the query.
-                let (query_matches, _) = trigram_find_files(
-                    &index_arc,
-                    &query,
-                    &SearchFilter::None,
-                    usize::MAX,
-                    0,
-                );
-                let search_ms = search_start.elapsed().as_millis();
-                write_debug_log(state, &format!(
-                    "find_files_in_ngram_index ext_search mode=exact query={:?} cat_candidates={} query_candidates={} ms={}",
-                    query, cat_set.len(), query_matches.len(), search_ms,
-                ));
-                let query_set: HashSet<u32> = query_matches.into_iter().collect();
-                let mut combined: Vec<u32> = cat_set.into_iter()
-                    .filter(|idx| query_set.contains(idx))
-                    .collect();
-                combined.sort_unstable();
-                let s = offset.min(combined.len());
-                let e = (s + limit).min(combined.len());
-                let next_off = if e < combined.len() { Some(offset + limit) } else { None };
-                let items: Vec<SearchEntry> = combined[s..e].iter()
-                    .map(|&i| search_entry_from_disk_object(&index_arc.objects[i as usize]))
-                    .collect();
-                (items, next_off)
-            }
-        } else if apply_fuzzy {
-            // Fuzzy, folder/other — scan all objects, post-filter by category, then nucleo.
-            let search_ms = search_start.elapsed().as_millis();
-            let candidates: Vec<&DiskObject> = index_arc.objects.iter()
-                .filter(|o| passes_filter(o))
-                .collect();
-            write_debug_log(state, &format!(
-                "find_files_in_ngram_index category_scan mode=fuzzy query={:?} candidates={} ms={}",
-                query, candidates.len(), search_ms,
-            ));
-            let items = nucleo_rank_objects(&query, candidates, limit);
-            (items, None)
-        } else {
-            // Exact, folder/other — overfetch from trigram index and post-filter.
-            let fetch_limit = (limit * 4).max(2000);
-            let (indices, _) = trigram_find_files(
-                &index_arc,
-                &query,
-                &SearchFilter::None,
-                fetch_limit,
-                0,
-            );
-            let search_ms = search_start.elapsed().as_millis();
-            write_debug_log(state, &format!(
-                "find_files_in_ngram_index trigram_search mode=exact query={:?} candidates={} ms={}",
-                query, indices.len(), search_ms,
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let old = load_scan_for_diff(&db_path, Some(old_scan_id))?;
+        let new = load_scan_for_diff(&db_path, new_scan_id)?;
+        let defaults = DiffOptions::default();
+        let options = DiffOptions {
+            min_file_size: min_file_size.unwrap_or(defaults.min_file_size),
+            limit: limit.unwrap_or(defaults.limit),
+            tree_max_children: tree_max_children.unwrap_or(defaults.tree_max_children),
+            tree_max_depth: tree_max_depth.unwrap_or(defaults.tree_max_depth),
+        };
+        Ok::<_, String>(scan_diff::diff_scans(&old, &new, &options))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(report)) => {
+            write_debug_log(&state, &format!(
+                "diff_scans done total_delta={} folders={} new_files={} removed_files={} ms={}",
+                report.total_delta, report.folders.len(),
+                report.new_files_count, report.removed_files_count, t0.elapsed().as_millis(),
             ));
-            let filtered: Vec<SearchEntry> = indices.iter()
-                .map(|&i| &index_arc.objects[i as usize])
-                .filter(|o| passes_filter(o))
-                .take(limit)
-                .map(|o| search_entry_from_disk_object(o))
-                .collect();
-            (filtered, None)
+            Ok(report)
         }
-    } else if apply_fuzzy {
-        // No filter, fuzzy — scan all objects and let nucleo rank them.
-        let search_start = Instant::now();
-        let candidates: Vec<&DiskObject> = index_arc.objects.iter().collect();
-        let search_ms = search_start.elapsed().as_millis();
-        write_debug_log(state, &format!(
-            "find_files_in_ngram_index full_scan mode=fuzzy query={:?} candidates={} ms={}",
-            query, candidates.len(), search_ms,
-        ));
-        let items = nucleo_rank_objects(&query, candidates, limit);
-        (items, None)
-    } else {
-        // No filter, exact — trigram search with pagination.
-        let search_start = Instant::now();
-        let (indices, has_more_raw) = trigram_find_files(
-            &index_arc,
-            &query,
-            &SearchFilter::None,
-            limit,
-            offset,
-        );
-        let search_ms = search_start.elapsed().as_millis();
-        write_debug_log(state, &format!(
-            "find_files_in_ngram_index trigram_search mode=exact query={:?} candidates={} ms={}",
-            query, indices.len(), search_ms,
-        ));
-        let items: Vec<SearchEntry> = indices.iter()
-            .map(|&i| search_entry_from_disk_object(&index_arc.objects[i as usize]))
-            .collect();
-        let next_offset = if has_more_raw { Some(offset + limit) } else { None };
-        (items, next_offset)
-    };
-    let filter_ms = filter_start.elapsed().as_millis();
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error diff_scans: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error diff_scans spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
+}
 
-    let total_ms = total_start.elapsed().as_millis();
-    write_debug_log(state, &format!(
-        "find_files mode={} filter_ms={} total_ms={} count={} query={:?}",
-        if apply_fuzzy { "fuzzy" } else { "exact" },
-        filter_ms, total_ms, items.len(), query,
-    ));
+/// Delta tree below `start_path`, for drilling into a diff from the sunburst.
+#[tauri::command]
+async fn build_delta_tree(
+    state: tauri::State<'_, AppState>,
+    old_scan_id: i64,
+    new_scan_id: Option<i64>,
+    start_path: String,
+    max_children_per_node: u32,
+    max_depth: u32,
+) -> Result<Option<DeltaTreeNode>, String> {
+    let db_path = state.db_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let old = load_scan_for_diff(&db_path, Some(old_scan_id))?;
+        let new = load_scan_for_diff(&db_path, new_scan_id)?;
+        Ok::<_, String>(scan_diff::build_delta_tree(
+            &old,
+            &new,
+            &start_path,
+            max_children_per_node as usize,
+            max_depth as usize,
+        ))
+    })
+    .await;
 
-    Ok(FindFilesResponse { items, next_offset })
+    match result {
+        Ok(Ok(tree)) => Ok(tree),
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error build_delta_tree: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error build_delta_tree spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
 }
 
-fn find_files_in_compressed_text_index(
-    state: &tauri::State<AppState>,
-    query: String,
-    extensions: Option<String>,
-    category: Option<String>,
-    limit: Option<u32>,
-    _use_fuzzy: bool,
-    offset: Option<u32>,
-) -> Result<FindFilesResponse, String> {
-    const DEFAULT_LIMIT: u32 = 500;
-    let limit = limit.unwrap_or(DEFAULT_LIMIT) as usize;
-    let offset = offset.unwrap_or(0) as usize;
-    let total_start = Instant::now();
-
-    write_debug_log(
-        state,
-        &format!(
-            ">>> find_files_in_compressed_text_index start query_len={} limit={} offset={}",
-            query.len(), limit, offset
-        ),
-    );
+/// Entries the last scan could not read, ordered by path.
+#[tauri::command]
+async fn get_scan_errors(
+    state: tauri::State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<ScanError>, String> {
+    let db_path = state.db_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        db::list_scan_errors(&conn, limit.unwrap_or(500)).map_err(|e| e.to_string())
+    })
+    .await;
 
-    let cti_path = resolve_compressed_text_index_path(state);
-    let filter = resolve_search_filter(extensions.as_deref(), category.as_deref());
+    match result {
+        Ok(Ok(errors)) => {
+            write_debug_log(&state, &format!("get_scan_errors done count={}", errors.len()));
+            Ok(errors)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error get_scan_errors: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error get_scan_errors spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
+}
 
-    let (disk_entries, has_more) = cti_find_files(
-        &cti_path,
-        &query,
-        &filter,
-        limit,
-        offset,
-    ).map_err(|e| format!("compressed text index search failed: {:?}", e))?;
+/// Findings in the current scan: broken symlinks, setuid/setgid and world-writable files.
+#[tauri::command]
+async fn get_findings(state: tauri::State<'_, AppState>) -> Result<Vec<Finding>, String> {
+    let db_path = state.db_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        db::get_findings(&conn).map_err(|e| e.to_string())
+    })
+    .await;
 
-    let items: Vec<SearchEntry> = disk_entries
-        .iter()
-        .map(|o| search_entry_from_disk_object(o))
-        .collect();
+    match result {
+        Ok(Ok(findings)) => {
+            write_debug_log(&state, &format!("get_findings done count={}", findings.len()));
+            Ok(findings)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error get_findings: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error get_findings spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
+}
 
-    let next_offset = if has_more { Some(offset + limit) } else { None };
-    let total_ms = total_start.elapsed().as_millis();
+/// Files and bytes of the current scan per owning user or group, largest first.
+#[tauri::command]
+async fn get_owner_usage(
+    state: tauri::State<'_, AppState>,
+    owner_kind: Option<OwnerKind>,
+    size_metric: Option<cutest_disk_tree::SizeMetric>,
+) -> Result<Vec<OwnerUsage>, String> {
+    let db_path = state.db_path.clone();
+    let kind = owner_kind.unwrap_or_default();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        db::usage_by_owner(&conn, kind, size_metric.unwrap_or_default()).map_err(|e| e.to_string())
+    })
+    .await;
 
-    write_debug_log(
-        state,
-        &format!(
-            "find_files_in_compressed_text_index done total_ms={} count={} next_offset={:?}",
-            total_ms, items.len(), next_offset
-        ),
-    );
+    match result {
+        Ok(Ok(usage)) => {
+            write_debug_log(&state, &format!("get_owner_usage done kind={:?} owners={}", kind, usage.len()));
+            Ok(usage)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error get_owner_usage: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error get_owner_usage spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
+}
 
-    Ok(FindFilesResponse {
-        items,
-        next_offset,
+/// The folders holding the most bytes owned by user or group `id`, as `(path, size)`.
+#[tauri::command]
+async fn get_owner_folders(
+    state: tauri::State<'_, AppState>,
+    id: u32,
+    owner_kind: Option<OwnerKind>,
+    limit: Option<usize>,
+    size_metric: Option<cutest_disk_tree::SizeMetric>,
+) -> Result<Vec<(String, u64)>, String> {
+    let db_path = state.db_path.clone();
+    let kind = owner_kind.unwrap_or_default();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        db::largest_folders_of_owner(&conn, kind, id, limit.unwrap_or(20), size_metric.unwrap_or_default())
+            .map_err(|e| e.to_string())
     })
+    .await;
+
+    match result {
+        Ok(Ok(folders)) => {
+            write_debug_log(&state, &format!("get_owner_folders done kind={:?} id={} count={}", kind, id, folders.len()));
+            Ok(folders)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error get_owner_folders: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error get_owner_folders spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
 }
 
-fn resolve_search_filter(
-    extensions: Option<&str>,
-    category: Option<&str>,
-) -> SearchFilter {
-    let manual_exts: Option<Vec<String>> = extensions.and_then(|s| {
-        let cleaned: Vec<String> = s
-            .split(',')
-            .map(|x| x.trim().trim_start_matches('.').to_ascii_lowercase())
-            .filter(|x| !x.is_empty())
-            .collect();
-        if cleaned.is_empty() {
-            None
-        } else {
-            Some(cleaned)
+/// Size of `path` across stored scans, with a linear trend. Given the capacity of the volume
+/// holding `path`, `full_at` estimates when it fills up.
+#[tauri::command]
+async fn get_size_series(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    capacity_bytes: Option<u64>,
+) -> Result<SizeSeries, String> {
+    let db_path = state.db_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        let points = db::get_size_series(&conn, &path).map_err(|e| e.to_string())?;
+        Ok::<_, String>(size_series(&path, points, capacity_bytes))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(series)) => Ok(series),
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error get_size_series: {}", e));
+            Err(e)
         }
-    });
+        Err(e) => {
+            write_debug_log(&state, &format!("error get_size_series spawn: {}", e));
+            Err(e.to_string())
+        }
+    }
+}
 
-    if let Some(exts) = manual_exts {
-        return SearchFilter::Extensions(exts);
+#[tauri::command]
+async fn get_device_usage(state: tauri::State<'_, AppState>) -> Result<Vec<DeviceUsage>, String> {
+    let db_path = state.db_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
+        let files = db::get_file_entries(&conn).map_err(|e| e.to_string())?;
+        Ok::<_, String>(usage_by_device(&files, &list_mounts()))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(report)) => {
+            write_debug_log(&state, &format!("get_device_usage done devices={}", report.len()));
+            Ok(report)
+        }
+        Ok(Err(e)) => {
+            write_debug_log(&state, &format!("error get_device_usage: {}", e));
+            Err(e)
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error get_device_usage spawn: {}", e));
+            Err(e.to_string())
+        }
     }
+}
 
-    let category = category
-        .map(|s| s.trim().to_lowercase())
-        .filter(|s| !s.is_empty() && s != "all");
-    let cat = category.as_deref();
-
-    match cat {
-        None => SearchFilter::None,
-        Some("folder") => SearchFilter::FoldersOnly,
-        Some("other") => SearchFilter::Other,
-        Some(c) => {
-            if let Some(exts) = search_category::extension_set(c) {
-                SearchFilter::Extensions(exts.iter().map(|s| (*s).to_string()).collect())
-            } else {
-                SearchFilter::None
-            }
+fn resolve_exclusions_path(state: &AppState) -> std::path::PathBuf {
+    state.db_path.parent()
+        .map(|p| p.join("exclusions.json"))
+        .unwrap_or_else(|| std::path::PathBuf::from("exclusions.json"))
+}
+
+/// The saved exclusion rules; the built-in ones when the file is missing or unreadable.
+fn load_exclusion_rules(state: &AppState) -> ExclusionRules {
+    ExclusionRules::load(&resolve_exclusions_path(state)).unwrap_or_else(|e| {
+        write_debug_log(state, &format!("error load_exclusion_rules: {}", e));
+        ExclusionRules::recommended()
+    })
+}
+
+#[tauri::command]
+fn get_exclusion_rules(state: tauri::State<AppState>) -> Result<Vec<ExclusionRule>, String> {
+    Ok(load_exclusion_rules(&state).rules().to_vec())
+}
+
+/// Validate and save the exclusion rules. They apply from the next scan on.
+#[tauri::command]
+fn set_exclusion_rules(state: tauri::State<AppState>, rules: Vec<ExclusionRule>) -> Result<(), String> {
+    let result = ExclusionRules::new(rules).and_then(|r| r.save(&resolve_exclusions_path(&state)).map(|_| r));
+    match result {
+        Ok(r) => {
+            write_debug_log(&state, &format!("set_exclusion_rules done rules={}", r.rules().len()));
+            Ok(())
+        }
+        Err(e) => {
+            write_debug_log(&state, &format!("error set_exclusion_rules: {}", e));
+            Err(e)
         }
     }
 }
 
-fn find_files_in_db(
-    state: &tauri::State<AppState>,
+fn resolve_compressed_text_index_path(state: &AppState) -> std::path::PathBuf {
+    state.db_path.parent()
+        .map(|p| p.join("index.compressed-text-index.lz4"))
+        .unwrap_or_else(|| std::path::PathBuf::from("index.compressed-text-index.lz4"))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn find_files(
+    state: tauri::State<AppState>,
     query: String,
     extensions: Option<String>,
     category: Option<String>,
     limit: Option<u32>,
-    _use_fuzzy: bool,
-    offset: Option<u32>,
+    mode: Option<String>,
+    sort: Option<String>,
+    direction: Option<String>,
+    cursor: Option<String>,
 ) -> Result<FindFilesResponse, String> {
-    const DEFAULT_LIMIT: u32 = 500;
-    let limit = limit.unwrap_or(DEFAULT_LIMIT) as usize;
-    let offset = offset.unwrap_or(0) as usize;
-
     let total_start = Instant::now();
+    let mode = match mode.as_deref().map(SearchMode::by_name) {
+        None => SearchMode::default(),
+        Some(Some(mode)) => mode,
+        Some(None) => return Err(format!("Unknown search mode (expected one of {})", SearchMode::NAMES.join(", "))),
+    };
+    let sort = match sort.as_deref().map(SortKey::by_name) {
+        None => SortKey::default(),
+        Some(Some(sort)) => sort,
+        Some(None) => return Err(format!("Unknown sort key (expected one of {})", SortKey::NAMES.join(", "))),
+    };
+    let direction = match direction.as_deref().map(SortDirection::by_name) {
+        None => None,
+        Some(Some(direction)) => Some(direction),
+        Some(None) => return Err(format!("Unknown sort direction (expected one of {})", SortDirection::NAMES.join(", "))),
+    };
+    let request = SearchRequest {
+        sort: SortOrder::new(sort, direction),
+        limit: limit.map_or(DEFAULT_SEARCH_LIMIT, |l| l as usize),
+        cursor: cursor.as_deref().map(Cursor::decode).transpose()?,
+        ..SearchRequest::parse_as(mode, &query, extensions.as_deref(), category.as_deref())?
+    };
 
-    write_debug_log(
-        state,
-        &format!(
-            ">>> find_files_in_db start query_len={} limit={} offset={} category={:?}",
-            query.len(),
-            limit,
-            offset,
-            category.as_deref()
-        ),
-    );
-
-    let db_path = state.db_path.clone();
-    let conn = db::open_db(&db_path).map_err(|e| e.to_string())?;
-
-    let resolve_start = Instant::now();
-    let filter = resolve_search_filter(extensions.as_deref(), category.as_deref());
-    let resolve_ms = resolve_start.elapsed().as_millis();
-
-    let db_start = Instant::now();
-    let (disk_entries, has_more) = sqlite_find_files(
-        &conn,
-        &query,
-        &filter,
-        limit,
-        offset,
-    )
-    .map_err(|e| e.to_string())?;
-    let db_total_ms = db_start.elapsed().as_millis();
-
-    let serialize_start = Instant::now();
-    let items: Vec<SearchEntry> = disk_entries
-        .iter()
-        .map(|o| search_entry_from_disk_object(o))
-        .collect();
-    let serialize_ms = serialize_start.elapsed().as_millis();
-
-    let next_offset = if has_more {
-        Some(offset + limit)
-    } else {
-        None
+    // The in-memory indexes are searched once built; until then, and in `Sqlite` mode, the
+    // database answers.
+    let from_index: Option<SearchResults> = match state.index_mode {
+        SearchIndexMode::Sqlite => None,
+        SearchIndexMode::CompressedText => {
+            Some(CompressedTextBackend::new(resolve_compressed_text_index_path(&state)).search(&request)?)
+        }
+        SearchIndexMode::InMemoryNgrams => {
+            let guard = state.trigram_index.lock().map_err(|e| format!("lock poisoned: {}", e))?;
+            if guard.objects.is_empty() { None } else { Some(guard.search(&request)?) }
+        }
+        SearchIndexMode::InMemorySuffix => {
+            let objects = state.disk_objects.lock().map_err(|e| format!("lock poisoned: {}", e))?.clone();
+            let index = state.name_reverse_index.lock().map_err(|e| format!("lock poisoned: {}", e))?.clone();
+            match (objects, index) {
+                (Some(objects), Some(index)) => Some(SuffixSearch::new(objects, index).search(&request)?),
+                _ => None,
+            }
+        }
+    };
+    let results = match from_index {
+        Some(results) => results,
+        None => {
+            let conn = db::open_db(&state.db_path).map_err(|e| e.to_string())?;
+            SqliteBackend::new(conn)?.search(&request)?
+        }
     };
 
-    let total_ms = total_start.elapsed().as_millis();
+    let items: Vec<SearchEntry> = results.items.iter().map(search_entry_from_disk_object).collect();
     write_debug_log(
-        state,
+        &state,
         &format!(
-            "find_files_in_db profile resolve_ms={} db_total_ms={} serialize_ms={} total_ms={} count={} next_offset={:?}",
-            resolve_ms,
-            db_total_ms,
-            serialize_ms,
-            total_ms,
-            items.len(),
-            next_offset
+            "find_files done index_mode={:?} mode={:?} sort={:?} total_ms={} count={} more={}",
+            state.index_mode, mode, request.sort, total_start.elapsed().as_millis(), items.len(), results.next_cursor.is_some()
         ),
     );
-
-    Ok(FindFilesResponse {
-        items,
-        next_offset,
-    })
+    Ok(FindFilesResponse { items, next_cursor: results.next_cursor.as_ref().map(Cursor::encode) })
 }
 
 #[cfg(debug_assertions)]
@@ -2126,6 +2424,8 @@ pub fn run() {
                 trigram_index: Arc::new(Mutex::new(trigram_build_index(&[]))),
                 phase2_cancel: Mutex::new(Arc::new(AtomicBool::new(false))),
                 is_scanning: Arc::new(AtomicBool::new(false)),
+                scan_control: Mutex::new(ScanControl::new()),
+                keep_partial_scan: AtomicBool::new(false),
                 scan_path_override: scan_path_override.clone(),
                 index_mode,
                 _watcher: Mutex::new(None),
@@ -2263,11 +2563,31 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             scan_directory,
             scan_directory_with_helper,
+            cancel_scan,
+            pause_scan,
+            resume_scan,
             get_scan_status,
             load_cached_scan,
             list_cached_tree_depths,
             build_disk_tree_cached,
             find_files,
+            find_duplicates,
+            find_duplicate_folders,
+            find_similar_images,
+            dedupe_files,
+            list_scans,
+            delete_scan,
+            build_snapshot_tree,
+            diff_scans,
+            build_delta_tree,
+            get_size_series,
+            get_scan_errors,
+            get_findings,
+            get_owner_usage,
+            get_owner_folders,
+            get_device_usage,
+            get_exclusion_rules,
+            set_exclusion_rules,
             debug_log,
             get_debug_log_path,
             debug_log_stats,
@@ -12,9 +12,14 @@ fn search_entry_from_disk_object_maps_file_and_folder() {
         kind: DiskObjectKind::File,
         size: Some(10),
         recursive_size: None,
+        allocated_size: None,
         dev: Some(1),
         ino: Some(2),
         mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     };
     let folder = DiskObject {
         path: "C:/root/folder".to_string(),
@@ -26,9 +31,14 @@ fn search_entry_from_disk_object_maps_file_and_folder() {
         kind: DiskObjectKind::Folder,
         size: None,
         recursive_size: Some(20),
+        allocated_size: None,
         dev: None,
         ino: None,
         mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     };
 
     let file_entry = search_entry_from_disk_object(&file);
@@ -58,9 +68,14 @@ fn make_file(name: &str, ino: u64) -> DiskObject {
         kind: DiskObjectKind::File,
         size: Some(0),
         recursive_size: None,
+        allocated_size: None,
         dev: Some(1),
         ino: Some(ino),
         mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     }
 }
 
@@ -79,13 +94,18 @@ fn suffix_index_skips_folders() {
         kind: DiskObjectKind::Folder,
         size: None,
         recursive_size: Some(0),
+        allocated_size: None,
         dev: None,
         ino: None,
         mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     });
 
     let index = suffix_build_index(&objs);
-    let candidates = suffix_find_files(&index, "notes")
+    let candidates = cutest_disk_tree::core::indexing::suffix::find_files(&index, "notes")
         .expect("should match the file");
 
     assert!(candidates.contains(&0));
@@ -100,74 +120,3 @@ fn get_filesystem_roots_returns_at_least_one() {
         assert!(root.exists(), "root {:?} should exist on disk", root);
     }
 }
-
-// ── nucleo_rank_objects tests ────────────────────────────────────────────────
-
-#[test]
-fn nucleo_rank_objects_matches_subsequence_not_substring() {
-    // "ntes" is a subsequence of "notes" but not a substring — this is the kind
-    // of query that the trigram pre-filter would drop (0 candidates), but nucleo
-    // should still find the match.
-    let objs = vec![
-        make_file("notes.txt", 1),
-        make_file("random.txt", 2),
-        make_file("unrelated.txt", 3),
-    ];
-    let candidates: Vec<&DiskObject> = objs.iter().collect();
-    let results = nucleo_rank_objects("ntes", candidates, 10);
-    let paths: Vec<&str> = results.iter().map(|e| e.path.as_str()).collect();
-    assert!(
-        paths.contains(&"C:/root/notes.txt"),
-        "nucleo should fuzzy-match 'ntes' to 'notes.txt', got: {:?}",
-        paths
-    );
-}
-
-#[test]
-fn nucleo_rank_objects_better_match_ranks_higher() {
-    // "note" is a stronger match for "notes.txt" than for "annotate.txt"
-    // (prefix match vs mid-word). Both contain "note" as a substring, but
-    // nucleo should score the closer match higher.
-    let objs = vec![
-        make_file("annotate.txt", 1),
-        make_file("notes.txt", 2),
-    ];
-    let candidates: Vec<&DiskObject> = objs.iter().collect();
-    let results = nucleo_rank_objects("notes", candidates, 10);
-    assert!(!results.is_empty(), "should have results");
-    assert_eq!(
-        results[0].path, "C:/root/notes.txt",
-        "exact name match should rank first, got: {:?}",
-        results.iter().map(|e| &e.path).collect::<Vec<_>>()
-    );
-}
-
-#[test]
-fn nucleo_rank_objects_respects_limit() {
-    let objs: Vec<DiskObject> = (0..20).map(|i| make_file(&format!("file{}.txt", i), i)).collect();
-    let candidates: Vec<&DiskObject> = objs.iter().collect();
-    let results = nucleo_rank_objects("file", candidates, 5);
-    assert_eq!(results.len(), 5);
-}
-
-#[test]
-fn nucleo_rank_objects_empty_candidates_returns_empty() {
-    let results = nucleo_rank_objects("anything", vec![], 10);
-    assert!(results.is_empty());
-}
-
-#[test]
-fn nucleo_rank_objects_case_insensitive() {
-    let objs = vec![
-        make_file("README.md", 1),
-        make_file("unrelated.txt", 2),
-    ];
-    let candidates: Vec<&DiskObject> = objs.iter().collect();
-    let results = nucleo_rank_objects("readme", candidates, 10);
-    let paths: Vec<&str> = results.iter().map(|e| e.path.as_str()).collect();
-    assert!(
-        paths.contains(&"C:/root/README.md"),
-        "nucleo should match 'readme' to 'README.md' case-insensitively, got: {:?}",
-        paths
-    );
-}
@@ -3,8 +3,8 @@
 //!   sqlite          – on-disk SQLite with name_lower index (files + folders)
 //!   compressed-text – on-disk LZ4-compressed path list (files + folders)
 //!
-//! Reads CUTE_DISK_TREE_SCAN_PATH from .env using the same resolution logic
-//! as the Tauri app.  Runs 3 iterations and prints a structured report.
+//! Reads CUTE_DISK_TREE_SCAN_PATH (and optionally CUTE_DISK_TREE_SCANNER) from .env using
+//! the same resolution logic as the Tauri app.  Runs 3 iterations and prints a structured report.
 //!
 //! Usage:
 //!   cargo run --bin benchmark --release
@@ -19,13 +19,15 @@ use cutest_disk_tree::core::indexing::compressed_text_index::{
     find_files_in_memory as cti_find_files_in_memory,
     InMemoryIndex as CtiInMemoryIndex,
 };
-use cutest_disk_tree::core::indexing::sqlite::{find_files as sqlite_find_files, SearchFilter};
+use cutest_disk_tree::core::indexing::sqlite::find_files as sqlite_find_files;
+use cutest_disk_tree::core::search::SearchFilter;
 use cutest_disk_tree::core::indexing::ngram::{
     build_index as trigram_build_index, find_files as trigram_find_files, TrigramIndex,
 };
 use cutest_disk_tree::core::indexing::suffix::{
     build_index as suffix_build_index, find_files as suffix_find_files, SuffixIndex,
 };
+use cutest_disk_tree::core::scanning::{scan_roots, scanner_by_name, ScanOptions, SCANNER_NAMES};
 use cutest_disk_tree::{db, compute_folder_sizes, DiskObject, DiskObjectKind, FileEntry};
 
 // ── Constants ──────────────────────────────────────────────────────────────
@@ -98,9 +100,14 @@ fn make_disk_object(path_string: String, kind: DiskObjectKind, size: Option<u64>
         kind,
         size,
         recursive_size: None,
+        allocated_size: None,
         dev: None,
         ino: None,
         mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     }
 }
 
@@ -344,14 +351,11 @@ fn bench_sqlite(
 
 /// Compressed-text strategy.
 ///
-/// Build:  write an LZ4-compressed newline-delimited list of all paths (files + folders)
-///         to a temp file.  Multiple shard files may be created for large datasets.
+/// Build:  write an LZ4-compressed newline-delimited list of all paths (files + folders),
+///         each with its kind, size and mtime, to a temp file.  Multiple shard files may be
+///         created for large datasets.
 /// Search: open the compressed file(s) and do a linear scan matching filenames against the
 ///         query.  Results are sorted and paginated.
-///
-/// FAIRNESS NOTE: The SearchFilter parameter is currently unused inside cti_find_files
-/// (parameter is named `_filter`), so all queries effectively run without category filtering –
-/// the same as SearchFilter::None for the other strategies.
 fn bench_cti(
     files: &[FileEntry],
     folder_sizes: &HashMap<PathBuf, u64>,
@@ -560,7 +564,6 @@ fn print_report(
     println!("  cti     : indexes files+folders as a sorted, LZ4-compressed newline list.");
     println!("            Build is a single sequential write.  Search is an O(n) linear scan;");
     println!("            OS page cache warms up after the first query in each iteration.");
-    println!("            SearchFilter is currently unused inside cti_find_files (_filter).");
 
     // ── Detailed per-iteration results ────────────────────────────────────
     println!("\n  DETAILED RESULTS");
@@ -718,11 +721,11 @@ fn main() {
     // ── Shared scan (not charged to any strategy) ─────────────────────────
     println!("Scanning '{}'...", scan_root.display());
     let scan_start = Instant::now();
+    let scanner_name = std::env::var("CUTE_DISK_TREE_SCANNER").unwrap_or_else(|_| SCANNER_NAMES[0].to_string());
+    let scanner = scanner_by_name(&scanner_name)
+        .unwrap_or_else(|| panic!("unknown CUTE_DISK_TREE_SCANNER={} (expected one of {})", scanner_name, SCANNER_NAMES.join(", ")));
     let (files_arc, folder_paths, _roots) =
-        cutest_disk_tree::core::scanning::ignore_scanner::scan_roots_with_ignore(
-            &[scan_root.clone()],
-            |_| {},
-        );
+        scan_roots(scanner.as_ref(), std::slice::from_ref(&scan_root), &ScanOptions::default(), |_| {});
     let scan_ms = scan_start.elapsed().as_millis();
 
     let sizes_start = Instant::now();
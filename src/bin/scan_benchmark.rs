@@ -0,0 +1,182 @@
+//! Benchmark comparing the filesystem scanners (see `SCANNER_NAMES`), with and without
+//! metadata. Results are appended to `benchmark_results.txt`.
+//!
+//! Usage:
+//!   cargo run --bin scan-benchmark --release -- <root> [scanner...]
+
+use cutest_disk_tree::core::scanning::{scanner_by_name, ScanCollector, ScanOptions, SCANNER_NAMES};
+use cutest_disk_tree::IndexMode;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+fn main() {
+    // Default to "C:/Program Files" as requested, but allow overriding via CLI arg.
+    let root = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("C:/Program Files"));
+
+    if !root.is_dir() {
+        eprintln!("Not a directory: {}", root.display());
+        std::process::exit(1);
+    }
+
+    let mut algorithms: Vec<String> = std::env::args().skip(2).collect();
+    if algorithms.is_empty() {
+        algorithms = SCANNER_NAMES.iter().map(|n| n.to_string()).collect();
+    }
+    if let Some(unknown) = algorithms.iter().find(|n| scanner_by_name(n).is_none()) {
+        eprintln!("Unknown scanner: {} (expected one of {})", unknown, SCANNER_NAMES.join(", "));
+        std::process::exit(1);
+    }
+
+    println!("Benchmarking filesystem indexing algorithms");
+    println!("Root: {}", root.display());
+    println!();
+
+    let mut log = open_log_file("benchmark_results.txt");
+
+    // Two modes: without metadata (path + filename only) and with metadata (also file size).
+    let modes = [false, true];
+
+    // Aggregates keyed by (algo, with_metadata)
+    let mut aggregates: HashMap<(String, bool), (usize, u128, usize)> = HashMap::new();
+
+    for with_metadata in modes {
+        println!(
+            "=== Benchmarking with_metadata={} ({} runs) ===",
+            with_metadata, 3
+        );
+
+        for iteration in 1..=3 {
+            println!("--- iteration {} ---", iteration);
+
+            for name in &algorithms {
+                println!("=== {} ===", name);
+
+                let scanner = scanner_by_name(name).expect("checked above");
+                let options = ScanOptions {
+                    metadata: if with_metadata { IndexMode::Full } else { IndexMode::Minimal },
+                    ..ScanOptions::default()
+                };
+                let scan_start = Instant::now();
+                let mut collector = ScanCollector::new(|_| {});
+                scanner.scan(std::slice::from_ref(&root), &options, &mut collector);
+                let files = collector.files.len() + collector.paths_count;
+                let folders = collector.folders.len();
+                let scan_elapsed = scan_start.elapsed();
+
+                let stats = compute_stats(
+                    name,
+                    &root,
+                    files,
+                    folders,
+                    scan_elapsed,
+                    with_metadata,
+                    iteration,
+                );
+
+                println!(
+                    "algo={} root={} with_metadata={} iter={} files={} folders={} paths_total={} scan_ms={} scan_paths_per_s={:.0}",
+                    stats.algo,
+                    stats.root,
+                    stats.with_metadata,
+                    stats.iteration,
+                    stats.files,
+                    stats.folders,
+                    stats.paths_total,
+                    stats.scan_ms,
+                    stats.scan_paths_per_second,
+                );
+                println!();
+
+                let _ = writeln!(
+                    &mut log,
+                    "{},{},{},{},{},{},{},{},{}",
+                    stats.algo,
+                    stats.root,
+                    stats.with_metadata,
+                    stats.iteration,
+                    stats.files,
+                    stats.folders,
+                    stats.paths_total,
+                    stats.scan_ms,
+                    stats.scan_paths_per_second,
+                );
+
+                let key = (stats.algo.clone(), stats.with_metadata);
+                let entry = aggregates.entry(key).or_insert((0, 0, 0));
+                entry.0 += 1;
+                entry.1 += stats.scan_ms;
+                entry.2 += stats.paths_total;
+            }
+        }
+    }
+
+    println!("=== Averages across runs ===");
+    for ((algo, with_metadata), (runs, total_ms, total_paths)) in aggregates {
+        if runs == 0 || total_ms == 0 {
+            continue;
+        }
+        let avg_ms = total_ms as f64 / runs as f64;
+        let total_s = (total_ms as f64) / 1000.0;
+        let paths_per_s = total_paths as f64 / total_s.max(0.000_001);
+        println!(
+            "algo={} with_metadata={} runs={} avg_scan_ms={:.1} avg_paths_per_s={:.0}",
+            algo, with_metadata, runs, avg_ms, paths_per_s
+        );
+    }
+}
+
+struct Stats {
+    algo: String,
+    root: String,
+    with_metadata: bool,
+    iteration: usize,
+    files: usize,
+    folders: usize,
+    paths_total: usize,
+    scan_ms: u128,
+    scan_paths_per_second: f64,
+}
+
+fn compute_stats(
+    algo: &str,
+    root: &Path,
+    files_count: usize,
+    folders: usize,
+    scan_elapsed: Duration,
+    with_metadata: bool,
+    iteration: usize,
+) -> Stats {
+    let paths_total = files_count + folders;
+
+    let scan_ms = scan_elapsed.as_millis();
+    let scan_s = scan_elapsed.as_secs_f64().max(0.000_001);
+    let scan_paths_per_second = paths_total as f64 / scan_s;
+
+    Stats {
+        algo: algo.to_string(),
+        root: root.display().to_string(),
+        with_metadata,
+        iteration,
+        files: files_count,
+        folders,
+        paths_total,
+        scan_ms,
+        scan_paths_per_second,
+    }
+}
+
+fn open_log_file(path: &str) -> std::fs::File {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .expect("failed to open benchmark log file");
+
+    file
+}
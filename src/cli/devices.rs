@@ -0,0 +1,38 @@
+use clap::Args;
+
+use cutest_disk_tree::core::scanning::mounts::{list_mounts, usage_by_device};
+use cutest_disk_tree::db;
+
+use super::output::{format_size, Align, Table};
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct DevicesArgs {}
+
+pub fn run(ctx: &Context, _args: DevicesArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let files = db::get_file_entries(&conn).map_err(|e| e.to_string())?;
+    let report = usage_by_device(&files, &list_mounts());
+
+    ctx.emit(&report, |report| {
+        let mut table = Table::new(&[
+            ("SIZE", Align::Right),
+            ("ON DISK", Align::Right),
+            ("FILES", Align::Right),
+            ("TYPE", Align::Left),
+            ("MOUNT", Align::Left),
+            ("SCANNED", Align::Left),
+        ]);
+        for d in report {
+            table.row(vec![
+                format_size(d.size),
+                format_size(d.allocated_size),
+                d.files_count.to_string(),
+                d.fs_type.clone().unwrap_or_default(),
+                d.mount_point.clone().unwrap_or_else(|| "?".to_string()),
+                d.root.clone(),
+            ]);
+        }
+        table.print();
+    })
+}
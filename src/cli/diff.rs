@@ -0,0 +1,139 @@
+use std::path::Path;
+
+use clap::Args;
+
+use cutest_disk_tree::core::diff::{diff_scans, DeltaTreeNode, DiffOptions};
+use cutest_disk_tree::{db, ScanResult};
+
+use super::output::{format_delta, format_size, Align, Table};
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// Older scan: a snapshot id from `history` or another database file. Defaults to the
+    /// snapshot before the current scan.
+    pub old: Option<String>,
+
+    /// Newer scan, same forms as OLD. Defaults to the current scan.
+    pub new: Option<String>,
+
+    /// Number of entries to list per section.
+    #[arg(long, short = 'n', default_value_t = 20)]
+    pub limit: usize,
+
+    /// Only list new or removed files of at least this many bytes.
+    #[arg(long, default_value_t = 0)]
+    pub min_size: u64,
+
+    /// Also print the delta tree, this many levels deep.
+    #[arg(long, value_name = "DEPTH")]
+    pub tree: Option<usize>,
+}
+
+/// Resolve a scan argument: a snapshot id in the current database, or another database file.
+fn load(ctx: &Context, spec: &str) -> Result<ScanResult, String> {
+    if let Ok(id) = spec.parse::<i64>() {
+        let snapshot = db::open_snapshot(&ctx.db_path, id)
+            .map_err(|e| format!("{}: {}", ctx.db_path.display(), e))?
+            .ok_or_else(|| format!("no snapshot with id {}", id))?;
+        return snapshot.scan_result().map_err(|e| e.to_string());
+    }
+    let path = Path::new(spec);
+    if !path.is_file() {
+        return Err(format!("not a snapshot id or database: {}", spec));
+    }
+    let conn = db::open_db(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    db::get_scan_result(&conn)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("no scan found in {}", path.display()))
+}
+
+pub fn run(ctx: &Context, args: DiffArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let old = match &args.old {
+        Some(spec) => load(ctx, spec)?,
+        None => {
+            // The newest snapshot is the current scan; compare against the one before it.
+            let scans = db::list_scans(&conn).map_err(|e| e.to_string())?;
+            let previous = scans
+                .get(1)
+                .ok_or("only one scan stored; scan again later or pass an older database")?;
+            load(ctx, &previous.id.to_string())?
+        }
+    };
+    let new = match &args.new {
+        Some(spec) => load(ctx, spec)?,
+        None => db::get_scan_result(&conn)
+            .map_err(|e| e.to_string())?
+            .ok_or("no current scan")?,
+    };
+
+    let options = DiffOptions {
+        min_file_size: args.min_size,
+        limit: args.limit,
+        tree_max_depth: args.tree.unwrap_or(0),
+        ..DiffOptions::default()
+    };
+    let report = diff_scans(&old, &new, &options);
+
+    ctx.emit(&report, |r| {
+        println!(
+            "Total change: {} ({} -> {})",
+            format_delta(r.total_delta),
+            format_size(r.old_total),
+            format_size(r.new_total),
+        );
+        println!(
+            "New files: {} ({}), removed files: {} ({})",
+            r.new_files_count,
+            format_size(r.new_files_bytes),
+            r.removed_files_count,
+            format_size(r.removed_files_bytes),
+        );
+        for (title, rows) in [
+            ("Top growth", &r.top_growth),
+            ("Top shrinkage", &r.top_shrinkage),
+            ("Largest new files", &r.new_files),
+            ("Largest removed files", &r.removed_files),
+        ] {
+            if rows.is_empty() {
+                continue;
+            }
+            println!();
+            println!("{}", title);
+            let mut table = Table::new(&[("CHANGE", Align::Right), ("SIZE", Align::Right), ("PATH", Align::Left)]);
+            for d in rows.iter() {
+                table.row(vec![format_delta(d.delta), format_size(d.new_size), d.path.clone()]);
+            }
+            table.print();
+        }
+        if args.tree.is_some() {
+            for root in &r.tree {
+                println!();
+                println!("{}  {}", format_delta(root.delta), root.path);
+                print_children(root, "");
+            }
+        }
+    })
+}
+
+fn print_children(node: &DeltaTreeNode, prefix: &str) {
+    let Some(children) = &node.children else {
+        return;
+    };
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+        println!(
+            "{}{}{}  {} ({})",
+            prefix,
+            branch,
+            child.name,
+            format_delta(child.delta),
+            format_size(child.new_size),
+        );
+        if !child.path.ends_with("__other") {
+            print_children(child, &format!("{}{}", prefix, indent));
+        }
+    }
+}
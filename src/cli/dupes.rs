@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use clap::Args;
+
+use cutest_disk_tree::core::duplicates::folders::find_duplicate_folders_with_cache;
+use cutest_disk_tree::core::duplicates::similar_images::{find_similar_images, SimilarImageOptions};
+use cutest_disk_tree::core::duplicates::{find_duplicates_with_cache, DuplicateOptions, DuplicateReport};
+use cutest_disk_tree::db;
+
+use super::output::format_size;
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct DupesArgs {
+    /// Report whole duplicated folders instead of single files.
+    #[arg(long, conflicts_with = "images")]
+    pub folders: bool,
+
+    /// Report visually similar images (resized or re-encoded copies).
+    #[arg(long)]
+    pub images: bool,
+
+    /// Ignore files (or folders) smaller than this many bytes.
+    #[arg(long, default_value_t = 1)]
+    pub min_size: u64,
+
+    /// Maximum perceptual-hash distance for `--images` (0-64).
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(0..=64))]
+    pub max_distance: u32,
+
+    /// Number of groups to print in table output.
+    #[arg(long, short = 'n', default_value_t = 50)]
+    pub limit: usize,
+}
+
+pub fn run(ctx: &Context, args: DupesArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let files = db::get_file_entries(&conn).map_err(|e| e.to_string())?;
+    let options = DuplicateOptions { min_size: args.min_size };
+
+    if args.images {
+        let options = SimilarImageOptions {
+            max_distance: args.max_distance,
+            min_size: args.min_size,
+        };
+        let report = find_similar_images(&files, &options);
+        return ctx.emit(&report, |r| print_file_groups(r, args.limit));
+    }
+
+    let mut cache = db::read_file_hashes(&conn).map_err(|e| e.to_string())?;
+    if args.folders {
+        let folder_sizes: HashMap<PathBuf, u64> = db::get_folders(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(p, s)| (PathBuf::from(p), s))
+            .collect();
+        let report = find_duplicate_folders_with_cache(&files, &folder_sizes, &options, &mut cache, |_| {});
+        db::write_file_hashes(&conn, &mut cache).map_err(|e| e.to_string())?;
+        return ctx.emit(&report, |r| {
+            for group in r.groups.iter().take(args.limit) {
+                println!(
+                    "{} x {} ({} files), {} wasted",
+                    group.folders.len(),
+                    format_size(group.size),
+                    group.file_count,
+                    format_size(group.wasted_bytes),
+                );
+                for folder in &group.folders {
+                    println!("  {}", folder);
+                }
+            }
+            println!(
+                "{} groups, {} wasted",
+                r.groups.len(),
+                format_size(r.total_wasted_bytes)
+            );
+        });
+    }
+
+    let report = find_duplicates_with_cache(&files, &options, &mut cache, |_| {});
+    db::write_file_hashes(&conn, &mut cache).map_err(|e| e.to_string())?;
+    ctx.emit(&report, |r| print_file_groups(r, args.limit))
+}
+
+fn print_file_groups(report: &DuplicateReport, limit: usize) {
+    for group in report.groups.iter().take(limit) {
+        println!(
+            "{} x {}, {} wasted",
+            group.files.len(),
+            format_size(group.size),
+            format_size(group.wasted_bytes),
+        );
+        for file in &group.files {
+            println!("  {}", file.path);
+        }
+    }
+    println!(
+        "{} groups, {} wasted ({} files read, {} from cache)",
+        report.groups.len(),
+        format_size(report.total_wasted_bytes),
+        report.files_hashed,
+        report.cache_hits,
+    );
+}
@@ -0,0 +1,61 @@
+use clap::Args;
+use serde::Serialize;
+
+use cutest_disk_tree::core::scanning::{ScanError, ScanErrorKind};
+use cutest_disk_tree::db;
+
+use super::output::{Align, Table};
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct ErrorsArgs {
+    /// Number of entries to show.
+    #[arg(long, short = 'n', default_value_t = 50)]
+    pub limit: usize,
+}
+
+#[derive(Serialize)]
+struct ErrorsOutput {
+    counts: Vec<KindCount>,
+    errors: Vec<ScanError>,
+}
+
+#[derive(Serialize)]
+struct KindCount {
+    kind: ScanErrorKind,
+    count: u64,
+}
+
+pub fn run(ctx: &Context, args: ErrorsArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let out = ErrorsOutput {
+        counts: db::count_scan_errors(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(kind, count)| KindCount { kind, count })
+            .collect(),
+        errors: db::list_scan_errors(&conn, args.limit).map_err(|e| e.to_string())?,
+    };
+
+    ctx.emit(&out, |o| {
+        if o.errors.is_empty() {
+            println!("The last scan read everything it found");
+            return;
+        }
+        let total: u64 = o.counts.iter().map(|c| c.count).sum();
+        let counts: Vec<String> = o.counts.iter().map(|c| format!("{} {}", c.count, c.kind.as_str())).collect();
+        println!("{} entries could not be read: {}", total, counts.join(", "));
+        let mut table = Table::new(&[("KIND", Align::Left), ("ERRNO", Align::Right), ("PATH", Align::Left)]);
+        for e in &o.errors {
+            table.row(vec![
+                e.kind.as_str().to_string(),
+                e.errno.map(|n| n.to_string()).unwrap_or_default(),
+                e.path.to_string_lossy().to_string(),
+            ]);
+        }
+        table.print();
+        if (o.errors.len() as u64) < total {
+            println!("… and {} more (use --limit)", total - o.errors.len() as u64);
+        }
+    })
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+
+use cutest_disk_tree::{db, DiskObject, DiskObjectKind};
+
+use super::Context;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Output format. `--json` is a shorthand for `--format json`.
+    #[arg(long, short, value_enum, default_value = "csv")]
+    pub format: ExportFormat,
+
+    /// File to write to. Defaults to stdout.
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+pub fn run(ctx: &Context, args: ExportArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let mut objects = db::get_disk_objects(&conn).map_err(|e| e.to_string())?;
+    objects.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let format = if ctx.json { ExportFormat::Json } else { args.format };
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    write_objects(&mut out, &objects, format).map_err(|e| e.to_string())?;
+    out.flush().map_err(|e| e.to_string())
+}
+
+fn write_objects<W: Write>(out: &mut W, objects: &[DiskObject], format: ExportFormat) -> io::Result<()> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, objects)?;
+            writeln!(out)
+        }
+        ExportFormat::Ndjson => {
+            for obj in objects {
+                serde_json::to_writer(&mut *out, obj)?;
+                writeln!(out)?;
+            }
+            Ok(())
+        }
+        ExportFormat::Csv => {
+            writeln!(out, "kind,path,size,allocated_size,dev,ino,mtime,link_target")?;
+            for obj in objects {
+                let (kind, size) = match obj.kind {
+                    DiskObjectKind::File => ("file", obj.size),
+                    DiskObjectKind::Folder => ("folder", obj.recursive_size),
+                    DiskObjectKind::Symlink => ("symlink", None),
+                    DiskObjectKind::Collapsed => ("collapsed", obj.size),
+                };
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{}",
+                    kind,
+                    csv_field(&obj.path),
+                    opt(size),
+                    opt(obj.allocated_size),
+                    opt(obj.dev),
+                    opt(obj.ino),
+                    opt(obj.mtime),
+                    obj.link_target.as_deref().map(csv_field).unwrap_or_default(),
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn opt<T: ToString>(v: Option<T>) -> String {
+    v.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break (RFC 4180).
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
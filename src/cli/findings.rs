@@ -0,0 +1,48 @@
+use clap::Args;
+use serde::Serialize;
+
+use cutest_disk_tree::core::findings::Finding;
+use cutest_disk_tree::db;
+
+use super::output::{Align, Table};
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct FindingsArgs {
+    /// Number of findings to show.
+    #[arg(long, short = 'n', default_value_t = 50)]
+    pub limit: usize,
+}
+
+#[derive(Serialize)]
+struct FindingsOutput {
+    total: usize,
+    findings: Vec<Finding>,
+}
+
+pub fn run(ctx: &Context, args: FindingsArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let mut findings = db::get_findings(&conn).map_err(|e| e.to_string())?;
+    let total = findings.len();
+    findings.truncate(args.limit);
+    let out = FindingsOutput { total, findings };
+
+    ctx.emit(&out, |o| {
+        if o.findings.is_empty() {
+            println!("Nothing to report in the last scan");
+            return;
+        }
+        let mut table = Table::new(&[("KIND", Align::Left), ("PATH", Align::Left), ("DETAIL", Align::Left)]);
+        for f in &o.findings {
+            table.row(vec![
+                f.kind.as_str().to_string(),
+                f.path.to_string_lossy().to_string(),
+                f.detail.clone(),
+            ]);
+        }
+        table.print();
+        if o.findings.len() < o.total {
+            println!("… and {} more (use --limit)", o.total - o.findings.len());
+        }
+    })
+}
@@ -0,0 +1,102 @@
+use chrono::{Local, TimeZone};
+use clap::Args;
+use serde::Serialize;
+
+use cutest_disk_tree::db::{self, RetentionPolicy, ScanInfo};
+
+use super::output::{format_size, Align, Table};
+use super::Context;
+
+/// Retention flags shared by `scan` (applied after every scan) and `history --prune`.
+#[derive(Args, Debug, Clone, Copy)]
+pub struct RetentionArgs {
+    /// Always keep this many of the newest snapshots.
+    #[arg(long, default_value_t = RetentionPolicy::default().keep_last)]
+    pub keep_last: usize,
+
+    /// Also keep the newest snapshot of each of the last N days.
+    #[arg(long, default_value_t = RetentionPolicy::default().keep_daily)]
+    pub keep_daily: u32,
+}
+
+impl RetentionArgs {
+    pub fn policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            keep_last: self.keep_last,
+            keep_daily: self.keep_daily,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// Delete snapshots the retention flags don't keep.
+    #[arg(long, conflicts_with = "delete")]
+    pub prune: bool,
+
+    /// Delete the snapshot with this id.
+    #[arg(long, value_name = "ID")]
+    pub delete: Option<i64>,
+
+    #[command(flatten)]
+    pub retention: RetentionArgs,
+}
+
+#[derive(Serialize)]
+struct HistoryOutput {
+    scans: Vec<ScanInfo>,
+    removed: usize,
+}
+
+pub fn run(ctx: &Context, args: HistoryArgs) -> Result<(), String> {
+    let conn = ctx.open_db()?;
+    let removed = if let Some(id) = args.delete {
+        if !db::delete_scan(&conn, id).map_err(|e| e.to_string())? {
+            return Err(format!("no snapshot with id {}", id));
+        }
+        1
+    } else if args.prune {
+        let now = chrono::Utc::now().timestamp_millis();
+        db::apply_retention(&conn, &args.retention.policy(), now).map_err(|e| e.to_string())?
+    } else {
+        0
+    };
+
+    let out = HistoryOutput {
+        scans: db::list_scans(&conn).map_err(|e| e.to_string())?,
+        removed,
+    };
+    ctx.emit(&out, |out| {
+        if out.removed > 0 {
+            println!("Removed {} snapshot(s)", out.removed);
+        }
+        let mut table = Table::new(&[
+            ("ID", Align::Right),
+            ("SCANNED", Align::Left),
+            ("FILES", Align::Right),
+            ("SIZE", Align::Right),
+            ("ROOTS", Align::Left),
+        ]);
+        for scan in &out.scans {
+            table.row(vec![
+                scan.id.to_string(),
+                match scan.complete {
+                    true => format_timestamp(scan.created_at),
+                    false => format!("{} (partial)", format_timestamp(scan.created_at)),
+                },
+                scan.files_count.to_string(),
+                format_size(scan.total_size),
+                scan.roots.join(", "),
+            ]);
+        }
+        table.print();
+    })
+}
+
+fn format_timestamp(ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
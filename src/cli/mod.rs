@@ -0,0 +1,184 @@
+mod devices;
+mod diff;
+mod dupes;
+mod errors;
+mod export;
+mod findings;
+mod history;
+mod output;
+mod owners;
+mod rules;
+mod scan;
+mod search;
+mod top;
+mod tree;
+mod trend;
+mod tui;
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use cutest_disk_tree::{db, SizeMetric};
+
+/// Environment variable overriding the database location (same as `--db`).
+const DB_PATH_ENV: &str = "CUTE_DISK_TREE_DB_PATH";
+
+/// File name of the saved exclusion rules, next to the database.
+const RULES_FILE: &str = "exclusions.json";
+
+/// App identifier of the desktop app; the CLI shares its database by default.
+const APP_IDENTIFIER: &str = "com.cutest.disk-tree";
+
+#[derive(Parser, Debug)]
+#[command(name = "cutest-disk-tree", version, about = "Disk usage and duplicate-file analyzer")]
+pub struct Cli {
+    /// SQLite database to read and write. Defaults to $CUTE_DISK_TREE_DB_PATH, then the
+    /// desktop app's `index.db`.
+    #[arg(long, global = true)]
+    pub db: Option<PathBuf>,
+
+    /// Print JSON instead of a human-readable table.
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Report space allocated on disk (like `du`) instead of apparent file sizes.
+    #[arg(long, global = true)]
+    pub allocated: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scan one or more directories and store the result in the database.
+    Scan(scan::ScanArgs),
+    /// Largest files or folders of the last scan.
+    Top(top::TopArgs),
+    /// Folder tree of the last scan, largest children first.
+    Tree(tree::TreeArgs),
+    /// Search the last scan by file or folder name.
+    Search(search::SearchArgs),
+    /// Duplicate files, duplicate folders or similar images in the last scan.
+    Dupes(dupes::DupesArgs),
+    /// What grew, shrank, appeared or disappeared between two scans.
+    Diff(diff::DiffArgs),
+    /// Totals of the last scan per filesystem, matched against the mount table.
+    Devices(devices::DevicesArgs),
+    /// Size of one folder across stored scans, with a growth trend.
+    Trend(trend::TrendArgs),
+    /// List, prune or delete stored scan snapshots.
+    History(history::HistoryArgs),
+    /// Entries the last scan could not read, such as folders it had no permission to list.
+    Errors(errors::ErrorsArgs),
+    /// Things worth a look in the last scan: broken symlinks, setuid/setgid and world-writable files.
+    Findings(findings::FindingsArgs),
+    /// Usage of the last scan per owning user or group, or the largest folders of one owner.
+    Owners(owners::OwnersArgs),
+    /// Write every file and folder of the last scan as CSV, JSON or NDJSON.
+    Export(export::ExportArgs),
+    /// List or edit the exclusion rules `scan` applies.
+    Rules(rules::RulesArgs),
+    /// Browse the last scan interactively, ncdu-style, and delete what you don't need.
+    Tui(tui::TuiArgs),
+}
+
+pub fn run(cli: Cli) -> Result<(), String> {
+    let ctx = Context {
+        db_path: cli.db.clone().unwrap_or_else(default_db_path),
+        json: cli.json,
+        metric: if cli.allocated { SizeMetric::Allocated } else { SizeMetric::Apparent },
+    };
+    match cli.command {
+        Command::Scan(args) => scan::run(&ctx, args),
+        Command::Top(args) => top::run(&ctx, args),
+        Command::Tree(args) => tree::run(&ctx, args),
+        Command::Search(args) => search::run(&ctx, args),
+        Command::Dupes(args) => dupes::run(&ctx, args),
+        Command::Diff(args) => diff::run(&ctx, args),
+        Command::Errors(args) => errors::run(&ctx, args),
+        Command::Findings(args) => findings::run(&ctx, args),
+        Command::Owners(args) => owners::run(&ctx, args),
+        Command::Export(args) => export::run(&ctx, args),
+        Command::History(args) => history::run(&ctx, args),
+        Command::Trend(args) => trend::run(&ctx, args),
+        Command::Devices(args) => devices::run(&ctx, args),
+        Command::Rules(args) => rules::run(&ctx, args),
+        Command::Tui(args) => tui::run(&ctx, args),
+    }
+}
+
+pub struct Context {
+    pub db_path: PathBuf,
+    pub json: bool,
+    pub metric: SizeMetric,
+}
+
+impl Context {
+    /// Open the database, creating it (and its folder) if needed.
+    pub fn open_db(&self) -> Result<Connection, String> {
+        if let Some(parent) = self.db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+        }
+        db::open_db(&self.db_path).map_err(|e| format!("{}: {}", self.db_path.display(), e))
+    }
+
+    /// Exclusion rules live next to the database, where the desktop app keeps them too.
+    pub fn rules_path(&self) -> PathBuf {
+        self.db_path.with_file_name(RULES_FILE)
+    }
+
+    /// Open the database and fail with a hint when nothing has been scanned yet.
+    pub fn open_scanned_db(&self) -> Result<Connection, String> {
+        let conn = self.open_db()?;
+        if !db::has_disk_objects(&conn).map_err(|e| e.to_string())? {
+            return Err(format!(
+                "no scan found in {}; run `cutest-disk-tree scan <path>` first",
+                self.db_path.display()
+            ));
+        }
+        Ok(conn)
+    }
+
+    /// Print `value` as JSON with `--json`, otherwise run `human`.
+    pub fn emit<T: Serialize>(&self, value: &T, human: impl FnOnce(&T)) -> Result<(), String> {
+        if self.json {
+            let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+            println!("{}", text);
+        } else {
+            human(value);
+        }
+        Ok(())
+    }
+}
+
+/// Same location as the Tauri app's `index.db` (see the README table).
+fn default_db_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(DB_PATH_ENV).filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+    data_dir()
+        .map(|d| d.join(APP_IDENTIFIER).join("index.db"))
+        .unwrap_or_else(|| PathBuf::from("index.db"))
+}
+
+#[cfg(windows)]
+fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Application Support"))
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+}
@@ -0,0 +1,26 @@
+use super::*;
+
+#[test]
+fn format_size_picks_binary_units() {
+    assert_eq!(format_size(0), "0 B");
+    assert_eq!(format_size(1023), "1023 B");
+    assert_eq!(format_size(1536), "1.5 KiB");
+    assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+}
+
+#[test]
+fn format_delta_is_signed() {
+    assert_eq!(format_delta(2048), "+2.0 KiB");
+    assert_eq!(format_delta(-12), "-12 B");
+}
+
+#[test]
+fn table_pads_columns_and_right_aligns_numbers() {
+    let mut table = Table::new(&[("SIZE", Align::Right), ("PATH", Align::Left)]);
+    table.row(vec!["1.5 KiB".into(), "/a".into()]);
+    table.row(vec!["3 B".into(), "/longer/path".into()]);
+    assert_eq!(
+        table.render(),
+        "   SIZE  PATH\n-------  ------------\n1.5 KiB  /a\n    3 B  /longer/path\n"
+    );
+}
@@ -0,0 +1,79 @@
+use clap::Args;
+use serde::Serialize;
+
+use cutest_disk_tree::core::owners::{resolve_owner, OwnerKind};
+use cutest_disk_tree::db;
+
+use super::output::{format_size, Align, Table};
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct OwnersArgs {
+    /// Show the largest folders of this user (or group, with `--group`), by name or id,
+    /// instead of the totals of every owner.
+    pub owner: Option<String>,
+
+    /// Group by owning group instead of owning user.
+    #[arg(long, short)]
+    pub group: bool,
+
+    /// Number of entries to show.
+    #[arg(long, short = 'n', default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Serialize)]
+struct OwnerFolder {
+    path: String,
+    size: u64,
+}
+
+pub fn run(ctx: &Context, args: OwnersArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let kind = if args.group { OwnerKind::Group } else { OwnerKind::User };
+
+    if let Some(owner) = &args.owner {
+        let label = if args.group { "group" } else { "user" };
+        let id = resolve_owner(kind, owner).ok_or_else(|| format!("unknown {}: {}", label, owner))?;
+        let folders: Vec<OwnerFolder> = db::largest_folders_of_owner(&conn, kind, id, args.limit, ctx.metric)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(path, size)| OwnerFolder { path, size })
+            .collect();
+        return ctx.emit(&folders, |folders| {
+            if folders.is_empty() {
+                println!("{} owns no files in the last scan", owner);
+                return;
+            }
+            let mut table = Table::new(&[("SIZE", Align::Right), ("PATH", Align::Left)]);
+            for f in folders {
+                table.row(vec![format_size(f.size), f.path.clone()]);
+            }
+            table.print();
+        });
+    }
+
+    let mut usage = db::usage_by_owner(&conn, kind, ctx.metric).map_err(|e| e.to_string())?;
+    usage.truncate(args.limit);
+    ctx.emit(&usage, |usage| {
+        if usage.is_empty() {
+            println!("The last scan recorded no owners (owners are only recorded on Unix)");
+            return;
+        }
+        let mut table = Table::new(&[
+            ("SIZE", Align::Right),
+            ("FILES", Align::Right),
+            ("ID", Align::Right),
+            ("NAME", Align::Left),
+        ]);
+        for u in usage {
+            table.row(vec![
+                format_size(u.size),
+                u.files_count.to_string(),
+                u.id.to_string(),
+                u.name.clone().unwrap_or_default(),
+            ]);
+        }
+        table.print();
+    })
+}
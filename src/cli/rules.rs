@@ -0,0 +1,102 @@
+use clap::{Args, Subcommand};
+
+use cutest_disk_tree::core::scanning::exclusions::{
+    ExclusionRule, ExclusionRules, PatternKind, RuleAction,
+};
+
+use super::output::{format_size, Align, Table};
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct RulesArgs {
+    #[command(subcommand)]
+    pub action: Option<RulesAction>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RulesAction {
+    /// Add a rule at the end of the list.
+    Add(AddArgs),
+    /// Remove the rule with this number (as listed).
+    Remove { number: usize },
+    /// Go back to the built-in rules (collapse `node_modules` and `.venv`).
+    Reset,
+}
+
+#[derive(Args, Debug)]
+pub struct AddArgs {
+    /// Gitignore-style glob (`*.iso`, `target/`, `/home/*/.cache`), or an absolute path with
+    /// `--prefix`.
+    pub pattern: String,
+
+    /// Treat the pattern as an absolute path prefix instead of a glob.
+    #[arg(long)]
+    pub prefix: bool,
+
+    /// Keep matching folders as a single node with their total size instead of leaving them out.
+    #[arg(long)]
+    pub collapse: bool,
+
+    /// Only match files of at least this many bytes.
+    #[arg(long, value_name = "BYTES")]
+    pub min_size: Option<u64>,
+
+    /// Only match entries not modified for this many days.
+    #[arg(long, value_name = "DAYS")]
+    pub older_than: Option<u64>,
+}
+
+pub fn run(ctx: &Context, args: RulesArgs) -> Result<(), String> {
+    let path = ctx.rules_path();
+    let mut rules = ExclusionRules::load(&path)?;
+    if let Some(action) = args.action {
+        let mut list = rules.rules().to_vec();
+        match action {
+            RulesAction::Add(add) => list.push(ExclusionRule {
+                pattern: add.pattern,
+                kind: if add.prefix { PatternKind::Prefix } else { PatternKind::Glob },
+                action: if add.collapse { RuleAction::Collapse } else { RuleAction::Exclude },
+                min_size: add.min_size,
+                older_than_days: add.older_than,
+            }),
+            RulesAction::Remove { number } => {
+                if number == 0 || number > list.len() {
+                    return Err(format!("no rule number {}", number));
+                }
+                list.remove(number - 1);
+            }
+            RulesAction::Reset => list = ExclusionRules::recommended().rules().to_vec(),
+        }
+        rules = ExclusionRules::new(list)?;
+        rules.save(&path)?;
+    }
+
+    ctx.emit(&rules.rules(), |list| {
+        let mut table = Table::new(&[
+            ("#", Align::Right),
+            ("ACTION", Align::Left),
+            ("PATTERN", Align::Left),
+            ("CONDITIONS", Align::Left),
+        ]);
+        for (i, rule) in list.iter().enumerate() {
+            let pattern = match rule.kind {
+                PatternKind::Glob => rule.pattern.clone(),
+                PatternKind::Prefix => format!("{} (prefix)", rule.pattern),
+            };
+            let mut conditions = Vec::new();
+            if let Some(size) = rule.min_size {
+                conditions.push(format!(">= {}", format_size(size)));
+            }
+            if let Some(days) = rule.older_than_days {
+                conditions.push(format!("older than {}d", days));
+            }
+            table.row(vec![
+                (i + 1).to_string(),
+                format!("{:?}", rule.action).to_lowercase(),
+                pattern,
+                conditions.join(", "),
+            ]);
+        }
+        table.print();
+    })
+}
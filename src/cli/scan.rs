@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use serde::Serialize;
+
+use cutest_disk_tree::core::scanning::exclusions::ExclusionRules;
+use cutest_disk_tree::core::scanning::checkpoint::{scan_resumable, DoneFolder};
+use cutest_disk_tree::core::scanning::{scanner_by_name, ScanCollector, ScanError, ScanOptions, SymlinkPolicy, SCANNER_NAMES};
+use cutest_disk_tree::core::trend::{select_tracked_folders, TrackingOptions};
+use cutest_disk_tree::{compute_folder_sizes, db, ScanProgress};
+
+use super::history::RetentionArgs;
+use super::output::format_size;
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct ScanArgs {
+    /// Directories to scan. Becomes the current scan; earlier scans stay available as
+    /// snapshots (see `history`).
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Do not print progress to stderr (also off when stderr is not a terminal).
+    #[arg(long, short)]
+    pub quiet: bool,
+
+    /// Stay on the filesystem of each path; other mounts below it are skipped (like `du -x`).
+    #[arg(long, short = 'x')]
+    pub one_file_system: bool,
+
+    /// Scanning backend.
+    #[arg(long, default_value = SCANNER_NAMES[0], value_parser = SCANNER_NAMES)]
+    pub scanner: String,
+
+    /// Worker threads for the parallel scanners (0: the scanner's default).
+    #[arg(long, default_value_t = 0)]
+    pub threads: usize,
+
+    /// What to do with symlinks: leave them out, record each link as an entry, or follow them
+    /// (entering every folder once; loops and broken links are recorded as links).
+    #[arg(long, default_value = SymlinkPolicy::NAMES[0], value_parser = SymlinkPolicy::NAMES)]
+    pub symlinks: String,
+
+    /// Ignore the exclusion rules (see `rules`) and scan everything.
+    #[arg(long)]
+    pub no_exclusions: bool,
+
+    /// Stop the scan after this many seconds.
+    #[arg(long, value_name = "SECS")]
+    pub time_limit: Option<u64>,
+
+    /// When the scan is stopped early, keep what it found as an incomplete snapshot instead
+    /// of discarding it. The current scan is left as it was either way.
+    #[arg(long, requires = "time_limit")]
+    pub keep_partial: bool,
+
+    /// Save finished folders to the database while scanning, so a scan that was interrupted
+    /// (crash, reboot, `--time-limit`) continues where it stopped when run again with the same
+    /// paths. Uses its own parallel walker instead of `--scanner`.
+    #[arg(long, conflicts_with = "scanner")]
+    pub resumable: bool,
+
+    /// Only read folders whose modification time changed since the last incremental scan and
+    /// reuse the stored entries of the rest. Files rewritten in place (which leaves their
+    /// folder's mtime alone) keep their old size until their folder changes.
+    #[arg(long, conflicts_with_all = ["scanner", "resumable"])]
+    pub incremental: bool,
+
+    #[command(flatten)]
+    pub retention: RetentionArgs,
+}
+
+#[derive(Serialize)]
+struct ScanOutput {
+    scan_id: i64,
+    roots: Vec<String>,
+    files_count: u64,
+    folders_count: u64,
+    total_size: u64,
+    elapsed_ms: u64,
+    db_path: String,
+    snapshots_removed: usize,
+    complete: bool,
+    /// Folders an incremental scan had to read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    folders_read: Option<u64>,
+    /// Entries that could not be read; listed by `errors`.
+    errors_count: u64,
+    /// Symlinks recorded as entries; broken ones are listed by `findings`.
+    symlinks_count: u64,
+}
+
+pub fn run(ctx: &Context, args: ScanArgs) -> Result<(), String> {
+    let mut roots = Vec::with_capacity(args.paths.len());
+    for path in &args.paths {
+        let root = canonical_root(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if !root.is_dir() {
+            return Err(format!("not a directory: {}", path.display()));
+        }
+        roots.push(root);
+    }
+    // A root inside another root would be scanned (and stored) twice.
+    roots.sort();
+    roots.dedup_by(|later, earlier| later.starts_with(earlier));
+
+    let start = Instant::now();
+    let quiet = args.quiet || !std::io::stderr().is_terminal();
+    let exclusions = if args.no_exclusions {
+        ExclusionRules::none()
+    } else {
+        ExclusionRules::load(&ctx.rules_path())?
+    };
+    let options = ScanOptions {
+        one_file_system: args.one_file_system,
+        exclusions: Arc::new(exclusions),
+        threads: args.threads,
+        symlinks: SymlinkPolicy::by_name(&args.symlinks).unwrap_or_default(),
+        ..ScanOptions::default()
+    };
+    if let Some(secs) = args.time_limit {
+        let control = options.control.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_secs(secs));
+            control.cancel();
+        });
+    }
+    let report = |p: ScanProgress| {
+        if quiet {
+            return;
+        }
+        let mut err = std::io::stderr();
+        match (&p.status, &p.current_path) {
+            (Some(status), _) if !status.is_empty() => {
+                let _ = write!(err, "\r\x1b[K{}", status);
+            }
+            (_, Some(path)) => {
+                let _ = write!(err, "\r\x1b[K{} files  {}", p.files_count, path);
+            }
+            _ => {}
+        }
+        let _ = err.flush();
+    };
+    let conn = ctx.open_db()?;
+    let mut incremental: Option<(Vec<DoneFolder>, HashSet<PathBuf>)> = None;
+    let mut collector = ScanCollector::new(report);
+    if args.incremental {
+        let previous = db::current_scan_checkpoint(&conn).map_err(|e| e.to_string())?;
+        let mut read = Vec::new();
+        scan_resumable(&roots, &options, Some(&previous), &mut collector, &mut |f| read.extend_from_slice(f));
+        incremental = Some((read, std::mem::take(&mut collector.folders)));
+    } else if args.resumable {
+        scan_with_checkpoint(&conn, &roots, &options, quiet, &mut collector)?;
+    } else {
+        let scanner = scanner_by_name(&args.scanner).ok_or_else(|| format!("unknown scanner: {}", args.scanner))?;
+        scanner.scan(&roots, &options, &mut collector);
+    }
+    let errors: Vec<ScanError> = collector.errors;
+    let symlinks = collector.symlinks;
+    let files = Arc::new(collector.files);
+    if !quiet {
+        eprint!("\r\x1b[K");
+    }
+    let complete = !options.control.is_cancelled();
+    if !complete && !args.keep_partial {
+        let hint = match args.resumable {
+            true => "finished folders are checkpointed; run it again to continue",
+            false => "nothing was saved (use --keep-partial to keep what was found)",
+        };
+        return Err(format!("scan stopped after {}s; {}", start.elapsed().as_secs(), hint));
+    }
+
+    let mut folder_sizes: HashMap<PathBuf, u64> = HashMap::new();
+    for root in &roots {
+        for (k, v) in compute_folder_sizes(root, &files) {
+            *folder_sizes.entry(k).or_insert(0) += v;
+        }
+    }
+
+    let update_id = chrono::Utc::now().timestamp_millis();
+    let root_strs: Vec<String> = roots.iter().map(|r| r.to_string_lossy().to_string()).collect();
+    let (scan_id, snapshots_removed) = if complete {
+        let scan_id = match &incremental {
+            Some((read, visited)) => db::write_incremental_scan(&conn, read, visited, update_id),
+            None => db::write_scan_with_symlinks(&conn, &files, &symlinks, &folder_sizes, update_id),
+        }
+        .map_err(|e| e.to_string())?;
+        let tracked = select_tracked_folders(&roots, &folder_sizes, &TrackingOptions::default());
+        db::write_size_history(&conn, update_id, &tracked).map_err(|e| e.to_string())?;
+        let removed =
+            db::apply_retention(&conn, &args.retention.policy(), update_id).map_err(|e| e.to_string())?;
+        if args.resumable {
+            db::clear_checkpoint(&conn).map_err(|e| e.to_string())?;
+        }
+        db::write_scan_errors(&conn, &errors).map_err(|e| e.to_string())?;
+        (scan_id, removed)
+    } else {
+        let scan_id = db::write_partial_scan(&conn, &root_strs, &files, &folder_sizes, update_id)
+            .map_err(|e| e.to_string())?;
+        (scan_id, 0)
+    };
+
+    let out = ScanOutput {
+        scan_id,
+        roots: root_strs,
+        files_count: files.len() as u64,
+        folders_count: folder_sizes.len() as u64,
+        total_size: roots.iter().filter_map(|r| folder_sizes.get(r)).sum(),
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        db_path: ctx.db_path.to_string_lossy().to_string(),
+        snapshots_removed,
+        complete,
+        folders_read: incremental.as_ref().map(|(read, _)| read.len() as u64),
+        errors_count: errors.len() as u64,
+        symlinks_count: symlinks.len() as u64,
+    };
+    ctx.emit(&out, |o| {
+        if o.complete {
+            println!("Scanned {}", o.roots.join(", "));
+        } else {
+            println!("Stopped scanning {} at the time limit", o.roots.join(", "));
+        }
+        println!(
+            "{} files, {} folders, {} in {:.1}s",
+            o.files_count,
+            o.folders_count,
+            format_size(o.total_size),
+            o.elapsed_ms as f64 / 1000.0,
+        );
+        if let Some(read) = o.folders_read {
+            println!("Read {} changed folder(s); the rest came from the last scan", read);
+        }
+        if o.errors_count > 0 {
+            println!("{} entries could not be read and were skipped (see `errors`)", o.errors_count);
+        }
+        if o.symlinks_count > 0 {
+            println!("Recorded {} symlink(s) (broken ones are listed by `findings`)", o.symlinks_count);
+        }
+        if o.complete {
+            println!("Saved as snapshot {} to {}", o.scan_id, o.db_path);
+        } else {
+            println!("Saved as incomplete snapshot {} to {}; the current scan is unchanged", o.scan_id, o.db_path);
+        }
+        if o.snapshots_removed > 0 {
+            println!("Removed {} old snapshot(s)", o.snapshots_removed);
+        }
+    })
+}
+
+/// Scan with a checkpoint in the database, continuing the interrupted scan of the same roots
+/// if there is one.
+fn scan_with_checkpoint(
+    conn: &rusqlite::Connection,
+    roots: &[PathBuf],
+    options: &ScanOptions,
+    quiet: bool,
+    collector: &mut ScanCollector<impl FnMut(ScanProgress)>,
+) -> Result<(), String> {
+    let resume = db::load_checkpoint(conn)
+        .map_err(|e| e.to_string())?
+        .filter(|c| c.roots == roots);
+    match &resume {
+        Some(c) if !quiet => eprintln!("Resuming an interrupted scan ({} folders already done)", c.folders.len()),
+        Some(_) => {}
+        None => db::start_checkpoint(conn, roots).map_err(|e| e.to_string())?,
+    }
+    let mut save_error = None;
+    scan_resumable(roots, options, resume.as_ref(), collector, &mut |folders| {
+        if let Err(e) = db::save_checkpoint(conn, folders) {
+            save_error.get_or_insert(e);
+        }
+    });
+    match save_error {
+        Some(e) => Err(format!("saving the scan checkpoint: {}", e)),
+        None => Ok(()),
+    }
+}
+
+/// Canonical path without the `\\?\` prefix Windows adds, so paths match the ones the app stores.
+fn canonical_root(path: &std::path::Path) -> std::io::Result<PathBuf> {
+    let canonical = std::fs::canonicalize(path)?;
+    let s = canonical.to_string_lossy();
+    match s.strip_prefix(r"\\?\") {
+        Some(rest) if !rest.starts_with("UNC") => Ok(PathBuf::from(rest)),
+        _ => Ok(canonical),
+    }
+}
@@ -0,0 +1,103 @@
+use clap::Args;
+use serde::Serialize;
+
+use cutest_disk_tree::core::indexing::sqlite::SqliteBackend;
+use cutest_disk_tree::core::search::{Cursor, SearchBackend, SearchMode, SearchRequest, SortDirection, SortKey, SortOrder};
+use cutest_disk_tree::DiskObjectKind;
+
+use super::output::{format_size, Align, Table};
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    /// Text to look for in file and folder names (case-insensitive), or a structured query
+    /// such as `ext:mp4,mkv size:>2GB modified:>1y path:~/Downloads`.
+    pub query: String,
+
+    /// Comma-separated extensions, e.g. `jpg,png`. Takes precedence over `--category`.
+    #[arg(long, short)]
+    pub ext: Option<String>,
+
+    /// `folder`, `other`, or a category such as `image`, `video`, `document`.
+    #[arg(long, short)]
+    pub category: Option<String>,
+
+    #[arg(long, short = 'n', default_value_t = 50)]
+    pub limit: usize,
+
+    /// Continue after the page that printed this cursor.
+    #[arg(long)]
+    pub cursor: Option<String>,
+
+    /// Order results by match quality, name, size or modification time.
+    #[arg(long, default_value = SortKey::NAMES[0], value_parser = SortKey::NAMES)]
+    pub sort: String,
+
+    /// Sort direction; largest and newest first for size and time, from the start otherwise.
+    #[arg(long, value_parser = SortDirection::NAMES)]
+    pub order: Option<String>,
+
+    /// How to match the text: names containing it, names ranked by fuzzy match (the best
+    /// `--limit` in one page), full paths containing it, such as `photos/2019`, ranked by
+    /// where in the path it matches, or names matching it as a glob (`*.tar.gz`) or regex
+    /// (`img_\d{4}\.jpg`).
+    #[arg(long, default_value = SearchMode::NAMES[0], value_parser = SearchMode::NAMES)]
+    pub mode: String,
+}
+
+#[derive(Serialize)]
+struct SearchOutput {
+    items: Vec<SearchItem>,
+    /// Pass as `--cursor` for the next page; absent on the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SearchItem {
+    kind: DiskObjectKind,
+    path: String,
+    size: u64,
+}
+
+pub fn run(ctx: &Context, args: SearchArgs) -> Result<(), String> {
+    let backend = SqliteBackend::new(ctx.open_scanned_db()?)?;
+    let mode = SearchMode::by_name(&args.mode).unwrap_or_default();
+    let sort = SortKey::by_name(&args.sort).unwrap_or_default();
+    let request = SearchRequest {
+        sort: SortOrder::new(sort, args.order.as_deref().and_then(SortDirection::by_name)),
+        limit: args.limit,
+        cursor: args.cursor.as_deref().map(Cursor::decode).transpose()?,
+        ..SearchRequest::parse_as(mode, &args.query, args.ext.as_deref(), args.category.as_deref())?
+    };
+    let results = backend.search(&request)?;
+
+    let out = SearchOutput {
+        next_cursor: results.next_cursor.as_ref().map(Cursor::encode),
+        items: results
+            .items
+            .into_iter()
+            .map(|o| SearchItem {
+                size: o.size_by(ctx.metric).unwrap_or(0),
+                kind: o.kind,
+                path: o.path,
+            })
+            .collect(),
+    };
+    ctx.emit(&out, |out| {
+        let mut table = Table::new(&[("KIND", Align::Left), ("SIZE", Align::Right), ("PATH", Align::Left)]);
+        for item in &out.items {
+            let kind = match item.kind {
+                DiskObjectKind::File => "file",
+                DiskObjectKind::Folder => "folder",
+                DiskObjectKind::Symlink => "symlink",
+                DiskObjectKind::Collapsed => "collapsed",
+            };
+            table.row(vec![kind.to_string(), format_size(item.size), item.path.clone()]);
+        }
+        table.print();
+        if let Some(cursor) = &out.next_cursor {
+            println!("… more results; use --cursor {} to continue", cursor);
+        }
+    })
+}
@@ -0,0 +1,48 @@
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use cutest_disk_tree::db;
+
+use super::output::{format_size, Align, Table};
+use super::Context;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum TopKind {
+    Files,
+    Folders,
+}
+
+#[derive(Args, Debug)]
+pub struct TopArgs {
+    /// Whether to list files or folders.
+    #[arg(value_enum, default_value = "files")]
+    pub kind: TopKind,
+
+    /// Number of entries to show.
+    #[arg(long, short = 'n', default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Serialize)]
+struct TopEntry {
+    path: String,
+    size: u64,
+}
+
+pub fn run(ctx: &Context, args: TopArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let rows = match args.kind {
+        TopKind::Files => db::get_largest_files_with_metric(&conn, args.limit, ctx.metric),
+        TopKind::Folders => db::get_largest_folders_with_metric(&conn, args.limit, ctx.metric),
+    }
+    .map_err(|e| e.to_string())?;
+    let entries: Vec<TopEntry> = rows.into_iter().map(|(path, size)| TopEntry { path, size }).collect();
+
+    ctx.emit(&entries, |entries| {
+        let mut table = Table::new(&[("SIZE", Align::Right), ("PATH", Align::Left)]);
+        for e in entries {
+            table.row(vec![format_size(e.size), e.path.clone()]);
+        }
+        table.print();
+    })
+}
@@ -0,0 +1,106 @@
+use clap::Args;
+
+use cutest_disk_tree::{build_disk_tree, build_disk_tree_from_db_with_metric, db, DiskTreeNode, SizeMetric};
+
+use super::output::format_size;
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct TreeArgs {
+    /// Folder to start from. Defaults to every scanned root.
+    pub path: Option<String>,
+
+    /// Levels below the start folder to expand.
+    #[arg(long, short, default_value_t = 3)]
+    pub depth: usize,
+
+    /// Children shown per folder; the rest are summed up as "Other".
+    #[arg(long, default_value_t = 10)]
+    pub max_children: usize,
+
+    /// Show a previous snapshot (id from `history`) instead of the current scan.
+    #[arg(long, value_name = "ID")]
+    pub scan: Option<i64>,
+}
+
+pub fn run(ctx: &Context, args: TreeArgs) -> Result<(), String> {
+    if let Some(scan_id) = args.scan {
+        return run_snapshot(ctx, &args, scan_id);
+    }
+    let conn = ctx.open_scanned_db()?;
+    let starts: Vec<String> = match args.path {
+        Some(p) => vec![p.trim_end_matches(['/', '\\']).to_string()],
+        None => db::get_root_folders(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect(),
+    };
+
+    let mut trees: Vec<DiskTreeNode> = Vec::with_capacity(starts.len());
+    for start in &starts {
+        let node = build_disk_tree_from_db_with_metric(&conn, start, args.max_children.max(2), args.depth, ctx.metric)
+            .ok_or_else(|| format!("folder not found in the last scan: {}", start))?;
+        trees.push(node);
+    }
+
+    print_trees(ctx, trees)
+}
+
+fn run_snapshot(ctx: &Context, args: &TreeArgs, scan_id: i64) -> Result<(), String> {
+    if ctx.metric == SizeMetric::Allocated {
+        return Err("snapshots only keep apparent sizes; drop --allocated to view them".to_string());
+    }
+    let snapshot = db::open_snapshot(&ctx.db_path, scan_id)
+        .map_err(|e| format!("{}: {}", ctx.db_path.display(), e))?
+        .ok_or_else(|| format!("no snapshot with id {}", scan_id))?;
+    let scan = snapshot.scan_result().map_err(|e| e.to_string())?;
+    let starts: Vec<String> = match &args.path {
+        Some(p) => vec![p.trim_end_matches(['/', '\\']).to_string()],
+        None => snapshot.info().roots.clone(),
+    };
+
+    let mut trees: Vec<DiskTreeNode> = Vec::with_capacity(starts.len());
+    for start in &starts {
+        let node = build_disk_tree(&scan, start, args.max_children.max(2), args.depth)
+            .ok_or_else(|| format!("folder not found in snapshot {}: {}", scan_id, start))?;
+        trees.push(node);
+    }
+    print_trees(ctx, trees)
+}
+
+fn print_trees(ctx: &Context, trees: Vec<DiskTreeNode>) -> Result<(), String> {
+    ctx.emit(&trees, |trees| {
+        for tree in trees {
+            println!("{}  {}", format_size(tree.size), tree.path);
+            print_children(tree, "");
+        }
+    })
+}
+
+fn print_children(node: &DiskTreeNode, prefix: &str) {
+    let Some(children) = &node.children else {
+        return;
+    };
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+        let share = if node.size > 0 {
+            child.size as f64 * 100.0 / node.size as f64
+        } else {
+            0.0
+        };
+        println!(
+            "{}{}{}  {} ({:.0}%)",
+            prefix,
+            branch,
+            child.name,
+            format_size(child.size),
+            share
+        );
+        // "Other" lists the remaining small entries flat; don't expand it.
+        if !child.path.ends_with("__other") {
+            print_children(child, &format!("{}{}", prefix, indent));
+        }
+    }
+}
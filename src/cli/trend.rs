@@ -0,0 +1,56 @@
+use chrono::{Local, TimeZone};
+use clap::Args;
+
+use cutest_disk_tree::core::trend::size_series;
+use cutest_disk_tree::db;
+
+use super::output::{format_delta, format_size, Align, Table};
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct TrendArgs {
+    /// Folder to chart.
+    pub path: String,
+
+    /// Capacity in bytes of the volume holding the folder, to estimate when it fills up.
+    #[arg(long)]
+    pub capacity: Option<u64>,
+}
+
+pub fn run(ctx: &Context, args: TrendArgs) -> Result<(), String> {
+    let conn = ctx.open_db()?;
+    let path = args.path.trim_end_matches(['/', '\\']);
+    let path = if path.is_empty() { "/" } else { path };
+    let points = db::get_size_series(&conn, path).map_err(|e| e.to_string())?;
+    if points.is_empty() {
+        return Err(format!("no size history for {}", path));
+    }
+    let series = size_series(path, points, args.capacity);
+
+    ctx.emit(&series, |s| {
+        let mut table = Table::new(&[("SCANNED", Align::Left), ("SIZE", Align::Right), ("CHANGE", Align::Right)]);
+        let mut previous: Option<u64> = None;
+        for p in &s.points {
+            let change = previous.map(|prev| format_delta(p.size as i64 - prev as i64)).unwrap_or_default();
+            table.row(vec![format_date(p.timestamp, "%Y-%m-%d %H:%M"), format_size(p.size), change]);
+            previous = Some(p.size);
+        }
+        table.print();
+        println!();
+        match &s.trend {
+            Some(trend) => println!("Trend: {} per day", format_delta(trend.bytes_per_day.round() as i64)),
+            None => println!("Not enough history for a trend yet; scan again later."),
+        }
+        if let Some(full_at) = s.full_at {
+            println!("Reaches capacity around {}", format_date(full_at, "%Y-%m-%d"));
+        }
+    })
+}
+
+fn format_date(ms: i64, format: &str) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map(|t| t.format(format).to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,400 @@
+//! Navigation state of the TUI, kept free of terminal code so it can be tested.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use cutest_disk_tree::core::scanning::exclusions::is_collapsed_entry;
+use cutest_disk_tree::{build_disk_tree_from_db_with_metric, db, DiskTreeNode, SizeMetric};
+
+use super::super::output::format_size;
+
+/// Suffix `build_disk_tree_from_db` gives the bucket that sums up the smallest children.
+const OTHER_SUFFIX: &str = "__other";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Folder,
+    /// The "Other" bucket; its children are listed flat when entered.
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    pub kind: EntryKind,
+    /// Newest file mtime at or below this entry, in seconds since the epoch.
+    pub mtime: Option<i64>,
+    /// Flat children of an [`EntryKind::Other`] bucket.
+    pub children: Vec<Entry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Size,
+    Name,
+    Mtime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewSource {
+    /// Every scanned root, used when the database holds more than one.
+    Roots,
+    Folder,
+    Other,
+}
+
+#[derive(Debug)]
+pub struct View {
+    pub source: ViewSource,
+    pub path: String,
+    pub title: String,
+    pub size: u64,
+    pub entries: Vec<Entry>,
+    pub selected: usize,
+}
+
+impl View {
+    pub fn selected_entry(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+}
+
+pub struct App {
+    conn: Connection,
+    max_children: usize,
+    metric: SizeMetric,
+    stack: Vec<View>,
+    pub sort: SortKey,
+    /// Ascending instead of the key's natural order (largest, A-Z, newest first).
+    pub reversed: bool,
+    /// Entry awaiting a y/N answer before it is deleted.
+    pub confirm_delete: Option<Entry>,
+    /// One-line status shown in the footer.
+    pub message: Option<String>,
+    pub should_quit: bool,
+}
+
+impl App {
+    /// Start at `start`, or at the only scanned root, or at a list of all roots.
+    pub fn new(
+        conn: Connection,
+        start: Option<&str>,
+        max_children: usize,
+        metric: SizeMetric,
+    ) -> Result<App, String> {
+        let mut app = App {
+            conn,
+            max_children: max_children.max(2),
+            metric,
+            stack: Vec::new(),
+            sort: SortKey::Size,
+            reversed: false,
+            confirm_delete: None,
+            message: None,
+            should_quit: false,
+        };
+        let view = match start {
+            Some(path) => {
+                let path = path.trim_end_matches(['/', '\\']);
+                app.load_folder(path)?
+                    .ok_or_else(|| format!("folder not found in the last scan: {}", path))?
+            }
+            None => {
+                let mut roots = app.load_roots()?;
+                if roots.entries.len() == 1 {
+                    let root = roots.entries.remove(0);
+                    app.load_folder(&root.path)?.unwrap_or(roots)
+                } else {
+                    roots
+                }
+            }
+        };
+        app.push(view);
+        Ok(app)
+    }
+
+    pub fn view(&self) -> &View {
+        self.stack.last().expect("view stack is never empty")
+    }
+
+    fn view_mut(&mut self) -> &mut View {
+        self.stack.last_mut().expect("view stack is never empty")
+    }
+
+    /// Folders entered so far, outermost first.
+    pub fn breadcrumbs(&self) -> Vec<&str> {
+        self.stack.iter().map(|v| v.title.as_str()).collect()
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let view = self.view_mut();
+        if view.entries.is_empty() {
+            return;
+        }
+        let last = view.entries.len() - 1;
+        view.selected = view.selected.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn select_first(&mut self) {
+        self.view_mut().selected = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        let view = self.view_mut();
+        view.selected = view.entries.len().saturating_sub(1);
+    }
+
+    /// Enter the selected folder or "Other" bucket. Files are ignored.
+    pub fn enter(&mut self) -> Result<(), String> {
+        let Some(entry) = self.view().selected_entry().cloned() else {
+            return Ok(());
+        };
+        let view = match entry.kind {
+            EntryKind::File => return Ok(()),
+            EntryKind::Folder => match self.load_folder(&entry.path)? {
+                Some(view) => view,
+                None => {
+                    self.message = Some(format!("{} is empty", entry.name));
+                    return Ok(());
+                }
+            },
+            EntryKind::Other => View {
+                source: ViewSource::Other,
+                title: entry.name.clone(),
+                path: entry.path.clone(),
+                size: entry.size,
+                entries: entry.children.clone(),
+                selected: 0,
+            },
+        };
+        self.push(view);
+        Ok(())
+    }
+
+    /// Go back to the parent view, keeping the previous selection there.
+    pub fn leave(&mut self) {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+        }
+    }
+
+    /// Pick a sort key; choosing the current one again flips the direction.
+    pub fn set_sort(&mut self, key: SortKey) {
+        if self.sort == key {
+            self.reversed = !self.reversed;
+        } else {
+            self.sort = key;
+            self.reversed = false;
+        }
+        for i in 0..self.stack.len() {
+            self.sort_view(i);
+        }
+    }
+
+    /// Ask for confirmation before deleting the selected entry.
+    pub fn request_delete(&mut self) {
+        let Some(entry) = self.view().selected_entry().cloned() else {
+            return;
+        };
+        if entry.kind == EntryKind::Other {
+            self.message = Some("enter \"Other\" to delete single entries".to_string());
+            return;
+        }
+        if self.view().source == ViewSource::Roots {
+            self.message = Some("refusing to delete a scanned root".to_string());
+            return;
+        }
+        if is_collapsed_entry(Path::new(&entry.path)) {
+            self.message = Some("the contents of a collapsed folder are not indexed; delete the folder".to_string());
+            return;
+        }
+        self.confirm_delete = Some(entry);
+    }
+
+    pub fn cancel_delete(&mut self) {
+        self.confirm_delete = None;
+    }
+
+    /// Delete the confirmed entry from disk and from the index, then refresh every open view.
+    pub fn confirm_delete(&mut self) -> Result<(), String> {
+        let Some(entry) = self.confirm_delete.take() else {
+            return Ok(());
+        };
+        let path = Path::new(&entry.path);
+        let removed = if entry.kind == EntryKind::Folder {
+            std::fs::remove_dir_all(path)
+        } else {
+            std::fs::remove_file(path)
+        };
+        // Already gone on disk: still drop it from the index so the view matches reality.
+        if let Err(e) = removed {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                self.message = Some(format!("could not delete {}: {}", entry.path, e));
+                return Ok(());
+            }
+        }
+        let bytes = db::delete_subtree(&self.conn, &entry.path).map_err(|e| e.to_string())?;
+        self.refresh_after_delete(&entry.path, bytes)?;
+        self.message = Some(format!("deleted {} ({})", entry.name, format_size(bytes)));
+        Ok(())
+    }
+
+    fn refresh_after_delete(&mut self, deleted: &str, bytes: u64) -> Result<(), String> {
+        for i in 0..self.stack.len() {
+            let fresh = match self.stack[i].source {
+                ViewSource::Roots => {
+                    let roots = self.load_roots()?;
+                    Some((roots.size, roots.entries))
+                }
+                ViewSource::Folder => {
+                    let path = self.stack[i].path.clone();
+                    // A folder whose last child was deleted has nothing left to list.
+                    Some(self.load_folder(&path)?.map_or((0, Vec::new()), |v| (v.size, v.entries)))
+                }
+                ViewSource::Other => {
+                    let view = &mut self.stack[i];
+                    if view.entries.iter().any(|e| e.path == deleted) {
+                        view.entries.retain(|e| e.path != deleted);
+                        view.size = view.size.saturating_sub(bytes);
+                    }
+                    None
+                }
+            };
+            if let Some((size, entries)) = fresh {
+                let view = &mut self.stack[i];
+                view.size = size;
+                view.entries = entries;
+            }
+            let view = &mut self.stack[i];
+            view.selected = view.selected.min(view.entries.len().saturating_sub(1));
+            self.sort_view(i);
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, view: View) {
+        self.stack.push(view);
+        let last = self.stack.len() - 1;
+        self.sort_view(last);
+    }
+
+    fn sort_view(&mut self, index: usize) {
+        let (sort, reversed) = (self.sort, self.reversed);
+        let view = &mut self.stack[index];
+        let selected_path = view.selected_entry().map(|e| e.path.clone());
+        view.entries.sort_by(|a, b| compare_entries(a, b, sort, reversed));
+        if let Some(path) = selected_path {
+            view.selected = view.entries.iter().position(|e| e.path == path).unwrap_or(0);
+        }
+    }
+
+    fn load_roots(&self) -> Result<View, String> {
+        let roots = db::get_root_folders_with_metric(&self.conn, self.metric).map_err(|e| e.to_string())?;
+        let entries: Vec<Entry> = roots
+            .into_iter()
+            .map(|(path, size)| {
+                let mtime = db::get_newest_mtime(&self.conn, &path).ok().flatten();
+                Entry {
+                    name: path.clone(),
+                    path,
+                    size,
+                    kind: EntryKind::Folder,
+                    mtime,
+                    children: Vec::new(),
+                }
+            })
+            .collect();
+        Ok(View {
+            source: ViewSource::Roots,
+            path: String::new(),
+            title: "All roots".to_string(),
+            size: entries.iter().map(|e| e.size).sum(),
+            entries,
+            selected: 0,
+        })
+    }
+
+    /// `None` when the folder has no indexed children.
+    fn load_folder(&self, path: &str) -> Result<Option<View>, String> {
+        let Some(node) = build_disk_tree_from_db_with_metric(&self.conn, path, self.max_children, 1, self.metric) else {
+            return Ok(None);
+        };
+        // At depth 1 folders and files both come back without children; tell them apart here.
+        let (folders, _) = db::get_children_for_path(&self.conn, path).map_err(|e| e.to_string())?;
+        let folders: HashSet<String> = folders.into_iter().map(|(p, _)| p).collect();
+        let entries = node
+            .children
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| self.entry_from_node(child, &folders))
+            .collect();
+        Ok(Some(View {
+            source: ViewSource::Folder,
+            title: node.path.clone(),
+            path: node.path,
+            size: node.size,
+            entries,
+            selected: 0,
+        }))
+    }
+
+    fn entry_from_node(&self, node: DiskTreeNode, folders: &HashSet<String>) -> Entry {
+        let kind = if node.path.ends_with(OTHER_SUFFIX) {
+            EntryKind::Other
+        } else if folders.contains(&node.path) {
+            EntryKind::Folder
+        } else {
+            EntryKind::File
+        };
+        let children: Vec<Entry> = match kind {
+            EntryKind::Other => node
+                .children
+                .unwrap_or_default()
+                .into_iter()
+                .map(|child| self.entry_from_node(child, folders))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mtime = match kind {
+            EntryKind::Other => children.iter().filter_map(|c| c.mtime).max(),
+            _ => db::get_newest_mtime(&self.conn, &node.path).ok().flatten(),
+        };
+        Entry {
+            path: node.path,
+            name: node.name,
+            size: node.size,
+            kind,
+            mtime,
+            children,
+        }
+    }
+}
+
+/// The "Other" bucket always stays last, whatever the sort order.
+fn compare_entries(a: &Entry, b: &Entry, key: SortKey, reversed: bool) -> Ordering {
+    let a_other = a.kind == EntryKind::Other;
+    let b_other = b.kind == EntryKind::Other;
+    if a_other != b_other {
+        return a_other.cmp(&b_other);
+    }
+    let ord = match key {
+        SortKey::Size => b.size.cmp(&a.size),
+        SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        SortKey::Mtime => b.mtime.cmp(&a.mtime),
+    }
+    .then_with(|| a.path.cmp(&b.path));
+    if reversed {
+        ord.reverse()
+    } else {
+        ord
+    }
+}
+
+#[cfg(test)]
+mod tests;
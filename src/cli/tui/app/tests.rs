@@ -0,0 +1,138 @@
+use super::*;
+
+use cutest_disk_tree::index_directory;
+
+/// Scan a small tree into a fresh database: `big/` (3 files), `small.txt`, `tiny.txt`.
+fn scanned(dir: &tempfile::TempDir) -> (Connection, String) {
+    let root = dir.path().join("root");
+    std::fs::create_dir_all(root.join("big")).unwrap();
+    std::fs::write(root.join("big").join("a.bin"), vec![0u8; 4000]).unwrap();
+    std::fs::write(root.join("big").join("b.bin"), vec![0u8; 3000]).unwrap();
+    std::fs::write(root.join("big").join("c.bin"), vec![0u8; 2000]).unwrap();
+    std::fs::write(root.join("small.txt"), vec![0u8; 500]).unwrap();
+    std::fs::write(root.join("tiny.txt"), vec![0u8; 10]).unwrap();
+
+    let (files, folder_sizes) = index_directory(&root);
+    let conn = db::open_db(&dir.path().join("index.db")).unwrap();
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+    (conn, root.to_string_lossy().into_owned())
+}
+
+fn names(app: &App) -> Vec<&str> {
+    app.view().entries.iter().map(|e| e.name.as_str()).collect()
+}
+
+#[test]
+fn opens_single_root_sorted_by_size_and_classifies_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let (conn, root) = scanned(&dir);
+    let app = App::new(conn, None, 10, SizeMetric::Apparent).unwrap();
+
+    assert_eq!(app.view().path, root);
+    assert_eq!(app.view().size, 9510);
+    assert_eq!(names(&app), ["big", "small.txt", "tiny.txt"]);
+    let kinds: Vec<EntryKind> = app.view().entries.iter().map(|e| e.kind).collect();
+    assert_eq!(kinds, [EntryKind::Folder, EntryKind::File, EntryKind::File]);
+    assert!(app.view().entries[0].mtime.is_some());
+}
+
+#[test]
+fn other_bucket_stays_last_and_can_be_entered() {
+    let dir = tempfile::tempdir().unwrap();
+    let (conn, _) = scanned(&dir);
+    let mut app = App::new(conn, None, 2, SizeMetric::Apparent).unwrap();
+
+    assert_eq!(names(&app), ["big", "Other"]);
+    app.set_sort(SortKey::Size);
+    assert!(app.reversed);
+    assert_eq!(names(&app), ["big", "Other"]);
+
+    app.set_sort(SortKey::Name);
+    app.select_last();
+    assert_eq!(app.view().selected_entry().unwrap().kind, EntryKind::Other);
+    app.enter().unwrap();
+    assert_eq!(app.view().source, ViewSource::Other);
+    assert_eq!(names(&app), ["small.txt", "tiny.txt"]);
+    assert_eq!(app.view().size, 510);
+
+    app.leave();
+    assert_eq!(app.view().source, ViewSource::Folder);
+}
+
+#[test]
+fn sort_toggles_direction_and_keeps_selection() {
+    let dir = tempfile::tempdir().unwrap();
+    let (conn, _) = scanned(&dir);
+    let mut app = App::new(conn, None, 10, SizeMetric::Apparent).unwrap();
+
+    app.move_selection(1);
+    app.set_sort(SortKey::Name);
+    assert_eq!(names(&app), ["big", "small.txt", "tiny.txt"]);
+    app.set_sort(SortKey::Name);
+    assert!(app.reversed);
+    assert_eq!(names(&app), ["tiny.txt", "small.txt", "big"]);
+    assert_eq!(app.view().selected_entry().unwrap().name, "small.txt");
+}
+
+#[test]
+fn delete_removes_from_disk_and_updates_sizes() {
+    let dir = tempfile::tempdir().unwrap();
+    let (conn, root) = scanned(&dir);
+    let mut app = App::new(conn, None, 10, SizeMetric::Apparent).unwrap();
+
+    app.enter().unwrap();
+    assert_eq!(names(&app), ["a.bin", "b.bin", "c.bin"]);
+    app.request_delete();
+    assert_eq!(app.confirm_delete.as_ref().unwrap().name, "a.bin");
+    app.confirm_delete().unwrap();
+
+    assert!(!Path::new(&root).join("big").join("a.bin").exists());
+    assert_eq!(names(&app), ["b.bin", "c.bin"]);
+    assert_eq!(app.view().size, 5000);
+    app.leave();
+    assert_eq!(app.view().size, 5510);
+    assert_eq!(app.view().entries[0].size, 5000);
+}
+
+#[test]
+fn delete_is_cancelled_by_any_other_answer() {
+    let dir = tempfile::tempdir().unwrap();
+    let (conn, root) = scanned(&dir);
+    let mut app = App::new(conn, None, 10, SizeMetric::Apparent).unwrap();
+
+    app.move_selection(1);
+    app.request_delete();
+    app.cancel_delete();
+    assert!(app.confirm_delete.is_none());
+    assert!(Path::new(&root).join("small.txt").exists());
+    assert_eq!(app.view().entries.len(), 3);
+}
+
+#[test]
+fn collapsed_summaries_are_not_deleted() {
+    use cutest_disk_tree::core::scanning::exclusions::COLLAPSED_ENTRY_NAME;
+    use cutest_disk_tree::{FileEntry, FileKey};
+
+    let dir = tempfile::tempdir().unwrap();
+    let deps = dir.path().join("root").join("deps");
+    std::fs::create_dir_all(&deps).unwrap();
+    let summary = FileEntry {
+        path: deps.join(COLLAPSED_ENTRY_NAME),
+        size: 100,
+        allocated_size: 100,
+        file_key: FileKey { dev: 1, ino: 2 },
+        mtime: None,
+        owner: None,
+    };
+    let folder_sizes = [(dir.path().join("root"), 100), (deps.clone(), 100)].into_iter().collect();
+    let conn = db::open_db(&dir.path().join("index.db")).unwrap();
+    db::write_scan(&conn, &[summary], &folder_sizes, 1).unwrap();
+    let mut app = App::new(conn, None, 10, SizeMetric::Apparent).unwrap();
+
+    app.enter().unwrap();
+    assert_eq!(names(&app), [COLLAPSED_ENTRY_NAME]);
+    app.request_delete();
+    assert!(app.confirm_delete.is_none());
+    assert!(app.message.is_some());
+    assert!(deps.exists());
+}
@@ -0,0 +1,99 @@
+//! ncdu-style browser for the last scan: navigate folders, sort, and delete entries.
+
+mod app;
+mod ui;
+
+use std::io;
+use std::time::Duration;
+
+use clap::Args;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::prelude::CrosstermBackend;
+use ratatui::Terminal;
+
+use app::{App, SortKey};
+
+use super::Context;
+
+#[derive(Args, Debug)]
+pub struct TuiArgs {
+    /// Folder to open. Defaults to the scanned root (or a list of roots).
+    pub path: Option<String>,
+
+    /// Entries listed per folder; the rest are summed up as "Other".
+    #[arg(long, default_value_t = 200)]
+    pub max_children: usize,
+}
+
+pub fn run(ctx: &Context, args: TuiArgs) -> Result<(), String> {
+    let conn = ctx.open_scanned_db()?;
+    let mut app = App::new(conn, args.path.as_deref(), args.max_children, ctx.metric)?;
+
+    enable_raw_mode().map_err(|e| e.to_string())?;
+    let mut stdout = io::stdout();
+    if let Err(e) = execute!(stdout, EnterAlternateScreen) {
+        let _ = disable_raw_mode();
+        return Err(e.to_string());
+    }
+    let result = Terminal::new(CrosstermBackend::new(stdout))
+        .map_err(|e| e.to_string())
+        .and_then(|mut terminal| event_loop(&mut terminal, &mut app));
+
+    // Always hand the terminal back, even when the loop failed.
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    result
+}
+
+fn event_loop<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<(), String> {
+    while !app.should_quit {
+        terminal.draw(|frame| ui::draw(frame, app)).map_err(|e| e.to_string())?;
+        if !event::poll(Duration::from_millis(250)).map_err(|e| e.to_string())? {
+            continue;
+        }
+        if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                break;
+            }
+            handle_key(app, key.code)?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_key(app: &mut App, code: KeyCode) -> Result<(), String> {
+    if app.confirm_delete.is_some() {
+        return match code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => app.confirm_delete(),
+            _ => {
+                app.cancel_delete();
+                Ok(())
+            }
+        };
+    }
+    app.message = None;
+    match code {
+        KeyCode::Char('q') | KeyCode::Esc => app.should_quit = true,
+        KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+        KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+        KeyCode::PageUp => app.move_selection(-20),
+        KeyCode::PageDown => app.move_selection(20),
+        KeyCode::Home | KeyCode::Char('g') => app.select_first(),
+        KeyCode::End | KeyCode::Char('G') => app.select_last(),
+        KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => app.enter()?,
+        KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => app.leave(),
+        KeyCode::Char('s') => app.set_sort(SortKey::Size),
+        KeyCode::Char('n') => app.set_sort(SortKey::Name),
+        KeyCode::Char('m') => app.set_sort(SortKey::Mtime),
+        KeyCode::Char('d') | KeyCode::Delete => app.request_delete(),
+        _ => {}
+    }
+    Ok(())
+}
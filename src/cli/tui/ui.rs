@@ -0,0 +1,91 @@
+use chrono::{Local, TimeZone};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use super::super::output::format_size;
+use super::app::{App, Entry, EntryKind, SortKey};
+
+/// Width of the usage bar in front of each entry.
+const BAR_WIDTH: usize = 12;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let view = app.view();
+    let title = format!(" {}  {} ", app.breadcrumbs().join(" > "), format_size(view.size));
+    frame.render_widget(Paragraph::new(title).bold().reversed(), header);
+
+    let items: Vec<ListItem> = view
+        .entries
+        .iter()
+        .map(|entry| ListItem::new(entry_line(entry, view.size)))
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::NONE))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(view.selected));
+    frame.render_stateful_widget(list, body, &mut state);
+
+    frame.render_widget(Paragraph::new(footer_line(app)), footer);
+}
+
+fn entry_line(entry: &Entry, parent_size: u64) -> Line<'static> {
+    let share = if parent_size > 0 {
+        entry.size as f64 / parent_size as f64
+    } else {
+        0.0
+    };
+    let filled = ((share * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    let bar = format!("[{}{}]", "#".repeat(filled), " ".repeat(BAR_WIDTH - filled));
+    let name = match entry.kind {
+        EntryKind::File => entry.name.clone(),
+        EntryKind::Folder => format!("{}/", entry.name),
+        EntryKind::Other => format!("<{} ({} entries)>", entry.name, entry.children.len()),
+    };
+    let modified = entry
+        .mtime
+        .and_then(|t| Local.timestamp_opt(t, 0).single())
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| "          ".to_string());
+    Line::from(vec![
+        Span::raw(format!("{:>10} ", format_size(entry.size))),
+        Span::raw(format!("{:>5.1}% ", share * 100.0)),
+        Span::raw(bar).cyan(),
+        Span::raw(format!(" {}  ", modified)).dark_gray(),
+        Span::raw(name),
+    ])
+}
+
+fn footer_line(app: &App) -> Line<'static> {
+    if let Some(entry) = &app.confirm_delete {
+        return Line::from(format!(
+            "Delete {} ({})? [y/N]",
+            entry.path,
+            format_size(entry.size)
+        ))
+        .red()
+        .bold();
+    }
+    if let Some(message) = &app.message {
+        return Line::from(message.clone());
+    }
+    let sort = match app.sort {
+        SortKey::Size => "size",
+        SortKey::Name => "name",
+        SortKey::Mtime => "mtime",
+    };
+    let direction = if app.reversed { " (reversed)" } else { "" };
+    Line::from(format!(
+        "sort: {}{}  |  ↑↓ move  ⏎ open  ← back  s/n/m sort  d delete  q quit",
+        sort, direction
+    ))
+    .dark_gray()
+}
@@ -0,0 +1,378 @@
+//! Comparing two scans: what grew, shrank, appeared or disappeared.
+//!
+//! Both sides are plain [`ScanResult`]s, so a diff works the same for the current scan, a stored
+//! snapshot or a scan from another database. Sizes are looked up per path; a file or folder that
+//! exists on one side only counts as size 0 on the other.
+//!
+//! The "top growth" list does not just rank folders by delta, because every ancestor of a folder
+//! that grew by 40 GB also grew by at least 40 GB. A folder is left out when a single child
+//! accounts for [`DOMINANT_CHILD_SHARE`] of its change; the child (or its own dominant child) is
+//! listed instead, so the list points at where the space actually went.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{basename, parent_dir, DiskObjectKind, ScanResult};
+
+/// Share of a folder's change above which one child is considered responsible for all of it.
+pub const DOMINANT_CHILD_SHARE: f64 = 0.9;
+
+#[derive(Clone, Debug)]
+pub struct DiffOptions {
+    /// New and removed files smaller than this are counted but not listed.
+    pub min_file_size: u64,
+    /// Entries per list (growth, shrinkage, new files, removed files).
+    pub limit: usize,
+    /// Children per node in [`DiffReport::tree`]; the rest are summed up as "Other".
+    pub tree_max_children: usize,
+    /// Levels of [`DiffReport::tree`] below each root.
+    pub tree_max_depth: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            min_file_size: 0,
+            limit: 50,
+            tree_max_children: 10,
+            tree_max_depth: 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Grown,
+    Shrunk,
+    Unchanged,
+}
+
+impl ChangeKind {
+    fn of(old: Option<u64>, new: Option<u64>) -> ChangeKind {
+        match (old, new) {
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Removed,
+            (Some(o), Some(n)) if n > o => ChangeKind::Grown,
+            (Some(o), Some(n)) if n < o => ChangeKind::Shrunk,
+            _ => ChangeKind::Unchanged,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PathDelta {
+    pub path: String,
+    pub kind: DiskObjectKind,
+    pub change: ChangeKind,
+    pub old_size: u64,
+    pub new_size: u64,
+    pub delta: i64,
+}
+
+/// Like [`crate::DiskTreeNode`], with both sizes so a chart can colour growth and shrinkage.
+#[derive(Clone, Debug, Serialize)]
+pub struct DeltaTreeNode {
+    pub path: String,
+    pub name: String,
+    pub change: ChangeKind,
+    pub old_size: u64,
+    pub new_size: u64,
+    pub delta: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<DeltaTreeNode>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DiffReport {
+    pub old_total: u64,
+    pub new_total: u64,
+    pub total_delta: i64,
+    /// Every folder whose recursive size changed, largest absolute change first.
+    pub folders: Vec<PathDelta>,
+    /// Files and folders responsible for growth, largest first (see the module docs).
+    pub top_growth: Vec<PathDelta>,
+    /// Same as `top_growth` for shrinkage, largest decrease first.
+    pub top_shrinkage: Vec<PathDelta>,
+    pub new_files_count: u64,
+    pub new_files_bytes: u64,
+    /// New files of at least `min_file_size`, largest first.
+    pub new_files: Vec<PathDelta>,
+    pub removed_files_count: u64,
+    pub removed_files_bytes: u64,
+    /// Removed files of at least `min_file_size`, largest first.
+    pub removed_files: Vec<PathDelta>,
+    /// One delta tree per root present in either scan.
+    pub tree: Vec<DeltaTreeNode>,
+}
+
+/// Sizes of both scans keyed by path, with a child index over the union of both.
+struct DeltaIndex<'a> {
+    old_folders: &'a HashMap<String, u64>,
+    new_folders: &'a HashMap<String, u64>,
+    old_files: HashMap<&'a str, u64>,
+    new_files: HashMap<&'a str, u64>,
+    children: HashMap<String, Vec<&'a str>>,
+    roots: Vec<&'a str>,
+}
+
+impl<'a> DeltaIndex<'a> {
+    fn new(old: &'a ScanResult, new: &'a ScanResult) -> Self {
+        let old_files: HashMap<&str, u64> = old.files.iter().map(|f| (f.path.as_str(), f.size)).collect();
+        let new_files: HashMap<&str, u64> = new.files.iter().map(|f| (f.path.as_str(), f.size)).collect();
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut children: HashMap<String, Vec<&str>> = HashMap::new();
+        let all_paths = old
+            .folder_sizes
+            .keys()
+            .chain(new.folder_sizes.keys())
+            .map(|p| p.as_str())
+            .chain(old_files.keys().copied())
+            .chain(new_files.keys().copied());
+        for path in all_paths {
+            if seen.insert(path) {
+                children.entry(parent_dir(path)).or_default().push(path);
+            }
+        }
+
+        // Roots are folders whose parent is not a folder on either side.
+        let mut roots: Vec<&str> = seen
+            .iter()
+            .copied()
+            .filter(|p| old.folder_sizes.contains_key(*p) || new.folder_sizes.contains_key(*p))
+            .filter(|p| {
+                let parent = parent_dir(p);
+                !old.folder_sizes.contains_key(&parent) && !new.folder_sizes.contains_key(&parent)
+            })
+            .collect();
+        roots.sort();
+
+        DeltaIndex {
+            old_folders: &old.folder_sizes,
+            new_folders: &new.folder_sizes,
+            old_files,
+            new_files,
+            children,
+            roots,
+        }
+    }
+
+    fn is_folder(&self, path: &str) -> bool {
+        self.old_folders.contains_key(path) || self.new_folders.contains_key(path)
+    }
+
+    fn delta(&self, path: &str) -> PathDelta {
+        let (kind, old, new) = if self.is_folder(path) {
+            (
+                DiskObjectKind::Folder,
+                self.old_folders.get(path).copied(),
+                self.new_folders.get(path).copied(),
+            )
+        } else {
+            (
+                DiskObjectKind::File,
+                self.old_files.get(path).copied(),
+                self.new_files.get(path).copied(),
+            )
+        };
+        let (old_size, new_size) = (old.unwrap_or(0), new.unwrap_or(0));
+        PathDelta {
+            path: path.to_string(),
+            kind,
+            change: ChangeKind::of(old, new),
+            old_size,
+            new_size,
+            delta: new_size as i64 - old_size as i64,
+        }
+    }
+
+    fn children_of(&self, path: &str) -> &[&'a str] {
+        self.children.get(path).map(|c| c.as_slice()).unwrap_or(&[])
+    }
+
+    /// Collect the entries responsible for the change in the direction of `sign` below `path`:
+    /// list the folder unless a dominant child accounts for its change, then look at every child.
+    fn responsible(&self, path: &str, sign: i64, out: &mut HashSet<String>) {
+        let own = self.delta(path).delta * sign;
+        let dominated = self
+            .children_of(path)
+            .iter()
+            .any(|c| (self.delta(c).delta * sign) as f64 >= own as f64 * DOMINANT_CHILD_SHARE);
+        if own > 0 && !dominated {
+            out.insert(path.to_string());
+        }
+        // The dominant child is followed below like any other: children can change on their own,
+        // even against the direction of this folder.
+        for child in self.children_of(path) {
+            if self.is_folder(child) {
+                self.responsible(child, sign, out);
+            } else if self.delta(child).delta * sign > 0 {
+                out.insert(child.to_string());
+            }
+        }
+    }
+
+    fn tree(&self, path: &str, depth: usize, max_depth: usize, max_children: usize) -> DeltaTreeNode {
+        let own = self.delta(path);
+        let mut node = DeltaTreeNode {
+            name: basename(path),
+            path: own.path,
+            change: own.change,
+            old_size: own.old_size,
+            new_size: own.new_size,
+            delta: own.delta,
+            children: None,
+        };
+        if depth >= max_depth || !self.is_folder(path) {
+            return node;
+        }
+        let mut kids: Vec<PathDelta> = self.children_of(path).iter().map(|c| self.delta(c)).collect();
+        if kids.is_empty() {
+            return node;
+        }
+        kids.sort_by(|a, b| {
+            b.delta
+                .unsigned_abs()
+                .cmp(&a.delta.unsigned_abs())
+                .then(b.new_size.cmp(&a.new_size))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        let take = (max_children.max(2) - 1).min(kids.len());
+        let rest = kids.split_off(take);
+        let mut children: Vec<DeltaTreeNode> = kids
+            .iter()
+            .map(|k| self.tree(&k.path, depth + 1, max_depth, max_children))
+            .collect();
+        if !rest.is_empty() {
+            let old_size: u64 = rest.iter().map(|k| k.old_size).sum();
+            let new_size: u64 = rest.iter().map(|k| k.new_size).sum();
+            children.push(DeltaTreeNode {
+                path: format!("{}__other", path),
+                name: "Other".to_string(),
+                change: ChangeKind::of(Some(old_size), Some(new_size)),
+                old_size,
+                new_size,
+                delta: new_size as i64 - old_size as i64,
+                children: Some(
+                    rest.into_iter()
+                        .map(|k| DeltaTreeNode {
+                            name: basename(&k.path),
+                            path: k.path,
+                            change: k.change,
+                            old_size: k.old_size,
+                            new_size: k.new_size,
+                            delta: k.delta,
+                            children: None,
+                        })
+                        .collect(),
+                ),
+            });
+        }
+        node.children = Some(children);
+        node
+    }
+}
+
+fn by_abs_delta(a: &PathDelta, b: &PathDelta) -> std::cmp::Ordering {
+    b.delta
+        .unsigned_abs()
+        .cmp(&a.delta.unsigned_abs())
+        .then_with(|| a.path.cmp(&b.path))
+}
+
+/// Compare `old` against `new`.
+pub fn diff_scans(old: &ScanResult, new: &ScanResult, options: &DiffOptions) -> DiffReport {
+    let index = DeltaIndex::new(old, new);
+
+    let mut folders: Vec<PathDelta> = index
+        .old_folders
+        .keys()
+        .chain(index.new_folders.keys().filter(|p| !index.old_folders.contains_key(*p)))
+        .map(|p| index.delta(p))
+        .filter(|d| d.delta != 0)
+        .collect();
+    folders.sort_by(by_abs_delta);
+
+    let top = |sign: i64| {
+        let mut paths = HashSet::new();
+        for root in &index.roots {
+            index.responsible(root, sign, &mut paths);
+        }
+        let mut list: Vec<PathDelta> = paths.iter().map(|p| index.delta(p)).collect();
+        list.sort_by(by_abs_delta);
+        list.truncate(options.limit);
+        list
+    };
+    let top_growth = top(1);
+    let top_shrinkage = top(-1);
+
+    let only_in = |a: &HashMap<&str, u64>, b: &HashMap<&str, u64>| {
+        let mut count = 0u64;
+        let mut bytes = 0u64;
+        let mut listed: Vec<PathDelta> = Vec::new();
+        for (path, &size) in a {
+            if b.contains_key(path) {
+                continue;
+            }
+            count += 1;
+            bytes += size;
+            if size >= options.min_file_size {
+                listed.push(index.delta(path));
+            }
+        }
+        listed.sort_by(by_abs_delta);
+        listed.truncate(options.limit);
+        (count, bytes, listed)
+    };
+    let (new_files_count, new_files_bytes, new_files) = only_in(&index.new_files, &index.old_files);
+    let (removed_files_count, removed_files_bytes, removed_files) =
+        only_in(&index.old_files, &index.new_files);
+
+    let old_total: u64 = index.old_files.values().sum();
+    let new_total: u64 = index.new_files.values().sum();
+    let tree = index
+        .roots
+        .iter()
+        .map(|r| index.tree(r, 0, options.tree_max_depth, options.tree_max_children))
+        .collect();
+
+    DiffReport {
+        old_total,
+        new_total,
+        total_delta: new_total as i64 - old_total as i64,
+        folders,
+        top_growth,
+        top_shrinkage,
+        new_files_count,
+        new_files_bytes,
+        new_files,
+        removed_files_count,
+        removed_files_bytes,
+        removed_files,
+        tree,
+    }
+}
+
+/// Delta tree below `start_path`, for drilling into one folder of a diff.
+///
+/// `None` when the folder exists in neither scan.
+pub fn build_delta_tree(
+    old: &ScanResult,
+    new: &ScanResult,
+    start_path: &str,
+    max_children_per_node: usize,
+    max_depth: usize,
+) -> Option<DeltaTreeNode> {
+    let index = DeltaIndex::new(old, new);
+    if !index.is_folder(start_path) {
+        return None;
+    }
+    Some(index.tree(start_path, 0, max_depth, max_children_per_node))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,144 @@
+use super::*;
+
+use crate::{FileEntrySer, FileKey};
+
+/// Build a scan from file paths and sizes; folder sizes are summed up to `/r`.
+fn scan(files: &[(&str, u64)]) -> ScanResult {
+    let mut folder_sizes: HashMap<String, u64> = HashMap::new();
+    for (path, size) in files {
+        let mut dir = parent_dir(path);
+        while dir.starts_with("/r") {
+            *folder_sizes.entry(dir.clone()).or_insert(0) += size;
+            dir = parent_dir(&dir);
+        }
+    }
+    ScanResult {
+        roots: vec!["/r".to_string()],
+        files: files
+            .iter()
+            .map(|(path, size)| FileEntrySer {
+                path: path.to_string(),
+                size: *size,
+                file_key: FileKey { dev: 0, ino: 0 },
+                mtime: None,
+            })
+            .collect(),
+        folder_sizes,
+    }
+}
+
+fn paths(list: &[PathDelta]) -> Vec<&str> {
+    list.iter().map(|d| d.path.as_str()).collect()
+}
+
+#[test]
+fn reports_new_removed_and_folder_deltas() {
+    let old = scan(&[("/r/a/keep.bin", 100), ("/r/a/gone.bin", 40), ("/r/b/x.bin", 10)]);
+    let new = scan(&[("/r/a/keep.bin", 100), ("/r/b/x.bin", 10), ("/r/b/new.bin", 500), ("/r/b/tiny", 1)]);
+    let options = DiffOptions {
+        min_file_size: 2,
+        ..DiffOptions::default()
+    };
+    let report = diff_scans(&old, &new, &options);
+
+    assert_eq!((report.old_total, report.new_total, report.total_delta), (150, 611, 461));
+    assert_eq!(paths(&report.folders), ["/r/b", "/r", "/r/a"]);
+    assert_eq!(report.folders[0].delta, 501);
+    assert_eq!(report.folders[2].change, ChangeKind::Shrunk);
+
+    assert_eq!((report.new_files_count, report.new_files_bytes), (2, 501));
+    assert_eq!(paths(&report.new_files), ["/r/b/new.bin"]);
+    assert_eq!(report.new_files[0].change, ChangeKind::Added);
+    assert_eq!((report.removed_files_count, report.removed_files_bytes), (1, 40));
+    assert_eq!(report.removed_files[0].delta, -40);
+}
+
+#[test]
+fn top_growth_skips_ancestors_of_a_dominant_child() {
+    let old = scan(&[("/r/x/y/z/old.bin", 10), ("/r/other.bin", 10)]);
+    let new = scan(&[
+        ("/r/x/y/z/old.bin", 10),
+        ("/r/x/y/z/big.bin", 1000),
+        ("/r/x/y/z/big2.bin", 1000),
+        ("/r/other.bin", 15),
+    ]);
+    let report = diff_scans(&old, &new, &DiffOptions::default());
+
+    // `/r`, `/r/x` and `/r/x/y` only grew because of `/r/x/y/z`; the change there is split
+    // between two files, so the folder itself is listed together with both files. The small
+    // sibling that grew as well still shows up.
+    assert_eq!(
+        paths(&report.top_growth),
+        ["/r/x/y/z", "/r/x/y/z/big.bin", "/r/x/y/z/big2.bin", "/r/other.bin"]
+    );
+    assert!(report.top_shrinkage.is_empty());
+}
+
+#[test]
+fn top_shrinkage_follows_a_removed_folder() {
+    let old = scan(&[("/r/cache/blob", 900), ("/r/doc", 50)]);
+    let new = scan(&[("/r/doc", 50)]);
+    let report = diff_scans(&old, &new, &DiffOptions::default());
+
+    assert_eq!(paths(&report.top_shrinkage), ["/r/cache/blob"]);
+    assert_eq!(report.top_shrinkage[0].change, ChangeKind::Removed);
+}
+
+#[test]
+fn delta_tree_marks_changes_and_groups_the_rest() {
+    let old = scan(&[("/r/a/1", 10), ("/r/b/1", 10), ("/r/c/1", 10), ("/r/d/1", 10)]);
+    let new = scan(&[("/r/a/1", 30), ("/r/b/1", 5), ("/r/c/1", 10), ("/r/e/1", 7)]);
+    let report = diff_scans(
+        &old,
+        &new,
+        &DiffOptions {
+            tree_max_children: 3,
+            tree_max_depth: 1,
+            ..DiffOptions::default()
+        },
+    );
+
+    assert_eq!(report.tree.len(), 1);
+    let root = &report.tree[0];
+    assert_eq!((root.old_size, root.new_size, root.delta), (40, 52, 12));
+    let children = root.children.as_ref().unwrap();
+    let summary: Vec<(&str, ChangeKind, i64)> =
+        children.iter().map(|c| (c.name.as_str(), c.change, c.delta)).collect();
+    assert_eq!(
+        summary,
+        [
+            ("a", ChangeKind::Grown, 20),
+            ("d", ChangeKind::Removed, -10),
+            ("Other", ChangeKind::Grown, 2),
+        ]
+    );
+    let other = children[2].children.as_ref().unwrap();
+    assert_eq!(other.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["e", "b", "c"]);
+    assert_eq!(other[0].change, ChangeKind::Added);
+
+    let sub = build_delta_tree(&old, &new, "/r/a", 10, 2).unwrap();
+    assert_eq!(sub.children.unwrap()[0].delta, 20);
+    assert!(build_delta_tree(&old, &new, "/nope", 10, 2).is_none());
+}
+
+#[test]
+fn shrinkage_inside_a_growing_folder_is_found() {
+    let old = scan(&[("/r/a/grow", 10), ("/r/b/shrink", 50)]);
+    let new = scan(&[("/r/a/grow", 500), ("/r/b/shrink", 20)]);
+    let report = diff_scans(&old, &new, &DiffOptions::default());
+
+    assert_eq!(paths(&report.top_growth), ["/r/a/grow"]);
+    assert_eq!(paths(&report.top_shrinkage), ["/r/b/shrink"]);
+}
+
+#[test]
+fn siblings_of_a_dominant_child_are_still_visited() {
+    // `/r/a` grew by 100: `big` by 130 dominates, while `b` shrank by 30 with one file growing
+    // inside it.
+    let old = scan(&[("/r/a/big", 0), ("/r/a/b/up", 0), ("/r/a/b/down", 40)]);
+    let new = scan(&[("/r/a/big", 130), ("/r/a/b/up", 10), ("/r/a/b/down", 0)]);
+    let report = diff_scans(&old, &new, &DiffOptions::default());
+
+    assert_eq!(paths(&report.top_growth), ["/r/a/big", "/r/a/b/up"]);
+    assert_eq!(paths(&report.top_shrinkage), ["/r/a/b/down"]);
+}
@@ -0,0 +1,424 @@
+//! Content-hash duplicate detection.
+//!
+//! # Pipeline
+//!
+//! 1. **Hard links**: entries that share a [`FileKey`] are the same file on disk, so only the
+//!    first path seen for each key takes part. Hardlinked copies never show up as duplicates.
+//! 2. **Size**: a file whose size is unique cannot have a duplicate. Files smaller than
+//!    [`DuplicateOptions::min_size`] are ignored.
+//! 3. **Partial hash**: hash the first and last [`PARTIAL_HASH_BYTES`] of every remaining
+//!    candidate. Files that differ are usually already distinguishable here without reading
+//!    the middle of large files.
+//! 4. **Full hash**: hash the entire content of the files that still collide.
+//!
+//! Stages 3 and 4 run in parallel with rayon. Files that cannot be read (deleted, permission
+//! denied) are silently dropped from the candidate set.
+//!
+//! Whole duplicated folders are found by [`folders::find_duplicate_folders`], which builds on the
+//! same hashes. [`dedupe`] reclaims the space by replacing copies with hard links or reflinks.
+//! Resized or re-encoded photos are matched by [`similar_images`] with a perceptual hash.
+//!
+//! # Hash cache
+//!
+//! Both hash stages consult a [`HashCache`] keyed on `(dev, ino, size, mtime)` before reading a
+//! file, so unchanged files are only read once. The Tauri host loads the cache from the
+//! `file_hashes` table (`db::read_file_hashes`) and writes new entries back afterwards
+//! (`db::write_file_hashes`). Files without a known identity or mtime are never cached.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{FileEntry, FileEntrySer, FileKey, ScanProgress};
+use crate::core::scanning::utils::PROGRESS_INTERVAL;
+
+pub mod dedupe;
+pub mod folders;
+pub mod similar_images;
+
+/// Bytes hashed from each end of a file during the partial-hash stage.
+///
+/// Files up to twice this size are fully covered by the partial hash, so they skip stage 4.
+pub const PARTIAL_HASH_BYTES: u64 = 16 * 1024;
+
+const READ_BUF_SIZE: usize = 256 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct DuplicateOptions {
+    /// Files smaller than this are never reported. Defaults to 1 so empty files are skipped.
+    pub min_size: u64,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        DuplicateOptions { min_size: 1 }
+    }
+}
+
+/// How the files of a [`DuplicateGroup`] were matched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateKind {
+    /// Byte-identical content.
+    #[default]
+    Exact,
+    /// Images that look alike (see [`similar_images`]); the bytes may differ.
+    Similar,
+}
+
+/// A set of files with identical (or, for [`DuplicateKind::Similar`], visually similar) content.
+#[derive(Clone, Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// Hex-encoded BLAKE3 hash of the shared content, or the perceptual hash of the first file
+    /// for similar images.
+    pub hash: String,
+    /// Size of one copy in bytes. For similar images, the size of the largest file.
+    pub size: u64,
+    /// One entry per distinct file on disk, sorted by path. Similar images are sorted largest
+    /// first instead, since the largest file is usually the one worth keeping.
+    pub files: Vec<FileEntrySer>,
+    /// Bytes that would be freed by keeping a single copy (the largest, for similar images).
+    pub wasted_bytes: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DuplicateReport {
+    /// Groups sorted by `wasted_bytes`, largest first.
+    pub groups: Vec<DuplicateGroup>,
+    pub total_wasted_bytes: u64,
+    /// Number of files read during the partial and full hash stages.
+    pub files_hashed: u64,
+    /// Number of bytes read during the partial and full hash stages.
+    pub bytes_hashed: u64,
+    /// Number of hashes taken from the [`HashCache`] instead of reading the file.
+    pub cache_hits: u64,
+}
+
+/// Identity of a file's content as far as the hash cache is concerned.
+///
+/// A file whose size or mtime changed gets a new key, so stale hashes are never returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HashCacheKey {
+    pub dev: u64,
+    pub ino: u64,
+    pub size: u64,
+    pub mtime: i64,
+}
+
+impl HashCacheKey {
+    /// `None` for entries without a real file key or mtime (mtime 0 is how the DB stores unknown).
+    pub fn for_entry(entry: &FileEntry) -> Option<HashCacheKey> {
+        let mtime = entry.mtime.filter(|&m| m != 0)?;
+        if is_unknown_key(&entry.file_key) {
+            return None;
+        }
+        Some(HashCacheKey {
+            dev: entry.file_key.dev,
+            ino: entry.file_key.ino,
+            size: entry.size,
+            mtime,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CachedHashes {
+    pub partial: Option<[u8; 32]>,
+    pub full: Option<[u8; 32]>,
+}
+
+/// Hashes computed by earlier runs, plus a record of which entries changed in this run.
+#[derive(Clone, Debug, Default)]
+pub struct HashCache {
+    entries: HashMap<HashCacheKey, CachedHashes>,
+    dirty: HashSet<HashCacheKey>,
+}
+
+impl HashCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a cache from persisted entries. Nothing is marked dirty.
+    pub fn from_entries<I: IntoIterator<Item = (HashCacheKey, CachedHashes)>>(entries: I) -> Self {
+        HashCache {
+            entries: entries.into_iter().collect(),
+            dirty: HashSet::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &HashCacheKey) -> Option<CachedHashes> {
+        self.entries.get(key).copied()
+    }
+
+    pub fn insert_partial(&mut self, key: HashCacheKey, hash: blake3::Hash) {
+        self.entries.entry(key).or_default().partial = Some(*hash.as_bytes());
+        self.dirty.insert(key);
+    }
+
+    pub fn insert_full(&mut self, key: HashCacheKey, hash: blake3::Hash) {
+        self.entries.entry(key).or_default().full = Some(*hash.as_bytes());
+        self.dirty.insert(key);
+    }
+
+    /// Entries added or updated since the cache was built, i.e. the ones that need persisting.
+    pub fn dirty_entries(&self) -> Vec<(HashCacheKey, CachedHashes)> {
+        self.dirty
+            .iter()
+            .filter_map(|k| self.entries.get(k).map(|h| (*k, *h)))
+            .collect()
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
+}
+
+pub fn find_duplicates(files: &[FileEntry], options: &DuplicateOptions) -> DuplicateReport {
+    find_duplicates_with_progress(files, options, |_| {})
+}
+
+pub fn find_duplicates_with_progress<F>(
+    files: &[FileEntry],
+    options: &DuplicateOptions,
+    progress: F,
+) -> DuplicateReport
+where
+    F: FnMut(ScanProgress) + Send,
+{
+    find_duplicates_with_cache(files, options, &mut HashCache::new(), progress)
+}
+
+/// Like [`find_duplicates_with_progress`], but looks up hashes in `cache` before reading a file
+/// and records every newly computed hash in it.
+pub fn find_duplicates_with_cache<F>(
+    files: &[FileEntry],
+    options: &DuplicateOptions,
+    cache: &mut HashCache,
+    progress: F,
+) -> DuplicateReport
+where
+    F: FnMut(ScanProgress) + Send,
+{
+    let progress = Mutex::new(progress);
+    let report = |status: &str, files_count: u64| {
+        if let Ok(mut cb) = progress.lock() {
+            cb(ScanProgress {
+                files_count,
+                current_path: None,
+                status: Some(status.to_string()),
+            });
+        }
+    };
+
+    report("Grouping files by size…", 0);
+    let by_size = group_by_size(files, options.min_size);
+    let candidates: Vec<&FileEntry> = by_size.values().flatten().copied().collect();
+
+    let hashed = AtomicU64::new(0);
+    let bytes_read = AtomicU64::new(0);
+    let cache_hits = AtomicU64::new(0);
+    let tick = |path: &Path| {
+        let n = hashed.fetch_add(1, Ordering::Relaxed) + 1;
+        if n.is_multiple_of(PROGRESS_INTERVAL) {
+            if let Ok(mut cb) = progress.lock() {
+                cb(ScanProgress {
+                    files_count: n,
+                    current_path: Some(path.to_string_lossy().to_string()),
+                    status: None,
+                });
+            }
+        }
+    };
+
+    report("Hashing file heads and tails…", 0);
+    let lookup = &*cache;
+    let partial: Vec<(&FileEntry, blake3::Hash, bool)> = candidates
+        .par_iter()
+        .filter_map(|entry| {
+            let cached = HashCacheKey::for_entry(entry)
+                .and_then(|k| lookup.get(&k))
+                .and_then(|h| h.partial);
+            if let Some(bytes) = cached {
+                cache_hits.fetch_add(1, Ordering::Relaxed);
+                return Some((*entry, blake3::Hash::from_bytes(bytes), false));
+            }
+            let (hash, n) = partial_hash(&entry.path, entry.size).ok()?;
+            bytes_read.fetch_add(n, Ordering::Relaxed);
+            tick(&entry.path);
+            Some((*entry, hash, true))
+        })
+        .collect();
+
+    for (entry, hash, _) in partial.iter().filter(|(_, _, fresh)| *fresh) {
+        if let Some(key) = HashCacheKey::for_entry(entry) {
+            cache.insert_partial(key, *hash);
+        }
+    }
+    let partial_groups = retain_collisions(partial.into_iter().map(|(e, h, _)| (e, h)).collect());
+
+    report("Hashing full file contents…", hashed.load(Ordering::Relaxed));
+    let lookup = &*cache;
+    let full: Vec<(&FileEntry, blake3::Hash, bool)> = partial_groups
+        .into_par_iter()
+        .flat_map_iter(|(_, group)| group)
+        .filter_map(|(entry, partial)| {
+            // The partial hash already covered the whole file.
+            if entry.size <= 2 * PARTIAL_HASH_BYTES {
+                return Some((entry, partial, false));
+            }
+            let cached = HashCacheKey::for_entry(entry)
+                .and_then(|k| lookup.get(&k))
+                .and_then(|h| h.full);
+            if let Some(bytes) = cached {
+                cache_hits.fetch_add(1, Ordering::Relaxed);
+                return Some((entry, blake3::Hash::from_bytes(bytes), false));
+            }
+            let (hash, n) = full_hash(&entry.path).ok()?;
+            bytes_read.fetch_add(n, Ordering::Relaxed);
+            tick(&entry.path);
+            Some((entry, hash, true))
+        })
+        .collect();
+
+    for (entry, hash, _) in full.iter().filter(|(_, _, fresh)| *fresh) {
+        if let Some(key) = HashCacheKey::for_entry(entry) {
+            cache.insert_full(key, *hash);
+        }
+    }
+    let full: Vec<(&FileEntry, blake3::Hash)> = full.into_iter().map(|(e, h, _)| (e, h)).collect();
+
+    let mut groups: Vec<DuplicateGroup> = retain_collisions(full)
+        .into_iter()
+        .map(|((size, hash), entries)| to_group(size, hash, entries))
+        .collect();
+    groups.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes).then_with(|| a.hash.cmp(&b.hash)));
+
+    let total_wasted_bytes = groups.iter().map(|g| g.wasted_bytes).sum();
+    let files_hashed = hashed.load(Ordering::Relaxed);
+    report("", files_hashed);
+
+    DuplicateReport {
+        groups,
+        total_wasted_bytes,
+        files_hashed,
+        bytes_hashed: bytes_read.load(Ordering::Relaxed),
+        cache_hits: cache_hits.load(Ordering::Relaxed),
+    }
+}
+
+/// Placeholder key used by loaders that have no real file identity (e.g. the compressed-text
+/// index). Entries with this key are never treated as hard links of each other.
+fn is_unknown_key(key: &FileKey) -> bool {
+    key.dev == 0 && key.ino == 0
+}
+
+/// Size buckets with at least two distinct files, after collapsing hard links.
+fn group_by_size(files: &[FileEntry], min_size: u64) -> HashMap<u64, Vec<&FileEntry>> {
+    let mut seen: HashSet<FileKey> = HashSet::with_capacity(files.len());
+    let mut by_size: HashMap<u64, Vec<&FileEntry>> = HashMap::new();
+    for entry in files {
+        if entry.size < min_size {
+            continue;
+        }
+        if !is_unknown_key(&entry.file_key) && !seen.insert(entry.file_key) {
+            continue;
+        }
+        by_size.entry(entry.size).or_default().push(entry);
+    }
+    by_size.retain(|_, v| v.len() > 1);
+    by_size
+}
+
+/// Group hashed entries by (size, hash), keeping only groups with more than one member.
+///
+/// The size is part of the key so a tail-only collision between different sizes cannot merge.
+#[allow(clippy::type_complexity)]
+fn retain_collisions(
+    hashed: Vec<(&FileEntry, blake3::Hash)>,
+) -> HashMap<(u64, blake3::Hash), Vec<(&FileEntry, blake3::Hash)>> {
+    let mut groups: HashMap<(u64, blake3::Hash), Vec<(&FileEntry, blake3::Hash)>> = HashMap::new();
+    for (entry, hash) in hashed {
+        groups.entry((entry.size, hash)).or_default().push((entry, hash));
+    }
+    groups.retain(|_, v| v.len() > 1);
+    groups
+}
+
+fn to_group(size: u64, hash: blake3::Hash, entries: Vec<(&FileEntry, blake3::Hash)>) -> DuplicateGroup {
+    let mut files: Vec<FileEntrySer> = entries
+        .into_iter()
+        .map(|(entry, _)| FileEntrySer {
+            path: entry.path.to_string_lossy().to_string(),
+            size: entry.size,
+            file_key: entry.file_key,
+            mtime: entry.mtime,
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let wasted_bytes = size * (files.len() as u64 - 1);
+    DuplicateGroup {
+        kind: DuplicateKind::Exact,
+        hash: hash.to_hex().to_string(),
+        size,
+        files,
+        wasted_bytes,
+    }
+}
+
+/// Hash the head and tail of a file. Returns the hash and the number of bytes read.
+///
+/// For files up to `2 * PARTIAL_HASH_BYTES` the head and tail are contiguous, so the result
+/// equals [`full_hash`] of the same file.
+pub fn partial_hash(path: &Path, size: u64) -> std::io::Result<(blake3::Hash, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut read = copy_into(&mut (&mut file).take(PARTIAL_HASH_BYTES), &mut hasher)?;
+    if size > PARTIAL_HASH_BYTES {
+        let tail_start = PARTIAL_HASH_BYTES.max(size.saturating_sub(PARTIAL_HASH_BYTES));
+        file.seek(SeekFrom::Start(tail_start))?;
+        read += copy_into(&mut file, &mut hasher)?;
+    }
+    Ok((hasher.finalize(), read))
+}
+
+/// Hash the full content of a file. Returns the hash and the number of bytes read.
+pub fn full_hash(path: &Path) -> std::io::Result<(blake3::Hash, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let read = copy_into(&mut file, &mut hasher)?;
+    Ok((hasher.finalize(), read))
+}
+
+fn copy_into<R: Read>(reader: &mut R, hasher: &mut blake3::Hasher) -> std::io::Result<u64> {
+    let mut buf = vec![0u8; READ_BUF_SIZE];
+    let mut total = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buf[..n]);
+        total += n as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,264 @@
+//! Reclaim space taken by duplicate files.
+//!
+//! Every copy is compared byte-for-byte with the file being kept right before it is replaced, so
+//! a stale hash (or a file edited since the scan) never loses data. The replacement is built
+//! next to the copy under a temporary name and then renamed over it, so the copy's path always
+//! points at a complete file.
+//!
+//! - [`DedupeMethod::HardLink`] makes the copy another name for the kept file. Both must be on
+//!   the same device, and afterwards they share permissions, owner and mtime.
+//! - [`DedupeMethod::Reflink`] clones the kept file's extents with `FICLONE` (Btrfs, XFS, …).
+//!   The copy stays an independent file that only shares storage until one side is written.
+//!   Linux only.
+//!
+//! Callers that keep an index (the Tauri host's `TrigramIndex`, `disk_objects`) should apply the
+//! new [`DedupedFile::file_key`] and `mtime` of every replaced file afterwards.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::DuplicateGroup;
+use crate::FileKey;
+
+const COMPARE_BUF_SIZE: usize = 256 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupeMethod {
+    HardLink,
+    Reflink,
+}
+
+#[derive(Clone, Debug)]
+pub struct DedupeOptions {
+    pub method: DedupeMethod,
+    /// Verify and report what would be replaced without touching the filesystem.
+    pub dry_run: bool,
+}
+
+/// A copy that was (or, in a dry run, would be) replaced.
+#[derive(Clone, Debug, Serialize)]
+pub struct DedupedFile {
+    pub path: String,
+    /// Identity of the file at `path` afterwards. In a dry run this is the current identity.
+    pub file_key: FileKey,
+    pub mtime: Option<i64>,
+    /// Bytes freed by this replacement. Zero when the old copy still has other hard links.
+    pub bytes_saved: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DedupeReport {
+    pub method: DedupeMethod,
+    pub dry_run: bool,
+    pub kept: String,
+    pub replaced: Vec<DedupedFile>,
+    pub skipped: Vec<SkippedFile>,
+    pub bytes_saved: u64,
+}
+
+/// Replace every file of `group` except `keep` (which must be one of its paths).
+pub fn dedupe_group(group: &DuplicateGroup, keep: &str, options: &DedupeOptions) -> io::Result<DedupeReport> {
+    if !group.files.iter().any(|f| f.path == keep) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not part of the duplicate group", keep),
+        ));
+    }
+    let copies: Vec<PathBuf> = group
+        .files
+        .iter()
+        .filter(|f| f.path != keep)
+        .map(|f| PathBuf::from(&f.path))
+        .collect();
+    dedupe_files(Path::new(keep), &copies, options)
+}
+
+/// Replace each of `copies` with a hard link or reflink of `keep`.
+///
+/// Returns an error only when `keep` itself is unusable; problems with individual copies are
+/// reported in [`DedupeReport::skipped`].
+pub fn dedupe_files(keep: &Path, copies: &[PathBuf], options: &DedupeOptions) -> io::Result<DedupeReport> {
+    let keep_meta = fs::metadata(keep)?;
+    if !keep_meta.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a regular file", keep.display()),
+        ));
+    }
+    let keep_key = crate::file_key_from_path(keep)
+        .ok_or_else(|| io::Error::other(format!("cannot read file identity of {}", keep.display())))?;
+
+    let mut report = DedupeReport {
+        method: options.method,
+        dry_run: options.dry_run,
+        kept: keep.to_string_lossy().to_string(),
+        replaced: Vec::new(),
+        skipped: Vec::new(),
+        bytes_saved: 0,
+    };
+
+    for copy in copies {
+        match dedupe_one(keep, &keep_meta, keep_key, copy, options) {
+            Ok(done) => {
+                report.bytes_saved += done.bytes_saved;
+                report.replaced.push(done);
+            }
+            Err(reason) => report.skipped.push(SkippedFile {
+                path: copy.to_string_lossy().to_string(),
+                reason,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+fn dedupe_one(
+    keep: &Path,
+    keep_meta: &fs::Metadata,
+    keep_key: FileKey,
+    copy: &Path,
+    options: &DedupeOptions,
+) -> Result<DedupedFile, String> {
+    let copy_meta = fs::symlink_metadata(copy).map_err(|e| e.to_string())?;
+    if !copy_meta.is_file() {
+        return Err("not a regular file".into());
+    }
+    if copy_meta.len() != keep_meta.len() {
+        return Err("size changed since the scan".into());
+    }
+    let copy_key = crate::file_key_from_path(copy).ok_or("cannot read file identity")?;
+    if copy_key == keep_key {
+        return Err("already a hard link of the kept file".into());
+    }
+    if options.method == DedupeMethod::HardLink && copy_key.dev != keep_key.dev {
+        return Err("on a different device than the kept file".into());
+    }
+    if !same_content(keep, copy).map_err(|e| e.to_string())? {
+        return Err("content differs from the kept file".into());
+    }
+
+    let bytes_saved = match options.method {
+        // Other names for the old copy keep its data alive.
+        DedupeMethod::HardLink if link_count(&copy_meta) > 1 => 0,
+        _ => copy_meta.len(),
+    };
+
+    if options.dry_run {
+        return Ok(DedupedFile {
+            path: copy.to_string_lossy().to_string(),
+            file_key: copy_key,
+            mtime: mtime_secs(&copy_meta),
+            bytes_saved,
+        });
+    }
+
+    let tmp = temp_path_for(copy);
+    let built = match options.method {
+        DedupeMethod::HardLink => fs::hard_link(keep, &tmp),
+        DedupeMethod::Reflink => reflink(keep, &tmp, &copy_meta),
+    };
+    if let Err(e) = built.and_then(|_| fs::rename(&tmp, copy)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.to_string());
+    }
+
+    let new_meta = fs::metadata(copy).map_err(|e| e.to_string())?;
+    Ok(DedupedFile {
+        path: copy.to_string_lossy().to_string(),
+        file_key: crate::file_key_from_path(copy).ok_or("cannot read file identity")?,
+        mtime: mtime_secs(&new_meta),
+        bytes_saved,
+    })
+}
+
+/// Byte-for-byte comparison of two files of equal length.
+pub fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut fa = File::open(a)?;
+    let mut fb = File::open(b)?;
+    let mut buf_a = vec![0u8; COMPARE_BUF_SIZE];
+    let mut buf_b = vec![0u8; COMPARE_BUF_SIZE];
+    loop {
+        let n = read_full(&mut fa, &mut buf_a)?;
+        let m = read_full(&mut fb, &mut buf_b)?;
+        if n != m || buf_a[..n] != buf_b[..m] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fill `buf` as far as possible; returns less than `buf.len()` only at end of file.
+fn read_full(reader: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.dedupe-tmp", name, std::process::id()))
+}
+
+fn mtime_secs(meta: &fs::Metadata) -> Option<i64> {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+#[cfg(unix)]
+fn link_count(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_meta: &fs::Metadata) -> u64 {
+    1
+}
+
+/// Create `dst` as a reflink clone of `src`, carrying over the permissions of the file it replaces.
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dst: &Path, replaced: &fs::Metadata) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let src_file = File::open(src)?;
+    let dst_file = fs::OpenOptions::new().write(true).create_new(true).open(dst)?;
+    // SAFETY: both descriptors are open for the duration of the call.
+    let rc = unsafe { libc::ioctl(dst_file.as_raw_fd(), libc::FICLONE, src_file.as_raw_fd()) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    dst_file.set_permissions(replaced.permissions())?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_src: &Path, _dst: &Path, _replaced: &fs::Metadata) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflinks are only supported on Linux",
+    ))
+}
+
+#[cfg(test)]
+mod tests;
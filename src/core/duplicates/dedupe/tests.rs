@@ -0,0 +1,121 @@
+use super::*;
+use crate::core::duplicates::{find_duplicates, DuplicateOptions};
+use crate::FileEntry;
+use tempfile::TempDir;
+
+fn write(dir: &TempDir, name: &str, contents: &[u8]) -> FileEntry {
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents).unwrap();
+    FileEntry {
+        size: contents.len() as u64,
+        allocated_size: contents.len() as u64,
+        file_key: crate::file_key_from_path(&path).unwrap(),
+        path,
+        mtime: None,
+        owner: None,
+    }
+}
+
+fn options(method: DedupeMethod, dry_run: bool) -> DedupeOptions {
+    DedupeOptions { method, dry_run }
+}
+
+#[test]
+fn hard_link_replaces_copies_and_reports_new_identity() {
+    let dir = TempDir::new().unwrap();
+    let files = vec![
+        write(&dir, "a.txt", b"duplicate"),
+        write(&dir, "b.txt", b"duplicate"),
+        write(&dir, "c.txt", b"duplicate"),
+    ];
+    let group = &find_duplicates(&files, &DuplicateOptions::default()).groups[0];
+    let keep = files[0].path.to_string_lossy().to_string();
+
+    let report = dedupe_group(group, &keep, &options(DedupeMethod::HardLink, false)).unwrap();
+    assert_eq!(report.replaced.len(), 2);
+    assert!(report.skipped.is_empty());
+    assert_eq!(report.bytes_saved, 18);
+    for done in &report.replaced {
+        assert_eq!(done.file_key, files[0].file_key);
+        assert_eq!(crate::file_key_from_path(Path::new(&done.path)).unwrap(), files[0].file_key);
+        assert_eq!(std::fs::read(&done.path).unwrap(), b"duplicate");
+    }
+    assert!(find_duplicates(&files_after(&files), &DuplicateOptions::default()).groups.is_empty());
+    let leftovers = std::fs::read_dir(dir.path()).unwrap().count();
+    assert_eq!(leftovers, 3, "no temporary files are left behind");
+}
+
+fn files_after(files: &[FileEntry]) -> Vec<FileEntry> {
+    files
+        .iter()
+        .map(|f| FileEntry {
+            file_key: crate::file_key_from_path(&f.path).unwrap(),
+            ..f.clone()
+        })
+        .collect()
+}
+
+#[test]
+fn dry_run_reports_savings_without_touching_files() {
+    let dir = TempDir::new().unwrap();
+    let a = write(&dir, "a.bin", b"same bytes");
+    let b = write(&dir, "b.bin", b"same bytes");
+
+    let report = dedupe_files(&a.path, std::slice::from_ref(&b.path), &options(DedupeMethod::HardLink, true)).unwrap();
+    assert!(report.dry_run);
+    assert_eq!(report.bytes_saved, 10);
+    assert_eq!(report.replaced[0].file_key, b.file_key);
+    assert_eq!(crate::file_key_from_path(&b.path).unwrap(), b.file_key);
+}
+
+#[test]
+fn copies_that_changed_since_the_scan_are_skipped() {
+    let dir = TempDir::new().unwrap();
+    let a = write(&dir, "a.bin", b"original");
+    let b = write(&dir, "b.bin", b"original");
+    std::fs::write(&b.path, b"modified").unwrap();
+
+    let report = dedupe_files(&a.path, std::slice::from_ref(&b.path), &options(DedupeMethod::HardLink, false)).unwrap();
+    assert!(report.replaced.is_empty());
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(std::fs::read(&b.path).unwrap(), b"modified");
+}
+
+#[test]
+fn copy_with_other_links_saves_nothing() {
+    let dir = TempDir::new().unwrap();
+    let a = write(&dir, "a.bin", b"shared");
+    let b = write(&dir, "b.bin", b"shared");
+    std::fs::hard_link(&b.path, dir.path().join("b-link.bin")).unwrap();
+
+    let report = dedupe_files(&a.path, std::slice::from_ref(&b.path), &options(DedupeMethod::HardLink, true)).unwrap();
+    assert_eq!(report.replaced.len(), 1);
+    assert_eq!(report.bytes_saved, 0);
+}
+
+#[test]
+fn keep_must_belong_to_the_group() {
+    let dir = TempDir::new().unwrap();
+    let files = vec![write(&dir, "a", b"xx"), write(&dir, "b", b"xx")];
+    let group = &find_duplicates(&files, &DuplicateOptions::default()).groups[0];
+    let err = dedupe_group(group, "/not/in/group", &options(DedupeMethod::HardLink, true)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn failed_reflink_leaves_the_copy_intact() {
+    let dir = TempDir::new().unwrap();
+    let a = write(&dir, "a.bin", b"reflink me");
+    let b = write(&dir, "b.bin", b"reflink me");
+
+    let report = dedupe_files(&a.path, std::slice::from_ref(&b.path), &options(DedupeMethod::Reflink, false)).unwrap();
+    // Whether FICLONE works depends on the filesystem backing the temp dir.
+    if report.replaced.is_empty() {
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(crate::file_key_from_path(&b.path).unwrap(), b.file_key);
+    } else {
+        assert_ne!(report.replaced[0].file_key, a.file_key, "a reflink is a separate file");
+    }
+    assert_eq!(std::fs::read(&b.path).unwrap(), b"reflink me");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
@@ -0,0 +1,401 @@
+//! Duplicate folder detection.
+//!
+//! Every folder gets a Merkle-style fingerprint: a BLAKE3 hash over its children sorted by name,
+//! where a file contributes its name, size and content hash and a subfolder contributes its name,
+//! recursive size and fingerprint. The folder's own name is not part of its fingerprint, so
+//! `photos` and `photos (copy)` match.
+//!
+//! Hashing every file on disk would defeat the point of the size pre-filter used for files, so a
+//! cheap "shape" fingerprint (names and sizes only) is computed first. Only files below folders
+//! whose shape occurs more than once are read.
+//!
+//! Nested matches are collapsed: when every copy of a folder sits inside a folder that is itself
+//! reported, only the outer group is kept.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use super::{
+    full_hash, is_unknown_key, partial_hash, DuplicateOptions, HashCache, HashCacheKey,
+    PARTIAL_HASH_BYTES,
+};
+use crate::core::scanning::utils::PROGRESS_INTERVAL;
+use crate::{FileEntry, FileKey, ScanProgress};
+
+/// A set of folders whose entire subtrees are identical.
+#[derive(Clone, Debug, Serialize)]
+pub struct DuplicateFolderGroup {
+    /// Hex-encoded fingerprint shared by all folders in the group.
+    pub fingerprint: String,
+    /// Recursive size of one copy in bytes.
+    pub size: u64,
+    /// Number of files in one copy.
+    pub file_count: u64,
+    /// Folder paths, sorted.
+    pub folders: Vec<String>,
+    /// Bytes that would be freed by keeping a single copy. Files that are hard links of a file
+    /// in another copy do not count.
+    pub wasted_bytes: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DuplicateFolderReport {
+    /// Groups sorted by `wasted_bytes`, largest first.
+    pub groups: Vec<DuplicateFolderGroup>,
+    pub total_wasted_bytes: u64,
+    pub files_hashed: u64,
+    pub bytes_hashed: u64,
+    pub cache_hits: u64,
+}
+
+/// Parent/child view over a scan: the folders are the keys of `folder_sizes`, the same set that
+/// is persisted as `disk_objects` folders.
+struct FolderTree<'a> {
+    files: HashMap<&'a Path, Vec<&'a FileEntry>>,
+    subfolders: HashMap<&'a Path, Vec<&'a Path>>,
+    /// Recursive size per folder, counting every file (hard links included).
+    sizes: HashMap<&'a Path, u64>,
+    /// All folders, deepest first.
+    bottom_up: Vec<&'a Path>,
+}
+
+impl<'a> FolderTree<'a> {
+    fn build<S>(files: &'a [FileEntry], folder_sizes: &'a HashMap<std::path::PathBuf, u64, S>) -> Self
+    where
+        S: std::hash::BuildHasher,
+    {
+        let known = |p: Option<&Path>| -> Option<&'a Path> {
+            folder_sizes.get_key_value(p?).map(|(k, _)| k.as_path())
+        };
+
+        let mut tree = FolderTree {
+            files: HashMap::new(),
+            subfolders: HashMap::new(),
+            sizes: HashMap::new(),
+            bottom_up: folder_sizes.keys().map(|k| k.as_path()).collect(),
+        };
+        for folder in &tree.bottom_up {
+            if let Some(parent) = known(folder.parent()) {
+                tree.subfolders.entry(parent).or_default().push(folder);
+            }
+        }
+        for entry in files {
+            if let Some(parent) = known(entry.path.parent()) {
+                tree.files.entry(parent).or_default().push(entry);
+            }
+        }
+        tree.bottom_up
+            .sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+
+        for i in 0..tree.bottom_up.len() {
+            let folder = tree.bottom_up[i];
+            let own: u64 = tree.files.get(folder).map_or(0, |fs| fs.iter().map(|f| f.size).sum());
+            let nested: u64 = tree
+                .subfolders
+                .get(folder)
+                .map_or(0, |subs| subs.iter().map(|s| tree.sizes.get(s).copied().unwrap_or(0)).sum());
+            tree.sizes.insert(folder, own + nested);
+        }
+        tree
+    }
+
+    fn size(&self, folder: &Path) -> u64 {
+        self.sizes.get(folder).copied().unwrap_or(0)
+    }
+
+    /// Fingerprint every folder accepted by `include`, bottom-up. A folder is `None` when one of
+    /// its files has no hash (e.g. unreadable), which keeps it out of every group.
+    fn fingerprints<I, H>(&self, include: I, file_hash: H) -> HashMap<&'a Path, Option<blake3::Hash>>
+    where
+        I: Fn(&Path) -> bool,
+        H: Fn(&FileEntry) -> Option<[u8; 32]>,
+    {
+        let mut out: HashMap<&'a Path, Option<blake3::Hash>> = HashMap::new();
+        for &folder in &self.bottom_up {
+            if !include(folder) {
+                continue;
+            }
+            let mut children: Vec<(&[u8], u8, u64, [u8; 32])> = Vec::new();
+            let mut complete = true;
+            for file in self.files.get(folder).into_iter().flatten() {
+                match file_hash(file) {
+                    Some(hash) => children.push((name_bytes(&file.path), b'f', file.size, hash)),
+                    None => complete = false,
+                }
+            }
+            for &sub in self.subfolders.get(folder).into_iter().flatten() {
+                match out.get(sub).copied().flatten() {
+                    Some(hash) => children.push((name_bytes(sub), b'd', self.size(sub), *hash.as_bytes())),
+                    None => complete = false,
+                }
+            }
+            if !complete {
+                out.insert(folder, None);
+                continue;
+            }
+            children.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            let mut hasher = blake3::Hasher::new();
+            for (name, kind, size, hash) in children {
+                hasher.update(&[kind]);
+                hasher.update(&(name.len() as u64).to_le_bytes());
+                hasher.update(name);
+                hasher.update(&size.to_le_bytes());
+                hasher.update(&hash);
+            }
+            out.insert(folder, Some(hasher.finalize()));
+        }
+        out
+    }
+
+    /// Every file below `folder`, at any depth.
+    fn subtree_files(&self, folder: &Path) -> Vec<&'a FileEntry> {
+        let mut out = Vec::new();
+        let mut stack = vec![folder];
+        while let Some(current) = stack.pop() {
+            out.extend(self.files.get(current).into_iter().flatten().copied());
+            stack.extend(self.subfolders.get(current).into_iter().flatten().copied());
+        }
+        out
+    }
+}
+
+fn name_bytes(path: &Path) -> &[u8] {
+    path.file_name().map_or(&[][..], |n| n.as_encoded_bytes())
+}
+
+pub fn find_duplicate_folders<S>(
+    files: &[FileEntry],
+    folder_sizes: &HashMap<std::path::PathBuf, u64, S>,
+    options: &DuplicateOptions,
+) -> DuplicateFolderReport
+where
+    S: std::hash::BuildHasher,
+{
+    find_duplicate_folders_with_cache(files, folder_sizes, options, &mut HashCache::new(), |_| {})
+}
+
+/// Report folders whose whole subtree exists more than once. `folder_sizes` is the folder map
+/// produced by the scanners (or loaded from `disk_objects`); folders outside it are ignored.
+/// [`DuplicateOptions::min_size`] applies to the recursive folder size.
+pub fn find_duplicate_folders_with_cache<S, F>(
+    files: &[FileEntry],
+    folder_sizes: &HashMap<std::path::PathBuf, u64, S>,
+    options: &DuplicateOptions,
+    cache: &mut HashCache,
+    progress: F,
+) -> DuplicateFolderReport
+where
+    S: std::hash::BuildHasher,
+    F: FnMut(ScanProgress) + Send,
+{
+    let progress = Mutex::new(progress);
+    let report = |status: &str, files_count: u64| {
+        if let Ok(mut cb) = progress.lock() {
+            cb(ScanProgress {
+                files_count,
+                current_path: None,
+                status: Some(status.to_string()),
+            });
+        }
+    };
+
+    report("Comparing folder structures…", 0);
+    let tree = FolderTree::build(files, folder_sizes);
+    let reportable = |folder: &Path| {
+        let size = tree.size(folder);
+        size > 0 && size >= options.min_size
+    };
+
+    // Stage 1: names and sizes only.
+    let shapes = tree.fingerprints(|_| true, |_| Some([0u8; 32]));
+    let mut shape_counts: HashMap<blake3::Hash, usize> = HashMap::new();
+    for (folder, shape) in &shapes {
+        if let Some(shape) = shape {
+            if reportable(folder) {
+                *shape_counts.entry(*shape).or_default() += 1;
+            }
+        }
+    }
+    let mut candidates: HashSet<&Path> = shapes
+        .iter()
+        .filter(|(folder, shape)| {
+            shape.is_some_and(|s| shape_counts.get(&s).is_some_and(|&n| n > 1)) && reportable(folder)
+        })
+        .map(|(folder, _)| *folder)
+        .collect();
+    // Descendants of a candidate are needed for its fingerprint even when too small to report.
+    for &folder in tree.bottom_up.iter().rev() {
+        if folder.parent().is_some_and(|p| candidates.contains(p)) {
+            candidates.insert(folder);
+        }
+    }
+
+    // Stage 2: content hashes for the files below candidates, one read per hard-link set.
+    let mut representatives: HashMap<FileKey, &FileEntry> = HashMap::new();
+    let mut unkeyed: Vec<&FileEntry> = Vec::new();
+    for folder in &candidates {
+        for &file in tree.files.get(folder).into_iter().flatten() {
+            if is_unknown_key(&file.file_key) {
+                unkeyed.push(file);
+            } else {
+                representatives.entry(file.file_key).or_insert(file);
+            }
+        }
+    }
+    let to_hash: Vec<&FileEntry> = representatives.values().copied().chain(unkeyed).collect();
+
+    report("Hashing files in matching folders…", 0);
+    let hashed = AtomicU64::new(0);
+    let bytes_read = AtomicU64::new(0);
+    let cache_hits = AtomicU64::new(0);
+    let lookup = &*cache;
+    let results: Vec<(&FileEntry, blake3::Hash, bool)> = to_hash
+        .par_iter()
+        .filter_map(|&entry| {
+            let small = entry.size <= 2 * PARTIAL_HASH_BYTES;
+            let cached = HashCacheKey::for_entry(entry)
+                .and_then(|k| lookup.get(&k))
+                .and_then(|h| if small { h.partial } else { h.full });
+            if let Some(bytes) = cached {
+                cache_hits.fetch_add(1, Ordering::Relaxed);
+                return Some((entry, blake3::Hash::from_bytes(bytes), false));
+            }
+            let (hash, n) = if small {
+                partial_hash(&entry.path, entry.size).ok()?
+            } else {
+                full_hash(&entry.path).ok()?
+            };
+            bytes_read.fetch_add(n, Ordering::Relaxed);
+            let count = hashed.fetch_add(1, Ordering::Relaxed) + 1;
+            if count.is_multiple_of(PROGRESS_INTERVAL) {
+                if let Ok(mut cb) = progress.lock() {
+                    cb(ScanProgress {
+                        files_count: count,
+                        current_path: Some(entry.path.to_string_lossy().to_string()),
+                        status: None,
+                    });
+                }
+            }
+            Some((entry, hash, true))
+        })
+        .collect();
+
+    let mut by_key: HashMap<FileKey, [u8; 32]> = HashMap::new();
+    let mut by_path: HashMap<&Path, [u8; 32]> = HashMap::new();
+    for (entry, hash, fresh) in results {
+        if fresh {
+            if let Some(key) = HashCacheKey::for_entry(entry) {
+                if entry.size <= 2 * PARTIAL_HASH_BYTES {
+                    cache.insert_partial(key, hash);
+                } else {
+                    cache.insert_full(key, hash);
+                }
+            }
+        }
+        if is_unknown_key(&entry.file_key) {
+            by_path.insert(entry.path.as_path(), *hash.as_bytes());
+        } else {
+            by_key.insert(entry.file_key, *hash.as_bytes());
+        }
+    }
+
+    // Stage 3: full fingerprints for the candidates.
+    report("Fingerprinting folders…", hashed.load(Ordering::Relaxed));
+    let prints = tree.fingerprints(
+        |folder| candidates.contains(folder),
+        |file| {
+            if is_unknown_key(&file.file_key) {
+                by_path.get(file.path.as_path()).copied()
+            } else {
+                by_key.get(&file.file_key).copied()
+            }
+        },
+    );
+    let mut grouped: HashMap<blake3::Hash, Vec<&Path>> = HashMap::new();
+    for (folder, print) in prints {
+        if let Some(print) = print {
+            if reportable(folder) {
+                grouped.entry(print).or_default().push(folder);
+            }
+        }
+    }
+    grouped.retain(|_, members| members.len() > 1);
+
+    // A group only repeats its parents' match when each member sits in a
+    // different copy of one duplicated parent; identical siblings inside a
+    // single parent are still worth reporting.
+    let group_of: HashMap<&Path, blake3::Hash> = grouped
+        .iter()
+        .flat_map(|(print, members)| members.iter().map(move |m| (*m, *print)))
+        .collect();
+    let nested: HashSet<blake3::Hash> = grouped
+        .iter()
+        .filter(|(_, members)| {
+            let Some(parents) = members.iter().map(|m| m.parent()).collect::<Option<HashSet<&Path>>>() else {
+                return false;
+            };
+            let parent_groups: HashSet<Option<&blake3::Hash>> = parents.iter().map(|p| group_of.get(p)).collect();
+            parents.len() == members.len() && parent_groups.len() == 1 && !parent_groups.contains(&None)
+        })
+        .map(|(print, _)| *print)
+        .collect();
+    let mut groups: Vec<DuplicateFolderGroup> = grouped
+        .into_iter()
+        .filter(|(print, _)| !nested.contains(print))
+        .filter_map(|(print, mut members)| {
+            members.sort();
+            let group = to_folder_group(&tree, print, &members);
+            (group.wasted_bytes > 0).then_some(group)
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.wasted_bytes
+            .cmp(&a.wasted_bytes)
+            .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+    });
+
+    let total_wasted_bytes = groups.iter().map(|g| g.wasted_bytes).sum();
+    let files_hashed = hashed.load(Ordering::Relaxed);
+    report("", files_hashed);
+
+    DuplicateFolderReport {
+        groups,
+        total_wasted_bytes,
+        files_hashed,
+        bytes_hashed: bytes_read.load(Ordering::Relaxed),
+        cache_hits: cache_hits.load(Ordering::Relaxed),
+    }
+}
+
+fn to_folder_group(tree: &FolderTree<'_>, print: blake3::Hash, members: &[&Path]) -> DuplicateFolderGroup {
+    let mut seen: HashSet<FileKey> = HashSet::new();
+    let mut wasted_bytes = 0u64;
+    let mut file_count = 0u64;
+    for (i, member) in members.iter().enumerate() {
+        let files = tree.subtree_files(member);
+        if i == 0 {
+            file_count = files.len() as u64;
+        }
+        for file in files {
+            let new_copy = is_unknown_key(&file.file_key) || seen.insert(file.file_key);
+            if i > 0 && new_copy {
+                wasted_bytes += file.size;
+            }
+        }
+    }
+    DuplicateFolderGroup {
+        fingerprint: print.to_hex().to_string(),
+        size: tree.size(members[0]),
+        file_count,
+        folders: members.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+        wasted_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests;
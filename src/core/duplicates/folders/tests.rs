@@ -0,0 +1,114 @@
+use super::*;
+use tempfile::TempDir;
+
+fn put(root: &Path, rel: &str, contents: &[u8]) {
+    let path = root.join(rel);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+fn scan(root: &Path) -> DuplicateFolderReport {
+    let (files, folder_sizes) = crate::index_directory(root);
+    find_duplicate_folders(&files, &folder_sizes, &DuplicateOptions::default())
+}
+
+fn names(group: &DuplicateFolderGroup) -> Vec<String> {
+    group
+        .folders
+        .iter()
+        .map(|f| Path::new(f).file_name().unwrap().to_string_lossy().to_string())
+        .collect()
+}
+
+#[test]
+fn copied_folder_is_reported_once_under_any_name() {
+    let dir = TempDir::new().unwrap();
+    for name in ["photos", "photos copy"] {
+        put(dir.path(), &format!("{}/a.jpg", name), b"jpeg bytes");
+        put(dir.path(), &format!("{}/b.jpg", name), b"more jpeg bytes");
+    }
+    put(dir.path(), "unrelated/a.jpg", b"something else");
+
+    let report = scan(dir.path());
+    assert_eq!(report.groups.len(), 1);
+    let group = &report.groups[0];
+    assert_eq!(names(group), vec!["photos", "photos copy"]);
+    assert_eq!(group.file_count, 2);
+    assert_eq!(group.size, 25);
+    assert_eq!(group.wasted_bytes, 25);
+    assert_eq!(report.total_wasted_bytes, 25);
+}
+
+#[test]
+fn nested_matches_collapse_into_the_top_most_folder() {
+    let dir = TempDir::new().unwrap();
+    for name in ["project", "project-backup"] {
+        put(dir.path(), &format!("{}/src/main.rs", name), b"fn main() {}");
+        put(dir.path(), &format!("{}/src/lib.rs", name), b"pub fn lib() {}");
+        put(dir.path(), &format!("{}/README", name), b"readme");
+    }
+
+    let report = scan(dir.path());
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(names(&report.groups[0]), vec!["project", "project-backup"]);
+}
+
+#[test]
+fn nested_match_with_a_copy_elsewhere_is_still_reported() {
+    let dir = TempDir::new().unwrap();
+    for name in ["a", "b"] {
+        put(dir.path(), &format!("{}/lib/x.bin", name), b"shared library");
+        put(dir.path(), &format!("{}/top.txt", name), b"top");
+    }
+    put(dir.path(), "c/lib/x.bin", b"shared library");
+    put(dir.path(), "c/top.txt", b"different");
+
+    let report = scan(dir.path());
+    assert_eq!(report.groups.len(), 2);
+    let lib_group = report
+        .groups
+        .iter()
+        .find(|g| g.folders.len() == 3)
+        .expect("lib folders from a, b and c");
+    assert!(lib_group.folders.iter().all(|f| f.ends_with("lib")));
+}
+
+#[test]
+fn identical_siblings_inside_a_duplicated_parent_are_still_reported() {
+    let dir = TempDir::new().unwrap();
+    for name in ["a", "b"] {
+        put(dir.path(), &format!("{}/x/data.bin", name), b"same contents");
+        put(dir.path(), &format!("{}/y/data.bin", name), b"same contents");
+    }
+
+    let report = scan(dir.path());
+    assert_eq!(report.groups.len(), 2);
+    let siblings = report
+        .groups
+        .iter()
+        .find(|g| g.folders.len() == 4)
+        .expect("x and y inside both copies");
+    assert_eq!(names(siblings), vec!["x", "y", "x", "y"]);
+}
+
+#[test]
+fn same_names_and_sizes_with_different_content_do_not_match() {
+    let dir = TempDir::new().unwrap();
+    put(dir.path(), "one/data.bin", b"aaaa");
+    put(dir.path(), "two/data.bin", b"bbbb");
+
+    let report = scan(dir.path());
+    assert!(report.groups.is_empty());
+    assert_eq!(report.files_hashed, 2);
+}
+
+#[test]
+fn hardlinked_folder_copy_wastes_nothing() {
+    let dir = TempDir::new().unwrap();
+    put(dir.path(), "orig/file.txt", b"linked");
+    std::fs::create_dir_all(dir.path().join("links")).unwrap();
+    std::fs::hard_link(dir.path().join("orig/file.txt"), dir.path().join("links/file.txt")).unwrap();
+
+    let report = scan(dir.path());
+    assert!(report.groups.is_empty());
+}
@@ -0,0 +1,253 @@
+//! Near-duplicate detection for images.
+//!
+//! Every file whose extension is in [`search_category::IMAGE`] is decoded on the CPU and reduced
+//! to a 64-bit difference hash (dHash): the image is converted to grayscale, scaled to 9×8
+//! pixels, and each bit records whether a pixel is brighter than its right neighbour. Resizing,
+//! re-encoding and small colour changes flip only a few bits, so two files are considered the
+//! same picture when their hashes are within [`SimilarImageOptions::max_distance`] bits
+//! (Hamming distance).
+//!
+//! Files are clustered transitively: if A is close to B and B is close to C, all three end up in
+//! one group even when A and C are further apart. Formats the `image` crate cannot decode (HEIC,
+//! SVG) and unreadable files are skipped.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use image::imageops::FilterType;
+use rayon::prelude::*;
+
+use super::{is_unknown_key, DuplicateGroup, DuplicateKind, DuplicateReport};
+use crate::core::scanning::utils::PROGRESS_INTERVAL;
+use crate::core::search_category;
+use crate::{FileEntry, FileEntrySer, FileKey, ScanProgress};
+
+#[derive(Clone, Debug)]
+pub struct SimilarImageOptions {
+    /// Maximum number of differing bits (out of 64) for two images to count as the same picture.
+    pub max_distance: u32,
+    /// Images smaller than this (in bytes) are ignored.
+    pub min_size: u64,
+}
+
+impl Default for SimilarImageOptions {
+    fn default() -> Self {
+        SimilarImageOptions {
+            max_distance: 10,
+            min_size: 1,
+        }
+    }
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .is_some_and(|e| search_category::IMAGE.contains(&e.as_str()))
+}
+
+/// 64-bit difference hash of the image at `path`.
+pub fn dhash(path: &Path) -> image::ImageResult<u64> {
+    let gray = image::open(path)?.grayscale();
+    let small = gray.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Ok(hash)
+}
+
+pub fn find_similar_images(files: &[FileEntry], options: &SimilarImageOptions) -> DuplicateReport {
+    find_similar_images_with_progress(files, options, |_| {})
+}
+
+/// Group visually similar images. Groups use [`DuplicateKind::Similar`].
+pub fn find_similar_images_with_progress<F>(
+    files: &[FileEntry],
+    options: &SimilarImageOptions,
+    progress: F,
+) -> DuplicateReport
+where
+    F: FnMut(ScanProgress) + Send,
+{
+    let progress = Mutex::new(progress);
+    if let Ok(mut cb) = progress.lock() {
+        cb(ScanProgress {
+            files_count: 0,
+            current_path: None,
+            status: Some("Hashing images…".to_string()),
+        });
+    }
+
+    let mut seen: HashSet<FileKey> = HashSet::new();
+    let candidates: Vec<&FileEntry> = files
+        .iter()
+        .filter(|e| e.size >= options.min_size && is_image(&e.path))
+        .filter(|e| is_unknown_key(&e.file_key) || seen.insert(e.file_key))
+        .collect();
+
+    let decoded = AtomicU64::new(0);
+    let bytes_read = AtomicU64::new(0);
+    let hashed: Vec<(&FileEntry, u64)> = candidates
+        .par_iter()
+        .filter_map(|&entry| {
+            let hash = dhash(&entry.path).ok()?;
+            bytes_read.fetch_add(entry.size, Ordering::Relaxed);
+            let n = decoded.fetch_add(1, Ordering::Relaxed) + 1;
+            if n.is_multiple_of(PROGRESS_INTERVAL) {
+                if let Ok(mut cb) = progress.lock() {
+                    cb(ScanProgress {
+                        files_count: n,
+                        current_path: Some(entry.path.to_string_lossy().to_string()),
+                        status: None,
+                    });
+                }
+            }
+            Some((entry, hash))
+        })
+        .collect();
+
+    let mut groups: Vec<DuplicateGroup> = cluster(&hashed, options.max_distance)
+        .into_iter()
+        .map(|members| to_similar_group(&hashed, members))
+        .collect();
+    groups.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes).then_with(|| a.files[0].path.cmp(&b.files[0].path)));
+
+    let files_hashed = decoded.load(Ordering::Relaxed);
+    if let Ok(mut cb) = progress.lock() {
+        cb(ScanProgress {
+            files_count: files_hashed,
+            current_path: None,
+            status: Some(String::new()),
+        });
+    }
+
+    DuplicateReport {
+        total_wasted_bytes: groups.iter().map(|g| g.wasted_bytes).sum(),
+        groups,
+        files_hashed,
+        bytes_hashed: bytes_read.load(Ordering::Relaxed),
+        cache_hits: 0,
+    }
+}
+
+/// Indices into `hashed` for every cluster with more than one member.
+fn cluster(hashed: &[(&FileEntry, u64)], max_distance: u32) -> Vec<Vec<usize>> {
+    let mut tree = BkTree::default();
+    let mut sets = UnionFind::new(hashed.len());
+    for (i, (_, hash)) in hashed.iter().enumerate() {
+        for j in tree.within(*hash, max_distance) {
+            sets.union(i, j);
+        }
+        tree.insert(*hash, i);
+    }
+
+    let mut clusters: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..hashed.len() {
+        clusters.entry(sets.find(i)).or_default().push(i);
+    }
+    clusters.into_values().filter(|c| c.len() > 1).collect()
+}
+
+fn to_similar_group(hashed: &[(&FileEntry, u64)], members: Vec<usize>) -> DuplicateGroup {
+    let mut members: Vec<(&FileEntry, u64)> = members.into_iter().map(|i| hashed[i]).collect();
+    members.sort_by(|a, b| b.0.size.cmp(&a.0.size).then_with(|| a.0.path.cmp(&b.0.path)));
+    let (largest, hash) = members[0];
+    DuplicateGroup {
+        kind: DuplicateKind::Similar,
+        hash: format!("{:016x}", hash),
+        size: largest.size,
+        wasted_bytes: members[1..].iter().map(|(e, _)| e.size).sum(),
+        files: members
+            .into_iter()
+            .map(|(entry, _)| FileEntrySer {
+                path: entry.path.to_string_lossy().to_string(),
+                size: entry.size,
+                file_key: entry.file_key,
+                mtime: entry.mtime,
+            })
+            .collect(),
+    }
+}
+
+/// Burkhard–Keller tree over 64-bit hashes with Hamming distance, so a lookup only visits
+/// subtrees that can contain a match instead of every hash.
+#[derive(Default)]
+struct BkTree {
+    /// (hash, item, children keyed by distance to this node)
+    nodes: Vec<(u64, usize, HashMap<u32, usize>)>,
+}
+
+impl BkTree {
+    fn insert(&mut self, hash: u64, item: usize) {
+        let new = self.nodes.len();
+        if new > 0 {
+            let mut current = 0;
+            loop {
+                let d = (self.nodes[current].0 ^ hash).count_ones();
+                match self.nodes[current].2.get(&d) {
+                    Some(&next) => current = next,
+                    None => {
+                        self.nodes[current].2.insert(d, new);
+                        break;
+                    }
+                }
+            }
+        }
+        self.nodes.push((hash, item, HashMap::new()));
+    }
+
+    fn within(&self, hash: u64, max_distance: u32) -> Vec<usize> {
+        let mut out = Vec::new();
+        if self.nodes.is_empty() {
+            return out;
+        }
+        let mut stack = vec![0];
+        while let Some(current) = stack.pop() {
+            let (node_hash, item, children) = &self.nodes[current];
+            let d = (node_hash ^ hash).count_ones();
+            if d <= max_distance {
+                out.push(*item);
+            }
+            let lo = d.saturating_sub(max_distance);
+            let hi = d.saturating_add(max_distance);
+            stack.extend(children.iter().filter(|(k, _)| (lo..=hi).contains(*k)).map(|(_, &c)| c));
+        }
+        out
+    }
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        UnionFind { parent: (0..n).collect() }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra != rb {
+            self.parent[ra.max(rb)] = ra.min(rb);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,87 @@
+use super::*;
+use image::{ImageBuffer, Rgb};
+use tempfile::TempDir;
+
+fn entry_for(path: &Path) -> FileEntry {
+    FileEntry {
+        path: path.to_path_buf(),
+        size: std::fs::metadata(path).unwrap().len(),
+        allocated_size: std::fs::metadata(path).unwrap().len(),
+        file_key: crate::file_key_from_path(path).unwrap(),
+        mtime: None,
+        owner: None,
+    }
+}
+
+/// A picture with some structure: diagonal bands whose direction depends on `flip`.
+fn picture(size: u32, flip: bool) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(size, size, |x, y| {
+        let (x, y) = (x * 64 / size, y * 64 / size);
+        let v = if flip { (x * 3 + y * 2) % 256 } else { (255 - x * 4 + y) % 256 };
+        Rgb([v as u8, (v / 2) as u8, 255 - v as u8])
+    })
+}
+
+#[test]
+fn resized_and_reencoded_copy_is_grouped() {
+    let dir = TempDir::new().unwrap();
+    let original = dir.path().join("original.png");
+    let resized = dir.path().join("resized.jpg");
+    let other = dir.path().join("other.png");
+    picture(128, false).save(&original).unwrap();
+    image::imageops::resize(&picture(128, false), 48, 48, FilterType::Triangle)
+        .save(&resized)
+        .unwrap();
+    picture(128, true).save(&other).unwrap();
+
+    let files = vec![entry_for(&original), entry_for(&resized), entry_for(&other)];
+    let report = find_similar_images(&files, &SimilarImageOptions::default());
+    assert_eq!(report.files_hashed, 3);
+    assert_eq!(report.groups.len(), 1);
+    let group = &report.groups[0];
+    assert_eq!(group.kind, DuplicateKind::Similar);
+    assert_eq!(group.files.len(), 2);
+    assert!(group.files[0].size >= group.files[1].size, "largest file first");
+    assert_eq!(group.size, group.files[0].size);
+    assert_eq!(group.wasted_bytes, group.files[1].size);
+}
+
+#[test]
+fn non_images_and_undecodable_files_are_skipped() {
+    let dir = TempDir::new().unwrap();
+    let text = dir.path().join("notes.txt");
+    let broken = dir.path().join("broken.jpg");
+    std::fs::write(&text, b"not an image").unwrap();
+    std::fs::write(&broken, b"not a jpeg either").unwrap();
+
+    let report = find_similar_images(&[entry_for(&text), entry_for(&broken)], &SimilarImageOptions::default());
+    assert!(report.groups.is_empty());
+    assert_eq!(report.files_hashed, 0);
+}
+
+#[test]
+fn bk_tree_lookup_matches_brute_force() {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let base: Vec<u64> = (0..20).map(|_| next()).collect();
+    // Cluster members a few bits away from their base hash.
+    let hashes: Vec<u64> = (0..400).map(|i| base[i % 20] ^ (next() & next() & next() & next())).collect();
+
+    let mut tree = BkTree::default();
+    for (i, h) in hashes.iter().enumerate() {
+        tree.insert(*h, i);
+    }
+    for query in hashes.iter().take(50) {
+        let mut found = tree.within(*query, 8);
+        found.sort_unstable();
+        let expected: Vec<usize> = (0..hashes.len())
+            .filter(|&i| (hashes[i] ^ query).count_ones() <= 8)
+            .collect();
+        assert_eq!(found, expected);
+    }
+}
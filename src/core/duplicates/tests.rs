@@ -0,0 +1,166 @@
+use super::*;
+use tempfile::TempDir;
+
+fn entry_for(path: &Path) -> FileEntry {
+    let meta = std::fs::metadata(path).unwrap();
+    FileEntry {
+        path: path.to_path_buf(),
+        size: meta.len(),
+        allocated_size: crate::allocated_size(&meta),
+        file_key: crate::file_key_from_path(path).unwrap(),
+        mtime: None,
+        owner: None,
+    }
+}
+
+fn write(dir: &TempDir, name: &str, contents: &[u8]) -> FileEntry {
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents).unwrap();
+    entry_for(&path)
+}
+
+#[test]
+fn identical_files_form_one_group() {
+    let dir = TempDir::new().unwrap();
+    let files = vec![
+        write(&dir, "a.txt", b"same content"),
+        write(&dir, "b.txt", b"same content"),
+        write(&dir, "c.txt", b"other stuff!"),
+    ];
+
+    let report = find_duplicates(&files, &DuplicateOptions::default());
+    assert_eq!(report.groups.len(), 1);
+    let group = &report.groups[0];
+    assert_eq!(group.files.len(), 2);
+    assert!(group.files[0].path.ends_with("a.txt"));
+    assert!(group.files[1].path.ends_with("b.txt"));
+    assert_eq!(group.size, 12);
+    assert_eq!(group.wasted_bytes, 12);
+    assert_eq!(report.total_wasted_bytes, 12);
+}
+
+#[test]
+fn same_size_different_content_is_not_a_duplicate() {
+    let dir = TempDir::new().unwrap();
+    let files = vec![
+        write(&dir, "a.bin", b"aaaa"),
+        write(&dir, "b.bin", b"bbbb"),
+    ];
+
+    let report = find_duplicates(&files, &DuplicateOptions::default());
+    assert!(report.groups.is_empty());
+    assert_eq!(report.files_hashed, 2);
+}
+
+#[test]
+fn large_files_differing_only_in_the_middle_are_distinguished() {
+    let dir = TempDir::new().unwrap();
+    let len = (PARTIAL_HASH_BYTES * 4) as usize;
+    let base = vec![7u8; len];
+    let mut changed = base.clone();
+    changed[len / 2] = 8;
+
+    let files = vec![
+        write(&dir, "one.img", &base),
+        write(&dir, "two.img", &base),
+        write(&dir, "three.img", &changed),
+    ];
+
+    let report = find_duplicates(&files, &DuplicateOptions::default());
+    assert_eq!(report.groups.len(), 1);
+    let paths: Vec<&str> = report.groups[0].files.iter().map(|f| f.path.as_str()).collect();
+    assert!(paths.iter().any(|p| p.ends_with("one.img")));
+    assert!(paths.iter().any(|p| p.ends_with("two.img")));
+    assert!(!paths.iter().any(|p| p.ends_with("three.img")));
+}
+
+#[test]
+fn hard_links_are_not_reported_as_duplicates() {
+    let dir = TempDir::new().unwrap();
+    let original = write(&dir, "original.txt", b"linked content");
+    let link_path = dir.path().join("link.txt");
+    std::fs::hard_link(&original.path, &link_path).unwrap();
+    let files = vec![original, entry_for(&link_path)];
+
+    let report = find_duplicates(&files, &DuplicateOptions::default());
+    assert!(report.groups.is_empty());
+}
+
+#[test]
+fn empty_and_small_files_respect_min_size() {
+    let dir = TempDir::new().unwrap();
+    let files = vec![
+        write(&dir, "empty1", b""),
+        write(&dir, "empty2", b""),
+        write(&dir, "tiny1", b"xy"),
+        write(&dir, "tiny2", b"xy"),
+    ];
+
+    let report = find_duplicates(&files, &DuplicateOptions::default());
+    assert_eq!(report.groups.len(), 1, "empty files are skipped by default");
+
+    let report = find_duplicates(&files, &DuplicateOptions { min_size: 3 });
+    assert!(report.groups.is_empty());
+}
+
+#[test]
+fn partial_hash_equals_full_hash_for_small_files() {
+    let dir = TempDir::new().unwrap();
+    let entry = write(&dir, "mid.bin", &vec![3u8; (PARTIAL_HASH_BYTES + 100) as usize]);
+    let (partial, _) = partial_hash(&entry.path, entry.size).unwrap();
+    let (full, _) = full_hash(&entry.path).unwrap();
+    assert_eq!(partial, full);
+}
+
+fn with_mtime(mut entry: FileEntry, mtime: i64) -> FileEntry {
+    entry.mtime = Some(mtime);
+    entry
+}
+
+#[test]
+fn second_run_reads_nothing_when_cache_is_warm() {
+    let dir = TempDir::new().unwrap();
+    let big = vec![5u8; (PARTIAL_HASH_BYTES * 3) as usize];
+    let files = vec![
+        with_mtime(write(&dir, "a.bin", &big), 100),
+        with_mtime(write(&dir, "b.bin", &big), 100),
+    ];
+
+    let mut cache = HashCache::new();
+    let first = find_duplicates_with_cache(&files, &DuplicateOptions::default(), &mut cache, |_| {});
+    assert_eq!(first.groups.len(), 1);
+    assert_eq!(first.cache_hits, 0);
+    assert_eq!(cache.dirty_entries().len(), 2);
+
+    let second = find_duplicates_with_cache(&files, &DuplicateOptions::default(), &mut cache, |_| {});
+    assert_eq!(second.groups.len(), 1);
+    assert_eq!(second.groups[0].hash, first.groups[0].hash);
+    assert_eq!(second.bytes_hashed, 0);
+    assert_eq!(second.cache_hits, 4, "partial and full hash of both files");
+}
+
+#[test]
+fn changed_mtime_misses_the_cache() {
+    let dir = TempDir::new().unwrap();
+    let files = vec![
+        with_mtime(write(&dir, "a.txt", b"cached"), 100),
+        with_mtime(write(&dir, "b.txt", b"cached"), 100),
+    ];
+    let mut cache = HashCache::new();
+    find_duplicates_with_cache(&files, &DuplicateOptions::default(), &mut cache, |_| {});
+
+    std::fs::write(&files[1].path, b"edited").unwrap();
+    let files = vec![files[0].clone(), with_mtime(files[1].clone(), 200)];
+    let report = find_duplicates_with_cache(&files, &DuplicateOptions::default(), &mut cache, |_| {});
+    assert!(report.groups.is_empty());
+    assert_eq!(report.cache_hits, 1);
+}
+
+#[test]
+fn entries_without_mtime_are_not_cached() {
+    let dir = TempDir::new().unwrap();
+    let files = vec![write(&dir, "a.txt", b"same"), write(&dir, "b.txt", b"same")];
+    let mut cache = HashCache::new();
+    find_duplicates_with_cache(&files, &DuplicateOptions::default(), &mut cache, |_| {});
+    assert!(cache.is_empty());
+}
@@ -8,13 +8,15 @@ pub use reconciler::IndexReconciler;
 pub use watcher::IndexWatcher;
 
 use std::path::Path;
-use crate::{DiskObject, DiskObjectKind};
-use crate::core::scanning::ignore_scanner::{is_virtual_fs, is_dependencies_dir};
+use crate::{allocated_size, DiskObject, DiskObjectKind};
+use crate::core::scanning::exclusions::ExclusionRules;
+use crate::core::scanning::ignore_scanner::is_virtual_fs;
 
-/// Returns `true` if `path` itself, or any of its ancestor directories, should be
-/// excluded from the index — matching the same rules as the main scanner.
-pub(crate) fn should_skip(path: &Path) -> bool {
-    path.ancestors().any(|p| is_virtual_fs(p) || is_dependencies_dir(p))
+/// Returns `true` if `path` should stay out of the index — matching the same rules as the
+/// main scanner: it is excluded, or lies on a virtual filesystem or below an excluded or
+/// collapsed folder.
+pub(crate) fn should_skip(path: &Path, rules: &ExclusionRules) -> bool {
+    path.ancestors().any(is_virtual_fs) || rules.hides(path, path.is_dir())
 }
 
 /// Build a [`DiskObject`] from a live filesystem path by reading its metadata.
@@ -40,6 +42,7 @@ pub(crate) fn disk_object_from_path(path: &Path) -> Option<DiskObject> {
     };
 
     let size = if kind == DiskObjectKind::File { Some(meta.len()) } else { None };
+    let allocated_size = if kind == DiskObjectKind::File { Some(allocated_size(&meta)) } else { None };
 
     let mtime = meta
         .modified()
@@ -60,9 +63,14 @@ pub(crate) fn disk_object_from_path(path: &Path) -> Option<DiskObject> {
         kind,
         size,
         recursive_size: None,
+        allocated_size,
         // dev/ino are populated on a full rescan via file_key_from_path; not needed here.
         dev: None,
         ino: None,
         mtime,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     })
 }
@@ -14,6 +14,7 @@ use std::time::Duration;
 use walkdir::WalkDir;
 
 use crate::core::indexing::ngram::TrigramIndex;
+use crate::core::scanning::exclusions::{is_collapsed_entry, ExclusionRules, RuleAction};
 use super::{disk_object_from_path, should_skip};
 
 const LOG_TARGET: &str = "disk_tree::reconciler";
@@ -67,6 +68,16 @@ impl IndexReconciler {
         index: Arc<Mutex<TrigramIndex>>,
         roots: Vec<PathBuf>,
         scan_in_progress: Arc<AtomicBool>,
+    ) -> Self {
+        Self::with_rules(index, roots, scan_in_progress, Arc::new(ExclusionRules::recommended()))
+    }
+
+    /// Like [`IndexReconciler::new`], leaving out what the scan leaves out under `rules`.
+    pub fn with_rules(
+        index: Arc<Mutex<TrigramIndex>>,
+        roots: Vec<PathBuf>,
+        scan_in_progress: Arc<AtomicBool>,
+        rules: Arc<ExclusionRules>,
     ) -> Self {
         let cancel = Arc::new(AtomicBool::new(false));
         let cancel_clone = Arc::clone(&cancel);
@@ -75,7 +86,7 @@ impl IndexReconciler {
             .name("index-reconciler".into())
             .stack_size(256 * 1024) // 256 KB — walkdir is iterative, no deep recursion
             .spawn(move || {
-                run(index, roots, scan_in_progress, cancel_clone);
+                run(index, roots, scan_in_progress, rules, cancel_clone);
             })
             .expect("failed to spawn reconciler thread");
 
@@ -95,6 +106,7 @@ fn run(
     index: Arc<Mutex<TrigramIndex>>,
     roots: Vec<PathBuf>,
     scan_in_progress: Arc<AtomicBool>,
+    rules: Arc<ExclusionRules>,
     cancel: Arc<AtomicBool>,
 ) {
     loop {
@@ -118,8 +130,12 @@ fn run(
         'walk: for root in &roots {
             let walker = WalkDir::new(root).follow_links(false).into_iter();
             for result in walker.filter_entry(|e| {
-                // Prune ignored directory subtrees (depth == 0 is the root itself — never prune).
-                e.depth() == 0 || !e.file_type().is_dir() || !should_skip(e.path())
+                // Prune ignored and collapsed directory subtrees (depth == 0 is the root
+                // itself — never prune).
+                e.depth() == 0
+                    || !e.file_type().is_dir()
+                    || !(should_skip(e.path(), &rules)
+                        || rules.action_at(e.path(), true) == Some(RuleAction::Collapse))
             }) {
                 if cancel.load(Ordering::Relaxed) {
                     return;
@@ -146,7 +162,7 @@ fn run(
                 }
 
                 // Skip files whose path falls inside an ignored directory.
-                if should_skip(entry.path()) {
+                if should_skip(entry.path(), &rules) {
                     continue;
                 }
 
@@ -204,7 +220,10 @@ fn run(
                 thread::sleep(ENTRY_SLEEP);
             }
 
-            if !Path::new(&path).exists() {
+            // The summary entry of a collapsed folder has no file behind it.
+            let p = Path::new(&path);
+            let collapsed = is_collapsed_entry(p) && p.parent().is_some_and(Path::is_dir);
+            if !collapsed && !p.exists() {
                 log::debug!(target: LOG_TARGET, "remove {}", path);
                 if let Ok(mut idx) = index.lock() {
                     idx.remove(&path);
@@ -55,9 +55,14 @@ fn removes_indexed_paths_that_no_longer_exist() {
         kind: crate::DiskObjectKind::File,
         size: Some(0),
         recursive_size: None,
+        allocated_size: None,
         dev: None,
         ino: None,
         mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     };
     let index = Arc::new(Mutex::new(build_index(&[ghost])));
     assert_eq!(index.lock().unwrap().live_count(), 1);
@@ -103,6 +108,45 @@ fn does_not_duplicate_already_indexed_files() {
     );
 }
 
+#[test]
+fn keeps_collapsed_folders_and_their_summary_but_not_their_contents() {
+    use crate::core::scanning::exclusions::{ExclusionRule, ExclusionRules, RuleAction, COLLAPSED_ENTRY_NAME};
+
+    let dir = TempDir::new().unwrap();
+    let deps = dir.path().join("deps");
+    std::fs::create_dir(&deps).unwrap();
+    std::fs::write(deps.join("inner.js"), b"x").unwrap();
+    std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+    std::fs::write(dir.path().join("skip.tmp"), b"t").unwrap();
+
+    // The summary entry a scan leaves behind for the collapsed folder.
+    let mut summary = disk_object_from_path(&dir.path().join("a.txt")).unwrap();
+    summary.path = path_str(&deps.join(COLLAPSED_ENTRY_NAME));
+    let index = Arc::new(Mutex::new(build_index(&[summary])));
+    let rules = ExclusionRules::new(vec![
+        ExclusionRule::glob("deps/", RuleAction::Collapse),
+        ExclusionRule::glob("*.tmp", RuleAction::Exclude),
+    ])
+    .unwrap();
+    let _rec = IndexReconciler::with_rules(
+        Arc::clone(&index),
+        vec![dir.path().to_path_buf()],
+        Arc::new(AtomicBool::new(false)),
+        Arc::new(rules),
+    );
+
+    let added = poll_until(
+        || index.lock().unwrap().path_to_idx.contains_key(&path_str(&dir.path().join("a.txt"))),
+        Duration::from_secs(2),
+    );
+    assert!(added);
+    thread::sleep(Duration::from_millis(300));
+    let idx = index.lock().unwrap();
+    assert!(idx.path_to_idx.contains_key(&path_str(&deps.join(COLLAPSED_ENTRY_NAME))));
+    assert!(!idx.path_to_idx.contains_key(&path_str(&deps.join("inner.js"))));
+    assert!(!idx.path_to_idx.contains_key(&path_str(&dir.path().join("skip.tmp"))));
+}
+
 #[test]
 fn pauses_while_scan_in_progress_then_resumes() {
     let dir = TempDir::new().unwrap();
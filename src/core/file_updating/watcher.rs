@@ -10,6 +10,7 @@ use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
 
 use crate::core::indexing::ngram::TrigramIndex;
+use crate::core::scanning::exclusions::ExclusionRules;
 use super::{disk_object_from_path, should_skip};
 
 const LOG_TARGET: &str = "disk_tree::watcher";
@@ -36,6 +37,15 @@ impl IndexWatcher {
     pub fn new(
         index: Arc<Mutex<TrigramIndex>>,
         paths: Vec<PathBuf>,
+    ) -> notify::Result<Self> {
+        Self::with_rules(index, paths, Arc::new(ExclusionRules::recommended()))
+    }
+
+    /// Like [`IndexWatcher::new`], ignoring events for paths the scan leaves out under `rules`.
+    pub fn with_rules(
+        index: Arc<Mutex<TrigramIndex>>,
+        paths: Vec<PathBuf>,
+        rules: Arc<ExclusionRules>,
     ) -> notify::Result<Self> {
         let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
 
@@ -57,7 +67,7 @@ impl IndexWatcher {
                         Ok(e) => e,
                         Err(_) => continue,
                     };
-                    handle_event(event, &index, &mut removal_count);
+                    handle_event(event, &index, &rules, &mut removal_count);
                 }
             })
             .expect("failed to spawn watcher thread");
@@ -68,11 +78,16 @@ impl IndexWatcher {
 
 // ── Event handling ────────────────────────────────────────────────────────────
 
-fn handle_event(event: Event, index: &Arc<Mutex<TrigramIndex>>, removal_count: &mut u32) {
+fn handle_event(
+    event: Event,
+    index: &Arc<Mutex<TrigramIndex>>,
+    rules: &ExclusionRules,
+    removal_count: &mut u32,
+) {
     match event.kind {
         EventKind::Create(CreateKind::File) | EventKind::Create(CreateKind::Any) => {
             for path in &event.paths {
-                if should_skip(path) { continue; }
+                if should_skip(path, rules) { continue; }
                 if path.is_file() {
                     if let Some(obj) = disk_object_from_path(path) {
                         log::debug!(target: LOG_TARGET, "add {}", obj.path);
@@ -86,7 +101,7 @@ fn handle_event(event: Event, index: &Arc<Mutex<TrigramIndex>>, removal_count: &
 
         EventKind::Create(CreateKind::Folder) => {
             for path in &event.paths {
-                if should_skip(path) { continue; }
+                if should_skip(path, rules) { continue; }
                 if let Some(obj) = disk_object_from_path(path) {
                     log::debug!(target: LOG_TARGET, "add {}", obj.path);
                     if let Ok(mut idx) = index.lock() {
@@ -122,7 +137,7 @@ fn handle_event(event: Event, index: &Arc<Mutex<TrigramIndex>>, removal_count: &
                         log::debug!(target: LOG_TARGET, "rename remove {}", from_str);
                         *removal_count += 1;
                     }
-                    if !should_skip(to) {
+                    if !should_skip(to, rules) {
                         if let Some(obj) = disk_object_from_path(to) {
                             log::debug!(target: LOG_TARGET, "rename add {}", obj.path);
                             idx.add(obj);
@@ -150,7 +165,7 @@ fn handle_event(event: Event, index: &Arc<Mutex<TrigramIndex>>, removal_count: &
         // Rename: only the new path is known
         EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
             for path in &event.paths {
-                if should_skip(path) { continue; }
+                if should_skip(path, rules) { continue; }
                 if let Some(obj) = disk_object_from_path(path) {
                     log::debug!(target: LOG_TARGET, "rename add {}", obj.path);
                     if let Ok(mut idx) = index.lock() {
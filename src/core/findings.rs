@@ -0,0 +1,38 @@
+//! Things a scan turned up that are worth a look, beyond sizes: symlinks whose target is gone
+//! and files with risky permission bits. Read from the current scan with `db::get_findings`.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// A symlink whose target does not exist; only recorded when links are not skipped.
+    BrokenSymlink,
+    /// A file that runs with its owner's rights (`chmod u+s`).
+    Setuid,
+    /// A file that runs with its group's rights (`chmod g+s`).
+    Setgid,
+    /// A file anyone may change (`chmod o+w`).
+    WorldWritable,
+}
+
+impl FindingKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FindingKind::BrokenSymlink => "broken_symlink",
+            FindingKind::Setuid => "setuid",
+            FindingKind::Setgid => "setgid",
+            FindingKind::WorldWritable => "world_writable",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub path: PathBuf,
+    /// What was found, e.g. the missing target of a broken link or a file's mode.
+    pub detail: String,
+}
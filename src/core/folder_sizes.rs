@@ -1,13 +1,21 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use rayon::prelude::*;
 
-use crate::{FileEntry, FileKey};
+use crate::{FileEntry, FileKey, SizeMetric};
 
 pub fn aggregate_folder_sizes(
     root: &std::path::Path,
     files: &[FileEntry],
+) -> HashMap<PathBuf, u64> {
+    aggregate_folder_sizes_by(root, files, SizeMetric::Apparent)
+}
+
+pub fn aggregate_folder_sizes_by(
+    root: &std::path::Path,
+    files: &[FileEntry],
+    metric: SizeMetric,
 ) -> HashMap<PathBuf, u64> {
     let root_len = root.as_os_str().len();
 
@@ -23,13 +31,14 @@ pub fn aggregate_folder_sizes(
             || (0u64, HashMap::<PathBuf, u64>::new()),
             |mut acc, entry| {
                 let (rs, map) = &mut acc;
-                *rs += entry.size;
+                let size = entry.size_by(metric);
+                *rs += size;
                 let mut a = entry.path.parent();
                 while let Some(anc) = a {
                     if anc.as_os_str().len() <= root_len {
                         break;
                     }
-                    *map.entry(anc.to_path_buf()).or_insert(0) += entry.size;
+                    *map.entry(anc.to_path_buf()).or_insert(0) += size;
                     a = anc.parent();
                 }
                 acc
@@ -49,10 +58,35 @@ pub fn aggregate_folder_sizes(
     folder_sizes
 }
 
+/// Re-total already known folders (e.g. the keys of a scan's apparent `folder_sizes`, which
+/// may span several roots) by `metric`. Hard links count once, like in a single-root scan.
+pub fn sum_folder_sizes<'a>(
+    folders: impl IntoIterator<Item = &'a PathBuf>,
+    files: &[FileEntry],
+    metric: SizeMetric,
+) -> HashMap<PathBuf, u64> {
+    let mut totals: HashMap<PathBuf, u64> = folders.into_iter().map(|p| (p.clone(), 0)).collect();
+    let known: HashSet<&Path> = totals.keys().map(|p| p.as_path()).collect();
+    let mut seen: HashSet<FileKey> = HashSet::with_capacity(files.len());
+    let mut sums: HashMap<&Path, u64> = HashMap::with_capacity(known.len());
+    for entry in files.iter().filter(|e| seen.insert(e.file_key)) {
+        let size = entry.size_by(metric);
+        let mut a = entry.path.parent();
+        while let Some(anc) = a.and_then(|p| known.get(p)) {
+            *sums.entry(anc).or_insert(0) += size;
+            a = anc.parent();
+        }
+    }
+    let sums: Vec<(PathBuf, u64)> = sums.into_iter().map(|(p, s)| (p.to_path_buf(), s)).collect();
+    for (path, size) in sums {
+        totals.insert(path, size);
+    }
+    totals
+}
+
 pub fn compute_folder_sizes(
     root: &std::path::Path,
     files: &[FileEntry],
 ) -> HashMap<PathBuf, u64> {
     aggregate_folder_sizes(root, files)
 }
-
@@ -1,12 +1,15 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Read, Write};
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 use lz4_flex::frame::{FrameDecoder, FrameEncoder};
 use rayon::prelude::*;
 
 use crate::{DiskObject, DiskObjectKind, FileEntry};
-use crate::core::indexing::sqlite::SearchFilter;
+use crate::core::search::{SearchBackend, SearchFilter};
+use crate::core::query::Query;
+use crate::core::scanning::exclusions::is_collapsed_entry;
 use crate::parent_dir;
 
 pub fn build_index(
@@ -28,6 +31,73 @@ pub fn find_files(
     Ok((results, has_more))
 }
 
+/// Like [`find_files`], for a structured [`Query`]. Size, modification time and kind come from
+/// the index lines; indexes written before they were stored treat every entry as a file
+/// without either.
+pub fn find_files_by_query(
+    index_path: &Path,
+    query: &Query,
+    limit: usize,
+    offset: usize,
+) -> CompressedTextIndexResult<(Vec<DiskObject>, bool)> {
+    let (results, has_more, _) = search_shards(index_path, query, limit, offset)?;
+    Ok((results, has_more))
+}
+
+/// The index file at a path as a [`SearchBackend`]. Nothing is kept in memory: every search
+/// streams the shards, and every update rewrites them.
+pub struct CompressedTextBackend {
+    index_path: PathBuf,
+}
+
+impl CompressedTextBackend {
+    pub fn new(index_path: impl Into<PathBuf>) -> CompressedTextBackend {
+        CompressedTextBackend { index_path: index_path.into() }
+    }
+}
+
+impl SearchBackend for CompressedTextBackend {
+    fn build(&mut self, objects: &[DiskObject]) -> Result<(), String> {
+        write_lines(&self.index_path, objects.iter().map(object_line).collect()).map_err(|e| format!("{:?}", e))
+    }
+
+    fn update(&mut self, removed: &[String], upserted: &[DiskObject]) -> Result<(), String> {
+        let replaced: HashSet<&str> = removed
+            .iter()
+            .map(String::as_str)
+            .chain(upserted.iter().map(|o| o.path.as_str()))
+            .collect();
+        let mut lines = read_lines(&self.index_path).map_err(|e| format!("{:?}", e))?;
+        lines.retain(|line| parse_line(line).is_some_and(|l| !replaced.contains(l.path)));
+        lines.extend(upserted.iter().map(object_line));
+        write_lines(&self.index_path, lines).map_err(|e| format!("{:?}", e))
+    }
+
+    fn size_bytes(&self) -> usize {
+        0
+    }
+
+    fn for_each_match(&self, query: &Query, visit: &mut dyn FnMut(&DiskObject) -> bool) -> Result<(), String> {
+        for shard_path in resolve_shard_paths(&self.index_path) {
+            let file = File::open(shard_path).map_err(|e| e.to_string())?;
+            let mut reader = BufReader::new(FrameDecoder::new(file));
+            let mut line_buf = String::new();
+            loop {
+                line_buf.clear();
+                if reader.read_line(&mut line_buf).map_err(|e| e.to_string())? == 0 {
+                    break;
+                }
+                if let Some(obj) = match_line(&line_buf, query) {
+                    if !visit(&obj) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A fully decompressed CTI held in RAM.  Built once via [`build_in_memory_index`]; searched
 /// with zero disk I/O via [`find_files_in_memory`].
 pub struct InMemoryIndex {
@@ -61,12 +131,20 @@ pub fn build_in_memory_index(index_path: &Path) -> CompressedTextIndexResult<InM
 pub fn find_files_in_memory(
     index: &InMemoryIndex,
     query: &str,
-    _filter: &SearchFilter,
+    filter: &SearchFilter,
+    limit: usize,
+    offset: usize,
+) -> CompressedTextIndexResult<(Vec<DiskObject>, bool)> {
+    find_files_in_memory_by_query(index, &Query::literal(query).and(filter.into()), limit, offset)
+}
+
+/// Like [`find_files_in_memory`], for a structured [`Query`].
+pub fn find_files_in_memory_by_query(
+    index: &InMemoryIndex,
+    query: &Query,
     limit: usize,
     offset: usize,
 ) -> CompressedTextIndexResult<(Vec<DiskObject>, bool)> {
-    let query_lower = query.to_ascii_lowercase();
-    let query_is_empty = query_lower.is_empty();
     let global_needed = limit.saturating_add(offset).saturating_add(1);
 
     let content = &index.content;
@@ -81,14 +159,10 @@ pub fn find_files_in_memory(
 
         if end > pos {
             if let Ok(line) = std::str::from_utf8(&content[pos..end]) {
-                if let Some(path) = parse_path_line(line) {
-                    let name = basename(path);
-                    if query_is_empty || ascii_case_insensitive_contains(name, &query_lower) {
-                        results.push(build_disk_object_from_path(path));
-                        if results.len() >= global_needed {
-                            pos = end + 1;
-                            break;
-                        }
+                if let Some(obj) = match_line(line, query) {
+                    results.push(obj);
+                    if results.len() >= global_needed {
+                        break;
                     }
                 }
             }
@@ -107,6 +181,8 @@ pub fn find_files_in_memory(
 
 const KIND_FILE: u8 = b'f';
 const KIND_FOLDER: u8 = b'd';
+const KIND_SYMLINK: u8 = b'l';
+const KIND_COLLAPSED: u8 = b'c';
 
 const CTI_MAX_ENTRIES_PER_SHARD: usize = 200_000;
 
@@ -157,33 +233,58 @@ fn ascii_case_insensitive_contains(haystack: &str, needle_lower: &str) -> bool {
     false
 }
 
-fn parse_path_line(line: &str) -> Option<&str> {
-    let line = line.trim();
-    if line.is_empty() {
-        return None;
-    }
-    // Support both the new "path only" format and the older
-    // tab-separated format by always taking just the first field.
-    if let Some((path, _rest)) = line.split_once('\t') {
-        if path.is_empty() {
-            None
-        } else {
-            Some(path)
+/// One entry of the index: `path\tkind\tsize\tmtime`, or just the path in indexes written
+/// before the other fields were stored.
+struct IndexLine<'a> {
+    path: &'a str,
+    kind: DiskObjectKind,
+    size: Option<u64>,
+    mtime: Option<i64>,
+}
+
+fn parse_line(line: &str) -> Option<IndexLine<'_>> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let mut fields = line.split('\t');
+    let path = fields.next().filter(|p| !p.trim().is_empty())?;
+    let kind = match fields.next().map(str::as_bytes) {
+        Some([KIND_FOLDER]) => DiskObjectKind::Folder,
+        Some([KIND_SYMLINK]) => DiskObjectKind::Symlink,
+        Some([KIND_COLLAPSED]) => DiskObjectKind::Collapsed,
+        _ => DiskObjectKind::File,
+    };
+    Some(IndexLine {
+        path,
+        kind,
+        size: fields.next().and_then(|s| s.parse().ok()),
+        mtime: fields.next().and_then(|s| s.parse().ok()),
+    })
+}
+
+/// The object for `line` if it matches `query`. Lines whose name lacks the query's required
+/// text are skipped before an object is built for them.
+fn match_line(line: &str, query: &Query) -> Option<DiskObject> {
+    let line = parse_line(line)?;
+    if let Some(text) = query.required_name() {
+        if !ascii_case_insensitive_contains(basename(line.path), text) {
+            return None;
         }
-    } else {
-        Some(line)
     }
+    let obj = build_disk_object(&line);
+    query.matches(&obj).then_some(obj)
 }
 
-fn build_disk_object_from_path(path: &str) -> DiskObject {
+fn build_disk_object(line: &IndexLine<'_>) -> DiskObject {
+    let path = line.path;
     let path_string = path.to_string();
     let path_lower = path_string.to_ascii_lowercase();
     let parent = parent_dir(path);
     let name_string = basename(path).to_string();
     let name_lower = name_string.to_ascii_lowercase();
+    let is_folder = line.kind == DiskObjectKind::Folder;
     let ext = std::path::Path::new(path)
         .extension()
         .and_then(|e| e.to_str())
+        .filter(|_| !is_folder)
         .map(|s| s.to_ascii_lowercase());
 
     DiskObject {
@@ -193,12 +294,17 @@ fn build_disk_object_from_path(path: &str) -> DiskObject {
         name: name_string,
         name_lower,
         ext,
-        kind: DiskObjectKind::File,
-        size: None,
-        recursive_size: None,
+        kind: line.kind.clone(),
+        size: line.size.filter(|_| !is_folder),
+        recursive_size: line.size.filter(|_| is_folder),
+        allocated_size: None,
         dev: None,
         ino: None,
-        mtime: None,
+        mtime: line.mtime,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     }
 }
 
@@ -208,15 +314,37 @@ pub fn write_compressed_text_index(
     folder_sizes: &std::collections::HashMap<std::path::PathBuf, u64>,
 ) -> CompressedTextIndexResult<()> {
     let mut paths: Vec<String> = Vec::with_capacity(files.len() + folder_sizes.len());
-    for f in files {
-        let path_str = f.path.to_string_lossy().to_string();
-        paths.push(path_str);
+    // Collapsed-folder summaries have nothing on disk to find.
+    for f in files.iter().filter(|f| !is_collapsed_entry(&f.path)) {
+        let mtime = f.mtime.map(|t| t.to_string()).unwrap_or_default();
+        paths.push(format!("{}\t{}\t{}\t{}", f.path.to_string_lossy(), KIND_FILE as char, f.size, mtime));
     }
-    for (path, _size) in folder_sizes {
-        let path_str = path.to_string_lossy().to_string();
-        paths.push(path_str);
+    for (path, size) in folder_sizes {
+        paths.push(format!("{}\t{}\t{}\t", path.to_string_lossy(), KIND_FOLDER as char, size));
     }
+    write_lines(index_path, paths)
+}
+
+/// The index line for `obj`: path, kind, size (the recursive size for folders) and mtime.
+fn object_line(obj: &DiskObject) -> String {
+    let kind = match obj.kind {
+        DiskObjectKind::Folder => KIND_FOLDER,
+        DiskObjectKind::Symlink => KIND_SYMLINK,
+        DiskObjectKind::Collapsed => KIND_COLLAPSED,
+        DiskObjectKind::File => KIND_FILE,
+    };
+    let size = obj.size.or(obj.recursive_size).map(|s| s.to_string()).unwrap_or_default();
+    let mtime = obj.mtime.map(|t| t.to_string()).unwrap_or_default();
+    format!("{}\t{}\t{}\t{}", obj.path, kind as char, size, mtime)
+}
+
+/// Sort `paths` and write them as the index at `index_path`, in shards when there are many,
+/// replacing whatever index files were there before.
+fn write_lines(index_path: &Path, mut paths: Vec<String>) -> CompressedTextIndexResult<()> {
     paths.sort();
+    for old in resolve_shard_paths(index_path) {
+        std::fs::remove_file(old)?;
+    }
 
     if paths.is_empty() {
         let file = File::create(index_path)?;
@@ -258,14 +386,21 @@ pub struct CompressedTextIndexSearchTimings {
 pub fn search_compressed_text_index(
     index_path: &Path,
     query: &str,
-    _filter: &SearchFilter,
+    filter: &SearchFilter,
+    limit: usize,
+    offset: usize,
+) -> CompressedTextIndexResult<(Vec<DiskObject>, bool, CompressedTextIndexSearchTimings)> {
+    search_shards(index_path, &Query::literal(query).and(filter.into()), limit, offset)
+}
+
+fn search_shards(
+    index_path: &Path,
+    query: &Query,
     limit: usize,
     offset: usize,
 ) -> CompressedTextIndexResult<(Vec<DiskObject>, bool, CompressedTextIndexSearchTimings)> {
     use std::time::Instant;
 
-    let query_lower = query.to_ascii_lowercase();
-    let query_is_empty = query_lower.is_empty();
     let global_needed = limit.saturating_add(offset).saturating_add(1);
 
     let open_start = Instant::now();
@@ -278,7 +413,7 @@ pub fn search_compressed_text_index(
     let shard_results: Vec<CompressedTextIndexResult<Vec<DiskObject>>> = shard_paths
         .par_iter()
         .map(|shard_path| {
-            search_shard(shard_path, &query_lower, query_is_empty, per_shard_limit)
+            search_shard(shard_path, query, per_shard_limit)
         })
         .collect();
 
@@ -290,7 +425,7 @@ pub fn search_compressed_text_index(
 
     combined.sort_by(|a, b| a.path.cmp(&b.path));
 
-    let has_more = combined.len() > global_needed;
+    let has_more = combined.len() >= global_needed;
 
     let start = offset.min(combined.len());
     let end = (start + limit).min(combined.len());
@@ -328,10 +463,21 @@ fn resolve_shard_paths(index_path: &Path) -> Vec<std::path::PathBuf> {
     paths
 }
 
+/// Every line of the index at `index_path`, in order, without line endings.
+fn read_lines(index_path: &Path) -> CompressedTextIndexResult<Vec<String>> {
+    let mut lines: Vec<String> = Vec::new();
+    for shard_path in resolve_shard_paths(index_path) {
+        let reader = BufReader::new(FrameDecoder::new(File::open(shard_path)?));
+        for line in reader.lines() {
+            lines.push(line?);
+        }
+    }
+    Ok(lines)
+}
+
 fn search_shard(
     shard_path: &Path,
-    query_lower: &str,
-    query_is_empty: bool,
+    query: &Query,
     limit: usize,
 ) -> CompressedTextIndexResult<Vec<DiskObject>> {
     let file = File::open(shard_path)?;
@@ -346,15 +492,11 @@ fn search_shard(
         if bytes_read == 0 {
             break;
         }
-        let path = match parse_path_line(&line_buf) {
-            Some(p) => p,
+        let obj = match match_line(&line_buf, query) {
+            Some(o) => o,
             None => continue,
         };
-        let name = basename(path);
-        if !query_is_empty && !ascii_case_insensitive_contains(name, query_lower) {
-            continue;
-        }
-        results.push(build_disk_object_from_path(path));
+        results.push(obj);
         if results.len() >= limit {
             break;
         }
@@ -385,6 +527,7 @@ pub fn write_scan_metadata(
         files_count,
         folders_count: folder_sizes.len() as u64,
         folder_sizes: folder_sizes_ser,
+        errors_count: 0,
     };
     let json = serde_json::to_string_pretty(&summary).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
     std::fs::write(metadata_path, json)
@@ -420,8 +563,8 @@ pub fn read_scan_result_from_compressed_text_index(
             if bytes_read == 0 {
                 break;
             }
-            let path = match parse_path_line(&line_buf) {
-                Some(p) => p,
+            let path = match parse_line(&line_buf) {
+                Some(line) => line.path,
                 None => continue,
             };
             files.push(crate::FileEntrySer {
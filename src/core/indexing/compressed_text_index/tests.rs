@@ -10,14 +10,18 @@ fn compressed_text_index_roundtrip() {
         FileEntry {
             path: std::path::PathBuf::from("C:/root/file1.txt"),
             size: 100,
+            allocated_size: 100,
             file_key: FileKey { dev: 1, ino: 10 },
             mtime: Some(12345),
+            owner: None,
         },
         FileEntry {
             path: std::path::PathBuf::from("C:/root/sub/readme.md"),
             size: 200,
+            allocated_size: 200,
             file_key: FileKey { dev: 1, ino: 11 },
             mtime: None,
+            owner: None,
         },
     ];
     let mut folder_sizes = HashMap::new();
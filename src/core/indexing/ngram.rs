@@ -17,16 +17,32 @@
 //! linear scan with early termination.
 //!
 //! **Search (empty query)**: Return the first `limit` live objects, O(n) on deleted set size.
+//!
+//! **Structured queries** ([`find_files_by_query`]): The longest name text the query requires
+//! picks the candidates as above; every candidate is then checked against the whole query.
+//!
+//! **Path text** (`photos/2019`): Only names are indexed, but every `/`-separated piece of the
+//! text lies within one segment of a matching path, i.e. in the name of the object or of one of
+//! its ancestors, or in the path above the topmost indexed folder. The posting lists find the
+//! objects whose name holds the longest piece, and the parent links (`parents`, one `u32` per
+//! object) carry that down to everything below them; those candidates are then checked against
+//! the full path.
+//!
+//! **Globs and regexes** (`*.tar.gz`, `img_\d{4}\.jpg`): The pattern's syntax tree gives the
+//! literal text every match must contain, such as `.tar.gz`, or `img_` and `.jpg`; a small
+//! alternation or class gives a set of literals of which one must occur. The posting lists of
+//! each such literal narrow the candidates, which are then checked with the full regex. A
+//! pattern without literals of 3+ bytes, like `^\w+$`, falls back to a linear scan.
 
 use std::collections::{HashMap, HashSet};
 
 use nucleo::{Config, Matcher, Utf32String};
 use nucleo::pattern::{Atom, AtomKind, CaseMatching, Normalization};
+use regex_syntax::hir::{Class, Hir, HirKind};
 
-use crate::DiskObject;
-use crate::DiskObjectKind;
-use crate::core::indexing::sqlite::SearchFilter;
-use crate::core::search_category;
+use crate::{DiskObject, FileKey};
+use crate::core::search::{SearchBackend, SearchFilter};
+use crate::core::query::{Filter, NamePattern, Query};
 
 // ── Internal helpers ────────────────────────────────────────────────────────
 
@@ -50,31 +66,6 @@ fn extract_trigrams(name_lower: &str, out: &mut Vec<u32>) {
     }
 }
 
-/// Test whether an object passes the search filter, mirroring the SQL conditions in sqlite.rs.
-fn passes_filter(obj: &DiskObject, filter: &SearchFilter) -> bool {
-    match filter {
-        SearchFilter::None => true,
-        SearchFilter::FoldersOnly => obj.kind == DiskObjectKind::Folder,
-        SearchFilter::Extensions(exts) => {
-            if exts.is_empty() {
-                return true;
-            }
-            obj.kind == DiskObjectKind::File
-                && obj.ext.as_ref().map_or(false, |e| exts.contains(e))
-        }
-        SearchFilter::Other => {
-            if obj.kind != DiskObjectKind::File {
-                return false;
-            }
-            let known = search_category::all_known_extensions();
-            match &obj.ext {
-                None => true,
-                Some(e) => !known.contains(&e.as_str()),
-            }
-        }
-    }
-}
-
 // ── Public types ────────────────────────────────────────────────────────────
 
 pub struct TrigramIndex {
@@ -87,6 +78,32 @@ pub struct TrigramIndex {
     pub deleted: HashSet<u32>,
     /// path → object index for O(1) remove().
     pub path_to_idx: HashMap<String, u32>,
+    /// object index → index of its parent folder, or [`NO_PARENT`] when that is not indexed.
+    pub parents: Vec<u32>,
+    /// Parent path → objects waiting for it: linked once it is added.
+    pub orphans: HashMap<String, Vec<u32>>,
+}
+
+/// The `parents` entry of an object whose parent folder is not in the index.
+pub const NO_PARENT: u32 = u32::MAX;
+
+/// The parent link for `obj` at `idx`, registering it in `orphans` if its parent is missing.
+fn parent_link(
+    obj: &DiskObject,
+    idx: u32,
+    path_to_idx: &HashMap<String, u32>,
+    orphans: &mut HashMap<String, Vec<u32>>,
+) -> u32 {
+    let Some(parent) = obj.parent_path.as_deref().filter(|p| !p.is_empty() && *p != obj.path) else {
+        return NO_PARENT;
+    };
+    match path_to_idx.get(parent) {
+        Some(&p) => p,
+        None => {
+            orphans.entry(parent.to_string()).or_default().push(idx);
+            NO_PARENT
+        }
+    }
 }
 
 impl TrigramIndex {
@@ -95,22 +112,16 @@ impl TrigramIndex {
     /// Accounts for the DiskObject vector (fixed struct size + all heap String data) and the
     /// HashMap (overhead buckets + Vec<u32> posting-list data).
     pub fn size_bytes(&self) -> usize {
-        let obj_fixed = std::mem::size_of::<DiskObject>() * self.objects.len();
-        let obj_heap: usize = self.objects.iter().map(|o| {
-            o.path.len()
-                + o.path_lower.len()
-                + o.name.len()
-                + o.name_lower.len()
-                + o.parent_path.as_ref().map_or(0, |s| s.len())
-                + o.ext.as_ref().map_or(0, |s| s.len())
-        }).sum();
+        let objects: usize = self.objects.iter().map(DiskObject::heap_bytes).sum();
         // HashMap overhead: per-bucket cost (key + Vec header + hash/pointer).
         let map_overhead = self.map.capacity() * (4 + std::mem::size_of::<Vec<u32>>() + 8);
         let map_data: usize = self.map.values().map(|v| v.len() * 4).sum();
         let deleted_overhead = self.deleted.capacity() * 4;
         // avg 32-char path + u32 idx + pointer overhead
         let path_idx_overhead = self.path_to_idx.capacity() * (40 + 4 + 8);
-        obj_fixed + obj_heap + map_overhead + map_data + deleted_overhead + path_idx_overhead
+        let parents = self.parents.capacity() * 4;
+        let orphans: usize = self.orphans.iter().map(|(path, idxs)| path.len() + idxs.len() * 4 + 48).sum();
+        objects + map_overhead + map_data + deleted_overhead + path_idx_overhead + parents + orphans
     }
 
     /// Add a single object to the index without a full rebuild.
@@ -123,6 +134,11 @@ impl TrigramIndex {
         for tri in &trigrams {
             self.map.entry(*tri).or_default().push(idx);
         }
+        let parent = parent_link(&obj, idx, &self.path_to_idx, &mut self.orphans);
+        self.parents.push(parent);
+        for child in self.orphans.remove(&obj.path).unwrap_or_default() {
+            self.parents[child as usize] = idx;
+        }
         self.path_to_idx.insert(obj.path.clone(), idx);
         self.objects.push(obj);
     }
@@ -157,6 +173,21 @@ impl TrigramIndex {
         self.objects.len() - self.deleted.len()
     }
 
+    /// Update the identity of an object in-place, e.g. after a duplicate was replaced by a hard
+    /// link. Returns false if the path is not indexed.
+    pub fn update_file_identity(&mut self, path: &str, key: FileKey, mtime: Option<i64>) -> bool {
+        match self.path_to_idx.get(path) {
+            Some(&idx) => {
+                let obj = &mut self.objects[idx as usize];
+                obj.dev = Some(key.dev);
+                obj.ino = Some(key.ino);
+                obj.mtime = mtime;
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Update `recursive_size` on existing folder objects in-place, then append any
     /// `new_objects` (folders that were not previously in the index).
     ///
@@ -205,11 +236,20 @@ pub fn build_index(objects: &[DiskObject]) -> TrigramIndex {
         path_to_idx.insert(obj.path.clone(), idx as u32);
     }
 
+    let mut orphans: HashMap<String, Vec<u32>> = HashMap::new();
+    let parents = objects
+        .iter()
+        .enumerate()
+        .map(|(idx, obj)| parent_link(obj, idx as u32, &path_to_idx, &mut orphans))
+        .collect();
+
     TrigramIndex {
         objects: objects.to_vec(),
         map,
         deleted: HashSet::new(),
         path_to_idx,
+        parents,
+        orphans,
     }
 }
 
@@ -218,88 +258,334 @@ pub fn build_index(objects: &[DiskObject]) -> TrigramIndex {
 pub fn find_files(
     index: &TrigramIndex,
     query: &str,
-    _filter: &SearchFilter,
+    filter: &SearchFilter,
     limit: usize,
     offset: usize,
 ) -> (Vec<u32>, bool) {
-    let query_lower = query.to_ascii_lowercase();
+    find_files_by_query(index, &Query::literal(query).and(filter.into()), limit, offset)
+}
 
-    // Empty query: return live objects in insertion order
-    if query_lower.is_empty() {
-        let live: Vec<u32> = (0..index.objects.len() as u32)
-            .filter(|i| !index.deleted.contains(i))
+/// Like [`find_files`], for a structured [`Query`]. The longest name text the query requires
+/// narrows the candidates through the posting lists; the whole query is then checked on each.
+pub fn find_files_by_query(
+    index: &TrigramIndex,
+    query: &Query,
+    limit: usize,
+    offset: usize,
+) -> (Vec<u32>, bool) {
+    let global_needed = limit.saturating_add(offset).saturating_add(1);
+    let mut matches: Vec<u32> = Vec::new();
+    for idx in query_candidates(index, query) {
+        if !index.deleted.contains(&idx) && query.matches(&index.objects[idx as usize]) {
+            matches.push(idx);
+            if matches.len() >= global_needed {
+                break;
+            }
+        }
+    }
+    let has_more = matches.len() >= global_needed;
+    let s = offset.min(matches.len());
+    let e = (s + limit).min(matches.len());
+    (matches[s..e].to_vec(), has_more)
+}
+
+impl SearchBackend for TrigramIndex {
+    fn build(&mut self, objects: &[DiskObject]) -> Result<(), String> {
+        *self = build_index(objects);
+        Ok(())
+    }
+
+    fn update(&mut self, removed: &[String], upserted: &[DiskObject]) -> Result<(), String> {
+        for path in removed {
+            self.remove(path);
+        }
+        for obj in upserted {
+            self.remove(&obj.path);
+            self.add(obj.clone());
+        }
+        Ok(())
+    }
+
+    fn size_bytes(&self) -> usize {
+        TrigramIndex::size_bytes(self)
+    }
+
+    fn for_each_match(&self, query: &Query, visit: &mut dyn FnMut(&DiskObject) -> bool) -> Result<(), String> {
+        for idx in query_candidates(self, query) {
+            let obj = &self.objects[idx as usize];
+            if !self.deleted.contains(&idx) && query.matches(obj) && !visit(obj) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Object indices that may match `query`, in index order: by its required name text when that
+/// is long enough to use the posting lists, else by the literals of its required name pattern,
+/// else by its required path text.
+fn query_candidates<'a>(index: &'a TrigramIndex, query: &Query) -> Box<dyn Iterator<Item = u32> + 'a> {
+    if let Some(name) = query.required_name().filter(|name| name.len() >= 3) {
+        return candidates(index, name);
+    }
+    if let Some(found) = query.required_pattern().and_then(|p| pattern_candidates(index, p)) {
+        return Box::new(found.into_iter());
+    }
+    match query.required_path_text() {
+        Some(path) => path_candidates(index, path),
+        None => candidates(index, query.required_name().unwrap_or("")),
+    }
+}
+
+/// Object indices that may match `pattern`, in index order: those holding a literal of every
+/// clause [`required_literals`] finds. `None` when no clause has only literals of 3+ bytes.
+fn pattern_candidates(index: &TrigramIndex, pattern: &NamePattern) -> Option<Vec<u32>> {
+    let mut found: Option<Vec<u32>> = None;
+    for clause in required_literals(pattern.source()) {
+        let texts: Option<Vec<&str>> = clause
+            .iter()
+            .map(|l| std::str::from_utf8(l).ok().filter(|t| t.len() >= 3))
             .collect();
-        let has_more = live.len() > offset + limit;
-        let s = offset.min(live.len());
-        let e = (s + limit).min(live.len());
-        return (live[s..e].to_vec(), has_more);
+        let Some(texts) = texts else { continue };
+        let mut any: Vec<u32> = texts.iter().flat_map(|t| candidates(index, t)).collect();
+        any.sort_unstable();
+        any.dedup();
+        found = Some(match found {
+            None => any,
+            Some(all) => all.into_iter().filter(|idx| any.binary_search(idx).is_ok()).collect(),
+        });
     }
+    found
+}
 
-    let qb = query_lower.as_bytes();
-    let global_needed = limit.saturating_add(offset).saturating_add(1);
+/// Most literals a set may hold; longer products of classes and alternations are split.
+const MAX_LITERALS: usize = 64;
 
-    // Short query (< 3 chars): trigrams don't apply — linear scan with early termination
-    if qb.len() < 3 {
-        let mut candidates: Vec<u32> = Vec::new();
-        for (i, obj) in index.objects.iter().enumerate() {
-            if index.deleted.contains(&(i as u32)) {
-                continue;
+/// Most characters a class may hold to be spelled out as literals.
+const MAX_CLASS_CHARS: u32 = 8;
+
+/// The literal text the regex `source` requires of every match, ASCII-lowercased like
+/// `name_lower`: each clause holds literals one of which every match contains.
+fn required_literals(source: &str) -> Vec<Vec<Vec<u8>>> {
+    let parser = regex_syntax::ParserBuilder::new().utf8(false).build().parse(source);
+    let Ok(hir) = parser else {
+        return Vec::new();
+    };
+    let mut literals = Literals::of(&hir);
+    if let Some(exact) = literals.exact.take() {
+        literals.require(exact);
+    }
+    literals.clauses
+}
+
+/// What a regex fragment requires of the text it matches.
+#[derive(Default)]
+struct Literals {
+    /// The strings it matches, when there are few enough to list.
+    exact: Option<Vec<Vec<u8>>>,
+    /// Sets of literals one of which every match contains.
+    clauses: Vec<Vec<Vec<u8>>>,
+}
+
+impl Literals {
+    fn exact(mut set: Vec<Vec<u8>>) -> Literals {
+        set.sort_unstable();
+        set.dedup();
+        Literals { exact: Some(set), clauses: Vec::new() }
+    }
+
+    /// Add `set` as a clause unless it holds the empty string, which every text contains.
+    fn require(&mut self, mut set: Vec<Vec<u8>>) {
+        if !set.iter().any(|l| l.is_empty()) {
+            set.sort_unstable();
+            set.dedup();
+            self.clauses.push(set);
+        }
+    }
+
+    fn of(hir: &Hir) -> Literals {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => Literals::exact(vec![Vec::new()]),
+            HirKind::Literal(literal) => Literals::exact(vec![literal.0.to_ascii_lowercase()]),
+            HirKind::Class(class) => class_literals(class).map_or_else(Literals::default, Literals::exact),
+            HirKind::Capture(capture) => Literals::of(&capture.sub),
+            HirKind::Repetition(rep) if rep.min == 0 => Literals::default(),
+            HirKind::Repetition(rep) => {
+                let mut sub = Literals::of(&rep.sub);
+                if rep.max != Some(1) {
+                    if let Some(exact) = sub.exact.take() {
+                        sub.require(exact);
+                    }
+                }
+                sub
             }
-            if obj.name_lower.contains(query_lower.as_str()) {
-                candidates.push(i as u32);
-                if candidates.len() >= global_needed {
-                    break;
+            HirKind::Concat(subs) => {
+                let mut all = Literals::default();
+                // The product of the exact sets since the last fragment that was not exact.
+                let mut run: Vec<Vec<u8>> = vec![Vec::new()];
+                let mut exact = true;
+                for sub in subs.iter().map(Literals::of) {
+                    all.clauses.extend(sub.clauses);
+                    match sub.exact {
+                        Some(next) if run.len() * next.len() <= MAX_LITERALS => {
+                            run = run.iter().flat_map(|a| next.iter().map(move |b| [a.as_slice(), b].concat())).collect();
+                        }
+                        next => {
+                            exact = false;
+                            all.require(std::mem::replace(&mut run, next.unwrap_or_else(|| vec![Vec::new()])));
+                        }
+                    }
+                }
+                match exact {
+                    true => Literals { exact: Some(run), ..all },
+                    false => {
+                        all.require(run);
+                        all
+                    }
+                }
+            }
+            HirKind::Alternation(subs) => {
+                let mut exact: Option<Vec<Vec<u8>>> = Some(Vec::new());
+                // One clause per branch, merged: whichever branch matches, one of them holds.
+                let mut any: Option<Vec<Vec<u8>>> = Some(Vec::new());
+                for mut branch in subs.iter().map(Literals::of) {
+                    match (exact.as_mut(), branch.exact.take()) {
+                        (Some(all), Some(set)) => {
+                            all.extend(set.iter().cloned());
+                            branch.require(set);
+                        }
+                        (_, set) => {
+                            exact = None;
+                            if let Some(set) = set {
+                                branch.require(set);
+                            }
+                        }
+                    }
+                    let best = branch.clauses.into_iter().max_by_key(|c| c.iter().map(Vec::len).min());
+                    any = any.zip(best).map(|(mut any, best)| {
+                        any.extend(best);
+                        any
+                    });
+                }
+                match exact {
+                    Some(set) if set.len() <= MAX_LITERALS => Literals::exact(set),
+                    _ => {
+                        let mut literals = Literals::default();
+                        if let Some(any) = any {
+                            literals.require(any);
+                        }
+                        literals
+                    }
                 }
             }
         }
-        let has_more = candidates.len() >= global_needed;
-        let s = offset.min(candidates.len());
-        let e = (s + limit).min(candidates.len());
-        return (candidates[s..e].to_vec(), has_more);
     }
+}
 
-    // Trigram query: collect unique query trigrams, look up posting lists, intersect
-    let mut query_trigrams: Vec<u32> = Vec::with_capacity(qb.len() - 2);
-    for i in 0..=(qb.len() - 3) {
-        let tri = pack(qb[i], qb[i + 1], qb[i + 2]);
-        if !query_trigrams.contains(&tri) {
-            query_trigrams.push(tri);
+/// The characters of a small class as lowercased literals; `None` for large or non-ASCII byte
+/// classes.
+fn class_literals(class: &Class) -> Option<Vec<Vec<u8>>> {
+    match class {
+        Class::Unicode(class) => {
+            let count: u32 = class.ranges().iter().map(|r| r.end() as u32 - r.start() as u32 + 1).sum();
+            if count > MAX_CLASS_CHARS {
+                return None;
+            }
+            let chars = class.ranges().iter().flat_map(|r| r.start()..=r.end());
+            Some(chars.map(|c| c.to_ascii_lowercase().to_string().into_bytes()).collect())
+        }
+        Class::Bytes(class) => {
+            let count: u32 = class.ranges().iter().map(|r| r.end() as u32 - r.start() as u32 + 1).sum();
+            if count > MAX_CLASS_CHARS || class.ranges().iter().any(|r| !r.end().is_ascii()) {
+                return None;
+            }
+            let bytes = class.ranges().iter().flat_map(|r| r.start()..=r.end());
+            Some(bytes.map(|b| vec![b.to_ascii_lowercase()]).collect())
+        }
+    }
+}
+
+/// Object indices whose path may contain `text` (see [`crate::core::query::path_text`]), in
+/// index order: the objects whose own or an ancestor's name, or the path above their topmost
+/// indexed ancestor, holds the longest `/`-separated piece of it. Every object when that piece
+/// is shorter than 3 bytes.
+fn path_candidates<'a>(index: &'a TrigramIndex, text: &str) -> Box<dyn Iterator<Item = u32> + 'a> {
+    let piece = text.split('/').max_by_key(|p| p.len()).unwrap_or("");
+    let count = index.objects.len();
+    if piece.len() < 3 {
+        return Box::new(0..count as u32);
+    }
+
+    const UNKNOWN: u8 = 0;
+    const BELOW: u8 = 1;
+    const ELSEWHERE: u8 = 2;
+    // Tombstoned objects stay in: they still name the folders their old children link to.
+    let mut state = vec![UNKNOWN; count];
+    for idx in candidates(index, piece) {
+        if index.objects[idx as usize].name_lower.contains(piece) {
+            state[idx as usize] = BELOW;
         }
     }
+    // Resolve each object by walking up to the first resolved ancestor, then settle the chain.
+    let mut chain: Vec<usize> = Vec::new();
+    for idx in 0..count {
+        let mut cur = idx;
+        while state[cur] == UNKNOWN {
+            chain.push(cur);
+            match index.parents[cur] {
+                NO_PARENT => break,
+                parent => cur = parent as usize,
+            }
+        }
+        // The topmost indexed ancestor: the piece may still be in the folders above it.
+        let resolved = match state[cur] {
+            UNKNOWN if above_in_path(&index.objects[cur]).contains(piece) => BELOW,
+            UNKNOWN => ELSEWHERE,
+            known => known,
+        };
+        for c in chain.drain(..) {
+            state[c] = resolved;
+        }
+    }
+    Box::new((0..count as u32).filter(move |&idx| state[idx as usize] == BELOW))
+}
+
+/// The lowercased path of the folder `obj` is in.
+fn above_in_path(obj: &DiskObject) -> &str {
+    let len = obj.parent_path.as_ref().map_or(0, |p| p.len());
+    obj.path_lower.get(..len).unwrap_or("")
+}
+
+/// Object indices that may contain `text_lower` in their name, in index order: the posting-list
+/// intersection for text of 3+ bytes, every object otherwise. Not yet checked against the name
+/// or the tombstones.
+fn candidates<'a>(index: &'a TrigramIndex, text_lower: &str) -> Box<dyn Iterator<Item = u32> + 'a> {
+    let qb = text_lower.as_bytes();
+    // Short text (< 3 bytes): trigrams don't apply — linear scan
+    if qb.len() < 3 {
+        return Box::new(0..index.objects.len() as u32);
+    }
+
+    // Collect unique query trigrams, look up posting lists, intersect
+    let mut query_trigrams: Vec<u32> = Vec::with_capacity(qb.len() - 2);
+    extract_trigrams(text_lower, &mut query_trigrams);
 
     // If any trigram has no posting list, there cannot be any matches
-    let mut lists: Vec<&[u32]> = Vec::with_capacity(query_trigrams.len());
+    let mut lists: Vec<&'a [u32]> = Vec::with_capacity(query_trigrams.len());
     for tri in &query_trigrams {
         match index.map.get(tri) {
             Some(v) => lists.push(v.as_slice()),
-            None => return (vec![], false),
+            None => return Box::new(std::iter::empty()),
         }
     }
 
-    // Sort by posting-list length so we iterate the shortest list (fewest candidates)
-    lists.sort_unstable_by_key(|l| l.len());
-    let (first, rest) = lists.split_first().unwrap();
-
-    // Intersect and verify.
-    // Verification with `str::contains` eliminates the false positives that arise when a name
-    // contains all the query trigrams individually but not in the right order/sequence
+    // Sort by posting-list length so we iterate the shortest list (fewest candidates).
+    // The caller's name check eliminates the false positives that arise when a name contains
+    // all the query trigrams individually but not in the right order/sequence
     // (e.g. query "abc" trigram found in "xaxbxc" which has 'a','b','c' but not "abc").
-    let candidates: Vec<u32> = first
-        .iter()
-        .copied()
-        .filter(|&idx| !index.deleted.contains(&idx))
-        .filter(|&idx| rest.iter().all(|list| list.binary_search(&idx).is_ok()))
-        .filter(|&idx| {
-            index.objects[idx as usize]
-                .name_lower
-                .contains(query_lower.as_str())
-        })
-        .collect();
-
-    let has_more = candidates.len() > offset + limit;
-    let s = offset.min(candidates.len());
-    let e = (s + limit).min(candidates.len());
-    (candidates[s..e].to_vec(), has_more)
+    lists.sort_unstable_by_key(|l| l.len());
+    let (first, rest) = lists.split_first().map(|(f, r)| (*f, r.to_vec())).unwrap();
+    Box::new(first.iter().copied().filter(move |&idx| rest.iter().all(|list| list.binary_search(&idx).is_ok())))
 }
 
 /// Fuzzy search using nucleo's matcher. Returns `(object, score)` pairs sorted by score desc.
@@ -316,6 +602,7 @@ pub fn find_files_fuzzy(
         return Vec::new();
     }
 
+    let filter = Filter::from(filter);
     let atom = Atom::new(query, CaseMatching::Ignore, Normalization::Smart, AtomKind::Fuzzy, false);
     let mut matcher = Matcher::new(Config::DEFAULT);
 
@@ -325,7 +612,7 @@ pub fn find_files_fuzzy(
         if index.deleted.contains(&idx) {
             continue;
         }
-        if !passes_filter(obj, filter) {
+        if !filter.matches(obj) {
             continue;
         }
         // Build the haystack once per candidate; score is the expensive step
@@ -1,4 +1,5 @@
 use super::*;
+use crate::core::query::{NamePattern, Predicate};
 use crate::DiskObjectKind;
 
 fn make_file(name: &str) -> DiskObject {
@@ -12,9 +13,14 @@ fn make_file(name: &str) -> DiskObject {
         kind: DiskObjectKind::File,
         size: Some(0),
         recursive_size: None,
+        allocated_size: None,
         dev: None,
         ino: None,
         mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     }
 }
 
@@ -141,6 +147,17 @@ fn remove_returns_false_for_unknown_path() {
     assert!(!idx.remove("C:/root/nonexistent.txt"));
 }
 
+#[test]
+fn update_file_identity_changes_dev_ino_in_place() {
+    let objs = vec![make_file("alpha.txt")];
+    let mut idx = build_index(&objs);
+    assert!(idx.update_file_identity("C:/root/alpha.txt", FileKey { dev: 7, ino: 42 }, Some(5)));
+    assert_eq!(idx.objects[0].dev, Some(7));
+    assert_eq!(idx.objects[0].ino, Some(42));
+    assert_eq!(idx.objects[0].mtime, Some(5));
+    assert!(!idx.update_file_identity("C:/root/missing.txt", FileKey { dev: 7, ino: 42 }, None));
+}
+
 // ── fuzzy search tests ───────────────────────────────────────────────────
 
 #[test]
@@ -181,3 +198,108 @@ fn fuzzy_limit_respected() {
     let results = find_files_fuzzy(&idx, "readme", &SearchFilter::None, 5);
     assert!(results.len() <= 5);
 }
+
+// ── path text tests ──────────────────────────────────────────────────────
+
+fn make_at(path: &str, kind: DiskObjectKind) -> DiskObject {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    DiskObject {
+        path: path.to_string(),
+        path_lower: path.to_ascii_lowercase(),
+        parent_path: Some(crate::parent_dir(path)),
+        name_lower: name.to_ascii_lowercase(),
+        name: name.to_string(),
+        kind,
+        ..make_file(name)
+    }
+}
+
+fn path_matches(idx: &TrigramIndex, text: &str) -> Vec<String> {
+    let query = Query { filter: Filter::Match(Predicate::PathText(text.to_string())) };
+    let (found, _) = find_files_by_query(idx, &query, 100, 0);
+    found.iter().map(|&i| idx.objects[i as usize].path.clone()).collect()
+}
+
+#[test]
+fn path_text_reaches_objects_below_the_matching_folder() {
+    let objs = vec![
+        make_at("/p/Photos", DiskObjectKind::Folder),
+        make_at("/p/Photos/2019", DiskObjectKind::Folder),
+        make_at("/p/Photos/2019/beach.jpg", DiskObjectKind::File),
+        make_at("/p/Photos/2020/snow.jpg", DiskObjectKind::File),
+        make_at("/p/other/2019.txt", DiskObjectKind::File),
+    ];
+    let idx = build_index(&objs);
+    assert_eq!(path_matches(&idx, "photos/2019"), ["/p/Photos/2019", "/p/Photos/2019/beach.jpg"]);
+    assert_eq!(path_matches(&idx, "os/20"), ["/p/Photos/2019", "/p/Photos/2019/beach.jpg", "/p/Photos/2020/snow.jpg"]);
+    assert_eq!(path_matches(&idx, "other/2019"), ["/p/other/2019.txt"]);
+    assert!(path_matches(&idx, "2019/snow").is_empty());
+}
+
+#[test]
+fn path_text_follows_parents_added_after_their_children() {
+    let mut idx = build_index(&[make_at("/p", DiskObjectKind::Folder)]);
+    idx.add(make_at("/p/Photos/2019/beach.jpg", DiskObjectKind::File));
+    idx.add(make_at("/p/Photos/2019", DiskObjectKind::Folder));
+    idx.add(make_at("/p/Photos", DiskObjectKind::Folder));
+    assert_eq!(idx.parents, [NO_PARENT, 2, 3, 0]);
+    assert!(idx.orphans.is_empty());
+    assert_eq!(path_matches(&idx, "photos/2019/"), ["/p/Photos/2019/beach.jpg"]);
+
+    // A replaced folder keeps its old children reachable.
+    idx.remove("/p/Photos");
+    idx.add(make_at("/p/Photos", DiskObjectKind::Folder));
+    assert_eq!(path_matches(&idx, "photos/2019/b"), ["/p/Photos/2019/beach.jpg"]);
+}
+
+// ── glob and regex tests ─────────────────────────────────────────────────
+
+fn literals(pattern: &NamePattern) -> Vec<Vec<String>> {
+    let text = |l: Vec<u8>| String::from_utf8(l).unwrap();
+    required_literals(pattern.source()).into_iter().map(|c| c.into_iter().map(text).collect()).collect()
+}
+
+#[test]
+fn patterns_require_their_literal_text() {
+    let glob = |p: &str| literals(&NamePattern::glob(p).unwrap());
+    let regex = |p: &str| literals(&NamePattern::regex(p).unwrap());
+    assert_eq!(glob("*.TAR.gz"), [[".tar.gz"]]);
+    assert_eq!(glob("report-{2023,2024}*"), [["report-2023", "report-2024"]]);
+    assert_eq!(regex(r"IMG_\d{4}\.jpg"), [["img_"], [".jpg"]]);
+    assert_eq!(regex(r"(foo|bar)baz"), [["barbaz", "foobaz"]]);
+    // Case folding keeps the long s, which `(?i)s` matches too.
+    assert_eq!(regex(r"(notes\d+|todo)\.md"), [vec!["notes", "note\u{17f}", "todo"], vec![".md"]]);
+    assert_eq!(regex(r"draft[12]"), [["draft1", "draft2"]]);
+    assert!(regex(r"^\w+$").is_empty());
+    assert_eq!(regex(r"(abc)?xyz"), [["xyz"]]);
+}
+
+#[test]
+fn pattern_queries_prefilter_by_literals_and_verify_the_regex() {
+    let objs = vec![
+        make_file("archive.tar.gz"),
+        make_file("notes.tar"),
+        make_file("IMG_2019.jpg"),
+        make_file("img_20.jpg"),
+        make_file("README"),
+    ];
+    let idx = build_index(&objs);
+    let matching = |pattern: NamePattern| {
+        let query = Query { filter: Filter::Match(Predicate::NamePattern(pattern)) };
+        let (found, _) = find_files_by_query(&idx, &query, 100, 0);
+        names(&idx, &found)
+    };
+
+    let glob = NamePattern::glob("*.tar.gz").unwrap();
+    assert_eq!(pattern_candidates(&idx, &glob), Some(vec![0]));
+    assert_eq!(matching(glob), ["archive.tar.gz"]);
+
+    let regex = NamePattern::regex(r"img_\d{4}\.jpg").unwrap();
+    assert_eq!(pattern_candidates(&idx, &regex), Some(vec![2, 3]));
+    assert_eq!(matching(regex), ["IMG_2019.jpg"]);
+
+    // Nothing to look up: every object is checked.
+    let scan = NamePattern::regex(r"^\w+$").unwrap();
+    assert_eq!(pattern_candidates(&idx, &scan), None);
+    assert_eq!(matching(scan), ["README"]);
+}
@@ -1,8 +1,12 @@
-use rusqlite::Connection;
-use rusqlite::types::Value;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, Error};
+use std::sync::Arc;
 use std::time::Instant;
 
 use crate::{DiskObject, DiskObjectKind};
+use crate::core::query::{Filter, Predicate, Query, Range};
+use crate::core::search::{Position, SearchBackend, SearchFilter, SortDirection, SortKey, SortOrder, SortValue};
 use crate::core::search_category;
 
 pub fn build_index() {
@@ -20,18 +24,27 @@ pub fn find_files(
     Ok((results, has_more))
 }
 
-#[derive(Clone, Debug)]
-pub enum SearchFilter {
-    None,
-    FoldersOnly,
-    Other,
-    Extensions(Vec<String>),
+/// Like [`find_files`], for a structured [`Query`].
+pub fn find_files_by_query(
+    conn: &Connection,
+    query: &Query,
+    limit: usize,
+    offset: usize,
+) -> rusqlite::Result<(Vec<DiskObject>, bool)> {
+    let (results, has_more, _) = search_disk_objects(conn, query, limit, offset)?;
+    Ok((results, has_more))
 }
 
+/// The columns [`row_to_disk_object`] reads, in order.
+const DISK_OBJECT_COLUMNS: &str = "path, path_lower, parent_path, name, name_lower, ext, kind, size, \
+    recursive_size, dev, ino, mtime, allocated_size, link_target, uid, gid, mode";
+
 fn row_to_disk_object(row: &rusqlite::Row<'_>) -> rusqlite::Result<DiskObject> {
     let kind_str: String = row.get(6)?;
     let kind = match kind_str.as_str() {
         "folder" => DiskObjectKind::Folder,
+        "symlink" => DiskObjectKind::Symlink,
+        "collapsed" => DiskObjectKind::Collapsed,
         _ => DiskObjectKind::File,
     };
 
@@ -40,6 +53,8 @@ fn row_to_disk_object(row: &rusqlite::Row<'_>) -> rusqlite::Result<DiskObject> {
     let dev_opt: Option<i64> = row.get(9)?;
     let ino_opt: Option<i64> = row.get(10)?;
     let mtime_opt: Option<i64> = row.get(11)?;
+    let allocated_opt: Option<i64> = row.get(12)?;
+    let link_target: Option<String> = row.get(13)?;
     let path: String = row.get(0)?;
     let path_lower_from_db: Option<String> = row.get(1)?;
     let name_opt: Option<String> = row.get(3)?;
@@ -56,55 +71,228 @@ fn row_to_disk_object(row: &rusqlite::Row<'_>) -> rusqlite::Result<DiskObject> {
         kind,
         size: size_opt.map(|n| n as u64),
         recursive_size: rec_opt.map(|n| n as u64),
+        allocated_size: allocated_opt.map(|n| n as u64),
         dev: dev_opt.map(|n| n as u64),
         ino: ino_opt.map(|n| n as u64),
         mtime: mtime_opt,
+        link_target,
+        uid: row.get(14)?,
+        gid: row.get(15)?,
+        mode: row.get(16)?,
     })
 }
 
-fn filter_where_and_params(filter: &SearchFilter) -> (String, Vec<Value>) {
-    let (condition, params) = match filter {
-        SearchFilter::None => ("".into(), vec![]),
-        SearchFilter::FoldersOnly => ("kind = 'folder'".into(), vec![]),
-        SearchFilter::Other => {
-            let known = search_category::all_known_extensions();
-            if known.is_empty() {
-                ("kind = 'file' AND ext IS NULL".into(), vec![])
-            } else {
-                let placeholders: Vec<String> = (0..known.len())
-                    .map(|i| format!("?{}", i + 1))
-                    .collect();
-                let sql = format!(
-                    "kind = 'file' AND (ext IS NULL OR ext NOT IN ({}))",
-                    placeholders.join(", ")
-                );
-                let vals = known
-                    .into_iter()
-                    .map(|s| Value::Text(s.to_string()))
-                    .collect();
-                (sql, vals)
-            }
+/// The SQL condition for the rows matching `query`. Collapsed-folder summaries are never
+/// results: there is nothing on disk at their path.
+fn search_sql(query: &Query, params: &mut Vec<Value>) -> String {
+    format!("kind != 'collapsed' AND {}", filter_sql(&query.filter, params))
+}
+
+/// The SQL condition for `filter`, pushing its values onto `params` as numbered parameters.
+/// Mirrors [`Filter::matches`]: every predicate is wrapped so a NULL column counts as no match,
+/// also under `NOT`.
+fn filter_sql(filter: &Filter, params: &mut Vec<Value>) -> String {
+    let mut param = |value: Value| {
+        params.push(value);
+        format!("?{}", params.len())
+    };
+    let predicate = match filter {
+        Filter::All(all) if all.is_empty() => return "1".into(),
+        Filter::Any(any) if any.is_empty() => return "0".into(),
+        Filter::All(all) => {
+            let parts: Vec<String> = all.iter().map(|f| filter_sql(f, params)).collect();
+            return format!("({})", parts.join(" AND "));
         }
-        SearchFilter::Extensions(exts) => {
-            if exts.is_empty() {
-                ("".into(), vec![])
+        Filter::Any(any) => {
+            let parts: Vec<String> = any.iter().map(|f| filter_sql(f, params)).collect();
+            return format!("({})", parts.join(" OR "));
+        }
+        Filter::Not(f) => return format!("NOT {}", filter_sql(f, params)),
+        Filter::Match(Predicate::Name(text)) => format!("instr(name_lower, {}) > 0", param(Value::Text(text.clone()))),
+        Filter::Match(Predicate::Size(range)) => range_sql("COALESCE(size, recursive_size)", range, &mut param),
+        Filter::Match(Predicate::Modified(range)) => range_sql("mtime", range, &mut param),
+        Filter::Match(Predicate::Ext(exts)) => {
+            let placeholders: Vec<String> = exts.iter().map(|e| param(Value::Text(e.clone()))).collect();
+            format!("kind = 'file' AND ext IN ({})", placeholders.join(", "))
+        }
+        Filter::Match(Predicate::OtherExt) => {
+            let placeholders: Vec<String> = search_category::all_known_extensions()
+                .into_iter()
+                .map(|e| param(Value::Text(e.to_string())))
+                .collect();
+            format!("kind = 'file' AND (ext IS NULL OR ext NOT IN ({}))", placeholders.join(", "))
+        }
+        Filter::Match(Predicate::Path(prefix)) => {
+            // `substr` counts characters, like `starts_with` on the same lowercased text.
+            let len = prefix.chars().count();
+            let p = param(Value::Text(prefix.clone()));
+            if prefix.ends_with(['/', '\\']) {
+                format!("substr(path_lower, 1, {len}) = {p}")
             } else {
-                let placeholders: Vec<String> = (0..exts.len())
-                    .map(|i| format!("?{}", i + 1))
-                    .collect();
-                let sql = format!(
-                    "kind = 'file' AND ext IN ({})",
-                    placeholders.join(", ")
-                );
-                let vals = exts
-                    .iter()
-                    .map(|s| Value::Text(s.clone()))
-                    .collect();
-                (sql, vals)
+                format!(
+                    "(path_lower = {p} OR (substr(path_lower, 1, {len}) = {p} AND substr(path_lower, {}, 1) IN ('/', '\\')))",
+                    len + 1
+                )
             }
         }
+        Filter::Match(Predicate::NamePattern(pattern)) => {
+            format!("regexp({}, name)", param(Value::Text(pattern.source().to_string())))
+        }
+        Filter::Match(Predicate::PathText(text)) => format!(
+            "instr(replace(path_lower, '\\', '/'), {}) > 0",
+            param(Value::Text(text.clone()))
+        ),
+        Filter::Match(Predicate::Kind(kind)) => {
+            let kind = match kind {
+                DiskObjectKind::File => "file",
+                DiskObjectKind::Folder => "folder",
+                DiskObjectKind::Symlink => "symlink",
+                DiskObjectKind::Collapsed => "collapsed",
+            };
+            format!("kind = '{}'", kind)
+        }
     };
-    (condition, params)
+    format!("IFNULL(({}), 0)", predicate)
+}
+
+fn range_sql(column: &str, range: &Range, param: &mut impl FnMut(Value) -> String) -> String {
+    let mut parts = Vec::new();
+    if let Some(min) = range.min {
+        parts.push(format!("{} >= {}", column, param(Value::Integer(min))));
+    }
+    if let Some(max) = range.max {
+        parts.push(format!("{} <= {}", column, param(Value::Integer(max))));
+    }
+    match parts.is_empty() {
+        true => format!("{} IS NOT NULL", column),
+        false => parts.join(" AND "),
+    }
+}
+
+/// Register the `regexp(pattern, text)` function that [`Predicate::NamePattern`] filters call.
+/// Each statement compiles its pattern once.
+pub fn add_regexp_function(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let regex: Arc<regex::bytes::Regex> = ctx.get_or_create_aux(0, |pattern| {
+                regex::bytes::Regex::new(pattern.as_str()?).map_err(|e| Error::UserFunctionError(e.into()))
+            })?;
+            match ctx.get_raw(1) {
+                ValueRef::Null => Ok(false),
+                text => Ok(regex.is_match(text.as_bytes()?)),
+            }
+        },
+    )
+}
+
+/// The scan database as a [`SearchBackend`]. Scans and watcher updates are written to it by
+/// [`crate::db`], so `build` and `update` leave it alone.
+pub struct SqliteBackend {
+    conn: Connection,
+}
+
+impl SqliteBackend {
+    /// Search `conn`, after registering [`add_regexp_function`] on it.
+    pub fn new(conn: Connection) -> Result<SqliteBackend, String> {
+        add_regexp_function(&conn).map_err(|e| e.to_string())?;
+        Ok(SqliteBackend { conn })
+    }
+}
+
+impl SearchBackend for SqliteBackend {
+    fn build(&mut self, _objects: &[DiskObject]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn update(&mut self, _removed: &[String], _upserted: &[DiskObject]) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Nothing is held in memory; SQLite pages the database in as needed.
+    fn size_bytes(&self) -> usize {
+        0
+    }
+
+    fn for_each_match(&self, query: &Query, visit: &mut dyn FnMut(&DiskObject) -> bool) -> Result<(), String> {
+        let mut params: Vec<Value> = Vec::new();
+        let where_clause = search_sql(query, &mut params);
+        let sql = format!(
+            "SELECT {} FROM disk_objects WHERE {} ORDER BY name_lower ASC",
+            DISK_OBJECT_COLUMNS, where_clause,
+        );
+        let mut stmt = self.conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params.iter())).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            if !visit(&row_to_disk_object(row).map_err(|e| e.to_string())?) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sorted, seeked and limited by SQLite, which keeps only the rows of the page while sorting.
+    fn find(
+        &self,
+        query: &Query,
+        sort: SortOrder,
+        after: Option<&Position>,
+        limit: usize,
+    ) -> Result<(Vec<DiskObject>, Option<Position>), String> {
+        let mut params: Vec<Value> = Vec::new();
+        let mut where_clause = search_sql(query, &mut params);
+        let column = match sort.key {
+            SortKey::Relevance | SortKey::Name => "name_lower",
+            SortKey::Size => "COALESCE(size, recursive_size)",
+            SortKey::Modified => "mtime",
+        };
+        let (beyond, direction) = match sort.direction {
+            SortDirection::Ascending => (">", "ASC"),
+            SortDirection::Descending => ("<", "DESC"),
+        };
+        if let Some(after) = after {
+            let value = match &after.value {
+                SortValue::Number(n) => Some(Value::Integer(*n)),
+                SortValue::Text(t) => Some(Value::Text(t.clone())),
+                SortValue::Missing => None,
+            };
+            params.push(Value::Text(after.path.clone()));
+            let path = params.len();
+            let seek = match value {
+                None => format!("{column} IS NULL AND path > ?{path}"),
+                Some(value) => {
+                    params.push(value);
+                    let v = params.len();
+                    format!("({column} IS NULL OR {column} {beyond} ?{v} OR ({column} = ?{v} AND path > ?{path}))")
+                }
+            };
+            where_clause = format!("{} AND {}", where_clause, seek);
+        }
+        params.push(Value::Integer(limit.saturating_add(1).min(i64::MAX as usize) as i64));
+        // Objects without a value go last in either direction, as in `SortOrder`.
+        let sql = format!(
+            "SELECT {} FROM disk_objects WHERE {} ORDER BY {column} IS NULL, {column} {direction}, path ASC LIMIT ?{}",
+            DISK_OBJECT_COLUMNS,
+            where_clause,
+            params.len(),
+        );
+        let mut stmt = self.conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let mut items = stmt
+            .query_map(rusqlite::params_from_iter(params.iter()), row_to_disk_object)
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<DiskObject>>>()
+            .map_err(|e| e.to_string())?;
+        let next = match items.len() > limit {
+            true => {
+                items.truncate(limit);
+                items.last().map(|last| sort.position(last))
+            }
+            false => None,
+        };
+        Ok((items, next))
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -121,50 +309,31 @@ pub fn search_disk_objects_by_name(
     limit: usize,
     offset: usize,
 ) -> rusqlite::Result<(Vec<DiskObject>, bool, SearchTimings)> {
-    let (filter_condition, filter_params) = filter_where_and_params(filter);
+    search_disk_objects(conn, &Query::literal(query).and(filter.into()), limit, offset)
+}
 
-    let pattern = format!("%{}%", query.to_lowercase());
+/// Objects matching a structured [`Query`], ordered by name.
+pub fn search_disk_objects(
+    conn: &Connection,
+    query: &Query,
+    limit: usize,
+    offset: usize,
+) -> rusqlite::Result<(Vec<DiskObject>, bool, SearchTimings)> {
+    let mut param_order: Vec<Value> = Vec::new();
+    let where_clause = search_sql(query, &mut param_order);
     let limit_plus_one = limit.saturating_add(1).min(i64::MAX as usize) as i64;
     let offset_i64 = offset as i64;
 
-    let (where_clause, param_order): (String, Vec<Value>) = if filter_condition.is_empty() {
-        (
-            "name_lower LIKE ?1".into(),
-            vec![Value::Text(pattern)],
-        )
-    } else {
-        let like_param = filter_params.len() + 1;
-        (
-            format!("{} AND name_lower LIKE ?{}", filter_condition, like_param),
-            {
-                let mut p = filter_params;
-                p.push(Value::Text(pattern));
-                p
-            },
-        )
-    };
-
     let limit_idx = param_order.len() + 1;
     let offset_idx = limit_idx + 1;
 
     let sql = format!(
-        "SELECT \
-            path, \
-            path_lower, \
-            parent_path, \
-            name, \
-            name_lower, \
-            ext, \
-            kind, \
-            size, \
-            recursive_size, \
-            dev, \
-            ino, \
-            mtime \
+        "SELECT {} \
          FROM disk_objects \
          WHERE {} \
          ORDER BY name_lower ASC \
          LIMIT ?{} OFFSET ?{}",
+        DISK_OBJECT_COLUMNS,
         where_clause,
         limit_idx,
         offset_idx,
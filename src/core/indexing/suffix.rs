@@ -1,9 +1,12 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::Instant;
 
 use suffix::SuffixTable;
 
 use crate::{DiskObject, DiskObjectKind};
+use crate::core::query::Query;
+use crate::core::search::SearchBackend;
 
 #[derive(Clone)]
 pub struct SuffixIndex {
@@ -22,6 +25,102 @@ pub fn find_files(index: &SuffixIndex, query: &str) -> Option<HashSet<usize>> {
     search_suffix_index(index, query)
 }
 
+/// Indices into `objects`, the slice `index` was built from, of the objects matching a
+/// structured [`Query`], and whether more exist. Only files are in the index, so it narrows the
+/// files by the query's longest required name text; other objects are checked directly.
+pub fn find_files_by_query(
+    index: &SuffixIndex,
+    objects: &[DiskObject],
+    query: &Query,
+    limit: usize,
+    offset: usize,
+) -> (Vec<usize>, bool) {
+    let candidates = query.required_name().and_then(|text| search_suffix_index(index, text));
+    let global_needed = limit.saturating_add(offset).saturating_add(1);
+    let mut matches: Vec<usize> = Vec::new();
+    for (i, o) in objects.iter().enumerate() {
+        if let Some(candidates) = &candidates {
+            if o.kind == DiskObjectKind::File && !candidates.contains(&i) {
+                continue;
+            }
+        }
+        if query.matches(o) {
+            matches.push(i);
+            if matches.len() >= global_needed {
+                break;
+            }
+        }
+    }
+    let has_more = matches.len() >= global_needed;
+    let s = offset.min(matches.len());
+    let e = (s + limit).min(matches.len());
+    (matches[s..e].to_vec(), has_more)
+}
+
+/// A [`SuffixIndex`] together with the objects it was built from, as a [`SearchBackend`].
+/// Both are shared so the host can hand out the ones it already holds.
+#[derive(Clone)]
+pub struct SuffixSearch {
+    pub objects: Arc<Vec<DiskObject>>,
+    pub index: Arc<SuffixIndex>,
+}
+
+impl SuffixSearch {
+    pub fn new(objects: Arc<Vec<DiskObject>>, index: Arc<SuffixIndex>) -> SuffixSearch {
+        SuffixSearch { objects, index }
+    }
+}
+
+impl SearchBackend for SuffixSearch {
+    fn build(&mut self, objects: &[DiskObject]) -> Result<(), String> {
+        self.index = Arc::new(build_index(objects));
+        self.objects = Arc::new(objects.to_vec());
+        Ok(())
+    }
+
+    /// The suffix table cannot be edited in place, so this rebuilds it.
+    fn update(&mut self, removed: &[String], upserted: &[DiskObject]) -> Result<(), String> {
+        let replaced: HashSet<&str> = removed
+            .iter()
+            .map(String::as_str)
+            .chain(upserted.iter().map(|o| o.path.as_str()))
+            .collect();
+        let objects: Vec<DiskObject> = self
+            .objects
+            .iter()
+            .filter(|o| !replaced.contains(o.path.as_str()))
+            .chain(upserted)
+            .cloned()
+            .collect();
+        self.build(&objects)
+    }
+
+    /// The objects, the name buffer (held twice, once by the table) and the table's `u32`
+    /// suffix positions, one per buffer byte.
+    fn size_bytes(&self) -> usize {
+        let objects: usize = self.objects.iter().map(DiskObject::heap_bytes).sum();
+        let index = &self.index;
+        objects
+            + index.buffer.len() * (2 + std::mem::size_of::<u32>())
+            + (index.offsets.len() + index.disk_object_indices.len()) * std::mem::size_of::<usize>()
+    }
+
+    fn for_each_match(&self, query: &Query, visit: &mut dyn FnMut(&DiskObject) -> bool) -> Result<(), String> {
+        let candidates = query.required_name().and_then(|text| search_suffix_index(&self.index, text));
+        for (i, o) in self.objects.iter().enumerate() {
+            if let Some(candidates) = &candidates {
+                if o.kind == DiskObjectKind::File && !candidates.contains(&i) {
+                    continue;
+                }
+            }
+            if query.matches(o) && !visit(o) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub fn build_suffix_index(objects: &[DiskObject]) -> (SuffixIndex, u128, u128) {
     let concat_start = Instant::now();
     let mut buffer = String::with_capacity(objects.len() * 16);
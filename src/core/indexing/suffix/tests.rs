@@ -11,9 +11,14 @@ fn make_file(name: &str, ino: u64) -> DiskObject {
         kind: DiskObjectKind::File,
         size: Some(0),
         recursive_size: None,
+        allocated_size: None,
         dev: Some(1),
         ino: Some(ino),
         mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     }
 }
 
@@ -18,7 +18,7 @@ use std::thread;
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
 
-use crate::{DiskObject, DiskObjectKind};
+use crate::{allocated_size, DiskObject, DiskObjectKind};
 use crate::core::indexing::ngram::TrigramIndex;
 
 // ── Public API ───────────────────────────────────────────────────────────────
@@ -191,6 +191,11 @@ fn disk_object_from_path(path: &Path) -> Option<DiskObject> {
     } else {
         None
     };
+    let allocated_size = if kind == DiskObjectKind::File {
+        Some(allocated_size(&meta))
+    } else {
+        None
+    };
 
     let mtime = meta
         .modified()
@@ -211,10 +216,15 @@ fn disk_object_from_path(path: &Path) -> Option<DiskObject> {
         kind,
         size,
         recursive_size: None,
+        allocated_size,
         // dev/ino are not tracked for watcher-created entries; they are populated
         // on a full rescan via file_key_from_path in the scanning utilities.
         dev: None,
         ino: None,
         mtime,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
     })
 }
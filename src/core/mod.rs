@@ -1,6 +1,13 @@
+pub mod diff;
+pub mod duplicates;
 pub mod file_updating;
+pub mod findings;
 pub mod folder_sizes;
 pub mod indexing;
+pub mod owners;
+pub mod query;
+pub mod search;
 pub mod search_category;
 pub mod scanning;
+pub mod trend;
 
@@ -0,0 +1,71 @@
+//! Disk usage per owning user or group, from the uid and gid recorded for every file on Unix
+//! (see [`crate::Ownership`]). Totals come from `db::usage_by_owner`.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Whether usage is grouped by the owning user or the owning group.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OwnerKind {
+    #[default]
+    User,
+    Group,
+}
+
+impl OwnerKind {
+    /// The `disk_objects` column holding the id.
+    pub fn column(self) -> &'static str {
+        match self {
+            OwnerKind::User => "uid",
+            OwnerKind::Group => "gid",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OwnerUsage {
+    pub id: u32,
+    /// The account name, if the id is known to this machine.
+    pub name: Option<String>,
+    pub files_count: u64,
+    pub size: u64,
+}
+
+/// Names by id from `/etc/passwd` or `/etc/group`. Accounts that only a directory service
+/// (LDAP, NIS) knows about are missing, and so is everything off Unix.
+pub fn owner_names(kind: OwnerKind) -> HashMap<u32, String> {
+    if !cfg!(unix) {
+        return HashMap::new();
+    }
+    let file = match kind {
+        OwnerKind::User => "/etc/passwd",
+        OwnerKind::Group => "/etc/group",
+    };
+    std::fs::read_to_string(file).map(|text| parse_id_file(&text)).unwrap_or_default()
+}
+
+/// The id for `owner`, given as a number or as a name from [`owner_names`].
+pub fn resolve_owner(kind: OwnerKind, owner: &str) -> Option<u32> {
+    if let Ok(id) = owner.parse() {
+        return Some(id);
+    }
+    owner_names(kind).into_iter().find(|(_, name)| name == owner).map(|(id, _)| id)
+}
+
+/// `name:password:id:…` lines, as in both `/etc/passwd` and `/etc/group`.
+fn parse_id_file(text: &str) -> HashMap<u32, String> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
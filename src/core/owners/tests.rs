@@ -0,0 +1,17 @@
+use super::*;
+
+#[test]
+fn id_files_map_ids_to_names() {
+    let text = "# comment\nroot:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000::/home/alice:/bin/sh\nbroken line\nwheel:x:10:alice,bob\n";
+    let names = parse_id_file(text);
+    assert_eq!(names.len(), 3);
+    assert_eq!(names[&0], "root");
+    assert_eq!(names[&1000], "alice");
+    assert_eq!(names[&10], "wheel");
+}
+
+#[test]
+fn owners_resolve_by_number_without_a_lookup() {
+    assert_eq!(resolve_owner(OwnerKind::User, "4242"), Some(4242));
+    assert_eq!(resolve_owner(OwnerKind::Group, "no-such-group-here"), None);
+}
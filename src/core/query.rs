@@ -0,0 +1,676 @@
+//! Structured search queries such as `ext:mp4,mkv size:>2GB modified:>1y path:~/Downloads`.
+//!
+//! [`Query::parse`] turns the text into a [`Filter`] tree that every index backend evaluates the
+//! same way: the in-memory ones call [`Query::matches`] on their candidates, `indexing::sqlite`
+//! translates it to a `WHERE` clause.
+//!
+//! # Syntax
+//!
+//! Terms are separated by whitespace and must all match. A bare word matches names containing
+//! it (case-insensitive); `"quoted words"` keep their spaces. Fields:
+//!
+//! - `size:>2GB`, `size:<=500k`, `size:1MB..5MB` — apparent size, the recursive size for folders.
+//!   Units are powers of 1024 like the sizes the app shows; a bare value means "at least".
+//! - `modified:>1y` (more than a year ago), `modified:<7d` (within the last week),
+//!   `modified:2024-01-01..2024-06-30`, `modified:>=2024-03-01`. Durations take `s`, `min`, `h`,
+//!   `d`, `w`, `mo` and `y`; dates are whole local days. `mtime:` is an alias.
+//! - `ext:mp4,mkv` — files with one of the extensions.
+//! - `category:video` — a category from [`search_category`], or `folder` / `other`.
+//! - `path:~/Downloads` — the path itself and everything below it.
+//! - `kind:file`, `kind:folder`, `kind:symlink`.
+//! - `name:foo` — the same as the bare word, for names that look like a field.
+//! - `glob:*.tar.gz` — names matching the whole glob; `regex:img_\d{4}\.jpg` — names containing a
+//!   match of the regex. Both ignore case; quote patterns with spaces or parentheses.
+//!
+//! `-term` or `NOT term` negates, `a OR b` matches either side (binding looser than the implicit
+//! AND) and parentheses group. A word with a prefix that is no field, like `12:30`, is searched
+//! for as a name.
+
+use chrono::{Local, NaiveDate, TimeZone};
+use globset::GlobBuilder;
+use regex::bytes::{Regex, RegexBuilder};
+
+use crate::core::search::SearchFilter;
+use crate::core::search_category;
+use crate::{DiskObject, DiskObjectKind};
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// A parsed search query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub filter: Filter,
+}
+
+/// The filter tree of a [`Query`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Every filter matches; matches everything when empty.
+    All(Vec<Filter>),
+    /// At least one filter matches; matches nothing when empty.
+    Any(Vec<Filter>),
+    Not(Box<Filter>),
+    Match(Predicate),
+}
+
+/// A single condition on a [`DiskObject`]. Text is ASCII-lowercased like the `*_lower` columns.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Predicate {
+    /// The name contains the text.
+    Name(String),
+    /// The apparent size, recursive for folders, is in the range.
+    Size(Range),
+    /// The modification time, in seconds since the Unix epoch, is in the range.
+    Modified(Range),
+    /// A file with one of these extensions.
+    Ext(Vec<String>),
+    /// A file whose extension is in none of the categories.
+    OtherExt,
+    /// The path itself or anything below it.
+    Path(String),
+    /// The full path contains the text, written with `/` separators; see [`path_text`].
+    PathText(String),
+    /// The name matches the glob or regex.
+    NamePattern(NamePattern),
+    Kind(DiskObjectKind),
+}
+
+/// A glob or regex over names, compiled to one case-insensitive regex.
+#[derive(Clone, Debug)]
+pub struct NamePattern {
+    source: String,
+    regex: Regex,
+}
+
+impl NamePattern {
+    /// Names matching the whole glob: `*` and `?` match any text and character, `[a-z]` a
+    /// class and `{tar,zip}` either word.
+    pub fn glob(glob: &str) -> Result<NamePattern, String> {
+        let glob = GlobBuilder::new(glob)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| format!("invalid glob `{}`: {}", glob, e.kind()))?;
+        NamePattern::compile(glob.regex().to_string())
+    }
+
+    /// Names containing a match of the regex; anchor it with `^` and `$` to match whole names.
+    pub fn regex(pattern: &str) -> Result<NamePattern, String> {
+        NamePattern::compile(format!("(?i){}", pattern))
+    }
+
+    fn compile(source: String) -> Result<NamePattern, String> {
+        // Keep pathological patterns from compiling into huge automata.
+        let regex = RegexBuilder::new(&source)
+            .size_limit(1 << 20)
+            .build()
+            .map_err(|e| format!("invalid pattern: {}", e))?;
+        Ok(NamePattern { source, regex })
+    }
+
+    /// The regex the pattern compiled to, as `indexing::sqlite` and `indexing::ngram` read it.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.regex.is_match(name.as_bytes())
+    }
+}
+
+impl PartialEq for NamePattern {
+    fn eq(&self, other: &NamePattern) -> bool {
+        self.source == other.source
+    }
+}
+
+/// An inclusive range of sizes or times; an open end is `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl Range {
+    pub fn contains(&self, value: i64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+impl Query {
+    /// Parse `text`, resolving relative times against the current time.
+    pub fn parse(text: &str) -> Result<Query, String> {
+        Query::parse_at(text, Local::now().timestamp())
+    }
+
+    /// Parse `text`, resolving relative times such as `modified:>1y` against `now` (seconds
+    /// since the Unix epoch).
+    pub fn parse_at(text: &str, now: i64) -> Result<Query, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0, now };
+        let filter = parser.parse_or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err("unbalanced `)` in query".to_string());
+        }
+        Ok(Query { filter })
+    }
+
+    /// Whether `text` uses query syntax: a known `field:` or a standalone `OR` or `NOT`. Other
+    /// text, parentheses and leading `-` included, is better searched as one name.
+    pub fn is_structured(text: &str) -> bool {
+        text.split_whitespace().any(|word| {
+            let word = word.trim_start_matches(['-', '(']);
+            word == "OR" || word == "NOT" || word.split_once(':').is_some_and(|(field, _)| is_field(field))
+        })
+    }
+
+    /// A query matching names that contain `text` as-is, without parsing it.
+    pub fn literal(text: &str) -> Query {
+        let text = text.to_ascii_lowercase();
+        let filter = match text.is_empty() {
+            true => Filter::All(Vec::new()),
+            false => Filter::Match(Predicate::Name(text)),
+        };
+        Query { filter }
+    }
+
+    /// This query, additionally restricted by `filter`.
+    pub fn and(self, filter: Filter) -> Query {
+        let filter = match (self.filter, filter) {
+            (f, Filter::All(rest)) if rest.is_empty() => f,
+            (Filter::All(first), f) if first.is_empty() => f,
+            (Filter::All(mut all), f) => {
+                all.push(f);
+                Filter::All(all)
+            }
+            (a, b) => Filter::All(vec![a, b]),
+        };
+        Query { filter }
+    }
+
+    pub fn matches(&self, obj: &DiskObject) -> bool {
+        self.filter.matches(obj)
+    }
+
+    /// The name terms joined by spaces when the query is nothing but names, i.e. a plain search
+    /// the backends' substring and fuzzy paths can run as before.
+    pub fn name_text(&self) -> Option<String> {
+        let name = |f: &Filter| match f {
+            Filter::Match(Predicate::Name(text)) => Some(text.clone()),
+            _ => None,
+        };
+        match &self.filter {
+            Filter::All(all) => all.iter().map(name).collect::<Option<Vec<_>>>().map(|v| v.join(" ")),
+            f => name(f),
+        }
+    }
+
+    /// The top-level name terms joined by spaces, and the query without them, for fuzzy search
+    /// to rank names by the one and filter by the other.
+    pub fn split_names(&self) -> (String, Query) {
+        let is_name = |f: &Filter| matches!(f, Filter::Match(Predicate::Name(_)));
+        let text = |f: &Filter| match f {
+            Filter::Match(Predicate::Name(text)) => text.clone(),
+            _ => String::new(),
+        };
+        match &self.filter {
+            Filter::All(all) => {
+                let (names, rest): (Vec<&Filter>, Vec<&Filter>) = all.iter().partition(|f| is_name(f));
+                let names: Vec<String> = names.into_iter().map(text).collect();
+                let rest = Filter::All(rest.into_iter().cloned().collect());
+                (names.join(" "), Query { filter: rest })
+            }
+            f if is_name(f) => (text(f), Query { filter: Filter::All(Vec::new()) }),
+            f => (String::new(), Query { filter: f.clone() }),
+        }
+    }
+
+    /// The longest name text every match must contain, for backends to narrow candidates with
+    /// their index before calling [`Query::matches`].
+    pub fn required_name(&self) -> Option<&str> {
+        fn name(f: &Filter) -> Option<&str> {
+            match f {
+                Filter::Match(Predicate::Name(text)) => Some(text.as_str()),
+                _ => None,
+            }
+        }
+        match &self.filter {
+            Filter::All(all) => all.iter().filter_map(name).max_by_key(|t| t.len()),
+            f => name(f),
+        }
+    }
+
+    /// A name pattern every match must satisfy, for backends to narrow candidates by the
+    /// literal text it requires.
+    pub fn required_pattern(&self) -> Option<&NamePattern> {
+        fn pattern(f: &Filter) -> Option<&NamePattern> {
+            match f {
+                Filter::Match(Predicate::NamePattern(pattern)) => Some(pattern),
+                _ => None,
+            }
+        }
+        match &self.filter {
+            Filter::All(all) => all.iter().find_map(pattern),
+            f => pattern(f),
+        }
+    }
+
+    /// Like [`Query::required_name`], for the text every match's full path must contain.
+    pub fn required_path_text(&self) -> Option<&str> {
+        fn path(f: &Filter) -> Option<&str> {
+            match f {
+                Filter::Match(Predicate::PathText(text)) => Some(text.as_str()),
+                _ => None,
+            }
+        }
+        match &self.filter {
+            Filter::All(all) => all.iter().filter_map(path).max_by_key(|t| t.len()),
+            f => path(f),
+        }
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, obj: &DiskObject) -> bool {
+        match self {
+            Filter::All(all) => all.iter().all(|f| f.matches(obj)),
+            Filter::Any(any) => any.iter().any(|f| f.matches(obj)),
+            Filter::Not(f) => !f.matches(obj),
+            Filter::Match(p) => p.matches(obj),
+        }
+    }
+}
+
+impl From<&SearchFilter> for Filter {
+    fn from(filter: &SearchFilter) -> Filter {
+        match filter {
+            SearchFilter::None => Filter::All(Vec::new()),
+            SearchFilter::Extensions(exts) if exts.is_empty() => Filter::All(Vec::new()),
+            SearchFilter::FoldersOnly => Filter::Match(Predicate::Kind(DiskObjectKind::Folder)),
+            SearchFilter::Other => Filter::Match(Predicate::OtherExt),
+            SearchFilter::Extensions(exts) => Filter::Match(Predicate::Ext(exts.clone())),
+        }
+    }
+}
+
+impl Predicate {
+    pub fn matches(&self, obj: &DiskObject) -> bool {
+        match self {
+            Predicate::Name(text) => obj.name_lower.contains(text.as_str()),
+            Predicate::Size(range) => obj.size.or(obj.recursive_size).is_some_and(|s| range.contains(s as i64)),
+            Predicate::Modified(range) => obj.mtime.is_some_and(|t| range.contains(t)),
+            Predicate::Ext(exts) => {
+                obj.kind == DiskObjectKind::File && obj.ext.as_ref().is_some_and(|e| exts.contains(e))
+            }
+            Predicate::OtherExt => {
+                obj.kind == DiskObjectKind::File
+                    && obj
+                        .ext
+                        .as_ref()
+                        .is_none_or(|e| !search_category::all_known_extensions().contains(&e.as_str()))
+            }
+            Predicate::Path(prefix) => is_path_or_below(&obj.path_lower, prefix),
+            Predicate::PathText(text) => path_text_positions(&obj.path_lower, text).next().is_some(),
+            Predicate::NamePattern(pattern) => pattern.is_match(&obj.name),
+            Predicate::Kind(kind) => obj.kind == *kind,
+        }
+    }
+}
+
+/// Whether `path` is `prefix` or lies below it. A `prefix` ending in a separator, such as `/`,
+/// only matches below it.
+pub fn is_path_or_below(path: &str, prefix: &str) -> bool {
+    if prefix.ends_with(['/', '\\']) {
+        return path.starts_with(prefix);
+    }
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with(['/', '\\']),
+        None => false,
+    }
+}
+
+/// `text` as a [`Predicate::PathText`] holds it: ASCII-lowercased, with `\` written as `/`.
+pub fn path_text(text: &str) -> String {
+    text.to_ascii_lowercase().replace('\\', "/")
+}
+
+/// Byte offsets at which `text`, as [`path_text`] returns it, occurs in `path_lower`. Either
+/// separator in the path matches a `/` in the text, so the same text finds Windows paths.
+pub fn path_text_positions<'a>(path_lower: &'a str, text: &'a str) -> impl Iterator<Item = usize> + 'a {
+    let (path, text) = (path_lower.as_bytes(), text.as_bytes());
+    let same = |p: u8, t: u8| p == t || (p == b'\\' && t == b'/');
+    let last = path.len().checked_sub(text.len());
+    (0..last.map_or(0, |l| l + 1))
+        .filter(move |&i| path[i..i + text.len()].iter().zip(text).all(|(&p, &t)| same(p, t)))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Or,
+    Not,
+    /// A word, with the text after a `field:` kept apart. Quoted words never have a field.
+    Word { field: Option<String>, text: String },
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '-' => {
+                chars.next();
+                match chars.peek() {
+                    Some(&n) if !n.is_whitespace() && n != ')' => tokens.push(Token::Not),
+                    _ => tokens.push(Token::Word { field: None, text: "-".to_string() }),
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                let mut field = None;
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' {
+                        quoted = true;
+                        word.push_str(&read_quoted(&mut chars)?);
+                    } else if c == ':' && field.is_none() && !quoted && is_field(&word) {
+                        field = Some(std::mem::take(&mut word).to_ascii_lowercase());
+                    } else {
+                        word.push(c);
+                    }
+                }
+                let token = match (field, word.as_str()) {
+                    (None, "OR") if !quoted => Token::Or,
+                    (None, "NOT") if !quoted => Token::Not,
+                    (field, _) => Token::Word { field, text: word },
+                };
+                tokens.push(token);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<String, String> {
+    let mut text = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(text);
+        }
+        text.push(c);
+    }
+    Err("unterminated `\"` in query".to_string())
+}
+
+fn is_field(word: &str) -> bool {
+    matches!(
+        word.to_ascii_lowercase().as_str(),
+        "name" | "size" | "modified" | "mtime" | "ext" | "category" | "path" | "kind" | "glob" | "regex"
+    )
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    now: i64,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, String> {
+        let mut any = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            any.push(self.parse_and()?);
+        }
+        Ok(match any.len() {
+            1 => any.remove(0),
+            _ => Filter::Any(any),
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, String> {
+        let mut all = Vec::new();
+        while let Some(token) = self.peek() {
+            if matches!(token, Token::Or | Token::Close) {
+                break;
+            }
+            all.push(self.parse_unary()?);
+        }
+        if all.is_empty() && self.pos > 0 {
+            return Err("`OR` or `(` without a term after it".to_string());
+        }
+        Ok(match all.len() {
+            1 => all.remove(0),
+            _ => Filter::All(all),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, String> {
+        let token = self.tokens[self.pos].clone();
+        self.pos += 1;
+        match token {
+            Token::Not => match self.peek() {
+                Some(Token::Or | Token::Close) | None => Err("nothing to negate after `-` or `NOT`".to_string()),
+                Some(_) => Ok(Filter::Not(Box::new(self.parse_unary()?))),
+            },
+            Token::Open => {
+                let inner = self.parse_or()?;
+                match self.peek() {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err("unbalanced `(` in query".to_string()),
+                }
+            }
+            Token::Word { field, text } => self.parse_word(field.as_deref(), &text),
+            Token::Close | Token::Or => unreachable!("stopped at by parse_and"),
+        }
+    }
+
+    fn parse_word(&self, field: Option<&str>, text: &str) -> Result<Filter, String> {
+        let predicate = match field {
+            None | Some("name") => Predicate::Name(text.to_ascii_lowercase()),
+            Some("size") => Predicate::Size(parse_size_range(text)?),
+            Some("modified" | "mtime") => Predicate::Modified(parse_time_range(text, self.now)?),
+            Some("ext") => Predicate::Ext(parse_list(text, "ext")?),
+            Some("category") => return parse_categories(text),
+            Some("path") => Predicate::Path(parse_path(text)?),
+            Some("kind") => Predicate::Kind(parse_kind(text)?),
+            Some(field @ ("glob" | "regex")) if text.is_empty() => return Err(format!("`{}:` needs a value", field)),
+            Some("glob") => Predicate::NamePattern(NamePattern::glob(text)?),
+            Some("regex") => Predicate::NamePattern(NamePattern::regex(text)?),
+            Some(other) => unreachable!("`{}` is not a field", other),
+        };
+        Ok(Filter::Match(predicate))
+    }
+}
+
+/// Comma-separated values, lowercased and without a leading `.`.
+fn parse_list(text: &str, field: &str) -> Result<Vec<String>, String> {
+    let values: Vec<String> = text
+        .split(',')
+        .map(|x| x.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|x| !x.is_empty())
+        .collect();
+    match values.is_empty() {
+        true => Err(format!("`{}:` needs a value", field)),
+        false => Ok(values),
+    }
+}
+
+fn parse_categories(text: &str) -> Result<Filter, String> {
+    let mut any = Vec::new();
+    for category in parse_list(text, "category")? {
+        let predicate = match category.as_str() {
+            "folder" => Predicate::Kind(DiskObjectKind::Folder),
+            "other" => Predicate::OtherExt,
+            c => match search_category::extension_set(c) {
+                Some(exts) => Predicate::Ext(exts.iter().map(|e| e.to_string()).collect()),
+                None => return Err(format!("unknown category `{}`", c)),
+            },
+        };
+        any.push(Filter::Match(predicate));
+    }
+    Ok(match any.len() {
+        1 => any.remove(0),
+        _ => Filter::Any(any),
+    })
+}
+
+fn parse_kind(text: &str) -> Result<DiskObjectKind, String> {
+    match text.to_ascii_lowercase().as_str() {
+        "file" => Ok(DiskObjectKind::File),
+        "folder" | "dir" | "directory" => Ok(DiskObjectKind::Folder),
+        "symlink" | "link" => Ok(DiskObjectKind::Symlink),
+        other => Err(format!("unknown kind `{}`; use file, folder or symlink", other)),
+    }
+}
+
+/// The lowercased path, with a leading `~` replaced by the home directory.
+fn parse_path(text: &str) -> Result<String, String> {
+    if text.is_empty() {
+        return Err("`path:` needs a value".to_string());
+    }
+    let path = match text.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            let home = std::env::var("HOME")
+                .or_else(|_| std::env::var("USERPROFILE"))
+                .map_err(|_| "`~` in `path:` but no home directory is set".to_string())?;
+            format!("{}{}", home, rest)
+        }
+        _ => text.to_string(),
+    };
+    Ok(path.to_ascii_lowercase())
+}
+
+/// The values one side of a comparison covers, e.g. a whole day for a date.
+struct Bound {
+    lo: i64,
+    hi: i64,
+    /// Counts back from now, like an age: `>1y` means an earlier time.
+    backwards: bool,
+    /// The comparison a value without one means.
+    bare: &'static str,
+}
+
+/// A comparison like `>2GB` or `<=7d`, a bare value, or a range `a..b` with either end open.
+fn parse_range(text: &str, value: impl Fn(&str) -> Result<Bound, String>) -> Result<Range, String> {
+    if let Some((from, to)) = text.split_once("..") {
+        return match (from.is_empty(), to.is_empty()) {
+            (true, true) => Err("a range needs at least one end".to_string()),
+            (false, true) => parse_range(&format!(">={}", from), value),
+            (true, false) => parse_range(&format!("<={}", to), value),
+            (false, false) => {
+                let (a, b) = (value(from)?, value(to)?);
+                Ok(Range { min: Some(a.lo.min(b.lo)), max: Some(a.hi.max(b.hi)) })
+            }
+        };
+    }
+    let rest = text.trim_start_matches(['<', '>', '=']);
+    let bound = value(rest)?;
+    let op = match &text[..text.len() - rest.len()] {
+        "" => bound.bare,
+        op => op,
+    };
+    let op = match (bound.backwards, op) {
+        (true, ">") => "<",
+        (true, ">=") => "<=",
+        (true, "<") => ">",
+        (true, "<=") => ">=",
+        (_, op) => op,
+    };
+    let out_of_range = || format!("`{}` is out of range", text);
+    match op {
+        "=" => Ok(Range { min: Some(bound.lo), max: Some(bound.hi) }),
+        ">=" => Ok(Range { min: Some(bound.lo), max: None }),
+        ">" => Ok(Range { min: Some(bound.hi.checked_add(1).ok_or_else(out_of_range)?), max: None }),
+        "<=" => Ok(Range { min: None, max: Some(bound.hi) }),
+        "<" => Ok(Range { min: None, max: Some(bound.lo.checked_sub(1).ok_or_else(out_of_range)?) }),
+        op => Err(format!("unknown comparison `{}`", op)),
+    }
+}
+
+fn parse_size_range(text: &str) -> Result<Range, String> {
+    parse_range(text, |v| {
+        let size = parse_size(v)?;
+        Ok(Bound { lo: size, hi: size, backwards: false, bare: ">=" })
+    })
+}
+
+/// Bytes for values like `2GB`, `1.5g`, `500k`, `10MiB` or `42`.
+fn parse_size(text: &str) -> Result<i64, String> {
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid size `{}`", text))?;
+    let power = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 1,
+        "m" | "mb" | "mib" => 2,
+        "g" | "gb" | "gib" => 3,
+        "t" | "tb" | "tib" => 4,
+        _ => return Err(format!("unknown size unit in `{}`", text)),
+    };
+    let bytes = number * 1024f64.powi(power);
+    if bytes >= i64::MAX as f64 {
+        return Err(format!("size `{}` is out of range", text));
+    }
+    Ok(bytes as i64)
+}
+
+fn parse_time_range(text: &str, now: i64) -> Result<Range, String> {
+    parse_range(text, |v| {
+        if let Ok(secs) = parse_duration(v) {
+            let at = now.checked_sub(secs).ok_or_else(|| format!("invalid duration `{}`", v))?;
+            return Ok(Bound { lo: at, hi: at, backwards: true, bare: "<" });
+        }
+        let date = NaiveDate::parse_from_str(v, "%Y-%m-%d")
+            .map_err(|_| format!("invalid time `{}`; use a date like 2024-01-31 or an age like 30d", v))?;
+        let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+        let start = Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map_or_else(|| midnight.and_utc().timestamp(), |t| t.timestamp());
+        Ok(Bound { lo: start, hi: start + DAY_SECS - 1, backwards: false, bare: "=" })
+    })
+}
+
+/// Seconds in a duration like `30d`, `2w` or `1y`.
+fn parse_duration(text: &str) -> Result<i64, String> {
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: i64 = number.parse().map_err(|_| format!("invalid duration `{}`", text))?;
+    let unit = match unit {
+        "s" => 1,
+        "min" => 60,
+        "h" => 60 * 60,
+        "d" => DAY_SECS,
+        "w" => 7 * DAY_SECS,
+        "mo" => 30 * DAY_SECS,
+        "y" => 365 * DAY_SECS,
+        _ => return Err(format!("unknown duration unit in `{}`", text)),
+    };
+    number.checked_mul(unit).ok_or_else(|| format!("invalid duration `{}`", text))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,143 @@
+use super::*;
+
+const NOW: i64 = 1_700_000_000;
+const GIB: i64 = 1024 * 1024 * 1024;
+
+fn object(path: &str, kind: DiskObjectKind, size: u64, mtime: Option<i64>) -> DiskObject {
+    let name = path.rsplit('/').next().unwrap_or(path).to_string();
+    let folder = kind == DiskObjectKind::Folder;
+    DiskObject {
+        path: path.to_string(),
+        path_lower: path.to_ascii_lowercase(),
+        parent_path: None,
+        name_lower: name.to_ascii_lowercase(),
+        ext: match folder {
+            false => name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()),
+            true => None,
+        },
+        name,
+        kind,
+        size: (!folder).then_some(size),
+        recursive_size: folder.then_some(size),
+        allocated_size: None,
+        dev: None,
+        ino: None,
+        mtime,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
+    }
+}
+
+fn parse(text: &str) -> Filter {
+    Query::parse_at(text, NOW).unwrap().filter
+}
+
+fn range(min: Option<i64>, max: Option<i64>) -> Range {
+    Range { min, max }
+}
+
+#[test]
+fn sizes_take_units_comparisons_and_ranges() {
+    let size = |text: &str| match parse(text) {
+        Filter::Match(Predicate::Size(r)) => r,
+        other => panic!("not a size: {:?}", other),
+    };
+    assert_eq!(size("size:>2GB"), range(Some(2 * GIB + 1), None));
+    assert_eq!(size("size:>=1.5g"), range(Some(3 * GIB / 2), None));
+    assert_eq!(size("size:<500k"), range(None, Some(500 * 1024 - 1)));
+    assert_eq!(size("size:1MiB..5mb"), range(Some(1 << 20), Some(5 << 20)));
+    assert_eq!(size("size:10"), range(Some(10), None));
+    assert!(Query::parse_at("size:>2XB", NOW).is_err());
+    assert!(Query::parse_at("size:..", NOW).is_err());
+    assert!(Query::parse_at("size:>99999999999999999999TB", NOW).is_err());
+    assert!(Query::parse_at("size:<99999999999999999999TB", NOW).is_err());
+}
+
+#[test]
+fn times_accept_ages_and_local_dates() {
+    let time = |text: &str| match parse(text) {
+        Filter::Match(Predicate::Modified(r)) => r,
+        other => panic!("not a time: {:?}", other),
+    };
+    let year = 365 * DAY_SECS;
+    assert_eq!(time("modified:>1y"), range(None, Some(NOW - year - 1)));
+    assert_eq!(time("mtime:<7d"), range(Some(NOW - 7 * DAY_SECS + 1), None));
+    assert_eq!(time("modified:2d"), time("modified:<2d"));
+    assert_eq!(time("modified:1d..1w"), range(Some(NOW - 7 * DAY_SECS), Some(NOW - DAY_SECS)));
+
+    let day = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+    let start = Local.from_local_datetime(&day).earliest().unwrap().timestamp();
+    assert_eq!(time("modified:2024-03-01"), range(Some(start), Some(start + DAY_SECS - 1)));
+    assert_eq!(time("modified:<2024-03-01"), range(None, Some(start - 1)));
+    assert_eq!(time("modified:>2024-03-01"), range(Some(start + DAY_SECS), None));
+    assert!(Query::parse_at("modified:yesterday", NOW).is_err());
+    assert!(Query::parse_at("modified:>99999999999999y", NOW).is_err());
+    assert!(Query::parse_at("modified:1d..99999999999999y", NOW).is_err());
+}
+
+#[test]
+fn negation_or_groups_and_quotes_build_the_tree() {
+    let name = |t: &str| Filter::Match(Predicate::Name(t.to_string()));
+    let kind = |k| Filter::Match(Predicate::Kind(k));
+    assert_eq!(
+        parse("Holiday -kind:folder (ext:MP4,.mkv OR category:folder) \"my clip\""),
+        Filter::All(vec![
+            name("holiday"),
+            Filter::Not(Box::new(kind(DiskObjectKind::Folder))),
+            Filter::Any(vec![
+                Filter::Match(Predicate::Ext(vec!["mp4".into(), "mkv".into()])),
+                kind(DiskObjectKind::Folder),
+            ]),
+            name("my clip"),
+        ])
+    );
+    assert_eq!(parse("a OR NOT b"), Filter::Any(vec![name("a"), Filter::Not(Box::new(name("b")))]));
+    assert_eq!(parse("12:30 - foo-bar"), Filter::All(vec![name("12:30"), name("-"), name("foo-bar")]));
+    assert_eq!(parse("  "), Filter::All(Vec::new()));
+
+    for bad in ["(a", "a)", "a OR", "-(", "NOT", "\"open", "kind:pipe", "category:nope", "ext:", "glob:", "regex:\"[\""] {
+        assert!(Query::parse_at(bad, NOW).is_err(), "{:?} should not parse", bad);
+    }
+}
+
+#[test]
+fn filters_match_objects() {
+    let old_movie = object("/home/u/Downloads/Movie.MKV", DiskObjectKind::File, 3 * GIB as u64, Some(NOW - 400 * DAY_SECS));
+    let new_movie = object("/home/u/Downloads/clip.mp4", DiskObjectKind::File, 3 * GIB as u64, Some(NOW - DAY_SECS));
+    let folder = object("/home/u/Downloads", DiskObjectKind::Folder, 6 * GIB as u64, None);
+    let sibling = object("/home/u/Downloads2/notes.log", DiskObjectKind::File, 10, Some(NOW));
+    let all = [&old_movie, &new_movie, &folder, &sibling];
+
+    let matching = |text: &str| -> Vec<&str> {
+        let query = Query::parse_at(text, NOW).unwrap();
+        all.iter().filter(|o| query.matches(o)).map(|o| o.path.as_str()).collect()
+    };
+    assert_eq!(matching("category:video size:>2GB modified:>1y path:/home/u/downloads"), vec![old_movie.path.as_str()]);
+    assert_eq!(matching("path:/home/u/Downloads"), vec![old_movie.path.as_str(), new_movie.path.as_str(), folder.path.as_str()]);
+    assert_eq!(matching("path:/home/u/downloads/ kind:folder"), Vec::<&str>::new());
+    assert_eq!(matching("size:>5g"), vec![folder.path.as_str()]);
+    assert_eq!(matching("-modified:<1w"), vec![old_movie.path.as_str(), folder.path.as_str()]);
+    assert_eq!(matching("movie OR notes"), vec![old_movie.path.as_str(), sibling.path.as_str()]);
+    assert_eq!(matching("category:other"), vec![sibling.path.as_str()]);
+    assert_eq!(matching("glob:*.mkv OR regex:^clip\\."), vec![old_movie.path.as_str(), new_movie.path.as_str()]);
+    assert_eq!(matching("glob:movie"), Vec::<&str>::new());
+
+    let legacy = Query::literal("CLIP").and(Filter::from(&SearchFilter::Extensions(vec!["mp4".into()])));
+    assert!(legacy.matches(&new_movie) && !legacy.matches(&old_movie));
+}
+
+#[test]
+fn plain_queries_keep_their_name_text() {
+    assert_eq!(Query::parse_at("foo  Bar", NOW).unwrap().name_text().as_deref(), Some("foo bar"));
+    assert_eq!(Query::parse_at("", NOW).unwrap().name_text().as_deref(), Some(""));
+    assert_eq!(Query::parse_at("foo -bar", NOW).unwrap().name_text(), None);
+
+    assert!(Query::is_structured("notes -ext:md") && Query::is_structured("a OR b") && Query::is_structured("(Size:>1k)"));
+    assert!(!Query::is_structured("file (1).pdf") && !Query::is_structured("-old or new") && !Query::is_structured("c:/tmp"));
+
+    let query = Query::parse_at("ab ext:txt abcd -abcdef", NOW).unwrap();
+    assert_eq!(query.required_name(), Some("abcd"));
+    assert_eq!(Query::parse_at("a OR b", NOW).unwrap().required_name(), None);
+}
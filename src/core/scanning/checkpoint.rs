@@ -0,0 +1,333 @@
+//! Scans that can be resumed after a crash, a reboot or a cancel.
+//!
+//! [`scan_resumable`] reads folders from an explicit work queue instead of one recursive walk,
+//! so it always knows which folders are finished. Those are handed to a save callback every few
+//! seconds; the host stores them (see `db::save_checkpoint`) and passes them back as a
+//! [`Checkpoint`] to the next scan of the same roots. That scan takes finished folders whose
+//! mtime is unchanged from the checkpoint and only reads the rest.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{FileEntry, FileKey, SymlinkEntry};
+use crate::core::scanning::exclusions::{collapsed_entry, is_collapsed_entry, RuleAction};
+use crate::core::scanning::scanner::{Emitter, ScanError, ScanItem, ScanSink};
+use crate::core::scanning::utils::{file_entry, file_key_from_path, symlink_entry};
+use crate::core::scanning::{ScanOptions, SymlinkPolicy};
+
+/// How often finished folders are handed to the save callback.
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Worker threads when `ScanOptions::threads` is 0.
+const DEFAULT_THREADS: usize = 4;
+
+/// A folder whose listing was read completely.
+#[derive(Clone, Debug)]
+pub struct DoneFolder {
+    pub path: PathBuf,
+    /// Nanoseconds since the epoch when the folder was read; see [`folder_mtime`].
+    pub mtime: Option<i64>,
+    /// Subfolders found inside; exclusions are applied to them when they are queued.
+    pub subfolders: Vec<PathBuf>,
+    /// Files directly inside, or the summary of a collapsed folder.
+    pub files: Vec<FileEntry>,
+    /// Links directly inside that are recorded rather than followed; see [`SymlinkPolicy`].
+    pub symlinks: Vec<SymlinkEntry>,
+}
+
+/// The finished part of an interrupted scan.
+#[derive(Clone, Debug, Default)]
+pub struct Checkpoint {
+    pub roots: Vec<PathBuf>,
+    pub folders: Vec<DoneFolder>,
+}
+
+/// Modification time of a folder in nanoseconds; it changes whenever an entry is added,
+/// removed or renamed inside.
+pub fn folder_mtime(path: &Path) -> Option<i64> {
+    std::fs::metadata(path)
+        .ok()?
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_nanos() as i64)
+}
+
+struct Job {
+    path: PathBuf,
+    collapse: bool,
+    root_dev: Option<u64>,
+}
+
+struct Queue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+struct QueueState {
+    jobs: Vec<Job>,
+    busy: usize,
+    /// Folders already queued, to stop symlink loops when links are followed.
+    seen: HashSet<FileKey>,
+}
+
+/// A folder as a worker hands it back: reused from the checkpoint or freshly read, with the
+/// entries that could not be read.
+struct Visited {
+    folder: DoneFolder,
+    reused: bool,
+    errors: Vec<ScanError>,
+}
+
+/// Scan `roots` on `options.threads` workers, streaming entries to `sink` like a
+/// [`crate::core::scanning::Scanner`], and pass finished folders to `save` every
+/// [`CHECKPOINT_INTERVAL`] and once more at the end. Folders taken from `resume` are not
+/// passed again; `resume` is ignored when its roots differ.
+///
+/// Metadata is always read (`options.metadata` is ignored), since the checkpoint stores sizes.
+pub fn scan_resumable(
+    roots: &[PathBuf],
+    options: &ScanOptions,
+    resume: Option<&Checkpoint>,
+    sink: &mut dyn ScanSink,
+    save: &mut dyn FnMut(&[DoneFolder]),
+) {
+    let done: HashMap<&Path, &DoneFolder> = resume
+        .filter(|c| c.roots == roots)
+        .map(|c| c.folders.iter().map(|f| (f.path.as_path(), f)).collect())
+        .unwrap_or_default();
+    let follow = options.symlinks == SymlinkPolicy::Follow;
+    let queue = Queue {
+        state: Mutex::new(QueueState {
+            jobs: Vec::new(),
+            busy: 0,
+            seen: HashSet::new(),
+        }),
+        changed: Condvar::new(),
+    };
+    {
+        let mut state = queue.state.lock().unwrap_or_else(|e| e.into_inner());
+        for root in roots.iter().rev() {
+            let key = file_key_from_path(root);
+            if follow && key.is_some_and(|k| !state.seen.insert(k)) {
+                continue;
+            }
+            state.jobs.push(Job {
+                path: root.clone(),
+                collapse: false,
+                root_dev: options.one_file_system.then_some(key).flatten().map(|k| k.dev),
+            });
+        }
+    }
+    let threads = match options.threads {
+        0 => DEFAULT_THREADS,
+        n => n,
+    };
+
+    let mut out = Emitter::start(sink);
+    let mut unsaved: Vec<DoneFolder> = Vec::new();
+    let mut last_save = Instant::now();
+    let (tx, rx) = mpsc::channel::<Visited>();
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let tx = tx.clone();
+            let (queue, done) = (&queue, &done);
+            scope.spawn(move || work(queue, done, options, &tx));
+        }
+        drop(tx);
+        for Visited { folder, reused, errors } in rx {
+            out.emit(ScanItem::Folder(folder.path.clone()));
+            for file in &folder.files {
+                out.emit(ScanItem::File(file.clone()));
+            }
+            for link in &folder.symlinks {
+                out.emit(ScanItem::Symlink(link.clone()));
+            }
+            for error in errors {
+                out.emit(ScanItem::Error(error));
+            }
+            if !reused {
+                unsaved.push(folder);
+            }
+            if last_save.elapsed() >= CHECKPOINT_INTERVAL {
+                save(&unsaved);
+                unsaved.clear();
+                last_save = Instant::now();
+            }
+        }
+    });
+    if !unsaved.is_empty() {
+        save(&unsaved);
+    }
+    out.finish();
+}
+
+fn work(
+    queue: &Queue,
+    done: &HashMap<&Path, &DoneFolder>,
+    options: &ScanOptions,
+    tx: &mpsc::Sender<Visited>,
+) {
+    loop {
+        let job = {
+            let mut state = queue.state.lock().unwrap_or_else(|e| e.into_inner());
+            loop {
+                if options.control.is_cancelled() {
+                    return;
+                }
+                if let Some(job) = state.jobs.pop() {
+                    state.busy += 1;
+                    break job;
+                }
+                if state.busy == 0 {
+                    return;
+                }
+                state = queue.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+        let mut visited = match options.control.checkpoint() {
+            true => Some(visit(&job, done, options)),
+            false => None,
+        };
+        let children: Vec<Job> = visited
+            .iter()
+            .flat_map(|v| &v.folder.subfolders)
+            .filter_map(|path| child_job(path, job.root_dev, options))
+            .collect();
+        let follow = options.symlinks == SymlinkPolicy::Follow;
+        let mut state = queue.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.busy -= 1;
+        let mut revisits = Vec::new();
+        for child in children {
+            if follow && file_key_from_path(&child.path).is_some_and(|k| !state.seen.insert(k)) {
+                revisits.push(child.path);
+                continue;
+            }
+            state.jobs.push(child);
+        }
+        drop(state);
+        // A folder entered before through another path is recorded as the link that leads
+        // back into it; a reused folder may have it already.
+        if let Some(Visited { folder, .. }) = &mut visited {
+            for path in revisits {
+                if !folder.symlinks.iter().any(|l| l.path == path) {
+                    folder.symlinks.extend(symlink_entry(&path));
+                }
+            }
+        }
+        if let Some(folder) = visited {
+            let _ = tx.send(folder);
+        }
+        queue.changed.notify_all();
+    }
+}
+
+/// Read one folder, or take it from the checkpoint when it has not changed since. A folder with
+/// entries that could not be read gets no mtime, so it is read again next time.
+fn visit(job: &Job, done: &HashMap<&Path, &DoneFolder>, options: &ScanOptions) -> Visited {
+    let mtime = folder_mtime(&job.path);
+    if let Some(folder) = done.get(job.path.as_path()) {
+        let was_collapsed = folder.files.first().is_some_and(|f| is_collapsed_entry(&f.path));
+        if mtime.is_some() && folder.mtime == mtime && was_collapsed == job.collapse {
+            return Visited {
+                folder: (*folder).clone(),
+                reused: true,
+                errors: Vec::new(),
+            };
+        }
+    }
+    let mut folder = DoneFolder {
+        path: job.path.clone(),
+        mtime,
+        subfolders: Vec::new(),
+        files: Vec::new(),
+        symlinks: Vec::new(),
+    };
+    let mut errors = Vec::new();
+    if job.collapse {
+        folder.files.extend(collapsed_entry(&job.path, options, |e| errors.push(e)));
+        if !errors.is_empty() {
+            folder.mtime = None;
+        }
+        return Visited { folder, reused: false, errors };
+    }
+    let entries = match std::fs::read_dir(&job.path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            folder.mtime = None;
+            errors.push(ScanError::io(&job.path, &err));
+            return Visited { folder, reused: false, errors };
+        }
+    };
+    let follow = options.symlinks == SymlinkPolicy::Follow;
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                errors.push(ScanError::io(&job.path, &err));
+                continue;
+            }
+        };
+        let path = entry.path();
+        let Ok(mut file_type) = entry.file_type() else { continue };
+        if file_type.is_symlink() {
+            if !follow {
+                if options.symlinks == SymlinkPolicy::Record && options.exclusions.action(&path, false, || std::fs::symlink_metadata(&path).ok()).is_none() {
+                    folder.symlinks.extend(symlink_entry(&path));
+                }
+                continue;
+            }
+            let Ok(target) = std::fs::metadata(&path) else {
+                folder.symlinks.extend(symlink_entry(&path));
+                continue;
+            };
+            file_type = target.file_type();
+        }
+        if file_type.is_dir() {
+            folder.subfolders.push(path);
+            continue;
+        }
+        if !file_type.is_file() {
+            continue;
+        }
+        let meta = match std::fs::metadata(&path) {
+            Ok(meta) => meta,
+            Err(err) => {
+                errors.push(ScanError::io(&path, &err));
+                continue;
+            }
+        };
+        if options.exclusions.action(&path, false, || Some(meta.clone())).is_some() {
+            continue;
+        }
+        folder.files.extend(file_entry(&path, &meta));
+    }
+    if !errors.is_empty() {
+        folder.mtime = None;
+    }
+    Visited { folder, reused: false, errors }
+}
+
+/// The job for a subfolder, or `None` when it is excluded or on another filesystem.
+fn child_job(path: &Path, root_dev: Option<u64>, options: &ScanOptions) -> Option<Job> {
+    if root_dev.is_some_and(|dev| file_key_from_path(path).is_none_or(|k| k.dev != dev)) {
+        return None;
+    }
+    let collapse = match options.exclusions.action(path, true, || std::fs::metadata(path).ok()) {
+        Some(RuleAction::Exclude) => return None,
+        Some(RuleAction::Collapse) => true,
+        None => false,
+    };
+    Some(Job {
+        path: path.to_path_buf(),
+        collapse,
+        root_dev,
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,103 @@
+use super::*;
+
+use crate::core::scanning::ScanCollector;
+
+fn fake_file(path: PathBuf, size: u64) -> FileEntry {
+    FileEntry {
+        file_key: file_key_from_path(&path).unwrap(),
+        path,
+        size,
+        allocated_size: size,
+        mtime: None,
+        owner: None,
+    }
+}
+
+fn scan(roots: &[PathBuf], resume: Option<&Checkpoint>) -> (HashMap<PathBuf, u64>, Vec<PathBuf>) {
+    let mut collector = ScanCollector::new(|_| {});
+    let mut saved = Vec::new();
+    scan_resumable(roots, &ScanOptions::default(), resume, &mut collector, &mut |folders| {
+        saved.extend(folders.iter().map(|f| f.path.clone()))
+    });
+    let sizes = collector.files.into_iter().map(|f| (f.path, f.size)).collect();
+    saved.sort();
+    (sizes, saved)
+}
+
+#[test]
+fn a_fresh_scan_saves_every_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    std::fs::create_dir_all(root.join("a/b")).unwrap();
+    std::fs::write(root.join("top.txt"), b"1").unwrap();
+    std::fs::write(root.join("a/b/deep.txt"), b"22").unwrap();
+
+    let (sizes, saved) = scan(std::slice::from_ref(&root), None);
+    assert_eq!(sizes.len(), 2);
+    assert_eq!(sizes[&root.join("a/b/deep.txt")], 2);
+    assert_eq!(saved, vec![root.clone(), root.join("a"), root.join("a/b")]);
+}
+
+#[test]
+fn resuming_reuses_unchanged_folders_and_rereads_the_rest() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().to_path_buf();
+    for sub in ["same", "changed", "new"] {
+        std::fs::create_dir(root.join(sub)).unwrap();
+        std::fs::write(root.join(sub).join("f.txt"), b"abc").unwrap();
+    }
+    let checkpoint = Checkpoint {
+        roots: vec![root.clone()],
+        folders: vec![
+            DoneFolder {
+                path: root.clone(),
+                mtime: folder_mtime(&root),
+                subfolders: ["same", "changed", "new"].iter().map(|s| root.join(s)).collect(),
+                files: Vec::new(),
+                symlinks: Vec::new(),
+            },
+            DoneFolder {
+                path: root.join("same"),
+                mtime: folder_mtime(&root.join("same")),
+                subfolders: Vec::new(),
+                files: vec![fake_file(root.join("same/f.txt"), 100)],
+                symlinks: Vec::new(),
+            },
+            DoneFolder {
+                path: root.join("changed"),
+                mtime: Some(0),
+                subfolders: Vec::new(),
+                files: vec![fake_file(root.join("changed/f.txt"), 100)],
+                symlinks: Vec::new(),
+            },
+        ],
+    };
+
+    let (sizes, saved) = scan(std::slice::from_ref(&root), Some(&checkpoint));
+    // The stale size shows the unchanged folder was not read again.
+    assert_eq!(sizes[&root.join("same/f.txt")], 100);
+    assert_eq!(sizes[&root.join("changed/f.txt")], 3);
+    assert_eq!(sizes[&root.join("new/f.txt")], 3);
+    assert_eq!(saved, vec![root.join("changed"), root.join("new")]);
+
+    // A checkpoint of other roots is not used.
+    let other = Checkpoint {
+        roots: vec![root.join("same")],
+        ..checkpoint
+    };
+    let (sizes, _) = scan(std::slice::from_ref(&root), Some(&other));
+    assert_eq!(sizes[&root.join("same/f.txt")], 3);
+}
+
+#[test]
+fn a_cancelled_scan_saves_nothing_unfinished() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    let options = ScanOptions::default();
+    options.control.cancel();
+    let mut saved = 0;
+    let mut collector = ScanCollector::new(|_| {});
+    scan_resumable(&[dir.path().to_path_buf()], &options, None, &mut collector, &mut |f| saved += f.len());
+    assert_eq!(saved, 0);
+    assert!(collector.folders.is_empty());
+}
@@ -0,0 +1,337 @@
+//! User-editable rules for what a scan leaves out.
+//!
+//! A rule matches a path by a gitignore-style glob or by an absolute path prefix, optionally
+//! only when the file is large or old enough. Matching entries are either left out entirely
+//! ([`RuleAction::Exclude`]) or, for folders, kept as one node with their total size and no
+//! contents ([`RuleAction::Collapse`]) — what you want for `node_modules` and friends: big
+//! enough to matter when cleaning up, too many files to be worth indexing one by one.
+//!
+//! The scanners, the watcher and the reconciler all read the same [`ExclusionRules`]; the
+//! desktop app and the CLI keep them in `exclusions.json` next to `index.db`.
+
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{FileEntry, FileKey};
+use crate::core::scanning::scanner::ScanError;
+use crate::core::scanning::ScanOptions;
+use crate::core::scanning::utils::{allocated_size, file_key_from_path};
+
+/// File name of the entry standing in for the contents of a collapsed folder.
+pub const COLLAPSED_ENTRY_NAME: &str = "[collapsed]";
+
+/// Whether `path` is the summary entry [`collapsed_entry`] made for a folder.
+pub fn is_collapsed_entry(path: &Path) -> bool {
+    path.file_name() == Some(COLLAPSED_ENTRY_NAME.as_ref())
+}
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Leave the file or folder out of the scan.
+    #[default]
+    Exclude,
+    /// Keep the folder with its total size, but not what is inside it. Files are excluded.
+    Collapse,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternKind {
+    /// Gitignore-style glob. Without a `/` it matches a name at any depth (`*.iso`,
+    /// `node_modules`); a leading `/` anchors it at the filesystem root (`/home/*/.cache`);
+    /// a trailing `/` matches folders only.
+    #[default]
+    Glob,
+    /// Absolute path; the folder (or file) itself and everything below it.
+    Prefix,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExclusionRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub kind: PatternKind,
+    #[serde(default)]
+    pub action: RuleAction,
+    /// Only files of at least this many bytes. Rules with a size condition never match folders.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<u64>,
+    /// Only entries not modified for this many days.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub older_than_days: Option<u64>,
+}
+
+impl ExclusionRule {
+    pub fn glob(pattern: &str, action: RuleAction) -> Self {
+        ExclusionRule {
+            pattern: pattern.to_string(),
+            kind: PatternKind::Glob,
+            action,
+            min_size: None,
+            older_than_days: None,
+        }
+    }
+
+    pub fn prefix(path: &str, action: RuleAction) -> Self {
+        ExclusionRule {
+            kind: PatternKind::Prefix,
+            ..ExclusionRule::glob(path, action)
+        }
+    }
+
+    fn has_conditions(&self) -> bool {
+        self.min_size.is_some() || self.older_than_days.is_some()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Matcher {
+    Glob { glob: GlobMatcher, dirs_only: bool },
+    Prefix(PathBuf),
+}
+
+impl Matcher {
+    fn compile(rule: &ExclusionRule) -> Result<Self, String> {
+        match rule.kind {
+            PatternKind::Glob => {
+                let (pattern, dirs_only) = match rule.pattern.strip_suffix('/') {
+                    Some(p) => (p, true),
+                    None => (rule.pattern.as_str(), false),
+                };
+                let anchored = if pattern.starts_with('/') {
+                    pattern.to_string()
+                } else {
+                    format!("**/{}", pattern)
+                };
+                let glob = GlobBuilder::new(&anchored)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| format!("{}: {}", rule.pattern, e))?;
+                Ok(Matcher::Glob {
+                    glob: glob.compile_matcher(),
+                    dirs_only,
+                })
+            }
+            PatternKind::Prefix => {
+                let path = PathBuf::from(&rule.pattern);
+                if !path.is_absolute() {
+                    return Err(format!("{}: prefix rules need an absolute path", rule.pattern));
+                }
+                Ok(Matcher::Prefix(path))
+            }
+        }
+    }
+
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        match self {
+            Matcher::Glob { glob, dirs_only } => {
+                (is_dir || !dirs_only) && glob.is_match(path.to_string_lossy().replace('\\', "/"))
+            }
+            Matcher::Prefix(prefix) => path.starts_with(prefix),
+        }
+    }
+}
+
+/// A compiled rule set. Cheap to share between scanner threads behind an `Arc`.
+#[derive(Clone, Debug, Default)]
+pub struct ExclusionRules {
+    rules: Vec<ExclusionRule>,
+    matchers: Vec<Matcher>,
+    /// Unix seconds the age conditions are measured from.
+    now: i64,
+}
+
+impl ExclusionRules {
+    pub fn new(rules: Vec<ExclusionRule>) -> Result<Self, String> {
+        let matchers = rules.iter().map(Matcher::compile).collect::<Result<_, _>>()?;
+        Ok(ExclusionRules {
+            rules,
+            matchers,
+            now: chrono::Utc::now().timestamp(),
+        })
+    }
+
+    /// No rules: everything is scanned.
+    pub fn none() -> Self {
+        ExclusionRules::default()
+    }
+
+    /// Used until the user edits the rules: dependency folders are kept as a single node.
+    pub fn recommended() -> Self {
+        ExclusionRules::new(vec![
+            ExclusionRule::glob("node_modules/", RuleAction::Collapse),
+            ExclusionRule::glob(".venv/", RuleAction::Collapse),
+        ])
+        .expect("built-in rules are valid")
+    }
+
+    /// Read rules saved with [`ExclusionRules::save`]; [`ExclusionRules::recommended`] when
+    /// the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                let rules: Vec<ExclusionRule> =
+                    serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+                ExclusionRules::new(rules)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ExclusionRules::recommended()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(&self.rules).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn rules(&self) -> &[ExclusionRule] {
+        &self.rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// What to do with a scanned entry. `meta` is only called when a matching rule has a size
+    /// or age condition. [`RuleAction::Exclude`] wins over [`RuleAction::Collapse`]; collapse
+    /// only applies to folders.
+    pub fn action(
+        &self,
+        path: &Path,
+        is_dir: bool,
+        meta: impl FnOnce() -> Option<Metadata>,
+    ) -> Option<RuleAction> {
+        let mut meta = Some(meta);
+        let mut cached: Option<Metadata> = None;
+        let mut result = None;
+        for (rule, matcher) in self.rules.iter().zip(&self.matchers) {
+            if result == Some(rule.action) || !matcher.matches(path, is_dir) {
+                continue;
+            }
+            if rule.has_conditions() {
+                if let Some(f) = meta.take() {
+                    cached = f();
+                }
+                if !cached.as_ref().is_some_and(|m| self.conditions_hold(rule, m, is_dir)) {
+                    continue;
+                }
+            }
+            match rule.action {
+                RuleAction::Exclude => return Some(RuleAction::Exclude),
+                RuleAction::Collapse if is_dir => result = Some(RuleAction::Collapse),
+                RuleAction::Collapse => return Some(RuleAction::Exclude),
+            }
+        }
+        result
+    }
+
+    /// [`ExclusionRules::action`] for a path on disk, reading its metadata when needed.
+    pub fn action_at(&self, path: &Path, is_dir: bool) -> Option<RuleAction> {
+        self.action(path, is_dir, || std::fs::symlink_metadata(path).ok())
+    }
+
+    /// Whether a live path (from the watcher or the reconciler) stays out of the index: it is
+    /// excluded itself, or lies below an excluded or collapsed folder.
+    pub fn hides(&self, path: &Path, is_dir: bool) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        self.action_at(path, is_dir) == Some(RuleAction::Exclude)
+            || path.ancestors().skip(1).any(|a| self.action_at(a, true).is_some())
+    }
+
+    fn conditions_hold(&self, rule: &ExclusionRule, meta: &Metadata, is_dir: bool) -> bool {
+        if let Some(min_size) = rule.min_size {
+            if is_dir || meta.len() < min_size {
+                return false;
+            }
+        }
+        if let Some(days) = rule.older_than_days {
+            let mtime = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64);
+            match mtime {
+                Some(m) if self.now - m >= days as i64 * SECS_PER_DAY => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Walk a collapsed folder and sum it up into one entry, `<dir>/[collapsed]`, so the folder
+/// keeps its size (and its ancestors theirs) without its contents being stored. Exclude rules
+/// still apply inside; hard links count once. The entry takes the folder's own file key and is
+/// stored as [`crate::DiskObjectKind::Collapsed`]. Entries that cannot be read go to `on_error`.
+pub fn collapsed_entry(dir: &Path, options: &ScanOptions, mut on_error: impl FnMut(ScanError)) -> Option<FileEntry> {
+    let key = file_key_from_path(dir)?;
+    let mut seen: HashSet<FileKey> = HashSet::new();
+    let mut entry = FileEntry {
+        path: dir.join(COLLAPSED_ENTRY_NAME),
+        size: 0,
+        allocated_size: 0,
+        file_key: key,
+        mtime: None,
+        owner: None,
+    };
+    let rules = &options.exclusions;
+    let walker = WalkDir::new(dir)
+        .follow_links(false)
+        .same_file_system(options.one_file_system)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !e.file_type().is_dir()
+                || rules.action(e.path(), true, || e.metadata().ok()) != Some(RuleAction::Exclude)
+        });
+    for e in walker {
+        if !options.control.checkpoint() {
+            return None;
+        }
+        let e = match e {
+            Ok(e) => e,
+            Err(err) => {
+                on_error(ScanError::from_walk(err.path().unwrap_or(dir), err.io_error(), err.loop_ancestor().is_some(), &err));
+                continue;
+            }
+        };
+        if !e.file_type().is_file() {
+            continue;
+        }
+        let meta = match e.metadata() {
+            Ok(meta) => meta,
+            Err(err) => {
+                on_error(ScanError::from_walk(e.path(), err.io_error(), false, &err));
+                continue;
+            }
+        };
+        if rules.action(e.path(), false, || Some(meta.clone())).is_some() {
+            continue;
+        }
+        if file_key_from_path(e.path()).is_some_and(|k| !seen.insert(k)) {
+            continue;
+        }
+        entry.size += meta.len();
+        entry.allocated_size += allocated_size(&meta);
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64);
+        entry.mtime = entry.mtime.max(mtime);
+    }
+    Some(entry)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,158 @@
+use super::*;
+
+use std::time::{Duration, SystemTime};
+
+fn rules(list: Vec<ExclusionRule>) -> ExclusionRules {
+    ExclusionRules::new(list).unwrap()
+}
+
+fn check(rules: &ExclusionRules, path: &str, is_dir: bool) -> Option<RuleAction> {
+    rules.action(Path::new(path), is_dir, || None)
+}
+
+#[test]
+fn globs_follow_gitignore_conventions() {
+    let r = rules(vec![
+        ExclusionRule::glob("*.iso", RuleAction::Exclude),
+        ExclusionRule::glob("target/", RuleAction::Collapse),
+        ExclusionRule::glob("/home/*/.cache", RuleAction::Exclude),
+    ]);
+    assert_eq!(check(&r, "/a/b/disk.iso", false), Some(RuleAction::Exclude));
+    assert_eq!(check(&r, "/a/b/disk.iso.txt", false), None);
+    assert_eq!(check(&r, "/src/proj/target", true), Some(RuleAction::Collapse));
+    // A trailing `/` only matches folders.
+    assert_eq!(check(&r, "/src/proj/target", false), None);
+    assert_eq!(check(&r, "/home/me/.cache", true), Some(RuleAction::Exclude));
+    // `*` does not cross `/`, and a leading `/` anchors the pattern.
+    assert_eq!(check(&r, "/home/me/x/.cache", true), None);
+    assert_eq!(check(&r, "/mnt/home/me/.cache", true), None);
+}
+
+#[test]
+fn prefixes_cover_the_subtree_and_exclude_beats_collapse() {
+    let r = rules(vec![
+        ExclusionRule::glob("node_modules/", RuleAction::Collapse),
+        ExclusionRule::prefix("/srv/app/node_modules", RuleAction::Exclude),
+        ExclusionRule::glob("*.log", RuleAction::Collapse),
+    ]);
+    assert_eq!(check(&r, "/srv/app/node_modules", true), Some(RuleAction::Exclude));
+    assert_eq!(check(&r, "/srv/app/node_modules/x/y.js", false), Some(RuleAction::Exclude));
+    assert_eq!(check(&r, "/srv/web/node_modules", true), Some(RuleAction::Collapse));
+    assert_eq!(check(&r, "/srv/app-old/node_modules", true), Some(RuleAction::Collapse));
+    // Files cannot be collapsed; a collapse rule leaves them out.
+    assert_eq!(check(&r, "/srv/web/out.log", false), Some(RuleAction::Exclude));
+
+    assert!(ExclusionRules::new(vec![ExclusionRule::prefix("relative", RuleAction::Exclude)]).is_err());
+    assert!(ExclusionRules::new(vec![ExclusionRule::glob("a[", RuleAction::Exclude)]).is_err());
+}
+
+#[test]
+fn size_and_age_conditions_read_metadata_only_when_needed() {
+    let dir = tempfile::tempdir().unwrap();
+    let big = dir.path().join("big.bin");
+    let old = dir.path().join("old.bin");
+    std::fs::write(&big, vec![0u8; 2048]).unwrap();
+    std::fs::write(&old, b"x").unwrap();
+    let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 60 * 60);
+    std::fs::File::options().write(true).open(&old).unwrap().set_modified(month_ago).unwrap();
+
+    let r = rules(vec![
+        ExclusionRule {
+            min_size: Some(1024),
+            ..ExclusionRule::glob("*.bin", RuleAction::Exclude)
+        },
+        ExclusionRule {
+            older_than_days: Some(7),
+            ..ExclusionRule::glob("*.bin", RuleAction::Exclude)
+        },
+    ]);
+    assert_eq!(r.action_at(&big, false), Some(RuleAction::Exclude));
+    assert_eq!(r.action_at(&old, false), Some(RuleAction::Exclude));
+    std::fs::write(dir.path().join("new.bin"), b"x").unwrap();
+    assert_eq!(r.action_at(&dir.path().join("new.bin"), false), None);
+    // Size conditions never match folders.
+    assert_eq!(r.action_at(dir.path(), true), None);
+
+    let plain = rules(vec![ExclusionRule::glob("*.bin", RuleAction::Exclude)]);
+    let mut called = false;
+    plain.action(&big, false, || {
+        called = true;
+        None
+    });
+    assert!(!called);
+}
+
+#[test]
+fn hides_paths_below_excluded_or_collapsed_folders() {
+    let r = ExclusionRules::recommended();
+    assert!(!r.hides(Path::new("/p/node_modules"), true));
+    assert!(r.hides(Path::new("/p/node_modules/left-pad/index.js"), false));
+    assert!(r.hides(Path::new("/p/.venv/bin"), true));
+    assert!(!r.hides(Path::new("/p/src/main.rs"), false));
+    assert!(!ExclusionRules::none().hides(Path::new("/p/node_modules/x"), false));
+}
+
+#[test]
+fn rules_round_trip_through_the_rules_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("exclusions.json");
+    assert_eq!(ExclusionRules::load(&path).unwrap().rules(), ExclusionRules::recommended().rules());
+
+    let saved = rules(vec![ExclusionRule {
+        older_than_days: Some(90),
+        ..ExclusionRule::prefix("/var/cache", RuleAction::Collapse)
+    }]);
+    saved.save(&path).unwrap();
+    assert_eq!(ExclusionRules::load(&path).unwrap().rules(), saved.rules());
+
+    std::fs::write(&path, r#"[{"pattern": "*.tmp"}]"#).unwrap();
+    let loaded = ExclusionRules::load(&path).unwrap();
+    assert_eq!(loaded.rules(), &[ExclusionRule::glob("*.tmp", RuleAction::Exclude)]);
+}
+
+#[test]
+fn collapsed_entry_sums_the_folder_without_excluded_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let deps = dir.path().join("node_modules");
+    std::fs::create_dir_all(deps.join("a/b")).unwrap();
+    std::fs::write(deps.join("a/one.js"), vec![0u8; 10]).unwrap();
+    std::fs::write(deps.join("a/b/two.js"), vec![0u8; 20]).unwrap();
+    std::fs::write(deps.join("a/debug.log"), vec![0u8; 400]).unwrap();
+
+    let options = ScanOptions {
+        exclusions: std::sync::Arc::new(rules(vec![ExclusionRule::glob("*.log", RuleAction::Exclude)])),
+        ..ScanOptions::default()
+    };
+    let entry = collapsed_entry(&deps, &options, |e| panic!("unexpected error {:?}", e)).unwrap();
+    assert_eq!(entry.path, deps.join(COLLAPSED_ENTRY_NAME));
+    assert_eq!(entry.size, 30);
+    assert_eq!(entry.file_key, file_key_from_path(&deps).unwrap());
+    assert!(entry.mtime.is_some());
+}
+
+#[cfg(unix)]
+#[test]
+fn collapsed_entry_reports_what_it_cannot_read() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let deps = dir.path().join("node_modules");
+    let locked = deps.join("locked");
+    std::fs::create_dir_all(&locked).unwrap();
+    std::fs::write(locked.join("secret.js"), b"s").unwrap();
+    std::fs::write(deps.join("open.js"), vec![0u8; 10]).unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+    // Root reads through permissions, so there is nothing to report.
+    if std::fs::read_dir(&locked).is_ok() {
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        return;
+    }
+
+    let mut errors = Vec::new();
+    let entry = collapsed_entry(&deps, &ScanOptions::default(), |e| errors.push(e)).unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(entry.size, 10);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path, locked);
+    assert_eq!(errors[0].kind, crate::core::scanning::scanner::ScanErrorKind::PermissionDenied);
+}
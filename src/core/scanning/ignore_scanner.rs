@@ -1,23 +1,18 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicUsize;
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::sync::Arc;
 
-use ignore::WalkBuilder;
+use ignore::{WalkBuilder, WalkState};
 
-use crate::{FileEntry, ScanProgress};
-use crate::core::scanning::utils::{PROGRESS_INTERVAL, file_key_from_path};
+use crate::{FileEntry, IndexMode, ScanProgress};
+use crate::core::scanning::exclusions::{collapsed_entry, RuleAction};
+use crate::core::scanning::scanner::{scan_roots, Emitter, ScanCollector, ScanError, ScanItem, ScanSink, Scanner};
+use crate::core::scanning::utils::{file_entry, link_for_error, symlink_entry, SeenDirs};
+use crate::core::scanning::{ScanOptions, SymlinkPolicy};
 
-const NODE_MODULES: &str = "node_modules";
-const VENV_DIR: &str = ".venv";
-
-// TODOdin: are we sure we want to ignore these?
-// They're at least relevant for the space-cleaning part of this app
-pub(crate) fn is_dependencies_dir(path: &Path) -> bool {
-    path.file_name()
-        .map(|n| n == NODE_MODULES || n == VENV_DIR)
-        .unwrap_or(false)
-}
+/// Worker threads of the `ignore` walkers when `ScanOptions::threads` is 0.
+const DEFAULT_THREADS: usize = 4;
 
 #[cfg(unix)]
 pub(crate) fn is_virtual_fs(path: &Path) -> bool {
@@ -42,163 +37,214 @@ pub(crate) fn is_virtual_fs(_path: &Path) -> bool {
     false
 }
 
-pub fn index_directory_ignore_with_progress<F>(
-    root: &Path,
-    progress: F,
-) -> (Vec<FileEntry>, HashSet<PathBuf>)
-where
-    F: FnMut(ScanProgress) + Send,
-{
-    let progress = Arc::new(Mutex::new(progress));
-
-    {
-        let mut cb = progress.lock().unwrap();
-        cb(ScanProgress {
-            files_count: 0,
-            current_path: None,
-            status: Some("Scanning files…".into()),
-        });
+/// Parallel scanner on the `ignore` crate's walker; skips virtual filesystems such as `/proc`.
+pub struct IgnoreScanner;
+
+impl Scanner for IgnoreScanner {
+    fn name(&self) -> &'static str {
+        "ignore"
     }
 
+    fn scan(&self, roots: &[PathBuf], options: &ScanOptions, sink: &mut dyn ScanSink) {
+        let mut out = Emitter::start(sink);
+        let seen = SeenDirs::default();
+        for root in roots {
+            if !options.control.checkpoint() {
+                break;
+            }
+            walk_parallel(root, options, true, &seen, &mut out);
+        }
+        out.finish();
+    }
+}
+
+/// Walk `root` on `ignore`'s worker threads and stream the entries through `out` on the
+/// calling thread. Shared with the lolcate-style scanner.
+pub(crate) fn walk_parallel(root: &Path, options: &ScanOptions, skip_virtual_fs: bool, seen: &SeenDirs, out: &mut Emitter) {
+    let follow = options.symlinks == SymlinkPolicy::Follow;
     let mut builder = WalkBuilder::new(root);
     builder
         .hidden(false)
         .parents(false)
-        .follow_links(false)
+        .follow_links(follow)
         .ignore(true)
         .git_global(false)
         .git_ignore(false)
         .git_exclude(false)
-        .threads(4);
-
-    let files_acc: Arc<Mutex<Vec<FileEntry>>> = Arc::new(Mutex::new(Vec::new()));
-    let folders_acc: Arc<Mutex<HashSet<PathBuf>>> =
-        Arc::new(Mutex::new(HashSet::new()));
-    let counter = Arc::new(AtomicUsize::new(0));
-
-    let walk = builder.build_parallel();
-    walk.run(|| {
-        let files_acc = Arc::clone(&files_acc);
-        let folders_acc = Arc::clone(&folders_acc);
-        let counter = Arc::clone(&counter);
-        let progress = Arc::clone(&progress);
-        Box::new(move |entry| {
-            use ignore::WalkState;
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => return WalkState::Continue,
-            };
-            let ft = match entry.file_type() {
-                Some(ft) => ft,
-                None => return WalkState::Continue,
+        .same_file_system(options.one_file_system)
+        .threads(match options.threads {
+            0 => DEFAULT_THREADS,
+            n => n,
+        });
+
+    let (tx, rx) = mpsc::channel::<ScanItem>();
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            builder.build_parallel().run(|| {
+                let tx = tx.clone();
+                Box::new(move |entry| visit(entry, options, skip_virtual_fs, seen, &tx))
+            });
+        });
+        for item in rx {
+            out.emit(item);
+        }
+    });
+}
+
+fn visit(
+    entry: Result<ignore::DirEntry, ignore::Error>,
+    options: &ScanOptions,
+    skip_virtual_fs: bool,
+    seen: &SeenDirs,
+    tx: &mpsc::Sender<ScanItem>,
+) -> WalkState {
+    let follow = options.symlinks == SymlinkPolicy::Follow;
+    if !options.control.checkpoint() {
+        return WalkState::Quit;
+    }
+    let entry = match entry {
+        Ok(e) => e,
+        Err(err) => {
+            let error = scan_error(&err);
+            let _ = match link_for_error(&error).filter(|_| follow) {
+                Some(link) => tx.send(ScanItem::Symlink(link)),
+                None => tx.send(ScanItem::Error(error)),
             };
-            if ft.is_symlink() {
-                return WalkState::Continue;
+            return WalkState::Continue;
+        }
+    };
+    let ft = match entry.file_type() {
+        Some(ft) => ft,
+        None => return WalkState::Continue,
+    };
+    let path = entry.path();
+    if !follow && entry.path_is_symlink() {
+        if options.symlinks == SymlinkPolicy::Record && options.exclusions.action(path, false, || std::fs::symlink_metadata(path).ok()).is_none() {
+            if let Some(link) = symlink_entry(path) {
+                let _ = tx.send(ScanItem::Symlink(link));
             }
-            if ft.is_dir() {
-                if is_virtual_fs(entry.path()) || is_dependencies_dir(entry.path()) {
-                    return WalkState::Skip;
-                }
-                if let Ok(mut guard) = folders_acc.lock() {
-                    guard.insert(entry.path().to_path_buf());
-                }
-                return WalkState::Continue;
+        }
+        return WalkState::Continue;
+    }
+    if ft.is_dir() {
+        if skip_virtual_fs && is_virtual_fs(path) {
+            return WalkState::Skip;
+        }
+        if follow && !seen.first_visit(path) {
+            if let Some(link) = symlink_entry(path).filter(|_| entry.path_is_symlink()) {
+                let _ = tx.send(ScanItem::Symlink(link));
             }
-            if !ft.is_file() {
-                return WalkState::Continue;
+            return WalkState::Skip;
+        }
+        let action = match entry.depth() {
+            0 => None,
+            _ => options.exclusions.action(path, true, || entry.metadata().ok()),
+        };
+        if action == Some(RuleAction::Exclude) {
+            return WalkState::Skip;
+        }
+        let _ = tx.send(ScanItem::Folder(path.to_path_buf()));
+        if action == Some(RuleAction::Collapse) {
+            if options.metadata == IndexMode::Full {
+                if let Some(summary) = collapsed_entry(path, options, |e| {
+                    let _ = tx.send(ScanItem::Error(e));
+                }) {
+                    let _ = tx.send(ScanItem::File(summary));
+                }
             }
+            return WalkState::Skip;
+        }
+        return WalkState::Continue;
+    }
+    if !ft.is_file() {
+        return WalkState::Continue;
+    }
 
-            let path = entry.path().to_path_buf();
+    let item = match options.metadata {
+        IndexMode::Full => {
             let meta = match entry.metadata() {
                 Ok(m) => m,
-                Err(_) => return WalkState::Continue,
+                Err(err) => {
+                    let _ = tx.send(ScanItem::Error(ScanError::from_walk(path, err.io_error(), false, &err)));
+                    return WalkState::Continue;
+                }
             };
-            let size = meta.len();
-            let mtime = meta
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs() as i64);
-            let key = match file_key_from_path(&path) {
-                Some(k) => k,
+            if options.exclusions.action(path, false, || Some(meta.clone())).is_some() {
+                return WalkState::Continue;
+            }
+            match file_entry(path, &meta) {
+                Some(file) => ScanItem::File(file),
                 None => return WalkState::Continue,
-            };
-
-            {
-                let mut guard = files_acc.lock().unwrap();
-                guard.push(FileEntry {
-                    path: path.clone(),
-                    size,
-                    file_key: key,
-                    mtime,
-                });
             }
-
-            let n = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
-            if n as u64 % PROGRESS_INTERVAL == 0 {
-                if let Ok(mut cb) = progress.lock() {
-                    cb(ScanProgress {
-                        files_count: n as u64,
-                        current_path: Some(path.to_string_lossy().to_string()),
-                        status: None,
-                    });
-                }
+        }
+        IndexMode::Minimal => {
+            if options.exclusions.action(path, false, || entry.metadata().ok()).is_some() {
+                return WalkState::Continue;
             }
-
-            WalkState::Continue
-        })
-    });
-
-    let files = match Arc::try_unwrap(files_acc) {
-        Ok(mutex) => mutex.into_inner().unwrap(),
-        Err(arc) => arc.lock().unwrap().clone(),
-    };
-    let folders = match Arc::try_unwrap(folders_acc) {
-        Ok(mutex) => mutex.into_inner().unwrap(),
-        Err(arc) => arc.lock().unwrap().clone(),
+            ScanItem::FilePath(path.to_path_buf())
+        }
     };
+    match tx.send(item) {
+        Ok(()) => WalkState::Continue,
+        Err(_) => WalkState::Quit,
+    }
+}
 
-    if let Ok(mut cb) = progress.lock() {
-        cb(ScanProgress {
-            files_count: files.len() as u64,
-            current_path: None,
-            status: None,
-        });
+/// The path an `ignore` error is about, found by unwrapping the context it was wrapped in.
+fn scan_error(err: &ignore::Error) -> ScanError {
+    fn path_of(err: &ignore::Error) -> Option<(&Path, bool)> {
+        match err {
+            ignore::Error::WithPath { path, .. } => Some((path, false)),
+            ignore::Error::Loop { child, .. } => Some((child, true)),
+            ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => path_of(err),
+            ignore::Error::Partial(errs) => errs.iter().find_map(path_of),
+            _ => None,
+        }
     }
+    let (path, is_loop) = path_of(err).unwrap_or((Path::new(""), false));
+    ScanError::from_walk(path, err.io_error(), is_loop, err)
+}
 
-    (files, folders)
+pub fn index_directory_ignore_with_progress<F>(
+    root: &Path,
+    progress: F,
+) -> (Vec<FileEntry>, HashSet<PathBuf>)
+where
+    F: FnMut(ScanProgress) + Send,
+{
+    index_directory_ignore_with_options(root, &ScanOptions::default(), progress)
+}
+
+pub fn index_directory_ignore_with_options<F>(
+    root: &Path,
+    options: &ScanOptions,
+    progress: F,
+) -> (Vec<FileEntry>, HashSet<PathBuf>)
+where
+    F: FnMut(ScanProgress) + Send,
+{
+    let mut collector = ScanCollector::new(progress);
+    IgnoreScanner.scan(&[root.to_path_buf()], options, &mut collector);
+    (collector.files, collector.folders)
 }
 
 pub fn scan_roots_with_ignore<F>(
     roots: &[PathBuf],
-    mut progress: F,
+    progress: F,
 ) -> (Arc<Vec<FileEntry>>, HashSet<PathBuf>, Vec<String>)
 where
     F: FnMut(ScanProgress) + Send,
 {
-    let mut all_files: Vec<FileEntry> = Vec::new();
-    let mut all_folders: HashSet<PathBuf> = HashSet::new();
-    let mut cumulative_offset: u64 = 0;
-
-    for root in roots {
-        let offset = cumulative_offset;
-        let (files, folder_paths) =
-            index_directory_ignore_with_progress(root, |p| {
-                let mut adjusted = p.clone();
-                adjusted.files_count += offset;
-                progress(adjusted);
-            });
-        cumulative_offset += files.len() as u64;
-        all_files.extend(files);
-        all_folders.extend(folder_paths);
-    }
-
-    let roots_str: Vec<String> = roots
-        .iter()
-        .map(|r| r.to_string_lossy().to_string())
-        .collect();
-    let files_arc = Arc::new(all_files);
-
-    (files_arc, all_folders, roots_str)
+    scan_roots_with_options(roots, &ScanOptions::default(), progress)
 }
 
+pub fn scan_roots_with_options<F>(
+    roots: &[PathBuf],
+    options: &ScanOptions,
+    progress: F,
+) -> (Arc<Vec<FileEntry>>, HashSet<PathBuf>, Vec<String>)
+where
+    F: FnMut(ScanProgress) + Send,
+{
+    scan_roots(&IgnoreScanner, roots, options, progress)
+}
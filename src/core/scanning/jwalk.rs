@@ -1,129 +1,214 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use jwalk::WalkDir as JwalkDir;
+use jwalk::{Parallelism, WalkDir as JwalkDir};
 
 use crate::{FileEntry, IndexMode, IndexStats, ScanProgress};
 use crate::core::folder_sizes::aggregate_folder_sizes;
-use crate::core::scanning::utils::{file_key_from_path, PROGRESS_INTERVAL};
+use crate::core::scanning::exclusions::{collapsed_entry, RuleAction};
+use crate::core::scanning::scanner::{Emitter, ScanCollector, ScanError, ScanItem, ScanSink, Scanner};
+use crate::core::scanning::utils::{file_entry, file_key_from_path, link_for_error, symlink_entry, SeenDirs};
+use crate::core::scanning::{ScanOptions, SymlinkPolicy};
 
-pub fn index_directory_parallel_with_progress<F>(
-    root: &Path,
-    mut progress: F,
-) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>)
-where
-    F: FnMut(ScanProgress),
-{
-    progress(ScanProgress {
-        files_count: 0,
-        current_path: None,
-        status: Some("Scanning files…".into()),
-    });
+/// Reads directories in parallel on a rayon pool; entries still arrive in walk order.
+pub struct JwalkScanner;
 
-    let (files, folder_sizes, _stats) =
-        index_directory_parallel_jwalk_internal(root, progress, IndexMode::Full);
-
-    (files, folder_sizes)
-}
+impl Scanner for JwalkScanner {
+    fn name(&self) -> &'static str {
+        "jwalk"
+    }
 
-pub fn index_directory_parallel_minimal(root: &Path) -> IndexStats {
-    let (_files, _folders, stats) = index_directory_parallel_jwalk_internal(
-        root,
-        |_| {},
-        IndexMode::Minimal,
-    );
-    stats
+    fn scan(&self, roots: &[PathBuf], options: &ScanOptions, sink: &mut dyn ScanSink) {
+        let mut out = Emitter::start(sink);
+        let seen = Arc::new(SeenDirs::default());
+        for root in roots {
+            if !options.control.checkpoint() {
+                break;
+            }
+            walk(root, options, &seen, &mut out);
+        }
+        out.finish();
+    }
 }
 
-fn index_directory_parallel_jwalk_internal<F>(
-    root: &Path,
-    mut progress: F,
-    mode: IndexMode,
-) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>, IndexStats)
-where
-    F: FnMut(ScanProgress),
-{
-    let mut stats = IndexStats::default();
-
-    let mut files: Vec<FileEntry> = Vec::new();
-    let walk = match JwalkDir::new(root).follow_links(false).try_into_iter() {
+fn walk(root: &Path, options: &ScanOptions, seen: &Arc<SeenDirs>, out: &mut Emitter) {
+    let follow = options.symlinks == SymlinkPolicy::Follow;
+    let mut walk_dir = JwalkDir::new(root).follow_links(follow);
+    match options.threads {
+        0 => {}
+        1 => walk_dir = walk_dir.parallelism(Parallelism::Serial),
+        n => walk_dir = walk_dir.parallelism(Parallelism::RayonNewPool(n)),
+    }
+    // jwalk has no same-file-system switch; drop subdirectories on other devices before they
+    // are read. Excluded folders are dropped the same way; collapsed ones are kept but not read.
+    // When links are followed, folders entered before are dropped too and their links kept
+    // aside, to be recorded once the walk is done.
+    let root_dev = options.one_file_system.then(|| file_key_from_path(root)).flatten().map(|k| k.dev);
+    let revisits = Arc::new(Mutex::new(Vec::new()));
+    if root_dev.is_some() || !options.exclusions.is_empty() || follow {
+        let rules = Arc::clone(&options.exclusions);
+        let (seen, revisits) = (Arc::clone(seen), Arc::clone(&revisits));
+        walk_dir = walk_dir.process_read_dir(move |_depth, _path, _state, children| {
+            children.retain_mut(|child| {
+                let Ok(e) = child else { return true };
+                if !e.file_type().is_dir() {
+                    return true;
+                }
+                let path = e.path();
+                if root_dev.is_some_and(|dev| file_key_from_path(&path).is_none_or(|k| k.dev != dev)) {
+                    return false;
+                }
+                if follow && !seen.first_visit(&path) {
+                    if e.path_is_symlink() {
+                        revisits.lock().unwrap_or_else(|e| e.into_inner()).extend(symlink_entry(&path));
+                    }
+                    return false;
+                }
+                match rules.action(&path, true, || e.metadata().ok()) {
+                    Some(RuleAction::Exclude) => false,
+                    Some(RuleAction::Collapse) => {
+                        e.read_children_path = None;
+                        true
+                    }
+                    None => true,
+                }
+            });
+        });
+    }
+    let walk = match walk_dir.try_into_iter() {
         Ok(w) => w,
         Err(_) => {
-            progress(ScanProgress {
-                files_count: 0,
-                current_path: None,
-                status: Some("Scan failed (try_into_iter)".into()),
-            });
-            return (files, HashMap::new(), stats);
+            out.status("Scan failed (try_into_iter)");
+            return;
         }
     };
 
-    for entry in walk.filter_map(Result::ok) {
-        if entry.path_is_symlink() {
+    for entry in walk {
+        if !options.control.checkpoint() {
+            return;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                let path = err.path().unwrap_or(root);
+                let error = ScanError::from_walk(path, err.io_error(), err.loop_ancestor().is_some(), &err);
+                match link_for_error(&error).filter(|_| follow) {
+                    Some(link) => out.emit(ScanItem::Symlink(link)),
+                    None => out.emit(ScanItem::Error(error)),
+                }
+                continue;
+            }
+        };
+        // A folder that could not be listed still arrives, with the error attached.
+        if let Some(err) = &entry.read_children_error {
+            let path = err.path().map(Path::to_path_buf).unwrap_or_else(|| entry.path());
+            out.emit(ScanItem::Error(ScanError::from_walk(&path, err.io_error(), err.loop_ancestor().is_some(), err)));
+        }
+        let path = entry.path();
+        if !follow && entry.path_is_symlink() {
+            if options.symlinks == SymlinkPolicy::Record && options.exclusions.action(&path, false, || std::fs::symlink_metadata(&path).ok()).is_none() {
+                out.emit_symlink(&path);
+            }
             continue;
         }
         let file_type = entry.file_type();
-        let path = entry.path();
 
         if file_type.is_dir() {
-            stats.folders += 1;
+            out.emit(ScanItem::Folder(path.clone()));
+            // Only collapsed folders below the root are left unread.
+            if entry.depth() > 0 && entry.read_children_path.is_none() && options.metadata == IndexMode::Full {
+                if let Some(summary) = collapsed_entry(&path, options, |e| out.emit(ScanItem::Error(e))) {
+                    out.emit(ScanItem::File(summary));
+                }
+            }
             continue;
         }
         if !file_type.is_file() {
             continue;
         }
 
-        match mode {
+        match options.metadata {
             IndexMode::Full => {
                 let meta = match entry.metadata() {
-                    Ok(m) => m,
-                    Err(_) => continue,
-                };
-                let size = meta.len();
-                let mtime = meta
-                    .modified()
-                    .ok()
-                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                    .map(|d| d.as_secs() as i64);
-                let key = match file_key_from_path(&path) {
-                    Some(k) => k,
-                    None => continue,
+                    Ok(meta) => meta,
+                    Err(err) => {
+                        out.emit(ScanItem::Error(ScanError::from_walk(&path, err.io_error(), false, &err)));
+                        continue;
+                    }
                 };
-                files.push(FileEntry {
-                    path: path.clone(),
-                    size,
-                    file_key: key,
-                    mtime,
-                });
-                if files.len() as u64 % PROGRESS_INTERVAL == 0 {
-                    progress(ScanProgress {
-                        files_count: files.len() as u64,
-                        current_path: Some(path.to_string_lossy().to_string()),
-                        status: None,
-                    });
+                if options.exclusions.action(&path, false, || Some(meta.clone())).is_some() {
+                    continue;
+                }
+                if let Some(file) = file_entry(&path, &meta) {
+                    out.emit(ScanItem::File(file));
                 }
             }
             IndexMode::Minimal => {
-                stats.files += 1;
-                if stats.files as u64 % PROGRESS_INTERVAL == 0 {
-                    progress(ScanProgress {
-                        files_count: stats.files as u64,
-                        current_path: Some(path.to_string_lossy().to_string()),
-                        status: None,
-                    });
+                if options.exclusions.action(&path, false, || entry.metadata().ok()).is_none() {
+                    out.emit(ScanItem::FilePath(path));
                 }
             }
         }
     }
+    for link in std::mem::take(&mut *revisits.lock().unwrap_or_else(|e| e.into_inner())) {
+        out.emit(ScanItem::Symlink(link));
+    }
+}
+
+pub fn index_directory_parallel_with_progress<F>(
+    root: &Path,
+    progress: F,
+) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>)
+where
+    F: FnMut(ScanProgress),
+{
+    index_directory_parallel_with_options(root, &ScanOptions::default(), progress)
+}
+
+pub fn index_directory_parallel_with_options<F>(
+    root: &Path,
+    options: &ScanOptions,
+    progress: F,
+) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>)
+where
+    F: FnMut(ScanProgress),
+{
+    let (files, folder_sizes, _stats) =
+        index_directory_parallel_jwalk_internal(root, options, progress, IndexMode::Full);
+
+    (files, folder_sizes)
+}
+
+pub fn index_directory_parallel_minimal(root: &Path) -> IndexStats {
+    let (_files, _folders, stats) = index_directory_parallel_jwalk_internal(
+        root,
+        &ScanOptions::default(),
+        |_| {},
+        IndexMode::Minimal,
+    );
+    stats
+}
+
+fn index_directory_parallel_jwalk_internal<F>(
+    root: &Path,
+    options: &ScanOptions,
+    mut progress: F,
+    mode: IndexMode,
+) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>, IndexStats)
+where
+    F: FnMut(ScanProgress),
+{
+    let options = ScanOptions {
+        metadata: mode,
+        ..options.clone()
+    };
+    let mut collector = ScanCollector::new(&mut progress);
+    JwalkScanner.scan(&[root.to_path_buf()], &options, &mut collector);
+    let ScanCollector { files, folders, paths_count, .. } = collector;
 
     match mode {
         IndexMode::Full => {
-            progress(ScanProgress {
-                files_count: files.len() as u64,
-                current_path: None,
-                status: None,
-            });
-
             progress(ScanProgress {
                 files_count: files.len() as u64,
                 current_path: None,
@@ -138,19 +223,17 @@ where
                 folder_sizes,
                 IndexStats {
                     files: file_count,
-                    folders: stats.folders,
+                    folders: folders.len(),
                 },
             )
         }
-        IndexMode::Minimal => {
-            progress(ScanProgress {
-                files_count: stats.files as u64,
-                current_path: None,
-                status: None,
-            });
-
-            (Vec::new(), HashMap::new(), stats)
-        }
+        IndexMode::Minimal => (
+            Vec::new(),
+            HashMap::new(),
+            IndexStats {
+                files: paths_count,
+                folders: folders.len(),
+            },
+        ),
     }
 }
-
@@ -1,6 +1,128 @@
+pub mod checkpoint;
+pub mod exclusions;
 pub mod ignore_scanner;
+pub mod mounts;
+pub mod scanner;
 pub mod utils;
 pub mod walkdir;
 pub mod jwalk;
 pub mod lolcate;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use exclusions::ExclusionRules;
+
+use crate::IndexMode;
+
+pub use scanner::{
+    scan_roots, scanner_by_name, ScanCollector, ScanError, ScanErrorKind, ScanItem, ScanSink, Scanner, SCANNER_NAMES,
+};
+
+/// Options the scanners share.
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    /// Stay on the root's device (like `du -x`): directories on another filesystem, such as
+    /// bind, network or removable mounts below the root, are not entered.
+    pub one_file_system: bool,
+    /// What to leave out, or keep as a single summarized folder.
+    pub exclusions: Arc<ExclusionRules>,
+    pub symlinks: SymlinkPolicy,
+    /// `Full` reads size, file key and mtime of every file; `Minimal` only reports paths.
+    pub metadata: IndexMode,
+    /// Worker threads for the parallel scanners; 0 leaves the choice to the scanner.
+    pub threads: usize,
+    pub control: ScanControl,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// Symlinks are not reported and not followed.
+    #[default]
+    Skip,
+    /// Symlinks are reported as [`ScanItem::Symlink`] with their target, and not followed.
+    Record,
+    /// Symlinks are resolved and reported as what they point to. Each directory is entered
+    /// once, tracked by [`crate::FileKey`]: a link to a directory the scan already entered (a
+    /// loop back to an ancestor, or a second way into the same folder) is recorded instead, and
+    /// so are broken links.
+    Follow,
+}
+
+impl SymlinkPolicy {
+    pub const NAMES: [&'static str; 3] = ["skip", "record", "follow"];
+
+    pub fn by_name(name: &str) -> Option<SymlinkPolicy> {
+        match name {
+            "skip" => Some(SymlinkPolicy::Skip),
+            "record" => Some(SymlinkPolicy::Record),
+            "follow" => Some(SymlinkPolicy::Follow),
+            _ => None,
+        }
+    }
+}
+
+/// Cancels or pauses a running scan from another thread. Clones control the same scan.
+///
+/// Scanners call [`ScanControl::checkpoint`] between entries, so a pause takes effect after
+/// the entry being read and a cancel makes the scan return what it found so far.
+#[derive(Clone, Debug, Default)]
+pub struct ScanControl(Arc<ControlState>);
+
+#[derive(Debug, Default)]
+struct ControlState {
+    cancelled: AtomicBool,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+impl ScanControl {
+    pub fn new() -> Self {
+        ScanControl::default()
+    }
+
+    /// Stop the scan; also wakes it if it is paused.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+        // Hold the lock so a `checkpoint` that just saw the flag unset is already waiting.
+        let _guard = self.paused();
+        self.0.resumed.notify_all();
+    }
+
+    pub fn pause(&self) {
+        *self.paused() = true;
+    }
+
+    pub fn resume(&self) {
+        *self.paused() = false;
+        self.0.resumed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused()
+    }
+
+    /// Block while the scan is paused. Returns `false` once it has been cancelled.
+    pub fn checkpoint(&self) -> bool {
+        if self.is_cancelled() {
+            return false;
+        }
+        let mut paused = self.paused();
+        while *paused && !self.is_cancelled() {
+            paused = self.0.resumed.wait(paused).unwrap_or_else(|e| e.into_inner());
+        }
+        !self.is_cancelled()
+    }
+
+    fn paused(&self) -> std::sync::MutexGuard<'_, bool> {
+        self.0.paused.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,194 @@
+//! The system mount table, and scan totals broken down per device.
+//!
+//! A scan of `/` without `one_file_system` crosses into every mounted filesystem; grouping its
+//! files by `FileKey.dev` shows each of them as its own root, with hard links (and files seen
+//! again through a bind mount) counted once.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{FileEntry, FileKey};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MountPoint {
+    pub path: String,
+    /// `st_dev` of files on this filesystem, when it could be determined.
+    pub device: Option<u64>,
+    pub fs_type: String,
+    /// What is mounted, e.g. `/dev/sda1` or `server:/export`.
+    pub source: String,
+}
+
+/// Currently mounted filesystems; empty where the table cannot be read.
+#[cfg(target_os = "linux")]
+pub fn list_mounts() -> Vec<MountPoint> {
+    std::fs::read_to_string("/proc/self/mountinfo")
+        .map(|text| parse_mountinfo(&text))
+        .unwrap_or_default()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn list_mounts() -> Vec<MountPoint> {
+    let Ok(output) = std::process::Command::new("mount").output() else {
+        return Vec::new();
+    };
+    let mut mounts = parse_mount_output(&String::from_utf8_lossy(&output.stdout));
+    for mount in &mut mounts {
+        mount.device = crate::file_key_from_path(Path::new(&mount.path)).map(|k| k.dev);
+    }
+    mounts
+}
+
+#[cfg(windows)]
+pub fn list_mounts() -> Vec<MountPoint> {
+    crate::get_filesystem_roots()
+        .into_iter()
+        .map(|root| MountPoint {
+            device: crate::file_key_from_path(&root).map(|k| k.dev),
+            path: root.to_string_lossy().to_string(),
+            fs_type: String::new(),
+            source: String::new(),
+        })
+        .collect()
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn list_mounts() -> Vec<MountPoint> {
+    Vec::new()
+}
+
+/// Parse Linux `/proc/<pid>/mountinfo`. Its `major:minor` field gives the device number
+/// without having to stat (and possibly hang on) every mount point.
+pub fn parse_mountinfo(text: &str) -> Vec<MountPoint> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(' ').collect();
+            let sep = fields.iter().position(|f| *f == "-")?;
+            let (major, minor) = fields.get(2)?.split_once(':')?;
+            Some(MountPoint {
+                path: unescape_octal(fields.get(4)?),
+                device: Some(makedev(major.parse().ok()?, minor.parse().ok()?)),
+                fs_type: fields.get(sep + 1)?.to_string(),
+                source: unescape_octal(fields.get(sep + 2)?),
+            })
+        })
+        .collect()
+}
+
+/// Parse the output of BSD/macOS `mount`: `source on path (type, options…)`.
+pub fn parse_mount_output(text: &str) -> Vec<MountPoint> {
+    text.lines()
+        .filter_map(|line| {
+            let (source, rest) = line.split_once(" on ")?;
+            let (path, details) = rest.rsplit_once(" (")?;
+            let fs_type = details.split([',', ')']).next()?.trim();
+            Some(MountPoint {
+                path: path.to_string(),
+                device: None,
+                fs_type: fs_type.to_string(),
+                source: source.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// glibc's `makedev`, matching `st_dev` on Linux.
+fn makedev(major: u64, minor: u64) -> u64 {
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0x0000_0fff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0x0000_00ff)
+}
+
+/// The mount table escapes space, tab, newline and backslash as `\ooo`.
+fn unescape_octal(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u8::from_str_radix(d, 8).ok());
+        match code {
+            Some(c) => {
+                out.push(c);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Scan totals of one device.
+#[derive(Clone, Debug, Serialize)]
+pub struct DeviceUsage {
+    pub device: u64,
+    /// Deepest folder containing every scanned file on this device.
+    pub root: String,
+    pub mount_point: Option<String>,
+    pub fs_type: Option<String>,
+    pub source: Option<String>,
+    pub files_count: u64,
+    pub size: u64,
+    pub allocated_size: u64,
+}
+
+/// Group `files` by device, largest first. Each device is matched to the mount in `mounts`
+/// that contains its root; bind mounts share a device, so the deepest such mount wins.
+pub fn usage_by_device(files: &[FileEntry], mounts: &[MountPoint]) -> Vec<DeviceUsage> {
+    let mut seen: HashSet<FileKey> = HashSet::with_capacity(files.len());
+    let mut by_device: HashMap<u64, (PathBuf, u64, u64, u64)> = HashMap::new();
+    for entry in files.iter().filter(|e| seen.insert(e.file_key)) {
+        let parent = entry.path.parent().unwrap_or(&entry.path);
+        let usage = by_device
+            .entry(entry.file_key.dev)
+            .or_insert_with(|| (parent.to_path_buf(), 0, 0, 0));
+        if !parent.starts_with(&usage.0) {
+            usage.0 = common_ancestor(&usage.0, parent);
+        }
+        usage.1 += 1;
+        usage.2 += entry.size;
+        usage.3 += entry.allocated_size;
+    }
+
+    let mut report: Vec<DeviceUsage> = by_device
+        .into_iter()
+        .map(|(device, (root, files_count, size, allocated_size))| {
+            let mount = mounts
+                .iter()
+                .filter(|m| m.device == Some(device) && root.starts_with(&m.path))
+                .max_by_key(|m| m.path.len());
+            DeviceUsage {
+                device,
+                root: root.to_string_lossy().to_string(),
+                mount_point: mount.map(|m| m.path.clone()),
+                fs_type: mount.map(|m| m.fs_type.clone()),
+                source: mount.map(|m| m.source.clone()),
+                files_count,
+                size,
+                allocated_size,
+            }
+        })
+        .collect();
+    report.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.root.cmp(&b.root)));
+    report
+}
+
+fn common_ancestor(a: &Path, b: &Path) -> PathBuf {
+    a.components()
+        .zip(b.components())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
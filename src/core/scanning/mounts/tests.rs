@@ -0,0 +1,100 @@
+use super::*;
+
+fn entry(path: &str, size: u64, dev: u64, ino: u64) -> FileEntry {
+    FileEntry {
+        path: PathBuf::from(path),
+        size,
+        allocated_size: size * 2,
+        file_key: FileKey { dev, ino },
+        mtime: None,
+        owner: None,
+    }
+}
+
+fn mount(path: &str, device: u64, fs_type: &str) -> MountPoint {
+    MountPoint {
+        path: path.to_string(),
+        device: Some(device),
+        fs_type: fs_type.to_string(),
+        source: format!("/dev/{}", fs_type),
+    }
+}
+
+#[test]
+fn parses_mountinfo_with_escapes_and_device_numbers() {
+    let text = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+45 22 0:40 / /mnt/my\\040disk rw,nosuid - fuseblk /dev/sdb1 rw
+46 22 259:3 /data /srv master:2 - xfs /dev/nvme0n1p3 rw
+";
+    let mounts = parse_mountinfo(text);
+    assert_eq!(mounts.len(), 3);
+    assert_eq!(
+        mounts[0],
+        MountPoint {
+            path: "/".to_string(),
+            device: Some(8 << 8 | 1),
+            fs_type: "ext4".to_string(),
+            source: "/dev/sda1".to_string(),
+        }
+    );
+    assert_eq!(mounts[1].path, "/mnt/my disk");
+    assert_eq!(mounts[1].device, Some(40));
+    assert_eq!(mounts[2].device, Some(makedev(259, 3)));
+    assert_eq!(mounts[2].fs_type, "xfs");
+}
+
+#[test]
+fn parses_bsd_mount_output() {
+    let text = "\
+/dev/disk3s1s1 on / (apfs, sealed, local, read-only, journaled)
+map auto_home on /System/Volumes/Data/home (autofs, automounted, nobrowse)
+";
+    let mounts = parse_mount_output(text);
+    assert_eq!(mounts.len(), 2);
+    assert_eq!((mounts[0].path.as_str(), mounts[0].fs_type.as_str()), ("/", "apfs"));
+    assert_eq!(mounts[1].source, "map auto_home");
+    assert_eq!(mounts[1].path, "/System/Volumes/Data/home");
+}
+
+#[test]
+fn usage_is_split_per_device_and_matched_to_the_deepest_mount() {
+    let files = vec![
+        entry("/etc/hosts", 10, 1, 1),
+        entry("/var/log/syslog", 30, 1, 2),
+        entry("/home/me/a.bin", 500, 2, 1),
+        entry("/home/me/docs/b.txt", 5, 2, 2),
+        // Same file again through a bind mount of /home/me.
+        entry("/srv/me/a.bin", 500, 2, 1),
+        entry("/media/usb/x.jpg", 7, 3, 1),
+    ];
+    let mounts = vec![
+        mount("/", 1, "ext4"),
+        mount("/home", 2, "btrfs"),
+        mount("/srv/me", 2, "btrfs"),
+    ];
+    let report = usage_by_device(&files, &mounts);
+
+    let summary: Vec<(&str, Option<&str>, u64, u64)> = report
+        .iter()
+        .map(|d| (d.root.as_str(), d.mount_point.as_deref(), d.files_count, d.size))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("/home/me", Some("/home"), 2, 505),
+            ("/", Some("/"), 2, 40),
+            ("/media/usb", None, 1, 7),
+        ]
+    );
+    assert_eq!(report[0].allocated_size, 1010);
+    assert_eq!(report[0].fs_type.as_deref(), Some("btrfs"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn root_mount_device_matches_stat() {
+    let mounts = list_mounts();
+    let dev = crate::file_key_from_path(Path::new("/")).unwrap().dev;
+    assert!(mounts.iter().any(|m| m.path == "/" && m.device == Some(dev)));
+}
@@ -0,0 +1,253 @@
+//! One interface over the scanning backends.
+//!
+//! Every backend walks a list of roots under the same [`ScanOptions`] and streams what it
+//! finds to a [`ScanSink`] on the calling thread, so hosts can pick one by name
+//! ([`scanner_by_name`]) and either consume entries as they arrive or gather them with a
+//! [`ScanCollector`].
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FileEntry, ScanProgress, SymlinkEntry};
+use crate::core::scanning::ignore_scanner::IgnoreScanner;
+use crate::core::scanning::jwalk::JwalkScanner;
+use crate::core::scanning::lolcate::LolcateScanner;
+use crate::core::scanning::utils::{symlink_entry, PROGRESS_INTERVAL};
+use crate::core::scanning::walkdir::WalkdirScanner;
+use crate::core::scanning::ScanOptions;
+
+/// Names accepted by [`scanner_by_name`]; the first is the default.
+pub const SCANNER_NAMES: [&str; 4] = ["ignore", "jwalk", "walkdir", "lolcate"];
+
+/// One entry found by a scan.
+#[derive(Clone, Debug)]
+pub enum ScanItem {
+    File(FileEntry),
+    /// A file seen with [`crate::IndexMode::Minimal`], which reads no metadata.
+    FilePath(PathBuf),
+    Folder(PathBuf),
+    /// A link recorded as itself; see [`crate::core::scanning::SymlinkPolicy`].
+    Symlink(SymlinkEntry),
+    /// An entry that could not be read. For a folder, nothing below it was counted.
+    Error(ScanError),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanErrorKind {
+    PermissionDenied,
+    NotFound,
+    /// A symlink cycle, when links are followed.
+    Loop,
+    Other,
+}
+
+impl ScanErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanErrorKind::PermissionDenied => "permission_denied",
+            ScanErrorKind::NotFound => "not_found",
+            ScanErrorKind::Loop => "loop",
+            ScanErrorKind::Other => "other",
+        }
+    }
+
+    pub fn parse(s: &str) -> ScanErrorKind {
+        match s {
+            "permission_denied" => ScanErrorKind::PermissionDenied,
+            "not_found" => ScanErrorKind::NotFound,
+            "loop" => ScanErrorKind::Loop,
+            _ => ScanErrorKind::Other,
+        }
+    }
+}
+
+/// Why a scan skipped an entry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub kind: ScanErrorKind,
+    /// The OS error code, when there is one.
+    pub errno: Option<i32>,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn io(path: &Path, err: &std::io::Error) -> Self {
+        ScanError::from_walk(path, Some(err), false, err)
+    }
+
+    /// From a walker's error: the I/O error behind it, if any, or a symlink cycle. `message`
+    /// is used when there is no I/O error to describe it.
+    pub(crate) fn from_walk(
+        path: &Path,
+        io: Option<&std::io::Error>,
+        is_loop: bool,
+        message: impl std::fmt::Display,
+    ) -> Self {
+        let kind = match io.map(|e| e.kind()) {
+            _ if is_loop => ScanErrorKind::Loop,
+            Some(std::io::ErrorKind::PermissionDenied) => ScanErrorKind::PermissionDenied,
+            Some(std::io::ErrorKind::NotFound) => ScanErrorKind::NotFound,
+            _ => ScanErrorKind::Other,
+        };
+        ScanError {
+            path: path.to_path_buf(),
+            kind,
+            errno: io.and_then(|e| e.raw_os_error()),
+            message: io.map_or_else(|| message.to_string(), |e| e.to_string()),
+        }
+    }
+}
+
+/// Receives a scan's output as it is produced.
+pub trait ScanSink {
+    fn item(&mut self, item: ScanItem);
+
+    fn progress(&mut self, _progress: ScanProgress) {}
+}
+
+pub trait Scanner: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Walk `roots` one after another and stream what is found to `sink`. Waits while
+    /// `options.control` is paused and returns early, with what was found so far, once it
+    /// is cancelled.
+    fn scan(&self, roots: &[PathBuf], options: &ScanOptions, sink: &mut dyn ScanSink);
+}
+
+pub fn scanner_by_name(name: &str) -> Option<Box<dyn Scanner>> {
+    match name {
+        "ignore" => Some(Box::new(IgnoreScanner)),
+        "jwalk" => Some(Box::new(JwalkScanner)),
+        "walkdir" => Some(Box::new(WalkdirScanner)),
+        "lolcate" => Some(Box::new(LolcateScanner)),
+        _ => None,
+    }
+}
+
+/// Gathers a scan's entries and forwards its progress to a callback.
+pub struct ScanCollector<F> {
+    pub files: Vec<FileEntry>,
+    pub folders: HashSet<PathBuf>,
+    /// Files reported without metadata.
+    pub paths_count: usize,
+    pub symlinks: Vec<SymlinkEntry>,
+    pub errors: Vec<ScanError>,
+    progress: F,
+}
+
+impl<F: FnMut(ScanProgress)> ScanCollector<F> {
+    pub fn new(progress: F) -> Self {
+        ScanCollector {
+            files: Vec::new(),
+            folders: HashSet::new(),
+            paths_count: 0,
+            symlinks: Vec::new(),
+            errors: Vec::new(),
+            progress,
+        }
+    }
+}
+
+impl<F: FnMut(ScanProgress)> ScanSink for ScanCollector<F> {
+    fn item(&mut self, item: ScanItem) {
+        match item {
+            ScanItem::File(entry) => self.files.push(entry),
+            ScanItem::FilePath(_) => self.paths_count += 1,
+            ScanItem::Folder(path) => {
+                self.folders.insert(path);
+            }
+            ScanItem::Symlink(link) => self.symlinks.push(link),
+            ScanItem::Error(error) => self.errors.push(error),
+        }
+    }
+
+    fn progress(&mut self, progress: ScanProgress) {
+        (self.progress)(progress)
+    }
+}
+
+/// Scan `roots` with `scanner` and collect the files, the folders and the root paths. Errors
+/// are dropped; scan into a [`ScanCollector`] to keep them.
+pub fn scan_roots<F>(
+    scanner: &dyn Scanner,
+    roots: &[PathBuf],
+    options: &ScanOptions,
+    progress: F,
+) -> (Arc<Vec<FileEntry>>, HashSet<PathBuf>, Vec<String>)
+where
+    F: FnMut(ScanProgress),
+{
+    let mut collector = ScanCollector::new(progress);
+    scanner.scan(roots, options, &mut collector);
+    let roots_str: Vec<String> = roots
+        .iter()
+        .map(|r| r.to_string_lossy().to_string())
+        .collect();
+    (Arc::new(collector.files), collector.folders, roots_str)
+}
+
+/// Wraps the sink of a running scan: counts files across roots and reports progress every
+/// [`PROGRESS_INTERVAL`] files.
+pub(crate) struct Emitter<'a> {
+    sink: &'a mut dyn ScanSink,
+    files_count: u64,
+}
+
+impl<'a> Emitter<'a> {
+    pub(crate) fn start(sink: &'a mut dyn ScanSink) -> Self {
+        sink.progress(ScanProgress {
+            files_count: 0,
+            current_path: None,
+            status: Some("Scanning files…".into()),
+        });
+        Emitter { sink, files_count: 0 }
+    }
+
+    pub(crate) fn emit(&mut self, item: ScanItem) {
+        let file_path: Option<&Path> = match &item {
+            ScanItem::File(entry) => Some(&entry.path),
+            ScanItem::FilePath(path) => Some(path),
+            ScanItem::Folder(_) | ScanItem::Symlink(_) | ScanItem::Error(_) => None,
+        };
+        if let Some(path) = file_path {
+            self.files_count += 1;
+            if self.files_count.is_multiple_of(PROGRESS_INTERVAL) {
+                let progress = ScanProgress {
+                    files_count: self.files_count,
+                    current_path: Some(path.to_string_lossy().to_string()),
+                    status: None,
+                };
+                self.sink.progress(progress);
+            }
+        }
+        self.sink.item(item);
+    }
+
+    /// Record the link at `path`, if it still is one.
+    pub(crate) fn emit_symlink(&mut self, path: &Path) {
+        if let Some(link) = symlink_entry(path) {
+            self.emit(ScanItem::Symlink(link));
+        }
+    }
+
+    pub(crate) fn status(&mut self, status: &str) {
+        self.sink.progress(ScanProgress {
+            files_count: self.files_count,
+            current_path: None,
+            status: Some(status.to_string()),
+        });
+    }
+
+    pub(crate) fn finish(self) {
+        self.sink.progress(ScanProgress {
+            files_count: self.files_count,
+            current_path: None,
+            status: None,
+        });
+    }
+}
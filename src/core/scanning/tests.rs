@@ -0,0 +1,36 @@
+use super::*;
+
+use std::time::Duration;
+
+#[test]
+fn checkpoint_blocks_while_paused() {
+    let control = ScanControl::new();
+    assert!(control.checkpoint());
+
+    control.pause();
+    assert!(control.is_paused());
+    let waiter = std::thread::spawn({
+        let control = control.clone();
+        move || control.checkpoint()
+    });
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(!waiter.is_finished());
+    control.resume();
+    assert!(waiter.join().unwrap());
+    assert!(!control.is_paused());
+}
+
+#[test]
+fn cancel_wakes_a_paused_scan() {
+    let control = ScanControl::new();
+    control.pause();
+    let waiter = std::thread::spawn({
+        let control = control.clone();
+        move || control.checkpoint()
+    });
+    std::thread::sleep(Duration::from_millis(20));
+    control.cancel();
+    assert!(!waiter.join().unwrap());
+    assert!(control.is_cancelled());
+    assert!(!control.checkpoint());
+}
@@ -1,9 +1,40 @@
+use std::collections::HashSet;
 use std::path::Path;
+use std::sync::Mutex;
 
-use crate::FileKey;
+use crate::core::scanning::{ScanError, ScanErrorKind};
+use crate::{FileEntry, FileKey, Ownership, SymlinkEntry};
 
 pub const PROGRESS_INTERVAL: u64 = 5000;
 
+/// Bytes a file occupies on disk. `st_blocks` is always counted in 512-byte units.
+#[cfg(unix)]
+pub fn allocated_size(meta: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.blocks() * 512
+}
+
+/// Block counts are not exposed by std here, so the apparent size stands in.
+#[cfg(not(unix))]
+pub fn allocated_size(meta: &std::fs::Metadata) -> u64 {
+    meta.len()
+}
+
+#[cfg(unix)]
+pub fn ownership(meta: &std::fs::Metadata) -> Option<Ownership> {
+    use std::os::unix::fs::MetadataExt;
+    Some(Ownership {
+        uid: meta.uid(),
+        gid: meta.gid(),
+        mode: meta.mode(),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn ownership(_meta: &std::fs::Metadata) -> Option<Ownership> {
+    None
+}
+
 #[cfg(unix)]
 pub fn file_key_from_path(path: &Path) -> Option<FileKey> {
     use std::os::unix::fs::MetadataExt;
@@ -38,3 +69,52 @@ pub fn file_key_from_path(_path: &Path) -> Option<FileKey> {
     None
 }
 
+
+/// The [`FileEntry`] for a file whose metadata has been read; `None` when it has no file key
+/// (for example because it disappeared in the meantime).
+pub fn file_entry(path: &Path, meta: &std::fs::Metadata) -> Option<FileEntry> {
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    Some(FileEntry {
+        path: path.to_path_buf(),
+        size: meta.len(),
+        allocated_size: allocated_size(meta),
+        file_key: file_key_from_path(path)?,
+        mtime,
+        owner: ownership(meta),
+    })
+}
+
+/// The [`SymlinkEntry`] for `path`; `None` when it is not a symlink.
+pub fn symlink_entry(path: &Path) -> Option<SymlinkEntry> {
+    let target = std::fs::read_link(path).ok()?;
+    Some(SymlinkEntry {
+        path: path.to_path_buf(),
+        target,
+        broken: std::fs::metadata(path).is_err(),
+    })
+}
+
+/// With links followed, the walkers report a loop or a broken link as an error; the link to
+/// record in its place, if that is what `error` is about.
+pub(crate) fn link_for_error(error: &ScanError) -> Option<SymlinkEntry> {
+    symlink_entry(&error.path).filter(|link| link.broken || error.kind == ScanErrorKind::Loop)
+}
+
+/// Directories a scan that follows links has entered, so that none is entered twice.
+#[derive(Debug, Default)]
+pub(crate) struct SeenDirs(Mutex<HashSet<FileKey>>);
+
+impl SeenDirs {
+    /// Whether `path` is a directory not entered before; it counts as entered from now on.
+    /// Directories without a file key are always entered.
+    pub fn first_visit(&self, path: &Path) -> bool {
+        match file_key_from_path(path) {
+            Some(key) => self.0.lock().unwrap_or_else(|e| e.into_inner()).insert(key),
+            None => true,
+        }
+    }
+}
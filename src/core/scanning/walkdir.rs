@@ -1,11 +1,122 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use walkdir::WalkDir;
 
 use crate::{FileEntry, IndexMode, IndexStats, ScanProgress};
 use crate::core::folder_sizes::aggregate_folder_sizes;
-use crate::core::scanning::utils::{file_key_from_path, PROGRESS_INTERVAL};
+use crate::core::scanning::exclusions::{collapsed_entry, RuleAction};
+use crate::core::scanning::scanner::{Emitter, ScanCollector, ScanError, ScanItem, ScanSink, Scanner};
+use crate::core::scanning::utils::{file_entry, link_for_error, SeenDirs};
+use crate::core::scanning::{ScanOptions, SymlinkPolicy};
+
+/// Serial scanner on `walkdir`; ignores `threads`.
+pub struct WalkdirScanner;
+
+impl Scanner for WalkdirScanner {
+    fn name(&self) -> &'static str {
+        "walkdir"
+    }
+
+    fn scan(&self, roots: &[PathBuf], options: &ScanOptions, sink: &mut dyn ScanSink) {
+        let mut out = Emitter::start(sink);
+        let seen = SeenDirs::default();
+        for root in roots {
+            if !options.control.checkpoint() {
+                break;
+            }
+            walk(root, options, &seen, &mut out);
+        }
+        out.finish();
+    }
+}
+
+fn walk(root: &Path, options: &ScanOptions, seen: &SeenDirs, out: &mut Emitter) {
+    let follow = options.symlinks == SymlinkPolicy::Follow;
+    let mut walker = WalkDir::new(root)
+        .follow_links(follow)
+        .same_file_system(options.one_file_system)
+        .into_iter();
+
+    while let Some(entry) = walker.next() {
+        if !options.control.checkpoint() {
+            return;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                let path = err.path().unwrap_or(root);
+                let error = ScanError::from_walk(path, err.io_error(), err.loop_ancestor().is_some(), &err);
+                match link_for_error(&error).filter(|_| follow) {
+                    Some(link) => out.emit(ScanItem::Symlink(link)),
+                    None => out.emit(ScanItem::Error(error)),
+                }
+                continue;
+            }
+        };
+        let path = entry.path();
+        if !follow && entry.path_is_symlink() {
+            if options.symlinks == SymlinkPolicy::Record && options.exclusions.action(path, false, || std::fs::symlink_metadata(path).ok()).is_none() {
+                out.emit_symlink(path);
+            }
+            continue;
+        }
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            if follow && !seen.first_visit(path) {
+                if entry.path_is_symlink() {
+                    out.emit_symlink(path);
+                }
+                walker.skip_current_dir();
+                continue;
+            }
+            let action = match entry.depth() {
+                0 => None,
+                _ => options.exclusions.action(path, true, || entry.metadata().ok()),
+            };
+            if action == Some(RuleAction::Exclude) {
+                walker.skip_current_dir();
+                continue;
+            }
+            out.emit(ScanItem::Folder(path.to_path_buf()));
+            if action == Some(RuleAction::Collapse) {
+                walker.skip_current_dir();
+                if options.metadata == IndexMode::Full {
+                    if let Some(summary) = collapsed_entry(path, options, |e| out.emit(ScanItem::Error(e))) {
+                        out.emit(ScanItem::File(summary));
+                    }
+                }
+            }
+            continue;
+        }
+        if !file_type.is_file() {
+            continue;
+        }
+
+        match options.metadata {
+            IndexMode::Full => {
+                let meta = match entry.metadata() {
+                    Ok(meta) => meta,
+                    Err(err) => {
+                        out.emit(ScanItem::Error(ScanError::from_walk(path, err.io_error(), false, &err)));
+                        continue;
+                    }
+                };
+                if options.exclusions.action(path, false, || Some(meta.clone())).is_some() {
+                    continue;
+                }
+                if let Some(file) = file_entry(path, &meta) {
+                    out.emit(ScanItem::File(file));
+                }
+            }
+            IndexMode::Minimal => {
+                if options.exclusions.action(path, false, || entry.metadata().ok()).is_none() {
+                    out.emit(ScanItem::FilePath(path.to_path_buf()));
+                }
+            }
+        }
+    }
+}
 
 pub fn index_directory(root: &Path) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>) {
     index_directory_with_progress(root, |_| {})
@@ -13,102 +124,52 @@ pub fn index_directory(root: &Path) -> (Vec<FileEntry>, HashMap<std::path::PathB
 
 pub fn index_directory_minimal(root: &Path) -> IndexStats {
     let (_files, _folders, stats) =
-        index_directory_internal(root, &mut |_| {}, IndexMode::Minimal);
+        index_directory_internal(root, &ScanOptions::default(), &mut |_| {}, IndexMode::Minimal);
     stats
 }
 
 pub fn index_directory_with_progress<F>(
     root: &Path,
+    progress: F,
+) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>)
+where
+    F: FnMut(ScanProgress),
+{
+    index_directory_with_options(root, &ScanOptions::default(), progress)
+}
+
+pub fn index_directory_with_options<F>(
+    root: &Path,
+    options: &ScanOptions,
     mut progress: F,
 ) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>)
 where
     F: FnMut(ScanProgress),
 {
     let (files, folder_sizes, _stats) =
-        index_directory_internal(root, &mut progress, IndexMode::Full);
+        index_directory_internal(root, options, &mut progress, IndexMode::Full);
     (files, folder_sizes)
 }
 
 fn index_directory_internal<F>(
     root: &Path,
+    options: &ScanOptions,
     progress: &mut F,
     mode: IndexMode,
 ) -> (Vec<FileEntry>, HashMap<std::path::PathBuf, u64>, IndexStats)
 where
     F: FnMut(ScanProgress),
 {
-    progress(ScanProgress {
-        files_count: 0,
-        current_path: None,
-        status: None,
-    });
-
-    let mut files: Vec<FileEntry> = Vec::new();
-    let mut stats = IndexStats::default();
-
-    let walker = WalkDir::new(root)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| !e.path_is_symlink());
-
-    for entry in walker.filter_map(Result::ok) {
-        let path = entry.path().to_path_buf();
-        let file_type = entry.file_type();
-        if file_type.is_dir() {
-            stats.folders += 1;
-            continue;
-        }
-        if !file_type.is_file() {
-            continue;
-        }
-
-        match mode {
-            IndexMode::Full => {
-                if let Ok(meta) = entry.metadata() {
-                    let size = meta.len();
-                    let mtime = meta
-                        .modified()
-                        .ok()
-                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                        .map(|d| d.as_secs() as i64);
-                    if let Some(key) = file_key_from_path(&path) {
-                        files.push(FileEntry {
-                            path: path.clone(),
-                            size,
-                            file_key: key,
-                            mtime,
-                        });
-                        if files.len() as u64 % PROGRESS_INTERVAL == 0 {
-                            progress(ScanProgress {
-                                files_count: files.len() as u64,
-                                current_path: Some(path.to_string_lossy().to_string()),
-                                status: None,
-                            });
-                        }
-                    }
-                }
-            }
-            IndexMode::Minimal => {
-                stats.files += 1;
-                if stats.files as u64 % PROGRESS_INTERVAL == 0 {
-                    progress(ScanProgress {
-                        files_count: stats.files as u64,
-                        current_path: Some(path.to_string_lossy().to_string()),
-                        status: None,
-                    });
-                }
-            }
-        }
-    }
+    let options = ScanOptions {
+        metadata: mode,
+        ..options.clone()
+    };
+    let mut collector = ScanCollector::new(&mut *progress);
+    WalkdirScanner.scan(&[root.to_path_buf()], &options, &mut collector);
+    let ScanCollector { files, folders, paths_count, .. } = collector;
 
     match mode {
         IndexMode::Full => {
-            progress(ScanProgress {
-                files_count: files.len() as u64,
-                current_path: None,
-                status: None,
-            });
-
             progress(ScanProgress {
                 files_count: files.len() as u64,
                 current_path: None,
@@ -123,19 +184,17 @@ where
                 folder_sizes,
                 IndexStats {
                     files: file_count,
-                    folders: stats.folders,
+                    folders: folders.len(),
                 },
             )
         }
-        IndexMode::Minimal => {
-            progress(ScanProgress {
-                files_count: stats.files as u64,
-                current_path: None,
-                status: None,
-            });
-
-            (Vec::new(), HashMap::new(), stats)
-        }
+        IndexMode::Minimal => (
+            Vec::new(),
+            HashMap::new(),
+            IndexStats {
+                files: paths_count,
+                folders: folders.len(),
+            },
+        ),
     }
 }
-
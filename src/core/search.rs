@@ -0,0 +1,555 @@
+//! One search model for every index mode. A [`SearchRequest`] runs the same way on each
+//! [`SearchBackend`] — the SQLite database, the trigram index (`ngram`), the suffix index and
+//! the compressed text index — so filtering, paging and ranking do not depend on which one
+//! `CUTE_DISK_TREE_INDEX_MODE` picks. Results come in a [`SortOrder`], and each page ends with
+//! a [`Cursor`] naming the last object's place in it, so the next page picks up right after
+//! that object even when the watcher has changed the index in between.
+
+use std::cmp::Ordering;
+
+use nucleo::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo::{Config, Matcher, Utf32Str};
+use serde::{Deserialize, Serialize};
+
+use crate::core::query::{path_text, path_text_positions, Filter, NamePattern, Predicate, Query};
+use crate::core::search_category;
+use crate::DiskObject;
+
+/// Page size when the caller does not ask for one.
+pub const DEFAULT_LIMIT: usize = 500;
+
+/// Name text shorter than this is matched as a substring even when fuzzy search is on.
+pub const FUZZY_MIN_CHARS: usize = 3;
+
+#[derive(Clone, Debug)]
+pub enum SearchFilter {
+    None,
+    FoldersOnly,
+    Other,
+    Extensions(Vec<String>),
+}
+
+impl SearchFilter {
+    /// Build a filter from a comma-separated extension list (takes precedence) or a category
+    /// name: `folder`, `other`, `all`, or one of the sets in [`search_category`].
+    pub fn from_extensions_or_category(extensions: Option<&str>, category: Option<&str>) -> SearchFilter {
+        let manual_exts: Option<Vec<String>> = extensions.and_then(|s| {
+            let cleaned: Vec<String> = s
+                .split(',')
+                .map(|x| x.trim().trim_start_matches('.').to_ascii_lowercase())
+                .filter(|x| !x.is_empty())
+                .collect();
+            if cleaned.is_empty() {
+                None
+            } else {
+                Some(cleaned)
+            }
+        });
+
+        if let Some(exts) = manual_exts {
+            return SearchFilter::Extensions(exts);
+        }
+
+        let category = category
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty() && s != "all");
+
+        match category.as_deref() {
+            None => SearchFilter::None,
+            Some("folder") => SearchFilter::FoldersOnly,
+            Some("other") => SearchFilter::Other,
+            Some(c) => match search_category::extension_set(c) {
+                Some(exts) => SearchFilter::Extensions(exts.iter().map(|s| (*s).to_string()).collect()),
+                None => SearchFilter::None,
+            },
+        }
+    }
+}
+
+/// How the name text of a [`SearchRequest`] is matched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Names contain the text.
+    #[default]
+    Exact,
+    /// Names are ranked by fuzzy match against the text.
+    Fuzzy,
+    /// Full paths contain the text, so `photos/2019` finds what lies in such folders. Results
+    /// are ranked: matches on whole path segments and near the end of the path come first.
+    Path,
+    /// The text is a glob that whole names match, such as `*.tar.gz`.
+    Glob,
+    /// The text is a regex that names contain a match of, such as `img_\d{4}\.jpg`.
+    Regex,
+}
+
+impl SearchMode {
+    pub const NAMES: [&'static str; 5] = ["exact", "fuzzy", "path", "glob", "regex"];
+
+    pub fn by_name(name: &str) -> Option<SearchMode> {
+        match name {
+            "exact" => Some(SearchMode::Exact),
+            "fuzzy" => Some(SearchMode::Fuzzy),
+            "path" => Some(SearchMode::Path),
+            "glob" => Some(SearchMode::Glob),
+            "regex" => Some(SearchMode::Regex),
+            _ => None,
+        }
+    }
+}
+
+/// What search results are ordered by. Equal values go by path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// Best match first in fuzzy and path mode; by name in the others, which do not rank.
+    #[default]
+    Relevance,
+    Name,
+    /// Apparent size, the recursive size for folders.
+    Size,
+    /// Modification time.
+    Modified,
+}
+
+impl SortKey {
+    pub const NAMES: [&'static str; 4] = ["relevance", "name", "size", "modified"];
+
+    pub fn by_name(name: &str) -> Option<SortKey> {
+        match name {
+            "relevance" => Some(SortKey::Relevance),
+            "name" => Some(SortKey::Name),
+            "size" => Some(SortKey::Size),
+            "modified" => Some(SortKey::Modified),
+            _ => None,
+        }
+    }
+
+    /// The direction the key is usually wanted in: largest and newest first, best matches and
+    /// names from the start.
+    pub fn default_direction(self) -> SortDirection {
+        match self {
+            SortKey::Relevance | SortKey::Name => SortDirection::Ascending,
+            SortKey::Size | SortKey::Modified => SortDirection::Descending,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub const NAMES: [&'static str; 2] = ["asc", "desc"];
+
+    pub fn by_name(name: &str) -> Option<SortDirection> {
+        match name {
+            "asc" => Some(SortDirection::Ascending),
+            "desc" => Some(SortDirection::Descending),
+            _ => None,
+        }
+    }
+}
+
+/// The order of search results: by `key` in `direction`, objects without a value last, then by
+/// path so that every object has one place.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub direction: SortDirection,
+}
+
+impl SortOrder {
+    /// By `key`, in `direction` or else the key's [`SortKey::default_direction`].
+    pub fn new(key: SortKey, direction: Option<SortDirection>) -> SortOrder {
+        SortOrder { key, direction: direction.unwrap_or(key.default_direction()) }
+    }
+
+    /// Where `obj` falls in this order when nothing ranks it.
+    pub fn position(&self, obj: &DiskObject) -> Position {
+        Position { value: self.value_of(obj).into_owned(), path: obj.path.clone() }
+    }
+
+    fn value_of<'a>(&self, obj: &'a DiskObject) -> Value<'a> {
+        match self.key {
+            SortKey::Relevance | SortKey::Name => Value::Text(&obj.name_lower),
+            SortKey::Size => obj.size.or(obj.recursive_size).map_or(Value::Missing, |s| Value::Number(s as i64)),
+            SortKey::Modified => obj.mtime.map_or(Value::Missing, Value::Number),
+        }
+    }
+
+    fn compare(&self, (a, a_path): (Value<'_>, &str), (b, b_path): (Value<'_>, &str)) -> Ordering {
+        let by_value = match (a, b) {
+            (Value::Missing, Value::Missing) => Ordering::Equal,
+            (Value::Missing, _) => Ordering::Greater,
+            (_, Value::Missing) => Ordering::Less,
+            (a, b) if self.direction == SortDirection::Descending => b.cmp(&a),
+            (a, b) => a.cmp(&b),
+        };
+        by_value.then_with(|| a_path.cmp(b_path))
+    }
+}
+
+/// Where an object falls in a [`SortOrder`]: its sort value, then its path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub value: SortValue,
+    pub path: String,
+}
+
+impl Position {
+    fn key(&self) -> (Value<'_>, &str) {
+        (self.value.borrow(), &self.path)
+    }
+}
+
+/// The value an object is sorted by: a size, a time or a rank, or a lowercased name.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortValue {
+    Number(i64),
+    Text(String),
+    Missing,
+}
+
+impl SortValue {
+    fn borrow(&self) -> Value<'_> {
+        match self {
+            SortValue::Number(n) => Value::Number(*n),
+            SortValue::Text(t) => Value::Text(t),
+            SortValue::Missing => Value::Missing,
+        }
+    }
+}
+
+/// A [`SortValue`] borrowed from the object it belongs to, so objects that do not make a page
+/// cost no allocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Value<'a> {
+    Number(i64),
+    Text(&'a str),
+    Missing,
+}
+
+impl Value<'_> {
+    fn into_owned(self) -> SortValue {
+        match self {
+            Value::Number(n) => SortValue::Number(n),
+            Value::Text(t) => SortValue::Text(t.to_string()),
+            Value::Missing => SortValue::Missing,
+        }
+    }
+
+    /// A rank, ordered like the other values: better scores sort first.
+    fn score(score: u32) -> Value<'static> {
+        Value::Number(-i64::from(score))
+    }
+}
+
+/// Where a page of results ended. Callers pass it back as the opaque [`Cursor::encode`] text to
+/// get the page after it. It holds the last object's [`Position`] rather than an offset, so a
+/// page neither repeats nor skips objects when the index changes in between.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    mode: SearchMode,
+    sort: SortOrder,
+    after: Position,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursors serialize");
+        json.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn decode(text: &str) -> Result<Cursor, String> {
+        let invalid = || "invalid search cursor".to_string();
+        let bytes = (0..text.len())
+            .step_by(2)
+            .map(|i| text.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+        serde_json::from_slice(&bytes).map_err(|_| invalid())
+    }
+
+    /// The position the next page starts after.
+    pub fn after(&self) -> &Position {
+        &self.after
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchRequest {
+    pub query: Query,
+    pub mode: SearchMode,
+    pub sort: SortOrder,
+    pub limit: usize,
+    /// The cursor of the previous page; `None` for the first one.
+    pub cursor: Option<Cursor>,
+}
+
+impl SearchRequest {
+    /// A first-page request for `text` (see [`Query::parse`]), narrowed by an extension list or
+    /// category as [`SearchFilter::from_extensions_or_category`] reads them. Text without any
+    /// field or operator (see [`Query::is_structured`]), or that does not parse, is one
+    /// substring, spaces and parentheses included.
+    pub fn parse(text: &str, extensions: Option<&str>, category: Option<&str>) -> Result<SearchRequest, String> {
+        SearchRequest::parse_as(SearchMode::Exact, text, extensions, category)
+    }
+
+    /// Like [`SearchRequest::parse`], in `mode`. In glob and regex mode the whole text is the
+    /// pattern, taken as written.
+    pub fn parse_as(
+        mode: SearchMode,
+        text: &str,
+        extensions: Option<&str>,
+        category: Option<&str>,
+    ) -> Result<SearchRequest, String> {
+        let pattern = |pattern: NamePattern| Query { filter: Filter::Match(Predicate::NamePattern(pattern)) };
+        let query = match mode {
+            _ if text.is_empty() => Query::literal(""),
+            SearchMode::Glob => pattern(NamePattern::glob(text)?),
+            SearchMode::Regex => pattern(NamePattern::regex(text)?),
+            SearchMode::Exact | SearchMode::Fuzzy | SearchMode::Path => {
+                let parsed = Query::is_structured(text).then(|| Query::parse(text).ok()).flatten();
+                match parsed {
+                    Some(parsed) => match parsed.name_text() {
+                        Some(name) => Query::literal(&name),
+                        None => parsed,
+                    },
+                    None => Query::literal(text),
+                }
+            }
+        };
+        let filter = SearchFilter::from_extensions_or_category(extensions, category);
+        Ok(SearchRequest {
+            query: query.and(Filter::from(&filter)),
+            mode,
+            sort: SortOrder::default(),
+            limit: DEFAULT_LIMIT,
+            cursor: None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SearchResults {
+    pub items: Vec<DiskObject>,
+    /// Where the next page starts; `None` on the last page.
+    pub next_cursor: Option<Cursor>,
+}
+
+/// An index that can answer a [`SearchRequest`].
+pub trait SearchBackend {
+    /// Replace the contents with `objects`, every entry of a scan.
+    fn build(&mut self, objects: &[DiskObject]) -> Result<(), String>;
+
+    /// Drop the objects at the `removed` paths and add `upserted` ones, replacing any object
+    /// already stored at the same path.
+    fn update(&mut self, removed: &[String], upserted: &[DiskObject]) -> Result<(), String>;
+
+    /// Approximate heap bytes the index keeps in memory.
+    fn size_bytes(&self) -> usize;
+
+    /// Call `visit` with every object matching `query`, in the backend's order, until it
+    /// returns `false`.
+    fn for_each_match(&self, query: &Query, visit: &mut dyn FnMut(&DiskObject) -> bool) -> Result<(), String>;
+
+    /// The first `limit` objects matching `query` in `sort` order after `after`, and the
+    /// position of the last one when more follow. The default keeps the best `limit` while
+    /// visiting every match; backends that can sort and seek themselves override it.
+    fn find(
+        &self,
+        query: &Query,
+        sort: SortOrder,
+        after: Option<&Position>,
+        limit: usize,
+    ) -> Result<(Vec<DiskObject>, Option<Position>), String> {
+        let mut page = TopK::new(sort, after, limit);
+        self.for_each_match(query, &mut |obj| {
+            page.offer(sort.value_of(obj), obj);
+            true
+        })?;
+        Ok(page.finish())
+    }
+
+    /// Run `request`. By relevance, fuzzy requests with enough name text rank every object
+    /// matching the rest of the query by how well its name matches, and path requests rank the
+    /// objects whose path contains the name text by [`path_score`]; fuzzy requests still only
+    /// return names that match when sorted otherwise.
+    fn search(&self, request: &SearchRequest) -> Result<SearchResults, String> {
+        let sort = request.sort;
+        let after = match &request.cursor {
+            Some(cursor) if cursor.mode != request.mode || cursor.sort != sort => {
+                return Err("the cursor belongs to a search with another mode or sort order".to_string());
+            }
+            cursor => cursor.as_ref().map(Cursor::after),
+        };
+        let ranked = sort.key == SortKey::Relevance;
+        let (names, rest) = request.query.split_names();
+        let (items, next) = match request.mode {
+            SearchMode::Fuzzy if names.chars().count() >= FUZZY_MIN_CHARS => {
+                let mut ranker = FuzzyRanker::new(&names, TopK::new(sort, after, request.limit));
+                self.for_each_match(&rest, &mut |obj| {
+                    ranker.offer(obj, ranked);
+                    true
+                })?;
+                ranker.finish()
+            }
+            SearchMode::Path if !names.is_empty() => {
+                let text = path_text(&names);
+                let query = rest.and(Filter::Match(Predicate::PathText(text.clone())));
+                if ranked {
+                    let mut page = TopK::new(sort, after, request.limit);
+                    self.for_each_match(&query, &mut |obj| {
+                        page.offer(Value::score(path_score(&obj.path_lower, &text).unwrap_or(0)), obj);
+                        true
+                    })?;
+                    page.finish()
+                } else {
+                    self.find(&query, sort, after, request.limit)?
+                }
+            }
+            _ => self.find(&request.query, sort, after, request.limit)?,
+        };
+        Ok(SearchResults {
+            items,
+            next_cursor: next.map(|after| Cursor { mode: request.mode, sort, after }),
+        })
+    }
+}
+
+/// The best `limit` of `candidates` by fuzzy match of their names against `text`, best first;
+/// equal scores go by path, so the result does not depend on the candidates' order.
+pub fn rank_fuzzy<'a>(text: &str, candidates: impl IntoIterator<Item = &'a DiskObject>, limit: usize) -> Vec<DiskObject> {
+    let mut ranker = FuzzyRanker::new(text, TopK::new(SortOrder::default(), None, limit));
+    for obj in candidates {
+        ranker.offer(obj, true);
+    }
+    ranker.finish().0
+}
+
+/// How well `text` (see [`path_text`]) matches `path_lower`, by its best occurrence, or `None`
+/// if it does not occur. An occurrence scores higher the more of it falls on segment
+/// boundaries (`2019` in `/photos/2019/` over `/photos/x2019y/`) and the fewer segments
+/// follow it, so the folder a fragment names ranks before the files deep inside it; shallower
+/// paths win ties.
+pub fn path_score(path_lower: &str, text: &str) -> Option<u32> {
+    let path = path_lower.as_bytes();
+    let is_sep = |b: u8| b == b'/' || b == b'\\';
+    let depth = path.iter().filter(|&&b| is_sep(b)).count().min(31) as u32;
+    path_text_positions(path_lower, text)
+        .map(|start| {
+            let end = start + text.len();
+            let starts_segment = start == 0 || is_sep(path[start - 1]) || text.starts_with('/');
+            let ends_segment = end == path.len() || is_sep(path[end]) || text.ends_with('/');
+            let below = path[end..].iter().filter(|&&b| is_sep(b)).count().min(7) as u32;
+            let mut score = 4096 - 32 * below - depth;
+            if starts_segment {
+                score += 1024;
+            }
+            if ends_segment {
+                score += 512;
+            }
+            if below == 0 {
+                score += 256;
+            }
+            score
+        })
+        .max()
+}
+
+/// The first `keep` objects offered, in `sort` order after `after`.
+struct TopK<'c> {
+    sort: SortOrder,
+    after: Option<&'c Position>,
+    keep: usize,
+    kept: Vec<(SortValue, DiskObject)>,
+    /// The last position kept by the last trim that filled all `keep` places; anything after
+    /// it cannot make it in.
+    floor: Option<Position>,
+    /// Objects offered after `after`, kept or not.
+    offered: usize,
+}
+
+impl<'c> TopK<'c> {
+    fn new(sort: SortOrder, after: Option<&'c Position>, keep: usize) -> TopK<'c> {
+        TopK { sort, after, keep, kept: Vec::new(), floor: None, offered: 0 }
+    }
+
+    fn offer(&mut self, value: Value<'_>, obj: &DiskObject) {
+        let here = (value, obj.path.as_str());
+        if self.after.is_some_and(|after| self.sort.compare(here, after.key()) != Ordering::Greater) {
+            return;
+        }
+        self.offered += 1;
+        if self.keep == 0 || self.floor.as_ref().is_some_and(|floor| self.sort.compare(here, floor.key()) == Ordering::Greater) {
+            return;
+        }
+        self.kept.push((value.into_owned(), obj.clone()));
+        // Trim now and then rather than keeping every match of a broad query.
+        if self.kept.len() >= self.keep.saturating_mul(2).max(1024) {
+            self.trim();
+        }
+    }
+
+    fn trim(&mut self) {
+        let sort = self.sort;
+        self.kept.sort_by(|(a, a_obj), (b, b_obj)| sort.compare((a.borrow(), &a_obj.path), (b.borrow(), &b_obj.path)));
+        self.kept.truncate(self.keep);
+        if self.kept.len() == self.keep {
+            self.floor = self.kept.last().map(|(value, obj)| Position { value: value.clone(), path: obj.path.clone() });
+        }
+    }
+
+    /// The objects kept, and the position of the last one if more were offered.
+    fn finish(mut self) -> (Vec<DiskObject>, Option<Position>) {
+        self.trim();
+        let next = match self.offered > self.keep {
+            true => self.kept.last().map(|(value, obj)| Position { value: value.clone(), path: obj.path.clone() }),
+            false => None,
+        };
+        (self.kept.into_iter().map(|(_, obj)| obj).collect(), next)
+    }
+}
+
+/// Keeps the objects offered whose names fuzzy-match the text.
+struct FuzzyRanker<'c> {
+    pattern: Pattern,
+    matcher: Matcher,
+    buf: Vec<char>,
+    page: TopK<'c>,
+}
+
+impl<'c> FuzzyRanker<'c> {
+    fn new(text: &str, page: TopK<'c>) -> FuzzyRanker<'c> {
+        FuzzyRanker {
+            pattern: Pattern::parse(text, CaseMatching::Smart, Normalization::Smart),
+            matcher: Matcher::new(Config::DEFAULT),
+            buf: Vec::new(),
+            page,
+        }
+    }
+
+    /// Offer `obj`, by its match score if `ranked`, else by the page's sort order.
+    fn offer(&mut self, obj: &DiskObject, ranked: bool) {
+        if let Some(score) = self.pattern.score(Utf32Str::new(&obj.name, &mut self.buf), &mut self.matcher) {
+            let value = match ranked {
+                true => Value::score(score),
+                false => self.page.sort.value_of(obj),
+            };
+            self.page.offer(value, obj);
+        }
+    }
+
+    fn finish(self) -> (Vec<DiskObject>, Option<Position>) {
+        self.page.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests;
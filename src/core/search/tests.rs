@@ -0,0 +1,262 @@
+use super::*;
+use crate::core::indexing::ngram;
+use crate::DiskObjectKind;
+
+fn make_file(name: &str) -> DiskObject {
+    DiskObject {
+        path: format!("/root/{}", name),
+        path_lower: format!("/root/{}", name.to_ascii_lowercase()),
+        parent_path: Some("/root".to_string()),
+        name: name.to_string(),
+        name_lower: name.to_ascii_lowercase(),
+        ext: name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()),
+        kind: DiskObjectKind::File,
+        size: Some(1),
+        recursive_size: None,
+        allocated_size: None,
+        dev: None,
+        ino: None,
+        mtime: None,
+        link_target: None,
+        uid: None,
+        gid: None,
+        mode: None,
+    }
+}
+
+fn paths(objects: &[DiskObject]) -> Vec<&str> {
+    objects.iter().map(|o| o.path.as_str()).collect()
+}
+
+fn request(text: &str, limit: usize) -> SearchRequest {
+    SearchRequest { limit, ..SearchRequest::parse(text, None, None).unwrap() }
+}
+
+/// The request for the page after `results`.
+fn next(request: &SearchRequest, results: &SearchResults) -> SearchRequest {
+    SearchRequest { cursor: results.next_cursor.clone(), ..request.clone() }
+}
+
+#[test]
+fn pages_follow_the_cursor_to_the_end() {
+    let objects: Vec<DiskObject> = (0..10).rev().map(|i| make_file(&format!("file{}", i))).collect();
+    let index = ngram::build_index(&objects);
+
+    let first = request("", 3);
+    let page1 = index.search(&first).unwrap();
+    assert_eq!(paths(&page1.items), ["/root/file0", "/root/file1", "/root/file2"]);
+
+    let page2 = index.search(&next(&first, &page1)).unwrap();
+    assert_eq!(paths(&page2.items), ["/root/file3", "/root/file4", "/root/file5"]);
+
+    let rest = SearchRequest { limit: 10, ..next(&first, &page2) };
+    let last = index.search(&rest).unwrap();
+    assert_eq!(last.items.len(), 4);
+    assert_eq!(last.next_cursor, None);
+
+    let everything = index.search(&request("", 100)).unwrap();
+    assert_eq!((everything.items.len(), everything.next_cursor), (10, None));
+}
+
+#[test]
+fn pages_count_only_matching_objects() {
+    let objects = vec![make_file("keep1"), make_file("skip"), make_file("keep2"), make_file("keep3")];
+    let index = ngram::build_index(&objects);
+
+    let first = request("keep", 2);
+    let page = index.search(&first).unwrap();
+    assert_eq!(paths(&page.items), ["/root/keep1", "/root/keep2"]);
+    assert!(page.next_cursor.is_some());
+
+    let page = index.search(&next(&first, &page)).unwrap();
+    assert_eq!(paths(&page.items), ["/root/keep3"]);
+    assert_eq!(page.next_cursor, None);
+}
+
+fn make_sized(name: &str, size: Option<u64>, mtime: Option<i64>) -> DiskObject {
+    DiskObject { size, mtime, ..make_file(name) }
+}
+
+#[test]
+fn results_sort_by_size_time_and_name_with_missing_values_last() {
+    let objects = vec![
+        make_sized("b", Some(10), Some(300)),
+        make_sized("a", Some(30), None),
+        make_sized("d", None, Some(100)),
+        make_sized("c", Some(10), Some(200)),
+        DiskObject { kind: DiskObjectKind::Folder, recursive_size: Some(20), ..make_sized("e", None, Some(400)) },
+    ];
+    let index = ngram::build_index(&objects);
+    let sorted = |key: SortKey, direction: Option<SortDirection>| {
+        let request = SearchRequest { sort: SortOrder::new(key, direction), ..request("", 10) };
+        paths(&index.search(&request).unwrap().items).join(",").replace("/root/", "")
+    };
+    assert_eq!(sorted(SortKey::Size, None), "a,e,b,c,d");
+    assert_eq!(sorted(SortKey::Size, Some(SortDirection::Ascending)), "b,c,e,a,d");
+    assert_eq!(sorted(SortKey::Modified, None), "e,b,c,d,a");
+    assert_eq!(sorted(SortKey::Name, Some(SortDirection::Descending)), "e,d,c,b,a");
+    assert_eq!(sorted(SortKey::Relevance, None), "a,b,c,d,e");
+
+    // Pages of a sort pick the best values across the whole index, not the first ones found.
+    let largest = SearchRequest { sort: SortOrder::new(SortKey::Size, None), ..request("", 2) };
+    let page1 = index.search(&largest).unwrap();
+    let page2 = index.search(&next(&largest, &page1)).unwrap();
+    assert_eq!((paths(&page1.items), paths(&page2.items)), (vec!["/root/a", "/root/e"], vec!["/root/b", "/root/c"]));
+}
+
+#[test]
+fn cursors_keep_their_place_while_the_index_changes() {
+    let objects: Vec<DiskObject> = (1..=6).map(|i| make_sized(&format!("f{}", i), Some(i * 10), None)).collect();
+    let mut index = ngram::build_index(&objects);
+    let largest = SearchRequest { sort: SortOrder::new(SortKey::Size, None), ..request("", 2) };
+    let page1 = index.search(&largest).unwrap();
+    assert_eq!(paths(&page1.items), ["/root/f6", "/root/f5"]);
+
+    // The last object of the page goes away, one ahead of the cursor and one behind it appear,
+    // and the next page still starts right after f5.
+    let added = [make_sized("big", Some(100), None), make_sized("f45", Some(45), None)];
+    index.update(&["/root/f5".to_string(), "/root/f3".to_string()], &added).unwrap();
+    let cursor = Cursor::decode(&page1.next_cursor.as_ref().unwrap().encode()).unwrap();
+    let page2 = index.search(&SearchRequest { cursor: Some(cursor), ..largest.clone() }).unwrap();
+    assert_eq!(paths(&page2.items), ["/root/f45", "/root/f4"]);
+    let page3 = index.search(&next(&largest, &page2)).unwrap();
+    assert_eq!((paths(&page3.items), page3.next_cursor), (vec!["/root/f2", "/root/f1"], None));
+
+    // A cursor only continues the search it came from.
+    let by_name = SearchRequest { sort: SortOrder::new(SortKey::Name, None), ..next(&largest, &page1) };
+    assert!(index.search(&by_name).is_err());
+    assert!(Cursor::decode("not a cursor").is_err());
+}
+
+#[test]
+fn requests_parse_plain_text_literally_and_apply_the_filter() {
+    let objects = vec![make_file("my notes.txt"), make_file("my notes.md"), make_file("notes my.txt")];
+    let index = ngram::build_index(&objects);
+
+    let plain = SearchRequest::parse("My Notes", Some("txt"), None).unwrap();
+    assert_eq!(paths(&index.search(&plain).unwrap().items), ["/root/my notes.txt"]);
+
+    let structured = SearchRequest::parse("notes -ext:md", None, None).unwrap();
+    assert_eq!(paths(&index.search(&structured).unwrap().items), ["/root/my notes.txt", "/root/notes my.txt"]);
+
+    let objects = vec![make_file("file (1).pdf"), make_file("report (draft.txt"), make_file("-old.txt"), make_file("file 1 .pdf")];
+    let index = ngram::build_index(&objects);
+    let found = |text: &str| paths(&index.search(&SearchRequest::parse(text, None, None).unwrap()).unwrap().items).join(",");
+    assert_eq!(found("file (1).pdf"), "/root/file (1).pdf");
+    assert_eq!(found("report (draft"), "/root/report (draft.txt");
+    assert_eq!(found("-old"), "/root/-old.txt");
+    // Text that looks structured but does not parse is searched as written, too.
+    assert!(found("size:>2XB").is_empty());
+}
+
+#[test]
+fn pattern_requests_take_the_text_as_written() {
+    let objects = vec![make_file("IMG_2019.jpg"), make_file("img_x20.jpg"), make_file("a (1).tar.gz"), make_file("b.tar")];
+    let index = ngram::build_index(&objects);
+    let found = |mode: SearchMode, text: &str, ext: Option<&str>| {
+        let request = SearchRequest::parse_as(mode, text, ext, None).unwrap();
+        paths(&index.search(&request).unwrap().items).join(",")
+    };
+    // `\D` would turn into `\d` if the text were lowercased like name text.
+    assert_eq!(found(SearchMode::Regex, r"^IMG_\D", None), "/root/img_x20.jpg");
+    assert_eq!(found(SearchMode::Glob, "* (1).tar.gz", None), "/root/a (1).tar.gz");
+    assert_eq!(found(SearchMode::Glob, "*.t*", Some("gz")), "/root/a (1).tar.gz");
+    assert_eq!(index.search(&SearchRequest::parse_as(SearchMode::Glob, "", None, None).unwrap()).unwrap().items.len(), 4);
+    assert!(SearchRequest::parse_as(SearchMode::Regex, "img_(", None, None).is_err());
+}
+
+#[test]
+fn fuzzy_requests_rank_by_name_and_keep_the_other_terms() {
+    let objects = vec![make_file("no_tes.txt"), make_file("notes.txt"), make_file("notes.md"), make_file("random.txt")];
+    let index = ngram::build_index(&objects);
+
+    let fuzzy = |text: &str| SearchRequest { mode: SearchMode::Fuzzy, ..request(text, 10) };
+    let results = index.search(&fuzzy("notes ext:txt")).unwrap();
+    assert_eq!(paths(&results.items), ["/root/notes.txt", "/root/no_tes.txt"]);
+    assert_eq!(results.next_cursor, None);
+
+    // Sorted otherwise, only fuzzy matches are listed.
+    let by_name = SearchRequest { sort: SortOrder::new(SortKey::Name, None), ..fuzzy("notes ext:txt") };
+    assert_eq!(paths(&index.search(&by_name).unwrap().items), ["/root/no_tes.txt", "/root/notes.txt"]);
+
+    // Too short to rank: matched as a substring.
+    let results = index.search(&SearchRequest { limit: 1, ..fuzzy("no") }).unwrap();
+    assert_eq!(paths(&results.items), ["/root/no_tes.txt"]);
+    assert!(results.next_cursor.is_some());
+}
+
+#[test]
+fn rank_fuzzy_matches_subsequences() {
+    // "ntes" is not a substring of "notes.txt", so the trigram prefilter would drop it.
+    let objects = vec![make_file("notes.txt"), make_file("random.txt"), make_file("unrelated.txt")];
+    assert_eq!(paths(&rank_fuzzy("ntes", &objects, 10)), ["/root/notes.txt"]);
+}
+
+#[test]
+fn rank_fuzzy_puts_the_better_match_first() {
+    let objects = vec![make_file("annotate.txt"), make_file("notes.txt")];
+    let ranked = rank_fuzzy("notes", &objects, 10);
+    assert_eq!(ranked[0].path, "/root/notes.txt");
+}
+
+#[test]
+fn rank_fuzzy_respects_limit_and_ignores_case() {
+    let objects: Vec<DiskObject> = (0..2000).map(|i| make_file(&format!("file{}.txt", i))).collect();
+    let ranked = rank_fuzzy("file", &objects, 5);
+    assert_eq!(ranked.len(), 5);
+    // Equal scores go by path, also across trims.
+    assert_eq!(paths(&ranked), ["/root/file0.txt", "/root/file1.txt", "/root/file10.txt", "/root/file100.txt", "/root/file1000.txt"]);
+
+    let objects = vec![make_file("README.md"), make_file("unrelated.txt")];
+    assert_eq!(paths(&rank_fuzzy("readme", &objects, 10)), ["/root/README.md"]);
+    assert!(rank_fuzzy("anything", &[], 10).is_empty());
+}
+
+#[test]
+fn path_scores_prefer_whole_segments_near_the_end() {
+    let score = |path: &str, text: &str| path_score(path, text).unwrap();
+    assert!(score("/p/photos/2019", "photos/2019") > score("/p/photos/2019x", "photos/2019"));
+    assert!(score("/p/photos/2019", "photos/2019") > score("/p/photos/2019/a.jpg", "photos/2019"));
+    assert!(score("/p/photos/2019/a.jpg", "photos/2019") > score("/p/myphotos/2019/a.jpg", "photos/2019"));
+    assert!(score("/p/photos", "photos") > score("/p/q/photos", "photos"));
+    assert_eq!(score("c:\\p\\photos\\2019", "photos/2019"), score("c:/p/photos/2019", "photos/2019"));
+    assert_eq!(path_score("/p/photos", "videos"), None);
+}
+
+#[test]
+fn path_requests_rank_and_page_full_path_matches() {
+    let at = |path: &str| DiskObject {
+        path: path.to_string(),
+        path_lower: path.to_ascii_lowercase(),
+        parent_path: Some(crate::parent_dir(path)),
+        ..make_file(path.rsplit('/').next().unwrap())
+    };
+    let objects = vec![
+        at("/p/Photos/2019/deep/a.jpg"),
+        at("/p/MyPhotos/2019x.jpg"),
+        at("/p/Photos/2019"),
+        at("/p/Photos/2019/b.jpg"),
+        at("/p/Photos/2020.jpg"),
+    ];
+    let index = ngram::build_index(&objects);
+    let path = |text: &str, limit: usize| SearchRequest { mode: SearchMode::Path, ..request(text, limit) };
+
+    let results = index.search(&path("Photos/2019", 10)).unwrap();
+    assert_eq!(
+        paths(&results.items),
+        ["/p/Photos/2019", "/p/Photos/2019/b.jpg", "/p/Photos/2019/deep/a.jpg", "/p/MyPhotos/2019x.jpg"]
+    );
+    assert_eq!(results.next_cursor, None);
+
+    let first = path("Photos/2019", 2);
+    let page1 = index.search(&first).unwrap();
+    assert_eq!(page1.items.len(), 2);
+    let page2 = index.search(&next(&first, &page1)).unwrap();
+    assert_eq!(paths(&page2.items), ["/p/Photos/2019/deep/a.jpg", "/p/MyPhotos/2019x.jpg"]);
+    assert_eq!(page2.next_cursor, None);
+
+    // Other terms still filter, and without name text the request is an exact one.
+    let filtered = SearchRequest { mode: SearchMode::Path, ..SearchRequest::parse("photos/2019 ext:jpg", None, None).unwrap() };
+    assert_eq!(index.search(&filtered).unwrap().items.len(), 3);
+    assert_eq!(index.search(&path("", 10)).unwrap().items.len(), 5);
+}
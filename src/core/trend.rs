@@ -0,0 +1,152 @@
+//! Size-over-time series for single folders, and a linear trend to extrapolate from them.
+//!
+//! Points come from three places (see `db::get_size_series`): the compact `size_history` table,
+//! which keeps a few folders per depth for every scan and is never pruned, every retained
+//! snapshot, and the current scan. Timestamps are the scan's update id (Unix milliseconds).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+const MS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+/// Two scans closer together than this are not enough to extrapolate a daily rate from.
+const MIN_TREND_SPAN_MS: i64 = 60 * 60 * 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct SizePoint {
+    /// Unix milliseconds.
+    pub timestamp: i64,
+    pub size: u64,
+}
+
+/// Which folders get a `size_history` row after each scan.
+#[derive(Clone, Copy, Debug)]
+pub struct TrackingOptions {
+    /// Largest folders kept per depth below each root.
+    pub per_depth: usize,
+    /// Deepest level tracked; the root itself is depth 0.
+    pub max_depth: usize,
+}
+
+impl Default for TrackingOptions {
+    fn default() -> Self {
+        TrackingOptions {
+            per_depth: 20,
+            max_depth: 4,
+        }
+    }
+}
+
+/// The `per_depth` largest folders at each depth up to `max_depth` below every root.
+pub fn select_tracked_folders(
+    roots: &[PathBuf],
+    folder_sizes: &HashMap<PathBuf, u64>,
+    options: &TrackingOptions,
+) -> Vec<(String, u64)> {
+    let mut by_depth: HashMap<(&Path, usize), Vec<(&Path, u64)>> = HashMap::new();
+    for (path, &size) in folder_sizes {
+        let Some(root) = roots.iter().find(|r| path.starts_with(r)) else {
+            continue;
+        };
+        let depth = path.strip_prefix(root).map(|rest| rest.components().count()).unwrap_or(0);
+        if depth <= options.max_depth {
+            by_depth.entry((root.as_path(), depth)).or_default().push((path.as_path(), size));
+        }
+    }
+
+    let mut tracked: Vec<(String, u64)> = Vec::new();
+    for mut folders in by_depth.into_values() {
+        folders.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        tracked.extend(
+            folders
+                .into_iter()
+                .take(options.per_depth)
+                .map(|(p, s)| (p.to_string_lossy().to_string(), s)),
+        );
+    }
+    tracked.sort();
+    tracked
+}
+
+/// Least-squares line through a series: `size(t) = base_size + bytes_per_day * days since base`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Trend {
+    pub bytes_per_day: f64,
+    pub base_timestamp: i64,
+    pub base_size: f64,
+}
+
+impl Trend {
+    pub fn size_at(&self, timestamp: i64) -> f64 {
+        self.base_size + self.bytes_per_day * (timestamp - self.base_timestamp) as f64 / MS_PER_DAY
+    }
+
+    /// When the line reaches `size`, or `None` if it never grows towards it.
+    pub fn reaches(&self, size: u64) -> Option<i64> {
+        if self.bytes_per_day <= 0.0 {
+            return None;
+        }
+        let days = (size as f64 - self.base_size) / self.bytes_per_day;
+        Some(self.base_timestamp + (days * MS_PER_DAY).round() as i64)
+    }
+}
+
+/// Fit a line through `points`. Needs at least two distinct timestamps.
+pub fn linear_trend(points: &[SizePoint]) -> Option<Trend> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    // Work relative to the first point so millisecond timestamps keep their precision.
+    let t0 = points[0].timestamp;
+    let days: Vec<f64> = points.iter().map(|p| (p.timestamp - t0) as f64 / MS_PER_DAY).collect();
+    let mean_t = days.iter().sum::<f64>() / n;
+    let mean_s = points.iter().map(|p| p.size as f64).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (t, p) in days.iter().zip(points) {
+        cov += (t - mean_t) * (p.size as f64 - mean_s);
+        var += (t - mean_t) * (t - mean_t);
+    }
+    if var == 0.0 {
+        return None;
+    }
+    Some(Trend {
+        bytes_per_day: cov / var,
+        base_timestamp: t0 + (mean_t * MS_PER_DAY).round() as i64,
+        base_size: mean_s,
+    })
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SizeSeries {
+    pub path: String,
+    /// Oldest first.
+    pub points: Vec<SizePoint>,
+    /// `None` until the points span at least an hour.
+    pub trend: Option<Trend>,
+    /// When the trend reaches the given capacity, if it is growing.
+    pub full_at: Option<i64>,
+}
+
+/// Bundle a series with its trend and, given the volume's capacity, the projected fill date.
+pub fn size_series(path: &str, points: Vec<SizePoint>, capacity: Option<u64>) -> SizeSeries {
+    let span = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => last.timestamp - first.timestamp,
+        _ => 0,
+    };
+    let trend = if span >= MIN_TREND_SPAN_MS { linear_trend(&points) } else { None };
+    let full_at = match (trend, capacity) {
+        (Some(trend), Some(capacity)) => trend.reaches(capacity),
+        _ => None,
+    };
+    SizeSeries {
+        path: path.to_string(),
+        points,
+        trend,
+        full_at,
+    }
+}
+
+#[cfg(test)]
+mod tests;
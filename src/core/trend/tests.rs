@@ -0,0 +1,70 @@
+use super::*;
+
+const DAY: i64 = 24 * 60 * 60 * 1000;
+
+fn point(day: i64, size: u64) -> SizePoint {
+    SizePoint {
+        timestamp: 1_700_000_000_000 + day * DAY,
+        size,
+    }
+}
+
+#[test]
+fn linear_trend_fits_steady_growth_and_projects_fill_date() {
+    let points = vec![point(0, 100), point(1, 200), point(2, 300), point(4, 500)];
+    let trend = linear_trend(&points).unwrap();
+    assert!((trend.bytes_per_day - 100.0).abs() < 1e-6);
+    assert!((trend.size_at(point(3, 0).timestamp) - 400.0).abs() < 1e-6);
+    assert_eq!(trend.reaches(1_000), Some(point(9, 0).timestamp));
+
+    let series = size_series("/r", points, Some(1_000));
+    assert_eq!(series.full_at, Some(point(9, 0).timestamp));
+}
+
+#[test]
+fn no_trend_without_two_timestamps_and_no_fill_date_when_shrinking() {
+    assert!(linear_trend(&[point(0, 1)]).is_none());
+    assert!(linear_trend(&[point(0, 1), point(0, 5)]).is_none());
+
+    let series = size_series("/r", vec![point(0, 500), point(1, 400)], Some(1_000));
+    assert!(series.trend.unwrap().bytes_per_day < 0.0);
+    assert_eq!(series.full_at, None);
+}
+
+#[test]
+fn no_trend_from_scans_moments_apart() {
+    let second = |ms: i64, size: u64| SizePoint { timestamp: point(0, 0).timestamp + ms, size };
+    let series = size_series("/r", vec![second(0, 100_000), second(1_300, 102_048)], Some(1 << 30));
+    assert!(series.trend.is_none());
+    assert_eq!(series.full_at, None);
+
+    let series = size_series("/r", vec![second(0, 100_000), second(1_300, 102_048), second(2_600, 104_096)], None);
+    assert!(series.trend.is_none());
+
+    let hour = 60 * 60 * 1000;
+    let series = size_series("/r", vec![second(0, 100_000), second(hour, 102_048)], None);
+    assert!(series.trend.is_some());
+}
+
+#[test]
+fn tracks_the_largest_folders_per_depth() {
+    let root = PathBuf::from("/r");
+    let sizes: HashMap<PathBuf, u64> = [
+        ("/r", 100),
+        ("/r/a", 60),
+        ("/r/b", 30),
+        ("/r/c", 10),
+        ("/r/a/x", 50),
+        ("/r/a/x/deep", 50),
+    ]
+    .into_iter()
+    .map(|(p, s)| (PathBuf::from(p), s))
+    .collect();
+    let options = TrackingOptions {
+        per_depth: 2,
+        max_depth: 2,
+    };
+    let tracked = select_tracked_folders(&[root], &sizes, &options);
+    let paths: Vec<&str> = tracked.iter().map(|(p, _)| p.as_str()).collect();
+    assert_eq!(paths, ["/r", "/r/a", "/r/a/x", "/r/b"]);
+}
@@ -0,0 +1,133 @@
+//! Storage for `core::scanning::checkpoint`: the finished folders of a resumable scan.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::core::scanning::checkpoint::{Checkpoint, DoneFolder};
+use crate::{FileEntry, FileKey, Ownership};
+
+fn to_json<T: serde::Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn path_strings(paths: &[PathBuf]) -> Vec<String> {
+    paths.iter().map(|p| p.to_string_lossy().to_string()).collect()
+}
+
+/// Drop any previous checkpoint and start a new one for `roots`.
+pub fn start_checkpoint(conn: &Connection, roots: &[PathBuf]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    clear_checkpoint(&tx)?;
+    tx.execute(
+        "INSERT INTO scan_checkpoint (id, roots, started_at) VALUES (1, ?1, ?2)",
+        rusqlite::params![to_json(&path_strings(roots))?, chrono::Utc::now().timestamp_millis()],
+    )?;
+    tx.commit()
+}
+
+/// Add finished folders to the checkpoint, replacing earlier versions of the same folders.
+pub fn save_checkpoint(conn: &Connection, folders: &[DoneFolder]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut folder_stmt = tx.prepare(
+            "INSERT OR REPLACE INTO checkpoint_folders (path, mtime, subfolders, symlinks) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut clear_stmt = tx.prepare("DELETE FROM checkpoint_files WHERE folder = ?1")?;
+        let mut file_stmt = tx.prepare(
+            "INSERT OR REPLACE INTO checkpoint_files (folder, path, size, allocated_size, dev, ino, mtime, uid, gid, mode) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        for folder in folders {
+            let path = folder.path.to_string_lossy().to_string();
+            folder_stmt.execute(rusqlite::params![
+                path,
+                folder.mtime,
+                to_json(&path_strings(&folder.subfolders))?,
+                to_json(&folder.symlinks)?,
+            ])?;
+            clear_stmt.execute(rusqlite::params![path])?;
+            for file in &folder.files {
+                file_stmt.execute(rusqlite::params![
+                    path,
+                    file.path.to_string_lossy().to_string(),
+                    file.size as i64,
+                    file.allocated_size as i64,
+                    file.file_key.dev as i64,
+                    file.file_key.ino as i64,
+                    file.mtime,
+                    file.owner.map(|o| o.uid),
+                    file.owner.map(|o| o.gid),
+                    file.owner.map(|o| o.mode),
+                ])?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+/// The stored checkpoint, if a resumable scan did not finish.
+pub fn load_checkpoint(conn: &Connection) -> rusqlite::Result<Option<Checkpoint>> {
+    let roots: Option<String> = conn
+        .query_row("SELECT roots FROM scan_checkpoint WHERE id = 1", [], |row| row.get(0))
+        .optional()?;
+    let Some(roots) = roots else {
+        return Ok(None);
+    };
+    let roots: Vec<String> = serde_json::from_str(&roots).unwrap_or_default();
+
+    let mut files: HashMap<String, Vec<FileEntry>> = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT folder, path, size, allocated_size, dev, ino, mtime, uid, gid, mode FROM checkpoint_files",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            FileEntry {
+                path: PathBuf::from(row.get::<_, String>(1)?),
+                size: row.get::<_, i64>(2)? as u64,
+                allocated_size: row.get::<_, i64>(3)? as u64,
+                file_key: FileKey {
+                    dev: row.get::<_, i64>(4)? as u64,
+                    ino: row.get::<_, i64>(5)? as u64,
+                },
+                mtime: row.get(6)?,
+                owner: Ownership::from_columns(row.get(7)?, row.get(8)?, row.get(9)?),
+            },
+        ))
+    })?;
+    for row in rows {
+        let (folder, file) = row?;
+        files.entry(folder).or_default().push(file);
+    }
+
+    let mut stmt = conn.prepare("SELECT path, mtime, subfolders, symlinks FROM checkpoint_folders")?;
+    let folders = stmt
+        .query_map([], |row| {
+            let path: String = row.get(0)?;
+            let subfolders: Vec<String> = serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default();
+            let symlinks = row.get::<_, Option<String>>(3)?;
+            Ok(DoneFolder {
+                files: files.remove(&path).unwrap_or_default(),
+                path: PathBuf::from(path),
+                mtime: row.get(1)?,
+                subfolders: subfolders.into_iter().map(PathBuf::from).collect(),
+                symlinks: symlinks.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(Some(Checkpoint {
+        roots: roots.into_iter().map(PathBuf::from).collect(),
+        folders,
+    }))
+}
+
+/// Forget the checkpoint, e.g. once the scan it belongs to has been written.
+pub fn clear_checkpoint(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM checkpoint_files", [])?;
+    conn.execute("DELETE FROM checkpoint_folders", [])?;
+    conn.execute("DELETE FROM scan_checkpoint", [])?;
+    Ok(())
+}
@@ -3,10 +3,16 @@ use rusqlite::OptionalExtension;
 use std::path::Path;
 use std::time::Instant;
 
-use crate::{FileEntry, FileKey};
+use crate::{FileEntry, FileKey, Ownership, SizeMetric, SymlinkEntry};
+use crate::core::duplicates::{CachedHashes, HashCache, HashCacheKey};
+use crate::core::folder_sizes::sum_folder_sizes;
+use crate::core::scanning::exclusions::is_collapsed_entry;
 use crate::DiskTreeNode;
-use crate::parent_dir;
+use crate::{parent_dir, path_separator};
 use super::migrations::migrations;
+use super::snapshots::{current_snapshot_id, record_snapshot};
+
+type PathSizes = Vec<(String, u64)>;
 
 #[derive(Clone, Debug, Default)]
 pub struct GetScanTimings {
@@ -32,12 +38,27 @@ const CREATE_SECONDARY_INDEXES: &[&str] = &[
     "CREATE INDEX idx_disk_objects_name_lower ON disk_objects(name_lower)",
 ];
 
+/// Replace the current scan in `disk_objects` and record it as a new snapshot.
+///
+/// Returns the id of the new `scans` row; earlier snapshots are kept until
+/// [`apply_retention`](super::apply_retention) removes them.
 pub fn write_scan(
     conn: &Connection,
     files: &[FileEntry],
     folder_sizes: &std::collections::HashMap<std::path::PathBuf, u64>,
     update_id: i64,
-) -> rusqlite::Result<()> {
+) -> rusqlite::Result<i64> {
+    write_scan_with_symlinks(conn, files, &[], folder_sizes, update_id)
+}
+
+/// Like [`write_scan`], also storing the symlinks the scan recorded.
+pub fn write_scan_with_symlinks(
+    conn: &Connection,
+    files: &[FileEntry],
+    symlinks: &[SymlinkEntry],
+    folder_sizes: &std::collections::HashMap<std::path::PathBuf, u64>,
+    update_id: i64,
+) -> rusqlite::Result<i64> {
     let tx = conn.unchecked_transaction()?;
 
     tx.execute("DELETE FROM disk_objects", [])?;
@@ -48,80 +69,17 @@ pub fn write_scan(
     }
 
     {
-        let mut stmt = tx.prepare(
-            "INSERT INTO disk_objects \
-             (path, path_lower, parent_path, name, name_lower, ext, kind, size, recursive_size, dev, ino, mtime) \
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
-        )?;
-
+        let mut stmt = tx.prepare(INSERT_DISK_OBJECT)?;
         for entry in files {
-            let path_str = entry.path.to_string_lossy().to_string();
-            let path_lower = path_str.to_ascii_lowercase();
-            let parent_path = parent_dir(&path_str);
-            let name: Option<String> = entry
-                .path
-                .file_name()
-                .and_then(|os| os.to_str())
-                .map(|s| s.to_string());
-            let name_lower: Option<String> = name
-                .as_ref()
-                .map(|s| s.to_ascii_lowercase());
-            let ext: Option<String> = entry
-                .path
-                .extension()
-                .and_then(|os| os.to_str())
-                .map(|s| s.to_ascii_lowercase());
-
-            stmt.execute(rusqlite::params![
-                path_str,
-                path_lower,
-                parent_path,
-                name,
-                name_lower,
-                ext,
-                "file",
-                entry.size as i64,
-                None::<i64>,
-                entry.file_key.dev as i64,
-                entry.file_key.ino as i64,
-                entry.mtime.unwrap_or(0),
-            ])?;
+            insert_file(&mut stmt, entry)?;
         }
-    }
-
-    {
-        let mut stmt = tx.prepare(
-            "INSERT INTO disk_objects \
-             (path, path_lower, parent_path, name, name_lower, ext, kind, size, recursive_size, dev, ino, mtime) \
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
-        )?;
-
+        let allocated_sizes = sum_folder_sizes(folder_sizes.keys(), files, SizeMetric::Allocated);
         for (path, size) in folder_sizes.iter() {
-            let path_str = path.to_string_lossy().to_string();
-            let path_lower = path_str.to_ascii_lowercase();
-            let parent_path = parent_dir(&path_str);
-            let name = path
-                .file_name()
-                .and_then(|os| os.to_str())
-                .map(|s| s.to_string());
-            let name_lower: Option<String> = name
-                .as_ref()
-                .map(|s| s.to_ascii_lowercase());
-
-            stmt.execute(rusqlite::params![
-                path_str,
-                path_lower,
-                parent_path,
-                name,
-                name_lower,
-                None::<String>,
-                "folder",
-                None::<i64>,
-                *size as i64,
-                None::<i64>,
-                None::<i64>,
-                None::<i64>,
-            ])?;
+            insert_folder(&mut stmt, path, *size, allocated_sizes.get(path).copied(), None)?;
+        }
+        let mut stmt = tx.prepare(INSERT_SYMLINK)?;
+        for link in symlinks {
+            insert_symlink(&mut stmt, link)?;
         }
     }
 
@@ -129,6 +87,141 @@ pub fn write_scan(
         tx.execute(ddl, [])?;
     }
 
+    let scan_id = finish_scan_write(&tx, update_id)?;
+    tx.commit()?;
+    Ok(scan_id)
+}
+
+pub(super) const INSERT_DISK_OBJECT: &str = "INSERT INTO disk_objects \
+     (path, path_lower, parent_path, name, name_lower, ext, kind, size, recursive_size, allocated_size, dev, ino, mtime, dir_mtime, \
+      uid, gid, mode) \
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)";
+
+/// The `kind` column for a scanned file: `collapsed` for the summary of a collapsed folder, so
+/// queries over `kind = 'file'` (hashing, owners, largest files) never see it.
+pub(super) fn file_kind(path: &Path) -> &'static str {
+    if is_collapsed_entry(path) {
+        "collapsed"
+    } else {
+        "file"
+    }
+}
+
+/// Insert a file row with [`INSERT_DISK_OBJECT`].
+pub(super) fn insert_file(stmt: &mut rusqlite::Statement, entry: &FileEntry) -> rusqlite::Result<()> {
+    let path_str = entry.path.to_string_lossy().to_string();
+    let path_lower = path_str.to_ascii_lowercase();
+    let parent_path = parent_dir(&path_str);
+    let name: Option<String> = entry
+        .path
+        .file_name()
+        .and_then(|os| os.to_str())
+        .map(|s| s.to_string());
+    let name_lower: Option<String> = name
+        .as_ref()
+        .map(|s| s.to_ascii_lowercase());
+    let ext: Option<String> = entry
+        .path
+        .extension()
+        .and_then(|os| os.to_str())
+        .map(|s| s.to_ascii_lowercase());
+
+    stmt.execute(rusqlite::params![
+        path_str,
+        path_lower,
+        parent_path,
+        name,
+        name_lower,
+        ext,
+        file_kind(&entry.path),
+        entry.size as i64,
+        None::<i64>,
+        entry.allocated_size as i64,
+        entry.file_key.dev as i64,
+        entry.file_key.ino as i64,
+        entry.mtime.unwrap_or(0),
+        None::<i64>,
+        entry.owner.map(|o| o.uid),
+        entry.owner.map(|o| o.gid),
+        entry.owner.map(|o| o.mode),
+    ])?;
+    Ok(())
+}
+
+/// Insert a folder row with [`INSERT_DISK_OBJECT`]. `dir_mtime` is the folder's own mtime
+/// when it was read, which only incremental scans record.
+pub(super) fn insert_folder(
+    stmt: &mut rusqlite::Statement,
+    path: &Path,
+    recursive_size: u64,
+    allocated_size: Option<u64>,
+    dir_mtime: Option<i64>,
+) -> rusqlite::Result<()> {
+    let path_str = path.to_string_lossy().to_string();
+    let path_lower = path_str.to_ascii_lowercase();
+    let parent_path = parent_dir(&path_str);
+    let name = path
+        .file_name()
+        .and_then(|os| os.to_str())
+        .map(|s| s.to_string());
+    let name_lower: Option<String> = name
+        .as_ref()
+        .map(|s| s.to_ascii_lowercase());
+
+    stmt.execute(rusqlite::params![
+        path_str,
+        path_lower,
+        parent_path,
+        name,
+        name_lower,
+        None::<String>,
+        "folder",
+        None::<i64>,
+        recursive_size as i64,
+        allocated_size.map(|n| n as i64),
+        None::<i64>,
+        None::<i64>,
+        None::<i64>,
+        dir_mtime,
+        None::<u32>,
+        None::<u32>,
+        None::<u32>,
+    ])?;
+    Ok(())
+}
+
+pub(super) const INSERT_SYMLINK: &str = "INSERT INTO disk_objects \
+     (path, path_lower, parent_path, name, name_lower, kind, link_target, link_broken) \
+     VALUES (?1, ?2, ?3, ?4, ?5, 'symlink', ?6, ?7)";
+
+/// Insert a symlink row with [`INSERT_SYMLINK`]. Symlinks have no size of their own.
+pub(super) fn insert_symlink(stmt: &mut rusqlite::Statement, link: &SymlinkEntry) -> rusqlite::Result<()> {
+    let path_str = link.path.to_string_lossy().to_string();
+    let name = link.path.file_name().map(|os| os.to_string_lossy().to_string());
+    stmt.execute(rusqlite::params![
+        path_str,
+        path_str.to_ascii_lowercase(),
+        parent_dir(&path_str),
+        name,
+        name.as_ref().map(|s| s.to_ascii_lowercase()),
+        link.target.to_string_lossy().to_string(),
+        link.broken,
+    ])?;
+    Ok(())
+}
+
+/// Bookkeeping after `disk_objects` got a new scan: prune stale file hashes, bump the update
+/// id and record the snapshot. Returns the snapshot id.
+pub(super) fn finish_scan_write(tx: &Connection, update_id: i64) -> rusqlite::Result<i64> {
+    // Hashes survive the rescan only while some file still has the same identity, size and mtime.
+    tx.execute(
+        "DELETE FROM file_hashes WHERE NOT EXISTS ( \
+             SELECT 1 FROM disk_objects d \
+             WHERE d.kind = 'file' AND d.dev = file_hashes.dev AND d.ino = file_hashes.ino \
+               AND d.size = file_hashes.size AND d.mtime = file_hashes.mtime)",
+        [],
+    )?;
+
     tx.execute(
         "INSERT INTO scan_metadata \
             (id, disk_objects_update_id, disk_objects_last_updated, \
@@ -141,8 +234,7 @@ pub fn write_scan(
         rusqlite::params![update_id],
     )?;
 
-    tx.commit()?;
-    Ok(())
+    record_snapshot(tx, update_id)
 }
 
 pub fn has_disk_objects(conn: &Connection) -> rusqlite::Result<bool> {
@@ -172,17 +264,116 @@ pub fn get_file_index(
     rows.collect()
 }
 
+/// All indexed files as [`FileEntry`] values, e.g. to feed the duplicate finder.
+pub fn get_file_entries(conn: &Connection) -> rusqlite::Result<Vec<FileEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, size, COALESCE(allocated_size, size), dev, ino, mtime, uid, gid, mode \
+         FROM disk_objects WHERE kind = 'file'",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(FileEntry {
+            path: std::path::PathBuf::from(row.get::<_, String>(0)?),
+            size: row.get::<_, i64>(1)? as u64,
+            allocated_size: row.get::<_, i64>(2)? as u64,
+            file_key: FileKey {
+                dev: row.get::<_, i64>(3)? as u64,
+                ino: row.get::<_, i64>(4)? as u64,
+            },
+            mtime: row.get::<_, Option<i64>>(5)?,
+            owner: Ownership::from_columns(row.get(6)?, row.get(7)?, row.get(8)?),
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn read_file_hashes(conn: &Connection) -> rusqlite::Result<HashCache> {
+    let mut stmt = conn.prepare(
+        "SELECT dev, ino, size, mtime, partial_hash, full_hash FROM file_hashes",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let key = HashCacheKey {
+            dev: row.get::<_, i64>(0)? as u64,
+            ino: row.get::<_, i64>(1)? as u64,
+            size: row.get::<_, i64>(2)? as u64,
+            mtime: row.get::<_, i64>(3)?,
+        };
+        let hashes = CachedHashes {
+            partial: row.get::<_, Option<Vec<u8>>>(4)?.and_then(|b| b.try_into().ok()),
+            full: row.get::<_, Option<Vec<u8>>>(5)?.and_then(|b| b.try_into().ok()),
+        };
+        Ok((key, hashes))
+    })?;
+    let entries = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(HashCache::from_entries(entries))
+}
+
+/// Persist the entries of `cache` that changed since it was loaded, then mark them clean.
+pub fn write_file_hashes(conn: &Connection, cache: &mut HashCache) -> rusqlite::Result<usize> {
+    let dirty = cache.dirty_entries();
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO file_hashes (dev, ino, size, mtime, partial_hash, full_hash) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6) \
+             ON CONFLICT(dev, ino, size, mtime) DO UPDATE SET \
+                partial_hash = COALESCE(excluded.partial_hash, file_hashes.partial_hash), \
+                full_hash = COALESCE(excluded.full_hash, file_hashes.full_hash)",
+        )?;
+        for (key, hashes) in &dirty {
+            stmt.execute(rusqlite::params![
+                key.dev as i64,
+                key.ino as i64,
+                key.size as i64,
+                key.mtime,
+                hashes.partial.as_ref().map(|h| &h[..]),
+                hashes.full.as_ref().map(|h| &h[..]),
+            ])?;
+        }
+    }
+    tx.commit()?;
+    cache.clear_dirty();
+    Ok(dirty.len())
+}
+
+/// Point a file row at a new identity, e.g. after a duplicate was replaced by a hard link.
+/// Cached trees stay valid because sizes do not change. Returns false if the path is unknown.
+pub fn update_file_identity(
+    conn: &Connection,
+    path: &str,
+    key: FileKey,
+    mtime: Option<i64>,
+) -> rusqlite::Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    let n = tx.execute(
+        "UPDATE disk_objects SET dev = ?1, ino = ?2, mtime = ?3 WHERE path = ?4 AND kind = 'file'",
+        rusqlite::params![key.dev as i64, key.ino as i64, mtime.unwrap_or(0), path],
+    )?;
+    if let Some(scan_id) = current_snapshot_id(&tx)? {
+        tx.execute(
+            "UPDATE snapshot_objects SET dev = ?1, ino = ?2, mtime = ?3 \
+             WHERE scan_id = ?4 AND path = ?5 AND kind = 'file'",
+            rusqlite::params![key.dev as i64, key.ino as i64, mtime.unwrap_or(0), scan_id, path],
+        )?;
+    }
+    tx.commit()?;
+    Ok(n > 0)
+}
+
+/// Every stored file, folder and symlink, e.g. to build a search index or export. Summaries of
+/// collapsed folders are left out: nothing on disk has their path.
 pub fn get_disk_objects(
     conn: &Connection,
 ) -> rusqlite::Result<Vec<crate::DiskObject>> {
     let mut stmt = conn.prepare(
-        "SELECT path, path_lower, parent_path, name, name_lower, ext, kind, size, recursive_size, dev, ino, mtime \
-         FROM disk_objects",
+        "SELECT path, path_lower, parent_path, name, name_lower, ext, kind, size, recursive_size, dev, ino, mtime, \
+                allocated_size, link_target, uid, gid, mode \
+         FROM disk_objects WHERE kind != 'collapsed'",
     )?;
     let rows = stmt.query_map([], |row| {
         let kind_str: String = row.get(6)?;
         let kind = match kind_str.as_str() {
             "folder" => crate::DiskObjectKind::Folder,
+            "symlink" => crate::DiskObjectKind::Symlink,
             _ => crate::DiskObjectKind::File,
         };
         let size_opt: Option<i64> = row.get(7)?;
@@ -190,6 +381,8 @@ pub fn get_disk_objects(
         let dev_opt: Option<i64> = row.get(9)?;
         let ino_opt: Option<i64> = row.get(10)?;
         let mtime_opt: Option<i64> = row.get(11)?;
+        let allocated_opt: Option<i64> = row.get(12)?;
+        let link_target: Option<String> = row.get(13)?;
         let path: String = row.get(0)?;
         let path_lower_from_db: Option<String> = row.get(1)?;
         let name_opt: Option<String> = row.get(3)?;
@@ -205,9 +398,14 @@ pub fn get_disk_objects(
             kind,
             size: size_opt.map(|n| n as u64),
             recursive_size: rec_opt.map(|n| n as u64),
+            allocated_size: allocated_opt.map(|n| n as u64),
             dev: dev_opt.map(|n| n as u64),
             ino: ino_opt.map(|n| n as u64),
             mtime: mtime_opt,
+            link_target,
+            uid: row.get(14)?,
+            gid: row.get(15)?,
+            mode: row.get(16)?,
         })
     })?;
     rows.collect()
@@ -227,6 +425,90 @@ pub fn get_folders(
     Ok(rows)
 }
 
+/// Folders whose parent is not itself an indexed folder, i.e. the scanned roots.
+pub fn get_root_folders(conn: &Connection) -> rusqlite::Result<Vec<(String, u64)>> {
+    get_root_folders_with_metric(conn, SizeMetric::Apparent)
+}
+
+pub fn get_root_folders_with_metric(
+    conn: &Connection,
+    metric: SizeMetric,
+) -> rusqlite::Result<Vec<(String, u64)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT d.path, {} FROM disk_objects d \
+         WHERE d.kind = 'folder' AND NOT EXISTS ( \
+             SELECT 1 FROM disk_objects p WHERE p.path = d.parent_path AND p.kind = 'folder') \
+         ORDER BY d.path",
+        folder_size_sql(metric),
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// SQL expression for a file's size by `metric`. Rows from before allocated sizes were
+/// recorded fall back to the apparent size.
+pub(super) fn file_size_sql(metric: SizeMetric) -> &'static str {
+    match metric {
+        SizeMetric::Apparent => "size",
+        SizeMetric::Allocated => "COALESCE(allocated_size, size)",
+    }
+}
+
+/// SQL expression for a folder's recursive size by `metric`, see [`file_size_sql`].
+fn folder_size_sql(metric: SizeMetric) -> &'static str {
+    match metric {
+        SizeMetric::Apparent => "recursive_size",
+        SizeMetric::Allocated => "COALESCE(allocated_size, recursive_size)",
+    }
+}
+
+pub fn get_largest_files(conn: &Connection, limit: usize) -> rusqlite::Result<Vec<(String, u64)>> {
+    get_largest_files_with_metric(conn, limit, SizeMetric::Apparent)
+}
+
+pub fn get_largest_files_with_metric(
+    conn: &Connection,
+    limit: usize,
+    metric: SizeMetric,
+) -> rusqlite::Result<Vec<(String, u64)>> {
+    let size = file_size_sql(metric);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT path, {size} FROM disk_objects WHERE kind = 'file' ORDER BY {size} DESC, path LIMIT ?1",
+    ))?;
+    let rows = stmt
+        .query_map(rusqlite::params![limit as i64], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+pub fn get_largest_folders(conn: &Connection, limit: usize) -> rusqlite::Result<Vec<(String, u64)>> {
+    get_largest_folders_with_metric(conn, limit, SizeMetric::Apparent)
+}
+
+pub fn get_largest_folders_with_metric(
+    conn: &Connection,
+    limit: usize,
+    metric: SizeMetric,
+) -> rusqlite::Result<Vec<(String, u64)>> {
+    let size = folder_size_sql(metric);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT path, {size} FROM disk_objects WHERE kind = 'folder' \
+         ORDER BY {size} DESC, path LIMIT ?1",
+    ))?;
+    let rows = stmt
+        .query_map(rusqlite::params![limit as i64], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
 pub fn get_scan_result(
     conn: &Connection,
 ) -> rusqlite::Result<Option<crate::ScanResult>> {
@@ -267,6 +549,7 @@ pub fn get_scan_summary(
         .collect();
 
     let folders_count = folder_sizes.len() as u64;
+    let errors_count: i64 = conn.query_row("SELECT COUNT(1) FROM scan_errors", [], |row| row.get(0))?;
 
     let roots: Vec<String> = folder_sizes
         .iter()
@@ -283,6 +566,7 @@ pub fn get_scan_summary(
         files_count: files_count as u64,
         folders_count,
         folder_sizes,
+        errors_count: errors_count as u64,
     }))
 }
 
@@ -452,18 +736,30 @@ pub fn get_children_for_path(
     Vec<(String, u64)>,
     Vec<(String, u64)>,
 )> {
-    let mut folder_stmt = conn.prepare(
-        "SELECT path, recursive_size FROM disk_objects WHERE parent_path = ?1 AND kind = 'folder' ORDER BY recursive_size DESC",
-    )?;
+    get_children_for_path_with_metric(conn, parent_path, SizeMetric::Apparent)
+}
+
+/// Direct children of `parent_path` as `(folders, files)`, sized by `metric`. Recorded symlinks
+/// are listed with the files, at size 0.
+pub fn get_children_for_path_with_metric(
+    conn: &Connection,
+    parent_path: &str,
+    metric: SizeMetric,
+) -> rusqlite::Result<(PathSizes, PathSizes)> {
+    let folder_size = folder_size_sql(metric);
+    let mut folder_stmt = conn.prepare(&format!(
+        "SELECT path, {folder_size} FROM disk_objects WHERE parent_path = ?1 AND kind = 'folder' ORDER BY {folder_size} DESC",
+    ))?;
     let folders: Vec<(String, u64)> = folder_stmt
         .query_map(rusqlite::params![parent_path], |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
         })?
         .collect::<rusqlite::Result<Vec<_>>>()?;
 
-    let mut file_stmt = conn.prepare(
-        "SELECT path, size FROM disk_objects WHERE parent_path = ?1 AND kind = 'file'",
-    )?;
+    let mut file_stmt = conn.prepare(&format!(
+        "SELECT path, COALESCE({}, 0) FROM disk_objects WHERE parent_path = ?1 AND kind != 'folder'",
+        file_size_sql(metric),
+    ))?;
     let files: Vec<(String, u64)> = file_stmt
         .query_map(rusqlite::params![parent_path], |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
@@ -473,9 +769,120 @@ pub fn get_children_for_path(
     Ok((folders, files))
 }
 
+/// Bounds `(lower, upper)` such that every path strictly below `path` sorts in between, so
+/// subtree queries can use the primary-key index instead of `LIKE`.
+fn descendant_range(path: &str) -> (String, String) {
+    let sep = path_separator(path);
+    let base = path.trim_end_matches(sep);
+    let next = char::from_u32(sep as u32 + 1).unwrap_or(sep);
+    (format!("{}{}", base, sep), format!("{}{}", base, next))
+}
+
+/// The folders above `path`, nearest first. `parent_dir` stops short of `/`, so it is added
+/// for absolute Unix paths, e.g. for a scan of the whole disk.
+fn ancestors(path: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut ancestor = parent_dir(path);
+    while !ancestor.is_empty() {
+        out.push(ancestor.clone());
+        ancestor = parent_dir(&ancestor);
+    }
+    if path.starts_with('/') && path != "/" {
+        out.push("/".to_string());
+    }
+    out
+}
+
+/// mtime of a file, or the newest file mtime anywhere below a folder.
+pub fn get_newest_mtime(conn: &Connection, path: &str) -> rusqlite::Result<Option<i64>> {
+    let (lower, upper) = descendant_range(path);
+    conn.query_row(
+        "SELECT MAX(mtime) FROM disk_objects \
+         WHERE kind IN ('file', 'collapsed') AND mtime != 0 AND (path = ?1 OR (path > ?2 AND path < ?3))",
+        rusqlite::params![path, lower, upper],
+        |row| row.get::<_, Option<i64>>(0),
+    )
+}
+
+/// Remove `path` and everything below it from `disk_objects`, e.g. after deleting it on disk.
+///
+/// The removed size is subtracted from every ancestor folder's `recursive_size`, and cached
+/// trees are dropped. The snapshot of the current scan gets the same edit, so diffs and trends
+/// against it agree with `disk_objects`. Returns the number of bytes removed (0 if the path
+/// was not indexed).
+pub fn delete_subtree(conn: &Connection, path: &str) -> rusqlite::Result<u64> {
+    let tx = conn.unchecked_transaction()?;
+    let sizes: Option<(i64, Option<i64>)> = tx
+        .query_row(
+            "SELECT COALESCE(size, recursive_size, 0), allocated_size FROM disk_objects WHERE path = ?1",
+            rusqlite::params![path],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((size, allocated)) = sizes else {
+        return Ok(0);
+    };
+
+    let (lower, upper) = descendant_range(path);
+    let (files, folders): (i64, i64) = tx.query_row(
+        "SELECT COALESCE(SUM(kind = 'file'), 0), COALESCE(SUM(kind = 'folder'), 0) FROM disk_objects \
+         WHERE path = ?1 OR (path > ?2 AND path < ?3)",
+        rusqlite::params![path, lower, upper],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    tx.execute(
+        "DELETE FROM disk_objects WHERE path = ?1 OR (path > ?2 AND path < ?3)",
+        rusqlite::params![path, lower, upper],
+    )?;
+    let scan_id = current_snapshot_id(&tx)?;
+    if let Some(scan_id) = scan_id {
+        tx.execute(
+            "DELETE FROM snapshot_objects WHERE scan_id = ?1 AND (path = ?2 OR (path > ?3 AND path < ?4))",
+            rusqlite::params![scan_id, path, lower, upper],
+        )?;
+        tx.execute(
+            "UPDATE scans SET files_count = files_count - ?1, folders_count = folders_count - ?2, \
+                 total_size = MAX(total_size - ?3, 0) \
+             WHERE id = ?4",
+            rusqlite::params![files, folders, size, scan_id],
+        )?;
+    }
+    {
+        let mut stmt = tx.prepare(
+            "UPDATE disk_objects SET recursive_size = MAX(recursive_size - ?1, 0), \
+                 allocated_size = MAX(allocated_size - ?2, 0) \
+             WHERE path = ?3 AND kind = 'folder'",
+        )?;
+        let mut snapshot_stmt = tx.prepare(
+            "UPDATE snapshot_objects SET size = MAX(size - ?1, 0) \
+             WHERE scan_id = ?2 AND path = ?3 AND kind = 'folder'",
+        )?;
+        for ancestor in ancestors(path) {
+            stmt.execute(rusqlite::params![size, allocated.unwrap_or(0), ancestor])?;
+            if let Some(scan_id) = scan_id {
+                snapshot_stmt.execute(rusqlite::params![size, scan_id, ancestor])?;
+            }
+        }
+    }
+    tx.execute("DELETE FROM cached_trees", [])?;
+    tx.commit()?;
+    Ok(size as u64)
+}
+
 pub fn get_folder_size(conn: &Connection, path: &str) -> rusqlite::Result<Option<u64>> {
+    get_folder_size_with_metric(conn, path, SizeMetric::Apparent)
+}
+
+pub fn get_folder_size_with_metric(
+    conn: &Connection,
+    path: &str,
+    metric: SizeMetric,
+) -> rusqlite::Result<Option<u64>> {
     conn.query_row(
-        "SELECT recursive_size FROM disk_objects WHERE path = ?1 AND kind = 'folder'",
+        &format!(
+            "SELECT {} FROM disk_objects WHERE path = ?1 AND kind = 'folder'",
+            folder_size_sql(metric)
+        ),
         rusqlite::params![path],
         |row| row.get::<_, i64>(0).map(|n| n as u64),
     )
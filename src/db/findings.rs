@@ -0,0 +1,53 @@
+//! Findings in the current scan; see `core::findings`.
+
+use std::path::PathBuf;
+
+use rusqlite::Connection;
+
+use crate::core::findings::{Finding, FindingKind};
+
+/// Permission bits each mode-based finding looks for.
+const MODE_FINDINGS: [(FindingKind, u32); 3] = [
+    (FindingKind::Setuid, 0o4000),
+    (FindingKind::Setgid, 0o2000),
+    (FindingKind::WorldWritable, 0o002),
+];
+
+/// Every finding in the current scan, ordered by kind and path. A file can show up under
+/// several kinds.
+pub fn get_findings(conn: &Connection) -> rusqlite::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    let mut stmt = conn.prepare(
+        "SELECT path, link_target FROM disk_objects \
+         WHERE kind = 'symlink' AND link_broken = 1 ORDER BY path",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Finding {
+            kind: FindingKind::BrokenSymlink,
+            path: PathBuf::from(row.get::<_, String>(0)?),
+            detail: format!("target {} does not exist", row.get::<_, Option<String>>(1)?.unwrap_or_default()),
+        })
+    })?;
+    for row in rows {
+        findings.push(row?);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT path, mode, uid, gid FROM disk_objects \
+         WHERE kind = 'file' AND mode IS NOT NULL AND (mode & ?1) != 0 ORDER BY path",
+    )?;
+    for (kind, bits) in MODE_FINDINGS {
+        let rows = stmt.query_map([bits], |row| {
+            let mode: u32 = row.get(1)?;
+            Ok(Finding {
+                kind,
+                path: PathBuf::from(row.get::<_, String>(0)?),
+                detail: format!("mode {:04o}, uid {}, gid {}", mode & 0o7777, row.get::<_, u32>(2)?, row.get::<_, u32>(3)?),
+            })
+        })?;
+        for row in rows {
+            findings.push(row?);
+        }
+    }
+    Ok(findings)
+}
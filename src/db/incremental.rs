@@ -0,0 +1,174 @@
+//! Incremental rescans: the current scan as a [`Checkpoint`] for
+//! `core::scanning::checkpoint::scan_resumable`, and writing back only what changed.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+
+use crate::core::scanning::checkpoint::{Checkpoint, DoneFolder};
+use crate::{parent_dir, FileEntry, FileKey, Ownership, SymlinkEntry};
+use super::db::{finish_scan_write, insert_file, insert_folder, insert_symlink, INSERT_DISK_OBJECT, INSERT_SYMLINK};
+
+/// The current scan as a checkpoint: every folder read by an earlier incremental scan, with the
+/// mtime it had then and its stored children. Folders without a recorded mtime are left out,
+/// so they are read again.
+pub fn current_scan_checkpoint(conn: &Connection) -> rusqlite::Result<Checkpoint> {
+    let mut folders: HashMap<String, DoneFolder> = HashMap::new();
+    let mut roots = Vec::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT d.path, d.dir_mtime, NOT EXISTS ( \
+                 SELECT 1 FROM disk_objects p WHERE p.path = d.parent_path AND p.kind = 'folder') \
+             FROM disk_objects d WHERE d.kind = 'folder' ORDER BY d.path",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, bool>(2)?))
+        })?;
+        for row in rows {
+            let (path, dir_mtime, is_root) = row?;
+            if is_root {
+                roots.push(PathBuf::from(&path));
+            }
+            folders.insert(
+                path.clone(),
+                DoneFolder {
+                    path: PathBuf::from(path),
+                    mtime: dir_mtime,
+                    subfolders: Vec::new(),
+                    files: Vec::new(),
+                    symlinks: Vec::new(),
+                },
+            );
+        }
+    }
+    let mut stmt = conn.prepare(
+        "SELECT path, parent_path, kind, size, allocated_size, dev, ino, mtime, link_target, link_broken, \
+                uid, gid, mode \
+         FROM disk_objects",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<i64>>(3)?,
+            row.get::<_, Option<i64>>(4)?,
+            row.get::<_, Option<i64>>(5)?,
+            row.get::<_, Option<i64>>(6)?,
+            row.get::<_, Option<i64>>(7)?,
+            row.get::<_, Option<String>>(8)?,
+            row.get::<_, Option<bool>>(9)?,
+            Ownership::from_columns(row.get(10)?, row.get(11)?, row.get(12)?),
+        ))
+    })?;
+    for row in rows {
+        let (path, parent, kind, size, allocated, dev, ino, mtime, target, broken, owner) = row?;
+        let Some(folder) = folders.get_mut(&parent) else { continue };
+        if kind == "folder" {
+            folder.subfolders.push(PathBuf::from(path));
+            continue;
+        }
+        if kind == "symlink" {
+            folder.symlinks.push(SymlinkEntry {
+                path: PathBuf::from(path),
+                target: PathBuf::from(target.unwrap_or_default()),
+                broken: broken.unwrap_or(false),
+            });
+            continue;
+        }
+        folder.files.push(FileEntry {
+            path: PathBuf::from(path),
+            size: size.unwrap_or(0) as u64,
+            allocated_size: allocated.or(size).unwrap_or(0) as u64,
+            file_key: FileKey {
+                dev: dev.unwrap_or(0) as u64,
+                ino: ino.unwrap_or(0) as u64,
+            },
+            mtime: mtime.filter(|&m| m != 0),
+            owner,
+        });
+    }
+    Ok(Checkpoint {
+        roots,
+        folders: folders.into_values().filter(|f| f.mtime.is_some()).collect(),
+    })
+}
+
+/// Update the current scan from an incremental rescan and record it as a new snapshot.
+///
+/// `changed` are the folders the rescan read (those passed to its save callback) and `visited`
+/// every folder it reached; stored folders outside `visited` are removed. Sizes are recomputed
+/// only for changed folders and their ancestors, each as the sum of its direct children, so
+/// a file hard-linked from folders in different branches counts in both.
+pub fn write_incremental_scan(
+    conn: &Connection,
+    changed: &[DoneFolder],
+    visited: &HashSet<PathBuf>,
+    update_id: i64,
+) -> rusqlite::Result<i64> {
+    let tx = conn.unchecked_transaction()?;
+    let mut dirty: HashSet<String> = HashSet::new();
+
+    let stored: Vec<String> = tx
+        .prepare("SELECT path FROM disk_objects WHERE kind = 'folder'")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    {
+        let mut remove = tx.prepare("DELETE FROM disk_objects WHERE path = ?1 OR parent_path = ?1")?;
+        for path in stored.iter().filter(|p| !visited.contains(Path::new(p))) {
+            remove.execute(rusqlite::params![path])?;
+            dirty.insert(parent_dir(path));
+        }
+    }
+    {
+        let mut clear = tx.prepare("DELETE FROM disk_objects WHERE parent_path = ?1 AND kind != 'folder'")?;
+        let mut touch = tx.prepare("UPDATE disk_objects SET dir_mtime = ?2 WHERE path = ?1 AND kind = 'folder'")?;
+        let mut insert = tx.prepare(INSERT_DISK_OBJECT)?;
+        let mut insert_link = tx.prepare(INSERT_SYMLINK)?;
+        for folder in changed {
+            let path = folder.path.to_string_lossy().to_string();
+            clear.execute(rusqlite::params![path])?;
+            if touch.execute(rusqlite::params![path, folder.mtime])? == 0 {
+                insert_folder(&mut insert, &folder.path, 0, Some(0), folder.mtime)?;
+            }
+            for file in &folder.files {
+                insert_file(&mut insert, file)?;
+            }
+            for link in &folder.symlinks {
+                insert_symlink(&mut insert_link, link)?;
+            }
+            dirty.insert(path);
+        }
+    }
+
+    // Every ancestor of a dirty folder is dirty too; children are longer than their parents,
+    // so going by descending length totals each folder after its subfolders.
+    let mut chains: HashSet<String> = HashSet::new();
+    for path in dirty {
+        let mut current = path;
+        while !current.is_empty() && chains.insert(current.clone()) {
+            current = parent_dir(&current);
+        }
+    }
+    let mut chains: Vec<String> = chains.into_iter().collect();
+    chains.sort_by_key(|p| std::cmp::Reverse(p.len()));
+    {
+        let mut total = tx.prepare(
+            "UPDATE disk_objects SET \
+                 recursive_size = (SELECT COALESCE(SUM(COALESCE(c.size, c.recursive_size, 0)), 0) \
+                                   FROM disk_objects c WHERE c.parent_path = ?1), \
+                 allocated_size = (SELECT COALESCE(SUM(COALESCE(c.allocated_size, 0)), 0) \
+                                   FROM disk_objects c WHERE c.parent_path = ?1) \
+             WHERE path = ?1 AND kind = 'folder'",
+        )?;
+        for path in &chains {
+            total.execute(rusqlite::params![path])?;
+        }
+    }
+    tx.execute("DELETE FROM cached_trees", [])?;
+
+    let scan_id = finish_scan_write(&tx, update_id)?;
+    tx.commit()?;
+    Ok(scan_id)
+}
@@ -149,12 +149,165 @@ pub const MIGRATION_4_SEARCH_INDEX: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_disk_objects_kind_ext_name_lower ON disk_objects(kind, ext, name_lower);
 "#;
 
+/// Content hashes for the duplicate finder, keyed on file identity plus size and mtime.
+///
+/// Kept outside `disk_objects` so a rescan does not throw the hashes away. Hashes are raw
+/// 32-byte BLAKE3 digests; `full_hash` stays NULL until a file reaches the full-hash stage.
+pub const MIGRATION_5_FILE_HASHES: &str = r#"
+CREATE TABLE IF NOT EXISTS file_hashes (
+    dev INTEGER NOT NULL,
+    ino INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    mtime INTEGER NOT NULL,
+    partial_hash BLOB,
+    full_hash BLOB,
+    PRIMARY KEY (dev, ino, size, mtime)
+);
+"#;
+
+/// Scan history: one `scans` row per `write_scan`, plus a copy of that scan's objects.
+///
+/// `disk_objects` keeps holding the latest scan for the app and every query; snapshots are
+/// only read when browsing or comparing history. Folder rows store their recursive size in
+/// `size`. Old snapshots are removed by the retention policy, not by a rescan.
+pub const MIGRATION_6_SCAN_HISTORY: &str = r#"
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    update_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    roots TEXT NOT NULL,
+    files_count INTEGER NOT NULL,
+    folders_count INTEGER NOT NULL,
+    total_size INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_scans_created_at ON scans(created_at);
+
+CREATE TABLE IF NOT EXISTS snapshot_objects (
+    scan_id INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    parent_path TEXT,
+    kind TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    dev INTEGER,
+    ino INTEGER,
+    mtime INTEGER,
+    PRIMARY KEY (scan_id, path)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS idx_snapshot_objects_parent ON snapshot_objects(scan_id, parent_path);
+"#;
+
+/// Recursive size of a few folders per depth for every scan, for trend charts.
+///
+/// Unlike `snapshot_objects` this is never pruned by retention; `recorded_at` is the scan's
+/// update id (Unix milliseconds).
+pub const MIGRATION_7_SIZE_HISTORY: &str = r#"
+CREATE TABLE IF NOT EXISTS size_history (
+    path TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    PRIMARY KEY (path, recorded_at)
+) WITHOUT ROWID;
+"#;
+
+/// On-disk (allocated) size next to the apparent one: a file's blocks, or the recursive total
+/// of a folder. NULL for rows written before this migration until the next scan.
+pub const MIGRATION_8_ALLOCATED_SIZE: &str = r#"
+ALTER TABLE disk_objects ADD COLUMN allocated_size INTEGER;
+"#;
+
+/// Scans that were cancelled and kept with what they had found so far are stored with
+/// `complete = 0`.
+pub const MIGRATION_9_INCOMPLETE_SCANS: &str = r#"
+ALTER TABLE scans ADD COLUMN complete INTEGER NOT NULL DEFAULT 1;
+"#;
+
+/// Progress of an unfinished resumable scan: the folders it has read completely and the
+/// files directly inside them. Cleared when a scan finishes.
+pub const MIGRATION_10_SCAN_CHECKPOINTS: &str = r#"
+CREATE TABLE IF NOT EXISTS scan_checkpoint (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    roots TEXT NOT NULL,
+    started_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS checkpoint_folders (
+    path TEXT NOT NULL PRIMARY KEY,
+    mtime INTEGER,
+    subfolders TEXT NOT NULL
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS checkpoint_files (
+    folder TEXT NOT NULL,
+    path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    allocated_size INTEGER NOT NULL,
+    dev INTEGER NOT NULL,
+    ino INTEGER NOT NULL,
+    mtime INTEGER,
+    PRIMARY KEY (folder, path)
+) WITHOUT ROWID;
+"#;
+
+/// A folder's own modification time (nanoseconds) when an incremental scan last read it.
+/// Folders whose mtime still matches are not read again by the next incremental scan.
+pub const MIGRATION_11_FOLDER_MTIMES: &str = r#"
+ALTER TABLE disk_objects ADD COLUMN dir_mtime INTEGER;
+"#;
+
+/// Entries the last scan could not read (permission denied, vanished, symlink loops), so the
+/// subtrees it skipped can be listed. Replaced by every scan.
+pub const MIGRATION_12_SCAN_ERRORS: &str = r#"
+CREATE TABLE IF NOT EXISTS scan_errors (
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    errno INTEGER,
+    message TEXT NOT NULL
+);
+"#;
+
+/// Symlinks recorded by a scan are stored as `kind = 'symlink'` rows with the path they point
+/// to; `link_broken` marks those whose target is missing. Resumable scans keep them per folder.
+pub const MIGRATION_13_SYMLINKS: &str = r#"
+ALTER TABLE disk_objects ADD COLUMN link_target TEXT;
+ALTER TABLE disk_objects ADD COLUMN link_broken INTEGER;
+ALTER TABLE checkpoint_folders ADD COLUMN symlinks TEXT;
+"#;
+
+/// Owner, group and permission bits of files (Unix only; NULL elsewhere and for folders).
+pub const MIGRATION_14_OWNERSHIP: &str = r#"
+ALTER TABLE disk_objects ADD COLUMN uid INTEGER;
+ALTER TABLE disk_objects ADD COLUMN gid INTEGER;
+ALTER TABLE disk_objects ADD COLUMN mode INTEGER;
+ALTER TABLE checkpoint_files ADD COLUMN uid INTEGER;
+ALTER TABLE checkpoint_files ADD COLUMN gid INTEGER;
+ALTER TABLE checkpoint_files ADD COLUMN mode INTEGER;
+"#;
+
+/// Summaries of collapsed folders (`<folder>/[collapsed]`) get a kind of their own instead of
+/// `file`, so hashing, owners, search and exports leave them alone.
+pub const MIGRATION_15_COLLAPSED_KIND: &str = r#"
+UPDATE disk_objects SET kind = 'collapsed' WHERE kind = 'file' AND name = '[collapsed]';
+UPDATE snapshot_objects SET kind = 'collapsed'
+    WHERE kind = 'file' AND substr(path, -12) IN ('/[collapsed]', '\[collapsed]');
+"#;
+
 pub fn migrations() -> Migrations<'static> {
     Migrations::new(vec![
         M::up(MIGRATION_1_INITIAL_SCHEMA),
         M::up(MIGRATION_2_SCAN_METADATA),
         M::up(MIGRATION_3_REMOVE_ROOT),
         M::up(MIGRATION_4_SEARCH_INDEX),
+        M::up(MIGRATION_5_FILE_HASHES),
+        M::up(MIGRATION_6_SCAN_HISTORY),
+        M::up(MIGRATION_7_SIZE_HISTORY),
+        M::up(MIGRATION_8_ALLOCATED_SIZE),
+        M::up(MIGRATION_9_INCOMPLETE_SCANS),
+        M::up(MIGRATION_10_SCAN_CHECKPOINTS),
+        M::up(MIGRATION_11_FOLDER_MTIMES),
+        M::up(MIGRATION_12_SCAN_ERRORS),
+        M::up(MIGRATION_13_SYMLINKS),
+        M::up(MIGRATION_14_OWNERSHIP),
+        M::up(MIGRATION_15_COLLAPSED_KIND),
     ])
 }
 
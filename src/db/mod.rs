@@ -1,5 +1,16 @@
+mod checkpoints;
 mod db;
+mod findings;
+mod incremental;
 pub mod migrations;
+mod owners;
+mod scan_errors;
+mod snapshots;
 
+pub use checkpoints::*;
 pub use db::*;
-
+pub use findings::*;
+pub use incremental::*;
+pub use owners::*;
+pub use scan_errors::*;
+pub use snapshots::*;
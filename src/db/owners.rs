@@ -0,0 +1,66 @@
+//! Usage of the current scan per owning user or group; see `core::owners`.
+
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::Connection;
+
+use crate::core::owners::{owner_names, OwnerKind, OwnerUsage};
+use crate::{parent_dir, SizeMetric};
+use super::db::file_size_sql;
+
+/// Files and bytes per owner, largest first. Files without a recorded owner (scanned off Unix
+/// or before owners were recorded) are left out.
+pub fn usage_by_owner(conn: &Connection, kind: OwnerKind, metric: SizeMetric) -> rusqlite::Result<Vec<OwnerUsage>> {
+    let column = kind.column();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {column}, COUNT(1), COALESCE(SUM({size}), 0) FROM disk_objects \
+         WHERE kind = 'file' AND {column} IS NOT NULL \
+         GROUP BY {column} ORDER BY 3 DESC, 1",
+        size = file_size_sql(metric),
+    ))?;
+    let names = owner_names(kind);
+    let rows = stmt.query_map([], |row| {
+        let id: u32 = row.get(0)?;
+        Ok(OwnerUsage {
+            id,
+            name: names.get(&id).cloned(),
+            files_count: row.get::<_, i64>(1)? as u64,
+            size: row.get::<_, i64>(2)? as u64,
+        })
+    })?;
+    rows.collect()
+}
+
+/// The `limit` folders holding the most bytes owned by `id`, counting everything below them.
+pub fn largest_folders_of_owner(
+    conn: &Connection,
+    kind: OwnerKind,
+    id: u32,
+    limit: usize,
+    metric: SizeMetric,
+) -> rusqlite::Result<Vec<(String, u64)>> {
+    let folders: HashSet<String> = conn
+        .prepare("SELECT path FROM disk_objects WHERE kind = 'folder'")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT parent_path, {size} FROM disk_objects WHERE kind = 'file' AND {column} = ?1",
+        size = file_size_sql(metric),
+        column = kind.column(),
+    ))?;
+    let mut totals: HashMap<&str, u64> = HashMap::new();
+    let rows = stmt.query_map([id], |row| {
+        Ok((row.get::<_, Option<String>>(0)?.unwrap_or_default(), row.get::<_, Option<i64>>(1)?.unwrap_or(0) as u64))
+    })?;
+    for row in rows {
+        let (mut folder, size) = row?;
+        while let Some(known) = folders.get(&folder) {
+            *totals.entry(known.as_str()).or_insert(0) += size;
+            folder = parent_dir(&folder);
+        }
+    }
+    let mut totals: Vec<(String, u64)> = totals.into_iter().map(|(path, size)| (path.to_string(), size)).collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals.truncate(limit);
+    Ok(totals)
+}
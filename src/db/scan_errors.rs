@@ -0,0 +1,49 @@
+//! The entries the last scan could not read.
+
+use std::path::PathBuf;
+
+use rusqlite::Connection;
+
+use crate::core::scanning::{ScanError, ScanErrorKind};
+
+/// Replace the stored errors with those of the scan that was just written.
+pub fn write_scan_errors(conn: &Connection, errors: &[ScanError]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM scan_errors", [])?;
+    {
+        let mut stmt =
+            tx.prepare("INSERT INTO scan_errors (path, kind, errno, message) VALUES (?1, ?2, ?3, ?4)")?;
+        for error in errors {
+            stmt.execute(rusqlite::params![
+                error.path.to_string_lossy().to_string(),
+                error.kind.as_str(),
+                error.errno,
+                error.message,
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// The stored errors ordered by path, at most `limit` of them.
+pub fn list_scan_errors(conn: &Connection, limit: usize) -> rusqlite::Result<Vec<ScanError>> {
+    let mut stmt = conn.prepare("SELECT path, kind, errno, message FROM scan_errors ORDER BY path LIMIT ?1")?;
+    let rows = stmt.query_map([limit as i64], |row| {
+        Ok(ScanError {
+            path: PathBuf::from(row.get::<_, String>(0)?),
+            kind: ScanErrorKind::parse(&row.get::<_, String>(1)?),
+            errno: row.get(2)?,
+            message: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// How many entries the last scan could not read, per kind.
+pub fn count_scan_errors(conn: &Connection) -> rusqlite::Result<Vec<(ScanErrorKind, u64)>> {
+    let mut stmt = conn.prepare("SELECT kind, COUNT(1) FROM scan_errors GROUP BY kind ORDER BY COUNT(1) DESC")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((ScanErrorKind::parse(&row.get::<_, String>(0)?), row.get::<_, i64>(1)? as u64))
+    })?;
+    rows.collect()
+}
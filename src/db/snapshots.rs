@@ -0,0 +1,394 @@
+//! Scan history: snapshot bookkeeping, retention, read-only access to old scans, and size series.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+
+use crate::core::scanning::exclusions::is_collapsed_entry;
+use crate::core::trend::SizePoint;
+use crate::{parent_dir, FileEntry, FileEntrySer, FileKey, ScanResult};
+
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+type PathSizes = Vec<(String, u64)>;
+
+/// One row of the `scans` table.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct ScanInfo {
+    pub id: i64,
+    pub update_id: i64,
+    /// Unix milliseconds when the scan was written.
+    pub created_at: i64,
+    pub roots: Vec<String>,
+    pub files_count: u64,
+    pub folders_count: u64,
+    pub total_size: u64,
+    /// False for a scan that was cancelled and kept with what it had found by then.
+    pub complete: bool,
+}
+
+/// Which snapshots survive [`apply_retention`]. A snapshot is kept when either rule selects it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Always keep this many of the newest snapshots.
+    pub keep_last: usize,
+    /// Keep the newest snapshot of each of the last `keep_daily` days (UTC), today included.
+    pub keep_daily: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_last: 5,
+            keep_daily: 30,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Ids of the snapshots this policy keeps, given every snapshot and the current time.
+    pub fn retained(&self, scans: &[ScanInfo], now_ms: i64) -> HashSet<i64> {
+        let mut newest_first: Vec<&ScanInfo> = scans.iter().collect();
+        newest_first.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+
+        let mut keep: HashSet<i64> = newest_first.iter().take(self.keep_last).map(|s| s.id).collect();
+
+        let today = now_ms.div_euclid(MS_PER_DAY);
+        let oldest_day = today - i64::from(self.keep_daily) + 1;
+        let mut seen_days = HashSet::new();
+        for scan in &newest_first {
+            let day = scan.created_at.div_euclid(MS_PER_DAY);
+            if day >= oldest_day && day <= today && seen_days.insert(day) {
+                keep.insert(scan.id);
+            }
+        }
+        keep
+    }
+}
+
+/// Store the objects just written to `disk_objects` as a new snapshot. Runs inside `write_scan`.
+pub(super) fn record_snapshot(conn: &Connection, update_id: i64) -> rusqlite::Result<i64> {
+    let roots: Vec<(String, i64)> = conn
+        .prepare(
+            "SELECT d.path, d.recursive_size FROM disk_objects d \
+             WHERE d.kind = 'folder' AND NOT EXISTS ( \
+                 SELECT 1 FROM disk_objects p WHERE p.path = d.parent_path AND p.kind = 'folder') \
+             ORDER BY d.path",
+        )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let total_size: i64 = roots.iter().map(|(_, s)| *s).sum();
+    let root_paths: Vec<String> = roots.into_iter().map(|(p, _)| p).collect();
+    let roots_json = serde_json::to_string(&root_paths)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let (files_count, folders_count): (i64, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(kind = 'file'), 0), COALESCE(SUM(kind = 'folder'), 0) FROM disk_objects",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    conn.execute(
+        "INSERT INTO scans (update_id, created_at, roots, files_count, folders_count, total_size) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![
+            update_id,
+            chrono::Utc::now().timestamp_millis(),
+            roots_json,
+            files_count,
+            folders_count,
+            total_size,
+        ],
+    )?;
+    let scan_id = conn.last_insert_rowid();
+    conn.execute(
+        "INSERT INTO snapshot_objects (scan_id, path, parent_path, kind, size, dev, ino, mtime) \
+         SELECT ?1, path, parent_path, kind, COALESCE(size, recursive_size, 0), dev, ino, mtime \
+         FROM disk_objects",
+        rusqlite::params![scan_id],
+    )?;
+    Ok(scan_id)
+}
+
+/// The snapshot recorded together with the current scan in `disk_objects`, which edits to the
+/// current scan (deletes, dedupe) have to be applied to as well.
+pub(super) fn current_snapshot_id(conn: &Connection) -> rusqlite::Result<Option<i64>> {
+    conn.query_row(
+        "SELECT MAX(s.id) FROM scans s JOIN scan_metadata m ON m.id = 1 \
+         WHERE s.complete = 1 AND s.update_id = m.disk_objects_update_id",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Store the partial result of a cancelled scan as an incomplete snapshot. Unlike
+/// `write_scan` this leaves `disk_objects`, the current scan, as it was.
+pub fn write_partial_scan(
+    conn: &Connection,
+    roots: &[String],
+    files: &[FileEntry],
+    folder_sizes: &HashMap<PathBuf, u64>,
+    update_id: i64,
+) -> rusqlite::Result<i64> {
+    let total_size: u64 = roots
+        .iter()
+        .filter_map(|r| folder_sizes.get(Path::new(r)))
+        .sum();
+    let roots_json = serde_json::to_string(roots)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO scans (update_id, created_at, roots, files_count, folders_count, total_size, complete) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0)",
+        rusqlite::params![
+            update_id,
+            chrono::Utc::now().timestamp_millis(),
+            roots_json,
+            files.iter().filter(|f| !is_collapsed_entry(&f.path)).count() as i64,
+            folder_sizes.len() as i64,
+            total_size as i64,
+        ],
+    )?;
+    let scan_id = tx.last_insert_rowid();
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO snapshot_objects (scan_id, path, parent_path, kind, size, dev, ino, mtime) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for entry in files {
+            let path = entry.path.to_string_lossy().to_string();
+            stmt.execute(rusqlite::params![
+                scan_id,
+                path,
+                parent_dir(&path),
+                super::db::file_kind(&entry.path),
+                entry.size as i64,
+                entry.file_key.dev as i64,
+                entry.file_key.ino as i64,
+                entry.mtime,
+            ])?;
+        }
+        for (folder, size) in folder_sizes {
+            let path = folder.to_string_lossy().to_string();
+            stmt.execute(rusqlite::params![
+                scan_id,
+                path,
+                parent_dir(&path),
+                "folder",
+                *size as i64,
+                None::<i64>,
+                None::<i64>,
+                None::<i64>,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(scan_id)
+}
+
+fn scan_info_from_row(row: &rusqlite::Row) -> rusqlite::Result<ScanInfo> {
+    let roots_json: String = row.get(3)?;
+    Ok(ScanInfo {
+        id: row.get(0)?,
+        update_id: row.get(1)?,
+        created_at: row.get(2)?,
+        roots: serde_json::from_str(&roots_json).unwrap_or_default(),
+        files_count: row.get::<_, i64>(4)? as u64,
+        folders_count: row.get::<_, i64>(5)? as u64,
+        total_size: row.get::<_, i64>(6)? as u64,
+        complete: row.get(7)?,
+    })
+}
+
+const SCAN_INFO_COLUMNS: &str =
+    "id, update_id, created_at, roots, files_count, folders_count, total_size, complete";
+
+/// Every stored snapshot, newest first.
+pub fn list_scans(conn: &Connection) -> rusqlite::Result<Vec<ScanInfo>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scans ORDER BY created_at DESC, id DESC",
+        SCAN_INFO_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], scan_info_from_row)?;
+    rows.collect()
+}
+
+pub fn get_scan_info(conn: &Connection, scan_id: i64) -> rusqlite::Result<Option<ScanInfo>> {
+    conn.query_row(
+        &format!("SELECT {} FROM scans WHERE id = ?1", SCAN_INFO_COLUMNS),
+        rusqlite::params![scan_id],
+        scan_info_from_row,
+    )
+    .optional()
+}
+
+/// Remove one snapshot. Returns false if it did not exist. `disk_objects` is not touched.
+pub fn delete_scan(conn: &Connection, scan_id: i64) -> rusqlite::Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM snapshot_objects WHERE scan_id = ?1", rusqlite::params![scan_id])?;
+    let deleted = tx.execute("DELETE FROM scans WHERE id = ?1", rusqlite::params![scan_id])?;
+    tx.commit()?;
+    Ok(deleted > 0)
+}
+
+/// Delete every snapshot `policy` does not keep. Returns the number of snapshots removed.
+pub fn apply_retention(
+    conn: &Connection,
+    policy: &RetentionPolicy,
+    now_ms: i64,
+) -> rusqlite::Result<usize> {
+    let scans = list_scans(conn)?;
+    let keep = policy.retained(&scans, now_ms);
+    let mut removed = 0;
+    for scan in scans.iter().filter(|s| !keep.contains(&s.id)) {
+        if delete_scan(conn, scan.id)? {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Add one `size_history` point per folder, e.g. for `core::trend::select_tracked_folders`.
+pub fn write_size_history(
+    conn: &Connection,
+    recorded_at: i64,
+    folders: &[(String, u64)],
+) -> rusqlite::Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO size_history (path, recorded_at, size) VALUES (?1, ?2, ?3)",
+        )?;
+        for (path, size) in folders {
+            stmt.execute(rusqlite::params![path, recorded_at, *size as i64])?;
+        }
+    }
+    tx.commit()?;
+    Ok(folders.len())
+}
+
+/// Size of `path` over time, oldest first: `size_history`, every retained complete snapshot,
+/// and the current scan. Points from the same scan share a timestamp and are merged.
+pub fn get_size_series(conn: &Connection, path: &str) -> rusqlite::Result<Vec<SizePoint>> {
+    let mut points: BTreeMap<i64, u64> = BTreeMap::new();
+    let mut add = |sql: &str| -> rusqlite::Result<()> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(rusqlite::params![path], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (timestamp, size) = row?;
+            points.insert(timestamp, size as u64);
+        }
+        Ok(())
+    };
+    add("SELECT recorded_at, size FROM size_history WHERE path = ?1")?;
+    add(
+        "SELECT s.update_id, o.size FROM snapshot_objects o JOIN scans s ON s.id = o.scan_id \
+         WHERE o.path = ?1 AND s.complete = 1",
+    )?;
+    add(
+        "SELECT m.disk_objects_update_id, COALESCE(d.size, d.recursive_size, 0) \
+         FROM disk_objects d, scan_metadata m WHERE d.path = ?1 AND m.id = 1",
+    )?;
+    Ok(points
+        .into_iter()
+        .map(|(timestamp, size)| SizePoint { timestamp, size })
+        .collect())
+}
+
+/// A previous scan opened through its own read-only connection.
+pub struct Snapshot {
+    conn: Connection,
+    info: ScanInfo,
+}
+
+/// Open snapshot `scan_id` of the database at `db_path` read-only. `None` if it does not exist.
+pub fn open_snapshot(db_path: &Path, scan_id: i64) -> rusqlite::Result<Option<Snapshot>> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    Ok(get_scan_info(&conn, scan_id)?.map(|info| Snapshot { conn, info }))
+}
+
+impl Snapshot {
+    pub fn info(&self) -> &ScanInfo {
+        &self.info
+    }
+
+    /// Recursive size of a folder, or the size of a file, in this snapshot.
+    pub fn size_of(&self, path: &str) -> rusqlite::Result<Option<u64>> {
+        self.conn
+            .query_row(
+                "SELECT size FROM snapshot_objects WHERE scan_id = ?1 AND path = ?2",
+                rusqlite::params![self.info.id, path],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map(|s| s.map(|s| s as u64))
+    }
+
+    /// Direct children of `parent_path` as `(folders, files)`, like `get_children_for_path`.
+    pub fn children(&self, parent_path: &str) -> rusqlite::Result<(PathSizes, PathSizes)> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, size, kind FROM snapshot_objects WHERE scan_id = ?1 AND parent_path = ?2",
+        )?;
+        let mut folders = Vec::new();
+        let mut files = Vec::new();
+        let rows = stmt.query_map(rusqlite::params![self.info.id, parent_path], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (path, size, kind) = row?;
+            if kind == "folder" {
+                folders.push((path, size));
+            } else {
+                files.push((path, size));
+            }
+        }
+        Ok((folders, files))
+    }
+
+    /// Load the whole snapshot, e.g. for `build_disk_tree`.
+    pub fn scan_result(&self) -> rusqlite::Result<ScanResult> {
+        let mut file_stmt = self.conn.prepare(
+            "SELECT path, size, dev, ino, mtime FROM snapshot_objects \
+             WHERE scan_id = ?1 AND kind = 'file' ORDER BY path",
+        )?;
+        let files: Vec<FileEntrySer> = file_stmt
+            .query_map(rusqlite::params![self.info.id], |row| {
+                Ok(FileEntrySer {
+                    path: row.get(0)?,
+                    size: row.get::<_, i64>(1)? as u64,
+                    file_key: FileKey {
+                        dev: row.get::<_, Option<i64>>(2)?.unwrap_or(0) as u64,
+                        ino: row.get::<_, Option<i64>>(3)?.unwrap_or(0) as u64,
+                    },
+                    mtime: row.get::<_, Option<i64>>(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut folder_stmt = self.conn.prepare(
+            "SELECT path, size FROM snapshot_objects WHERE scan_id = ?1 AND kind = 'folder'",
+        )?;
+        let folder_sizes: HashMap<String, u64> = folder_stmt
+            .query_map(rusqlite::params![self.info.id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(ScanResult {
+            roots: self.info.roots.clone(),
+            files,
+            folder_sizes,
+        })
+    }
+}
@@ -35,6 +35,11 @@ pub struct FileEntrySer {
 pub enum DiskObjectKind {
     File,
     Folder,
+    /// A symbolic link that was recorded instead of followed; see `DiskObject::link_target`.
+    Symlink,
+    /// The summed-up contents of a folder a collapse rule kept out of the index, stored as
+    /// `<folder>/[collapsed]`. Nothing on disk has its path.
+    Collapsed,
 }
 
 #[derive(Clone, Debug, Serialize, serde::Deserialize)]
@@ -48,9 +53,56 @@ pub struct DiskObject {
     pub kind: DiskObjectKind,
     pub size: Option<u64>,
     pub recursive_size: Option<u64>,
+    /// Bytes actually allocated on disk: the file's blocks, or the sum of those below a folder.
+    #[serde(default)]
+    pub allocated_size: Option<u64>,
     pub dev: Option<u64>,
     pub ino: Option<u64>,
     pub mtime: Option<i64>,
+    /// Where a symlink points, as stored in the link.
+    #[serde(default)]
+    pub link_target: Option<String>,
+    /// Owner, group and permission bits of a file; see [`Ownership`].
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    #[serde(default)]
+    pub mode: Option<u32>,
+}
+
+/// Which size to report: the byte length of files (`ls -l`, the default) or the blocks they
+/// occupy on disk (`du`). The two differ for sparse, compressed and very small files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeMetric {
+    #[default]
+    Apparent,
+    Allocated,
+}
+
+impl DiskObject {
+    /// A file's size or a folder's recursive size by `metric`. Allocated sizes fall back to the
+    /// apparent one where they were not recorded.
+    pub fn size_by(&self, metric: SizeMetric) -> Option<u64> {
+        let apparent = self.size.or(self.recursive_size);
+        match metric {
+            SizeMetric::Apparent => apparent,
+            SizeMetric::Allocated => self.allocated_size.or(apparent),
+        }
+    }
+
+    /// Approximate bytes the object takes in memory: the struct plus its string data.
+    pub fn heap_bytes(&self) -> usize {
+        std::mem::size_of::<DiskObject>()
+            + self.path.len()
+            + self.path_lower.len()
+            + self.name.len()
+            + self.name_lower.len()
+            + self.parent_path.as_ref().map_or(0, |s| s.len())
+            + self.ext.as_ref().map_or(0, |s| s.len())
+            + self.link_target.as_ref().map_or(0, |s| s.len())
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -66,6 +118,9 @@ pub struct ScanSummary {
     pub files_count: u64,
     pub folders_count: u64,
     pub folder_sizes: HashMap<String, u64>,
+    /// Entries the scan could not read; see `db::list_scan_errors`.
+    #[serde(default)]
+    pub errors_count: u64,
 }
 
 #[derive(Clone, Debug, Serialize, serde::Deserialize)]
@@ -77,8 +132,9 @@ pub struct DiskTreeNode {
     pub children: Option<Vec<DiskTreeNode>>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum IndexMode {
+    #[default]
     Full,
     Minimal,
 }
@@ -89,7 +145,7 @@ pub struct IndexStats {
     pub folders: usize,
 }
 
-fn path_separator(path: &str) -> char {
+pub(crate) fn path_separator(path: &str) -> char {
     if path.contains('\\') {
         '\\'
     } else {
@@ -121,7 +177,7 @@ pub fn parent_dir(path: &str) -> String {
     }
 }
 
-fn basename(path: &str) -> String {
+pub(crate) fn basename(path: &str) -> String {
     let sep = path_separator(path);
     let parts: Vec<&str> = path.split(sep).filter(|s| !s.is_empty()).collect();
     parts.last().map(|s| (*s).to_string()).unwrap_or_default()
@@ -312,12 +368,29 @@ pub fn build_disk_tree_from_db(
     max_children_per_node: usize,
     max_depth: usize,
 ) -> Option<DiskTreeNode> {
-    let size = db::get_folder_size(conn, start_path).ok()??;
-    let (folders, files) = db::get_children_for_path(conn, start_path).ok()?;
+    build_disk_tree_from_db_with_metric(
+        conn,
+        start_path,
+        max_children_per_node,
+        max_depth,
+        SizeMetric::Apparent,
+    )
+}
+
+/// Like [`build_disk_tree_from_db`], with every size (and the child order) taken from `metric`.
+pub fn build_disk_tree_from_db_with_metric(
+    conn: &rusqlite::Connection,
+    start_path: &str,
+    max_children_per_node: usize,
+    max_depth: usize,
+    metric: SizeMetric,
+) -> Option<DiskTreeNode> {
+    let size = db::get_folder_size_with_metric(conn, start_path, metric).ok()??;
+    let (folders, files) = db::get_children_for_path_with_metric(conn, start_path, metric).ok()?;
     if folders.is_empty() && files.is_empty() {
         return None;
     }
-    build_node_from_db(conn, start_path, 0, max_depth, max_children_per_node, size)
+    build_node_from_db(conn, start_path, 0, max_depth, max_children_per_node, size, metric)
 }
 
 fn build_node_from_db(
@@ -327,8 +400,9 @@ fn build_node_from_db(
     max_depth: usize,
     max_children: usize,
     size: u64,
+    metric: SizeMetric,
 ) -> Option<DiskTreeNode> {
-    let (folders, files) = db::get_children_for_path(conn, path).ok()?;
+    let (folders, files) = db::get_children_for_path_with_metric(conn, path, metric).ok()?;
     let mut combined: Vec<(String, String, u64, bool)> = folders
         .into_iter()
         .map(|(p, s)| (p.clone(), basename(&p), s, true))
@@ -363,6 +437,7 @@ fn build_node_from_db(
                     max_depth,
                     max_children,
                     child_size,
+                    metric,
                 )
             } else {
                 Some(DiskTreeNode {
@@ -405,15 +480,56 @@ fn build_node_from_db(
     })
 }
 
+/// A symbolic link seen by a scan, with the path stored in it (possibly relative).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, serde::Deserialize)]
+pub struct SymlinkEntry {
+    pub path: PathBuf,
+    pub target: PathBuf,
+    /// The target does not exist or cannot be reached.
+    pub broken: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct FileEntry {
     pub path: PathBuf,
     pub size: u64,
+    /// Bytes allocated on disk (`st_blocks * 512` on Unix, the apparent size elsewhere).
+    pub allocated_size: u64,
     pub file_key: FileKey,
     pub mtime: Option<i64>,
+    /// Owner and permission bits; `None` off Unix.
+    pub owner: Option<Ownership>,
+}
+
+/// Owner, group and permission bits of a file (`st_uid`, `st_gid`, `st_mode`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, serde::Deserialize)]
+pub struct Ownership {
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+}
+
+impl Ownership {
+    /// From the nullable `uid`, `gid` and `mode` columns; `None` unless all three are set.
+    pub fn from_columns(uid: Option<u32>, gid: Option<u32>, mode: Option<u32>) -> Option<Ownership> {
+        Some(Ownership {
+            uid: uid?,
+            gid: gid?,
+            mode: mode?,
+        })
+    }
+}
+
+impl FileEntry {
+    pub fn size_by(&self, metric: SizeMetric) -> u64 {
+        match metric {
+            SizeMetric::Apparent => self.size,
+            SizeMetric::Allocated => self.allocated_size,
+        }
+    }
 }
 
-pub use crate::core::scanning::utils::{file_key_from_path, PROGRESS_INTERVAL};
+pub use crate::core::scanning::utils::{allocated_size, file_key_from_path, PROGRESS_INTERVAL};
 
 /// Returns the filesystem root paths for the current OS.
 /// On Windows, returns every drive letter that currently exists (e.g. `C:\`, `D:\`).
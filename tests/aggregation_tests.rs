@@ -1,12 +1,15 @@
-use cutest_disk_tree::{compute_folder_sizes, FileEntry, FileKey};
+use cutest_disk_tree::core::folder_sizes::sum_folder_sizes;
+use cutest_disk_tree::{compute_folder_sizes, FileEntry, FileKey, SizeMetric};
 use std::path::PathBuf;
 
 fn make_entry(path: &str, size: u64, dev: u64, ino: u64) -> FileEntry {
     FileEntry {
         path: PathBuf::from(path),
         size,
+        allocated_size: size,
         file_key: FileKey { dev, ino },
         mtime: None,
+        owner: None,
     }
 }
 
@@ -60,3 +63,25 @@ fn empty_files_list() {
     let root_size = sizes.get(&root).copied().unwrap_or(0);
     assert_eq!(root_size, 0);
 }
+
+#[test]
+fn sum_folder_sizes_by_allocated_size_across_roots() {
+    let mut files = vec![
+        make_entry("/a/sub/x.bin", 10, 1, 1),
+        make_entry("/a/sub/link.bin", 10, 1, 1),
+        make_entry("/b/y.bin", 5, 1, 2),
+    ];
+    files[0].allocated_size = 4096;
+    files[1].allocated_size = 4096;
+    files[2].allocated_size = 0;
+    let folders: Vec<PathBuf> = ["/a", "/a/sub", "/b"].iter().map(PathBuf::from).collect();
+
+    let sizes = sum_folder_sizes(&folders, &files, SizeMetric::Allocated);
+    assert_eq!(sizes[&PathBuf::from("/a")], 4096);
+    assert_eq!(sizes[&PathBuf::from("/a/sub")], 4096);
+    assert_eq!(sizes[&PathBuf::from("/b")], 0);
+
+    let root = PathBuf::from("/a");
+    let apparent = sum_folder_sizes(&folders, &files, SizeMetric::Apparent);
+    assert_eq!(apparent[&root], compute_folder_sizes(&root, &files[..2])[&root]);
+}
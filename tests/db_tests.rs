@@ -1,4 +1,4 @@
-use cutest_disk_tree::{db, index_directory, to_scan_result};
+use cutest_disk_tree::{db, index_directory, to_scan_result, SizeMetric};
 use std::path::PathBuf;
 
 #[test]
@@ -142,3 +142,655 @@ fn has_disk_objects_empty_and_populated() {
 
     assert!(db::has_disk_objects(&conn).unwrap());
 }
+
+#[test]
+fn file_hashes_survive_rescan_while_files_are_unchanged() {
+    use cutest_disk_tree::core::duplicates::{find_duplicates_with_cache, DuplicateOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(&root_dir).unwrap();
+    std::fs::write(root_dir.join("a.txt"), b"same bytes").unwrap();
+    std::fs::write(root_dir.join("b.txt"), b"same bytes").unwrap();
+
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let (files, folder_sizes) = index_directory(&root_dir);
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+
+    let mut cache = db::read_file_hashes(&conn).unwrap();
+    assert!(cache.is_empty());
+    let files = db::get_file_entries(&conn).unwrap();
+    find_duplicates_with_cache(&files, &DuplicateOptions::default(), &mut cache, |_| {});
+    assert_eq!(db::write_file_hashes(&conn, &mut cache).unwrap(), 2);
+
+    // Rescan without changes: hashes are kept and reused.
+    let (files, folder_sizes) = index_directory(&root_dir);
+    db::write_scan(&conn, &files, &folder_sizes, 2).unwrap();
+    let mut cache = db::read_file_hashes(&conn).unwrap();
+    assert_eq!(cache.len(), 2);
+    let report = find_duplicates_with_cache(
+        &db::get_file_entries(&conn).unwrap(),
+        &DuplicateOptions::default(),
+        &mut cache,
+        |_| {},
+    );
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(report.bytes_hashed, 0);
+
+    // A file that disappears takes its hash row with it.
+    std::fs::remove_file(root_dir.join("b.txt")).unwrap();
+    let (files, folder_sizes) = index_directory(&root_dir);
+    db::write_scan(&conn, &files, &folder_sizes, 3).unwrap();
+    assert_eq!(db::read_file_hashes(&conn).unwrap().len(), 1);
+}
+
+#[test]
+fn root_folders_and_largest_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(root_dir.join("sub")).unwrap();
+    std::fs::write(root_dir.join("small.txt"), b"a").unwrap();
+    std::fs::write(root_dir.join("sub").join("big.txt"), b"aaaaaaaaaa").unwrap();
+
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let (files, folder_sizes) = index_directory(&root_dir);
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+
+    let roots = db::get_root_folders(&conn).unwrap();
+    assert_eq!(roots, vec![(root_dir.to_string_lossy().to_string(), 11)]);
+
+    let files = db::get_largest_files(&conn, 1).unwrap();
+    assert_eq!(files.len(), 1);
+    assert!(files[0].0.ends_with("big.txt"));
+
+    let folders = db::get_largest_folders(&conn, 10).unwrap();
+    assert_eq!(folders.iter().map(|(_, s)| *s).collect::<Vec<_>>(), vec![11, 10]);
+}
+
+#[test]
+fn delete_subtree_updates_ancestor_sizes_and_newest_mtime() {
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(root_dir.join("sub").join("deep")).unwrap();
+    std::fs::create_dir_all(root_dir.join("sub-sibling")).unwrap();
+    std::fs::write(root_dir.join("keep.txt"), b"a").unwrap();
+    std::fs::write(root_dir.join("sub").join("deep").join("x.txt"), b"aaaa").unwrap();
+    std::fs::write(root_dir.join("sub-sibling").join("y.txt"), b"aa").unwrap();
+
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let (files, folder_sizes) = index_directory(&root_dir);
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+
+    let root = root_dir.to_string_lossy().to_string();
+    let sub = root_dir.join("sub").to_string_lossy().to_string();
+    assert!(db::get_newest_mtime(&conn, &sub).unwrap().is_some());
+    assert!(db::get_newest_mtime(&conn, &root).unwrap().is_some());
+
+    assert_eq!(db::delete_subtree(&conn, &sub).unwrap(), 4);
+    assert_eq!(db::get_folder_size(&conn, &root).unwrap(), Some(3));
+    assert_eq!(db::get_newest_mtime(&conn, &sub).unwrap(), None);
+    // `sub-sibling` shares the prefix but is not below `sub`.
+    let sibling = root_dir.join("sub-sibling").to_string_lossy().to_string();
+    assert_eq!(db::get_folder_size(&conn, &sibling).unwrap(), Some(2));
+    assert_eq!(db::delete_subtree(&conn, &sub).unwrap(), 0);
+}
+
+#[test]
+fn delete_subtree_updates_a_filesystem_root() {
+    use cutest_disk_tree::{FileEntry, FileKey};
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = |path: &str, ino: u64, size: u64| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        allocated_size: size,
+        file_key: FileKey { dev: 1, ino },
+        mtime: None,
+        owner: None,
+    };
+    let files = vec![file("/x/a.bin", 1, 40), file("/b.bin", 2, 2)];
+    let folder_sizes = [("/", 42), ("/x", 40)].into_iter().map(|(p, s)| (PathBuf::from(p), s)).collect();
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+
+    assert_eq!(db::delete_subtree(&conn, "/x").unwrap(), 40);
+    assert_eq!(db::get_folder_size(&conn, "/").unwrap(), Some(2));
+    assert_eq!(db::delete_subtree(&conn, "/b.bin").unwrap(), 2);
+    assert_eq!(db::get_folder_size(&conn, "/").unwrap(), Some(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn allocated_sizes_are_stored_and_queryable() {
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(root_dir.join("sub")).unwrap();
+    // A sparse file: large apparent size, (almost) nothing allocated.
+    let sparse = std::fs::File::create(root_dir.join("sparse.img")).unwrap();
+    sparse.set_len(64 * 1024 * 1024).unwrap();
+    std::fs::write(root_dir.join("sub").join("data.bin"), vec![1u8; 100]).unwrap();
+
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let (files, folder_sizes) = index_directory(&root_dir);
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+
+    let blocks = |p: PathBuf| cutest_disk_tree::allocated_size(&std::fs::metadata(p).unwrap());
+    let sparse_allocated = blocks(root_dir.join("sparse.img"));
+    let data_allocated = blocks(root_dir.join("sub").join("data.bin"));
+    let root = root_dir.to_string_lossy().to_string();
+    assert_eq!(db::get_folder_size(&conn, &root).unwrap(), Some(64 * 1024 * 1024 + 100));
+    assert_eq!(
+        db::get_folder_size_with_metric(&conn, &root, SizeMetric::Allocated).unwrap(),
+        Some(sparse_allocated + data_allocated)
+    );
+
+    let largest = db::get_largest_files_with_metric(&conn, 1, SizeMetric::Allocated).unwrap();
+    let expected = if data_allocated >= sparse_allocated { "data.bin" } else { "sparse.img" };
+    assert!(largest[0].0.ends_with(expected));
+    let entries = db::get_file_entries(&conn).unwrap();
+    assert_eq!(entries.iter().map(|e| e.allocated_size).sum::<u64>(), sparse_allocated + data_allocated);
+
+    let sub = root_dir.join("sub").to_string_lossy().to_string();
+    db::delete_subtree(&conn, &sub).unwrap();
+    assert_eq!(
+        db::get_folder_size_with_metric(&conn, &root, SizeMetric::Allocated).unwrap(),
+        Some(sparse_allocated)
+    );
+}
+
+#[test]
+fn checkpoints_round_trip_and_resume_a_scan() {
+    use cutest_disk_tree::core::scanning::checkpoint::scan_resumable;
+    use cutest_disk_tree::core::scanning::{ScanCollector, ScanOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("data");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::write(root.join("a.txt"), b"aaaa").unwrap();
+    std::fs::write(root.join("sub").join("b.txt"), b"bb").unwrap();
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    assert!(db::load_checkpoint(&conn).unwrap().is_none());
+
+    let roots = vec![root.clone()];
+    db::start_checkpoint(&conn, &roots).unwrap();
+    let mut collector = ScanCollector::new(|_| {});
+    scan_resumable(&roots, &ScanOptions::default(), None, &mut collector, &mut |folders| {
+        db::save_checkpoint(&conn, folders).unwrap()
+    });
+
+    let checkpoint = db::load_checkpoint(&conn).unwrap().unwrap();
+    assert_eq!(checkpoint.roots, roots);
+    assert_eq!(checkpoint.folders.len(), 2);
+    let sub = checkpoint.folders.iter().find(|f| f.path == root.join("sub")).unwrap();
+    assert!(sub.mtime.is_some());
+    assert_eq!(sub.files.len(), 1);
+    assert_eq!(sub.files[0].size, 2);
+    let top = checkpoint.folders.iter().find(|f| f.path == root).unwrap();
+    assert_eq!(top.subfolders, vec![root.join("sub")]);
+
+    // Nothing changed, so the resumed scan reads no folder again.
+    let mut resumed = ScanCollector::new(|_| {});
+    let mut saved = 0;
+    scan_resumable(&roots, &ScanOptions::default(), Some(&checkpoint), &mut resumed, &mut |f| saved += f.len());
+    assert_eq!(saved, 0);
+    assert_eq!(resumed.files.len(), collector.files.len());
+    assert_eq!(resumed.folders, collector.folders);
+
+    db::clear_checkpoint(&conn).unwrap();
+    assert!(db::load_checkpoint(&conn).unwrap().is_none());
+}
+
+#[test]
+fn incremental_scans_reread_only_changed_folders() {
+    use cutest_disk_tree::core::scanning::checkpoint::scan_resumable;
+    use cutest_disk_tree::core::scanning::{ScanCollector, ScanOptions};
+
+    fn rescan(conn: &rusqlite::Connection, root: &std::path::Path, update_id: i64) -> Vec<PathBuf> {
+        let previous = db::current_scan_checkpoint(conn).unwrap();
+        let mut collector = ScanCollector::new(|_| {});
+        let mut changed = Vec::new();
+        scan_resumable(&[root.to_path_buf()], &ScanOptions::default(), Some(&previous), &mut collector, &mut |f| {
+            changed.extend_from_slice(f)
+        });
+        db::write_incremental_scan(conn, &changed, &collector.folders, update_id).unwrap();
+        let mut paths: Vec<PathBuf> = changed.into_iter().map(|f| f.path).collect();
+        paths.sort();
+        paths
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("data");
+    for sub in ["keep", "gone", "grow"] {
+        std::fs::create_dir_all(root.join(sub)).unwrap();
+        std::fs::write(root.join(sub).join("f.txt"), b"1234").unwrap();
+    }
+    std::fs::write(root.join("top.txt"), b"12").unwrap();
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let size = |path: PathBuf| db::get_folder_size(&conn, &path.to_string_lossy()).unwrap();
+
+    // With nothing stored, every folder is read.
+    assert_eq!(rescan(&conn, &root, 1).len(), 4);
+    assert_eq!(size(root.clone()), Some(14));
+    assert_eq!(db::list_scans(&conn).unwrap().len(), 1);
+
+    std::fs::remove_dir_all(root.join("gone")).unwrap();
+    std::fs::write(root.join("grow").join("more.txt"), b"123456").unwrap();
+    // Rewriting a file in place leaves its folder's mtime alone, so it is not noticed.
+    std::fs::write(root.join("keep").join("f.txt"), b"12345678").unwrap();
+
+    assert_eq!(rescan(&conn, &root, 2), vec![root.clone(), root.join("grow")]);
+    assert_eq!(size(root.join("gone")), None);
+    assert_eq!(size(root.join("grow")), Some(10));
+    assert_eq!(size(root.join("keep")), Some(4));
+    assert_eq!(size(root.clone()), Some(16));
+    assert_eq!(db::get_file_entries(&conn).unwrap().len(), 4);
+
+    // Nothing changed since: no folder is read again.
+    assert!(rescan(&conn, &root, 3).is_empty());
+    assert_eq!(size(root.clone()), Some(16));
+    assert_eq!(db::list_scans(&conn).unwrap().len(), 3);
+}
+
+#[test]
+fn scan_errors_are_stored_counted_and_replaced_by_the_next_scan() {
+    use cutest_disk_tree::core::scanning::{ScanError, ScanErrorKind};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("data");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.txt"), b"aaaa").unwrap();
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let (files, folder_sizes) = index_directory(&root);
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+
+    let denied = std::io::Error::from_raw_os_error(13);
+    let errors = vec![
+        ScanError::io(&root.join("z-locked"), &denied),
+        ScanError::io(&root.join("b-locked"), &denied),
+        ScanError::io(&root.join("gone"), &std::io::Error::from(std::io::ErrorKind::NotFound)),
+    ];
+    db::write_scan_errors(&conn, &errors).unwrap();
+
+    let listed = db::list_scan_errors(&conn, 2).unwrap();
+    assert_eq!(listed, vec![errors[1].clone(), errors[2].clone()]);
+    assert_eq!(listed[0].kind, ScanErrorKind::PermissionDenied);
+    assert_eq!(listed[0].errno, Some(13));
+    assert_eq!(listed[1].errno, None);
+    assert_eq!(
+        db::count_scan_errors(&conn).unwrap(),
+        vec![(ScanErrorKind::PermissionDenied, 2), (ScanErrorKind::NotFound, 1)]
+    );
+    assert_eq!(db::get_scan_summary(&conn).unwrap().unwrap().errors_count, 3);
+
+    db::write_scan_errors(&conn, &[]).unwrap();
+    assert!(db::list_scan_errors(&conn, 10).unwrap().is_empty());
+    assert_eq!(db::get_scan_summary(&conn).unwrap().unwrap().errors_count, 0);
+}
+
+#[test]
+fn recorded_symlinks_are_stored_and_broken_ones_become_findings() {
+    use cutest_disk_tree::core::findings::FindingKind;
+    use cutest_disk_tree::core::scanning::checkpoint::{Checkpoint, DoneFolder};
+    use cutest_disk_tree::{DiskObjectKind, SymlinkEntry};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("data");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.txt"), b"aaaa").unwrap();
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let (files, folder_sizes) = index_directory(&root);
+    let links = vec![
+        SymlinkEntry { path: root.join("to-a"), target: PathBuf::from("a.txt"), broken: false },
+        SymlinkEntry { path: root.join("dangling"), target: PathBuf::from("/nowhere"), broken: true },
+    ];
+    db::write_scan_with_symlinks(&conn, &files, &links, &folder_sizes, 1).unwrap();
+
+    let objects = db::get_disk_objects(&conn).unwrap();
+    let link = objects.iter().find(|o| o.path == root.join("to-a").to_string_lossy()).unwrap();
+    assert_eq!(link.kind, DiskObjectKind::Symlink);
+    assert_eq!(link.link_target.as_deref(), Some("a.txt"));
+    assert_eq!(link.size, None);
+    // Links have no size, so the folder total is the file alone.
+    let folder = objects.iter().find(|o| o.path == root.to_string_lossy()).unwrap();
+    assert_eq!(folder.recursive_size, Some(4));
+
+    let findings = db::get_findings(&conn).unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].kind, FindingKind::BrokenSymlink);
+    assert_eq!(findings[0].path, root.join("dangling"));
+    assert!(findings[0].detail.contains("/nowhere"));
+
+    // Checkpoints keep the links of finished folders.
+    db::start_checkpoint(&conn, std::slice::from_ref(&root)).unwrap();
+    let folder = DoneFolder {
+        path: root.clone(),
+        mtime: Some(1),
+        subfolders: Vec::new(),
+        files: Vec::new(),
+        symlinks: links.clone(),
+    };
+    db::save_checkpoint(&conn, &[folder]).unwrap();
+    let Checkpoint { folders, .. } = db::load_checkpoint(&conn).unwrap().unwrap();
+    assert_eq!(folders[0].symlinks, links);
+}
+
+#[test]
+fn collapsed_summaries_keep_folder_sizes_but_are_not_files() {
+    use cutest_disk_tree::core::indexing::sqlite::SqliteBackend;
+    use cutest_disk_tree::core::scanning::exclusions::{ExclusionRule, ExclusionRules, RuleAction, COLLAPSED_ENTRY_NAME};
+    use cutest_disk_tree::core::scanning::{scan_roots, scanner_by_name, ScanOptions};
+    use cutest_disk_tree::core::search::{SearchBackend, SearchRequest};
+    use cutest_disk_tree::compute_folder_sizes;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("data");
+    std::fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+    std::fs::write(root.join("node_modules/pkg/index.js"), vec![0u8; 300]).unwrap();
+    std::fs::write(root.join("app.js"), vec![0u8; 20]).unwrap();
+    let options = ScanOptions {
+        exclusions: std::sync::Arc::new(
+            ExclusionRules::new(vec![ExclusionRule::glob("node_modules", RuleAction::Collapse)]).unwrap(),
+        ),
+        ..ScanOptions::default()
+    };
+    let scanner = scanner_by_name("walkdir").unwrap();
+    let (files, _, _) = scan_roots(scanner.as_ref(), std::slice::from_ref(&root), &options, |_| {});
+    let folder_sizes = compute_folder_sizes(&root, &files);
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+
+    let summary = root.join("node_modules").join(COLLAPSED_ENTRY_NAME).to_string_lossy().into_owned();
+    assert_eq!(db::get_folder_size(&conn, &root.to_string_lossy()).unwrap(), Some(320));
+    let (_, children) = db::get_children_for_path(&conn, &root.join("node_modules").to_string_lossy()).unwrap();
+    assert_eq!(children, [(summary.clone(), 300)]);
+
+    // Hashing, search and exports only see the real file.
+    let entries = db::get_file_entries(&conn).unwrap();
+    assert_eq!(entries.iter().map(|f| f.path.clone()).collect::<Vec<_>>(), [root.join("app.js")]);
+    assert!(db::get_disk_objects(&conn).unwrap().iter().all(|o| o.path != summary));
+    let backend = SqliteBackend::new(conn).unwrap();
+    assert!(backend.search(&SearchRequest::parse("collapsed", None, None).unwrap()).unwrap().items.is_empty());
+}
+
+#[test]
+fn usage_is_aggregated_per_owner_and_risky_modes_become_findings() {
+    use cutest_disk_tree::core::findings::FindingKind;
+    use cutest_disk_tree::core::owners::OwnerKind;
+    use cutest_disk_tree::{FileEntry, FileKey, Ownership};
+
+    let dir = tempfile::tempdir().unwrap();
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let file = |path: &str, ino: u64, size: u64, uid: u32, gid: u32, mode: u32| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        allocated_size: size,
+        file_key: FileKey { dev: 1, ino },
+        mtime: None,
+        owner: Some(Ownership { uid, gid, mode }),
+    };
+    let files = vec![
+        file("/r/a/one", 1, 100, 1000, 100, 0o100644),
+        file("/r/a/b/two", 2, 50, 1000, 100, 0o100666),
+        file("/r/c/three", 3, 70, 0, 100, 0o104755),
+        FileEntry { owner: None, ..file("/r/c/unknown", 4, 5, 0, 0, 0) },
+    ];
+    let folder_sizes = [("/r", 225), ("/r/a", 150), ("/r/a/b", 50), ("/r/c", 75)]
+        .into_iter()
+        .map(|(p, s)| (PathBuf::from(p), s))
+        .collect();
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+
+    let users = db::usage_by_owner(&conn, OwnerKind::User, SizeMetric::Apparent).unwrap();
+    let users: Vec<(u32, u64, u64)> = users.iter().map(|u| (u.id, u.files_count, u.size)).collect();
+    assert_eq!(users, vec![(1000, 2, 150), (0, 1, 70)]);
+    let groups = db::usage_by_owner(&conn, OwnerKind::Group, SizeMetric::Apparent).unwrap();
+    assert_eq!((groups.len(), groups[0].id, groups[0].size), (1, 100, 220));
+
+    let folders = db::largest_folders_of_owner(&conn, OwnerKind::User, 1000, 2, SizeMetric::Apparent).unwrap();
+    assert_eq!(folders, vec![("/r".to_string(), 150), ("/r/a".to_string(), 150)]);
+    let folders = db::largest_folders_of_owner(&conn, OwnerKind::User, 0, 10, SizeMetric::Apparent).unwrap();
+    assert_eq!(folders, vec![("/r".to_string(), 70), ("/r/c".to_string(), 70)]);
+
+    let stored = db::get_file_entries(&conn).unwrap();
+    let two = stored.iter().find(|f| f.path == std::path::Path::new("/r/a/b/two")).unwrap();
+    assert_eq!(two.owner, files[1].owner);
+
+    let findings: Vec<(FindingKind, PathBuf)> =
+        db::get_findings(&conn).unwrap().into_iter().map(|f| (f.kind, f.path)).collect();
+    assert_eq!(
+        findings,
+        vec![
+            (FindingKind::Setuid, PathBuf::from("/r/c/three")),
+            (FindingKind::WorldWritable, PathBuf::from("/r/a/b/two")),
+        ]
+    );
+}
+
+#[test]
+fn structured_queries_match_the_same_entries_in_every_backend() {
+    use cutest_disk_tree::core::indexing::{compressed_text_index as cti, ngram, sqlite, suffix};
+    use cutest_disk_tree::core::query::Query;
+    use cutest_disk_tree::{FileEntry, FileKey};
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 24 * 60 * 60;
+    let dir = tempfile::tempdir().unwrap();
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    let file = |path: &str, ino: u64, size: u64, age_days: i64| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        allocated_size: size,
+        file_key: FileKey { dev: 1, ino },
+        mtime: Some(NOW - age_days * DAY),
+        owner: None,
+    };
+    let gib = 1024 * 1024 * 1024;
+    let files = vec![
+        file("/r/Downloads/Old Movie.mkv", 1, 3 * gib, 400),
+        file("/r/Downloads/clip.mp4", 2, 3 * gib, 1),
+        file("/r/Downloads/notes.txt", 3, 10, 400),
+        file("/r/Downloads2/movie.log", 4, 20, 2),
+        file("/r/Music/movie theme.mp3", 5, 5_000_000, 30),
+    ];
+    let folder_sizes = [("/r", 6 * gib + 5_000_030), ("/r/Downloads", 6 * gib + 10), ("/r/Downloads2", 20), ("/r/Music", 5_000_000)]
+        .into_iter()
+        .map(|(p, s)| (PathBuf::from(p), s))
+        .collect();
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+    let objects = db::get_disk_objects(&conn).unwrap();
+    let trigram = ngram::build_index(&objects);
+    let suffix_index = suffix::build_index(&objects);
+    let cti_path = dir.path().join("index.compressed-text-index.lz4");
+    cti::write_compressed_text_index(&cti_path, &files, &folder_sizes).unwrap();
+    let in_memory = cti::build_in_memory_index(&cti_path).unwrap();
+
+    let cases: &[(&str, &[&str])] = &[
+        ("category:video size:>2GB modified:>1y path:/r/downloads", &["/r/Downloads/Old Movie.mkv"]),
+        ("movie -ext:mp3", &["/r/Downloads/Old Movie.mkv", "/r/Downloads2/movie.log"]),
+        ("path:/r/Downloads kind:file modified:<1w", &["/r/Downloads/clip.mp4"]),
+        ("(ext:txt OR ext:log) size:<1k", &["/r/Downloads/notes.txt", "/r/Downloads2/movie.log"]),
+        ("kind:folder size:>1g", &["/r", "/r/Downloads"]),
+        ("\"movie theme\" OR category:other", &["/r/Downloads2/movie.log", "/r/Music/movie theme.mp3"]),
+        ("mus -kind:file", &["/r/Music"]),
+    ];
+    for (text, expected) in cases {
+        let query = Query::parse_at(text, NOW).unwrap();
+        let sorted = |mut paths: Vec<String>| {
+            paths.sort();
+            paths
+        };
+        let expected: Vec<String> = expected.iter().map(|p| p.to_string()).collect();
+        let paths = |objs: Vec<cutest_disk_tree::DiskObject>| sorted(objs.into_iter().map(|o| o.path).collect());
+
+        let (found, _) = sqlite::find_files_by_query(&conn, &query, 100, 0).unwrap();
+        assert_eq!(paths(found), expected, "sqlite: {}", text);
+        let (found, _) = ngram::find_files_by_query(&trigram, &query, 100, 0);
+        let found = found.into_iter().map(|i| trigram.objects[i as usize].path.clone()).collect();
+        assert_eq!(sorted(found), expected, "ngram: {}", text);
+        let (found, _) = suffix::find_files_by_query(&suffix_index, &objects, &query, 100, 0);
+        let found = found.into_iter().map(|i| objects[i].path.clone()).collect();
+        assert_eq!(sorted(found), expected, "suffix: {}", text);
+        let (found, _) = cti::find_files_by_query(&cti_path, &query, 100, 0).unwrap();
+        assert_eq!(paths(found), expected, "compressed text: {}", text);
+        let (found, _) = cti::find_files_in_memory_by_query(&in_memory, &query, 100, 0).unwrap();
+        assert_eq!(paths(found), expected, "compressed text in memory: {}", text);
+    }
+
+    let query = Query::parse_at("path:/r", NOW).unwrap();
+    let (page, has_more) = sqlite::find_files_by_query(&conn, &query, 3, 6).unwrap();
+    assert_eq!((page.len(), has_more), (3, false));
+    let (page, has_more) = ngram::find_files_by_query(&trigram, &query, 3, 0);
+    assert_eq!((page.len(), has_more), (3, true));
+}
+
+#[test]
+fn regexp_treats_null_as_no_match() {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    cutest_disk_tree::core::indexing::sqlite::add_regexp_function(&conn).unwrap();
+    let matches = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, bool>(0)).unwrap();
+    assert!(matches("SELECT regexp('^no', 'notes.txt')"));
+    assert!(!matches("SELECT regexp('^no', 'photo.jpg')"));
+    assert!(!matches("SELECT regexp('^no', NULL)"));
+}
+
+#[test]
+fn search_requests_behave_the_same_on_every_backend() {
+    use cutest_disk_tree::core::indexing::{compressed_text_index as cti, ngram, sqlite, suffix};
+    use cutest_disk_tree::core::search::{SearchBackend, SearchMode, SearchRequest, SortDirection, SortKey, SortOrder};
+    use cutest_disk_tree::{DiskObject, FileEntry, FileKey};
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = |path: &str, ino: u64, size: u64| FileEntry {
+        path: PathBuf::from(path),
+        size,
+        allocated_size: size,
+        file_key: FileKey { dev: 1, ino },
+        mtime: Some(1_700_000_000),
+        owner: None,
+    };
+    let files = vec![
+        file("/r/notes.txt", 1, 10),
+        file("/r/notes-old.txt", 2, 20),
+        file("/r/no_tes.md", 3, 30),
+        file("/r/sub/Notebook.pdf", 4, 4000),
+        file("/r/sub/photo.jpg", 5, 50),
+    ];
+    let folder_sizes = [("/r", 4110), ("/r/sub", 4050)]
+        .into_iter()
+        .map(|(p, s)| (PathBuf::from(p), s))
+        .collect();
+    let conn = db::open_db(&dir.path().join("test.db")).unwrap();
+    db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+    let objects = db::get_disk_objects(&conn).unwrap();
+
+    let mut backends: Vec<(&str, Box<dyn SearchBackend>)> = vec![
+        ("sqlite", Box::new(sqlite::SqliteBackend::new(conn).unwrap())),
+        ("ngram", Box::new(ngram::build_index(&[]))),
+        ("suffix", Box::new(suffix::SuffixSearch::new(Arc::new(Vec::new()), Arc::new(suffix::build_index(&[]))))),
+        ("compressed text", Box::new(cti::CompressedTextBackend::new(dir.path().join("index.lz4")))),
+    ];
+    for (_, backend) in backends.iter_mut() {
+        backend.build(&objects).unwrap();
+    }
+
+    let sorted_paths = |objs: &[DiskObject]| {
+        let mut paths: Vec<String> = objs.iter().map(|o| o.path.clone()).collect();
+        paths.sort();
+        paths
+    };
+    let every_page = |backend: &dyn SearchBackend, request: &SearchRequest| {
+        let mut request = SearchRequest { limit: 2, ..request.clone() };
+        let mut items = Vec::new();
+        loop {
+            let results = backend.search(&request).unwrap();
+            items.extend(results.items);
+            match results.next_cursor {
+                Some(next) => request.cursor = Some(next),
+                None => return items,
+            }
+        }
+    };
+    // (query, extensions, category, expected paths)
+    type Case<'a> = (&'a str, Option<&'a str>, Option<&'a str>, &'a [&'a str]);
+    let cases: &[Case] = &[
+        ("notes", None, None, &["/r/notes-old.txt", "/r/notes.txt"]),
+        ("NOTE", Some("pdf,txt"), None, &["/r/notes-old.txt", "/r/notes.txt", "/r/sub/Notebook.pdf"]),
+        ("", None, Some("folder"), &["/r", "/r/sub"]),
+        ("size:>1k -kind:folder", None, None, &["/r/sub/Notebook.pdf"]),
+        ("path:/r/sub", None, Some("image"), &["/r/sub/photo.jpg"]),
+    ];
+    for (text, extensions, category, expected) in cases {
+        let request = SearchRequest::parse(text, *extensions, *category).unwrap();
+        for (name, backend) in &backends {
+            let items = every_page(backend.as_ref(), &request);
+            assert_eq!(sorted_paths(&items), *expected, "{}: {:?}", name, text);
+        }
+    }
+
+    let fuzzy = SearchRequest { mode: SearchMode::Fuzzy, limit: 3, ..SearchRequest::parse("notes ext:txt,md", None, None).unwrap() };
+    let ranked = |backend: &dyn SearchBackend| {
+        let results = backend.search(&fuzzy).unwrap();
+        assert_eq!(results.next_cursor, None);
+        results.items.into_iter().map(|o| o.path).collect::<Vec<_>>()
+    };
+    let expected = ranked(backends[0].1.as_ref());
+    let mut found = expected.clone();
+    found.sort();
+    assert_eq!(found, ["/r/no_tes.md", "/r/notes-old.txt", "/r/notes.txt"]);
+    for (name, backend) in &backends {
+        assert_eq!(ranked(backend.as_ref()), expected, "{}: fuzzy", name);
+    }
+
+    let patterns = [
+        (SearchMode::Glob, "*.TXT", &["/r/notes-old.txt", "/r/notes.txt"][..]),
+        (SearchMode::Regex, r"^no\w+\.", &["/r/no_tes.md", "/r/notes.txt", "/r/sub/Notebook.pdf"][..]),
+    ];
+    for (mode, text, expected) in patterns {
+        let request = SearchRequest::parse_as(mode, text, None, None).unwrap();
+        for (name, backend) in &backends {
+            let items = every_page(backend.as_ref(), &request);
+            assert_eq!(sorted_paths(&items), expected, "{}: {:?}", name, text);
+        }
+    }
+
+    // SQLite sorts and seeks in SQL; the others keep the top of every match.
+    let sorts = [
+        (SortKey::Size, None),
+        (SortKey::Size, Some(SortDirection::Ascending)),
+        (SortKey::Modified, None),
+        (SortKey::Name, Some(SortDirection::Descending)),
+        (SortKey::Relevance, None),
+    ];
+    for (key, direction) in sorts {
+        let request = SearchRequest { sort: SortOrder::new(key, direction), ..SearchRequest::parse("", None, None).unwrap() };
+        let ordered = |backend: &dyn SearchBackend| every_page(backend, &request).into_iter().map(|o| o.path).collect::<Vec<_>>();
+        let expected = ordered(backends[0].1.as_ref());
+        assert_eq!(expected.len(), objects.len());
+        for (name, backend) in &backends {
+            assert_eq!(ordered(backend.as_ref()), expected, "{}: {:?} {:?}", name, key, direction);
+        }
+        if (key, direction) == (SortKey::Size, None) {
+            assert_eq!(expected[..4], ["/r", "/r/sub", "/r/sub/Notebook.pdf", "/r/sub/photo.jpg"]);
+        }
+    }
+
+    let path = SearchRequest { mode: SearchMode::Path, ..SearchRequest::parse("r/sub", None, None).unwrap() };
+    for (name, backend) in &backends {
+        let items: Vec<String> = every_page(backend.as_ref(), &path).into_iter().map(|o| o.path).collect();
+        assert_eq!(items, ["/r/sub", "/r/sub/Notebook.pdf", "/r/sub/photo.jpg"], "{}: path", name);
+    }
+
+    // The database is written by `db`, so only the indexes built from objects take updates.
+    let mut added = objects.iter().find(|o| o.path == "/r/sub/photo.jpg").unwrap().clone();
+    added.path = "/r/sub/notes.jpg".to_string();
+    added.path_lower = added.path.clone();
+    added.name = "notes.jpg".to_string();
+    added.name_lower = added.name.clone();
+    let request = SearchRequest::parse("notes", None, None).unwrap();
+    for (name, backend) in backends.iter_mut().skip(1) {
+        backend.update(&["/r/notes-old.txt".to_string()], &[added.clone()]).unwrap();
+        let items = every_page(backend.as_ref(), &request);
+        assert_eq!(sorted_paths(&items), ["/r/notes.txt", "/r/sub/notes.jpg"], "{} after update", name);
+    }
+}
@@ -25,6 +25,10 @@ fn fresh_db_has_expected_tables_after_all_migrations() {
     assert!(tables.contains(&"cached_trees".to_string()));
     assert!(tables.contains(&"scan_metadata".to_string()));
     assert!(tables.contains(&"suffix_index_data".to_string()));
+    assert!(tables.contains(&"file_hashes".to_string()));
+    assert!(tables.contains(&"scans".to_string()));
+    assert!(tables.contains(&"snapshot_objects".to_string()));
+    assert!(tables.contains(&"size_history".to_string()));
     assert!(!tables.contains(&"file_search_trigrams".to_string()), "trigrams table should be dropped");
 }
 
@@ -44,6 +48,7 @@ fn disk_objects_has_path_as_primary_key() {
 
     assert!(!sql.contains("root TEXT"), "root column should not exist after migration 3");
     assert!(sql.contains("path TEXT NOT NULL PRIMARY KEY"));
+    assert!(sql.contains("allocated_size INTEGER"), "allocated_size added by migration 8");
 }
 
 #[test]
@@ -77,3 +77,310 @@ fn ignore_walker_finds_same_files() {
     assert_eq!(files.len(), 2);
     assert!(folders.len() >= 1);
 }
+
+#[test]
+fn one_file_system_keeps_everything_on_the_root_device() {
+    use cutest_disk_tree::core::scanning::{ignore_scanner, jwalk, lolcate, walkdir, ScanOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("sub").join("deeper")).unwrap();
+    std::fs::write(dir.path().join("a.txt"), b"aa").unwrap();
+    std::fs::write(dir.path().join("sub").join("deeper").join("b.txt"), b"bbb").unwrap();
+    let options = ScanOptions {
+        one_file_system: true,
+        ..ScanOptions::default()
+    };
+
+    let (files, _) = walkdir::index_directory_with_options(dir.path(), &options, |_| {});
+    assert_eq!(files.len(), 2);
+    let (files, _) = jwalk::index_directory_parallel_with_options(dir.path(), &options, |_| {});
+    assert_eq!(files.len(), 2);
+    let (files, _) = ignore_scanner::index_directory_ignore_with_options(dir.path(), &options, |_| {});
+    assert_eq!(files.len(), 2);
+    let (files, folder_sizes) = lolcate::index_directory_lolcate_with_options(dir.path(), &options);
+    assert_eq!(files.len(), 2);
+    assert_eq!(folder_sizes.get(dir.path()).copied(), Some(5));
+}
+
+#[test]
+fn exclusion_rules_apply_to_every_scanner() {
+    use std::sync::Arc;
+
+    use cutest_disk_tree::compute_folder_sizes;
+    use cutest_disk_tree::core::scanning::exclusions::{ExclusionRule, ExclusionRules, RuleAction};
+    use cutest_disk_tree::core::scanning::{ignore_scanner, jwalk, lolcate, walkdir, ScanOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("app/node_modules/pkg/lib")).unwrap();
+    std::fs::create_dir_all(root.join("build")).unwrap();
+    std::fs::write(root.join("app/main.js"), b"main").unwrap();
+    std::fs::write(root.join("app/node_modules/pkg/index.js"), vec![0u8; 100]).unwrap();
+    std::fs::write(root.join("app/node_modules/pkg/lib/util.js"), vec![0u8; 50]).unwrap();
+    std::fs::write(root.join("build/out.o"), vec![0u8; 1000]).unwrap();
+    std::fs::write(root.join("disk.iso"), vec![0u8; 7]).unwrap();
+
+    let mut rules = ExclusionRules::recommended().rules().to_vec();
+    rules.push(ExclusionRule::glob("*.iso", RuleAction::Exclude));
+    rules.push(ExclusionRule::prefix(&root.join("build").to_string_lossy(), RuleAction::Exclude));
+    let options = ScanOptions {
+        exclusions: Arc::new(ExclusionRules::new(rules).unwrap()),
+        ..ScanOptions::default()
+    };
+    let deps = root.join("app/node_modules");
+    let check = |files: &[cutest_disk_tree::FileEntry], sizes: &std::collections::HashMap<std::path::PathBuf, u64>| {
+        let mut paths: Vec<_> = files.iter().map(|f| f.path.strip_prefix(root).unwrap().to_path_buf()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [std::path::PathBuf::from("app/main.js"), std::path::PathBuf::from("app/node_modules/[collapsed]")]
+        );
+        assert_eq!(sizes.get(&deps).copied(), Some(150));
+        assert_eq!(sizes.get(root).copied(), Some(154));
+        assert!(!sizes.contains_key(&deps.join("pkg")));
+        assert!(!sizes.contains_key(&root.join("build")));
+    };
+
+    let (files, sizes) = walkdir::index_directory_with_options(root, &options, |_| {});
+    check(&files, &sizes);
+    let (files, sizes) = jwalk::index_directory_parallel_with_options(root, &options, |_| {});
+    check(&files, &sizes);
+    let (files, sizes) = lolcate::index_directory_lolcate_with_options(root, &options);
+    check(&files, &sizes);
+    let (files, folders) = ignore_scanner::index_directory_ignore_with_options(root, &options, |_| {});
+    check(&files, &compute_folder_sizes(root, &files));
+    assert!(folders.contains(&deps) && !folders.contains(&deps.join("pkg")));
+}
+
+#[test]
+fn every_named_scanner_streams_the_same_entries() {
+    use cutest_disk_tree::core::scanning::{scanner_by_name, ScanItem, ScanOptions, ScanSink, SCANNER_NAMES};
+    use cutest_disk_tree::IndexMode;
+
+    struct Recorder(Vec<String>, u64);
+    impl ScanSink for Recorder {
+        fn item(&mut self, item: ScanItem) {
+            self.0.push(match item {
+                ScanItem::File(f) => format!("file {}", f.path.display()),
+                ScanItem::FilePath(p) => format!("path {}", p.display()),
+                ScanItem::Folder(p) => format!("dir {}", p.display()),
+                ScanItem::Error(e) => format!("error {}", e.path.display()),
+                ScanItem::Symlink(l) => format!("link {}", l.path.display()),
+            });
+        }
+        fn progress(&mut self, progress: cutest_disk_tree::ScanProgress) {
+            self.1 = progress.files_count;
+        }
+    }
+
+    let a = tempfile::tempdir().unwrap();
+    let b = tempfile::tempdir().unwrap();
+    std::fs::create_dir(a.path().join("sub")).unwrap();
+    std::fs::write(a.path().join("sub").join("x.txt"), b"x").unwrap();
+    std::fs::write(b.path().join("y.txt"), b"yy").unwrap();
+    let roots = [a.path().to_path_buf(), b.path().to_path_buf()];
+
+    let mut expected = vec![
+        format!("dir {}", a.path().display()),
+        format!("dir {}", a.path().join("sub").display()),
+        format!("file {}", a.path().join("sub").join("x.txt").display()),
+        format!("dir {}", b.path().display()),
+        format!("file {}", b.path().join("y.txt").display()),
+    ];
+    expected.sort();
+    for name in SCANNER_NAMES {
+        let scanner = scanner_by_name(name).unwrap();
+        assert_eq!(scanner.name(), name);
+        let mut sink = Recorder(Vec::new(), 0);
+        scanner.scan(&roots, &ScanOptions { threads: 2, ..ScanOptions::default() }, &mut sink);
+        sink.0.sort();
+        assert_eq!(sink.0, expected, "{}", name);
+        assert_eq!(sink.1, 2, "{}", name);
+
+        let minimal = ScanOptions {
+            metadata: IndexMode::Minimal,
+            ..ScanOptions::default()
+        };
+        let mut sink = Recorder(Vec::new(), 0);
+        scanner.scan(&roots, &minimal, &mut sink);
+        assert_eq!(sink.0.iter().filter(|s| s.starts_with("path ")).count(), 2, "{}", name);
+    }
+    assert!(scanner_by_name("nope").is_none());
+}
+
+#[test]
+fn cancelled_scans_stop_early() {
+    use cutest_disk_tree::core::scanning::{scan_roots, scanner_by_name, ScanControl, ScanOptions, SCANNER_NAMES};
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+    let control = ScanControl::new();
+    control.cancel();
+    let options = ScanOptions {
+        control,
+        ..ScanOptions::default()
+    };
+    for name in SCANNER_NAMES {
+        let scanner = scanner_by_name(name).unwrap();
+        let (files, _, _) = scan_roots(scanner.as_ref(), &[dir.path().to_path_buf()], &options, |_| {});
+        assert!(files.is_empty(), "{}", name);
+    }
+}
+
+#[test]
+fn paused_scans_wait_until_resumed_or_cancelled() {
+    use cutest_disk_tree::core::scanning::{scan_roots, scanner_by_name, ScanControl, ScanOptions, SCANNER_NAMES};
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+    let roots = [dir.path().to_path_buf()];
+    for name in SCANNER_NAMES {
+        for cancel in [false, true] {
+            let control = ScanControl::new();
+            control.pause();
+            let options = ScanOptions {
+                control: control.clone(),
+                ..ScanOptions::default()
+            };
+            let scan = std::thread::spawn({
+                let roots = roots.clone();
+                move || {
+                    let scanner = scanner_by_name(name).unwrap();
+                    scan_roots(scanner.as_ref(), &roots, &options, |_| {}).0.len()
+                }
+            });
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!scan.is_finished(), "{}", name);
+            if cancel {
+                control.cancel();
+                assert_eq!(scan.join().unwrap(), 0, "{}", name);
+            } else {
+                control.resume();
+                assert_eq!(scan.join().unwrap(), 1, "{}", name);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_folders_are_reported_as_scan_errors() {
+    use std::os::unix::fs::PermissionsExt;
+
+    use cutest_disk_tree::core::scanning::checkpoint::scan_resumable;
+    use cutest_disk_tree::core::scanning::{
+        scanner_by_name, ScanCollector, ScanError, ScanErrorKind, ScanOptions, SCANNER_NAMES,
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let locked = dir.path().join("locked");
+    std::fs::create_dir(&locked).unwrap();
+    std::fs::write(locked.join("secret.txt"), b"s").unwrap();
+    std::fs::write(dir.path().join("open.txt"), b"o").unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+    // Root reads through permissions, so there is nothing to report.
+    if std::fs::read_dir(&locked).is_ok() {
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        return;
+    }
+
+    let roots = [dir.path().to_path_buf()];
+    let options = ScanOptions::default();
+    let check = |name: &str, files: usize, errors: &[ScanError]| {
+        assert_eq!(files, 1, "{}", name);
+        assert_eq!(errors.len(), 1, "{}", name);
+        let error = &errors[0];
+        assert_eq!(error.path, locked, "{}", name);
+        assert_eq!(error.kind, ScanErrorKind::PermissionDenied, "{}", name);
+        assert_eq!(error.errno, Some(13), "{}", name);
+    };
+    for name in SCANNER_NAMES {
+        let mut collector = ScanCollector::new(|_| {});
+        scanner_by_name(name).unwrap().scan(&roots, &options, &mut collector);
+        check(name, collector.files.len(), &collector.errors);
+    }
+    let mut collector = ScanCollector::new(|_| {});
+    scan_resumable(&roots, &options, None, &mut collector, &mut |_| {});
+    check("resumable", collector.files.len(), &collector.errors);
+
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_skipped_recorded_or_followed_once() {
+    use cutest_disk_tree::core::scanning::checkpoint::scan_resumable;
+    use cutest_disk_tree::core::scanning::{scanner_by_name, ScanCollector, ScanOptions, SymlinkPolicy, SCANNER_NAMES};
+    use std::os::unix::fs::symlink;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("a/b")).unwrap();
+    std::fs::write(root.join("a/f.txt"), b"abc").unwrap();
+    symlink("..", root.join("a/b/up")).unwrap();
+    symlink("missing", root.join("a/broken")).unwrap();
+    symlink("f.txt", root.join("a/flink")).unwrap();
+
+    let roots = [root.clone()];
+    let rel = |p: &std::path::Path| p.strip_prefix(&root).unwrap().to_string_lossy().to_string();
+    let expectations = [
+        (SymlinkPolicy::Skip, vec!["a/f.txt"], vec![]),
+        (SymlinkPolicy::Record, vec!["a/f.txt"], vec![("a/b/up", false), ("a/broken", true), ("a/flink", false)]),
+        // The loop leads back into `a`, which was already entered.
+        (SymlinkPolicy::Follow, vec!["a/f.txt", "a/flink"], vec![("a/b/up", false), ("a/broken", true)]),
+    ];
+    for (policy, files, links) in expectations {
+        let options = ScanOptions {
+            symlinks: policy,
+            ..ScanOptions::default()
+        };
+        let check = |name: &str, collector: ScanCollector<fn(cutest_disk_tree::ScanProgress)>| {
+            let mut found: Vec<String> = collector.files.iter().map(|f| rel(&f.path)).collect();
+            found.sort();
+            let mut recorded: Vec<(String, bool)> = collector.symlinks.iter().map(|l| (rel(&l.path), l.broken)).collect();
+            recorded.sort();
+            let expected: Vec<(String, bool)> = links.iter().map(|&(p, b)| (p.to_string(), b)).collect();
+            assert_eq!(found, files, "{} {:?}", name, policy);
+            assert_eq!(recorded, expected, "{} {:?}", name, policy);
+            assert!(collector.errors.is_empty(), "{} {:?}: {:?}", name, policy, collector.errors);
+        };
+        for name in SCANNER_NAMES {
+            let mut collector: ScanCollector<fn(_)> = ScanCollector::new(|_| {});
+            scanner_by_name(name).unwrap().scan(&roots, &options, &mut collector);
+            check(name, collector);
+        }
+        let mut collector: ScanCollector<fn(_)> = ScanCollector::new(|_| {});
+        scan_resumable(&roots, &options, None, &mut collector, &mut |_| {});
+        check("resumable", collector);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn scanned_files_carry_their_owner_and_mode() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    use cutest_disk_tree::core::scanning::checkpoint::scan_resumable;
+    use cutest_disk_tree::core::scanning::{scanner_by_name, ScanCollector, ScanOptions, SCANNER_NAMES};
+    use cutest_disk_tree::Ownership;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("tool");
+    std::fs::write(&file, b"#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o4751)).unwrap();
+    let meta = std::fs::metadata(&file).unwrap();
+    let expected = Some(Ownership { uid: meta.uid(), gid: meta.gid(), mode: meta.mode() });
+    assert_eq!(meta.mode() & 0o7777, 0o4751);
+
+    let roots = [dir.path().to_path_buf()];
+    let options = ScanOptions::default();
+    for name in SCANNER_NAMES {
+        let mut collector = ScanCollector::new(|_| {});
+        scanner_by_name(name).unwrap().scan(&roots, &options, &mut collector);
+        assert_eq!(collector.files[0].owner, expected, "{}", name);
+    }
+    let mut collector = ScanCollector::new(|_| {});
+    scan_resumable(&roots, &options, None, &mut collector, &mut |_| {});
+    assert_eq!(collector.files[0].owner, expected, "resumable");
+}
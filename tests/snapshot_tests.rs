@@ -0,0 +1,223 @@
+use cutest_disk_tree::db::{self, RetentionPolicy, ScanInfo};
+use cutest_disk_tree::{build_disk_tree, index_directory};
+
+const DAY: i64 = 24 * 60 * 60 * 1000;
+
+fn scan_info(id: i64, created_at: i64) -> ScanInfo {
+    ScanInfo {
+        id,
+        update_id: id,
+        created_at,
+        roots: Vec::new(),
+        files_count: 0,
+        folders_count: 0,
+        total_size: 0,
+        complete: true,
+    }
+}
+
+#[test]
+fn rescans_keep_previous_snapshots_readable() {
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(root_dir.join("sub")).unwrap();
+    std::fs::write(root_dir.join("a.txt"), b"aaaa").unwrap();
+    std::fs::write(root_dir.join("sub").join("b.txt"), b"bb").unwrap();
+    let db_path = dir.path().join("test.db");
+    let conn = db::open_db(&db_path).unwrap();
+
+    let (files, folder_sizes) = index_directory(&root_dir);
+    let first = db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+    std::fs::remove_file(root_dir.join("a.txt")).unwrap();
+    let (files, folder_sizes) = index_directory(&root_dir);
+    let second = db::write_scan(&conn, &files, &folder_sizes, 2).unwrap();
+    assert_ne!(first, second);
+
+    let scans = db::list_scans(&conn).unwrap();
+    assert_eq!(scans.iter().map(|s| s.id).collect::<Vec<_>>(), vec![second, first]);
+    let root = root_dir.to_string_lossy().to_string();
+    assert_eq!(scans[1].roots, vec![root.clone()]);
+    assert_eq!((scans[1].files_count, scans[1].total_size), (2, 6));
+    assert_eq!((scans[0].files_count, scans[0].total_size), (1, 2));
+
+    let old = db::open_snapshot(&db_path, first).unwrap().expect("first snapshot");
+    assert_eq!(old.size_of(&root).unwrap(), Some(6));
+    let (folders, files) = old.children(&root).unwrap();
+    assert_eq!(folders.len(), 1);
+    assert_eq!(files.len(), 1);
+    let tree = build_disk_tree(&old.scan_result().unwrap(), &root, 10, 2).unwrap();
+    assert_eq!(tree.size, 6);
+
+    // The current scan is untouched by reading history.
+    assert_eq!(db::get_folder_size(&conn, &root).unwrap(), Some(2));
+    assert!(db::open_snapshot(&db_path, 999).unwrap().is_none());
+}
+
+#[test]
+fn retention_keeps_newest_and_one_per_day() {
+    let now = 100 * DAY + 12 * 60 * 60 * 1000;
+    let scans = vec![
+        scan_info(1, now - 40 * DAY),
+        scan_info(2, now - 3 * DAY - 1000),
+        scan_info(3, now - 3 * DAY),
+        scan_info(4, now - 2000),
+        scan_info(5, now - 1000),
+    ];
+    let policy = RetentionPolicy {
+        keep_last: 1,
+        keep_daily: 30,
+    };
+    let mut kept: Vec<i64> = policy.retained(&scans, now).into_iter().collect();
+    kept.sort();
+    // Newest per day (3 for day -3, 5 for today); 1 is outside the 30-day window.
+    assert_eq!(kept, vec![3, 5]);
+
+    let policy = RetentionPolicy {
+        keep_last: 2,
+        keep_daily: 0,
+    };
+    let mut kept: Vec<i64> = policy.retained(&scans, now).into_iter().collect();
+    kept.sort();
+    assert_eq!(kept, vec![4, 5]);
+}
+
+#[test]
+fn apply_retention_and_delete_scan_remove_snapshots() {
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(&root_dir).unwrap();
+    std::fs::write(root_dir.join("a.txt"), b"a").unwrap();
+    let db_path = dir.path().join("test.db");
+    let conn = db::open_db(&db_path).unwrap();
+
+    let (files, folder_sizes) = index_directory(&root_dir);
+    let ids: Vec<i64> = (1..=3)
+        .map(|u| db::write_scan(&conn, &files, &folder_sizes, u).unwrap())
+        .collect();
+
+    let policy = RetentionPolicy {
+        keep_last: 2,
+        keep_daily: 0,
+    };
+    let now = chrono::Utc::now().timestamp_millis();
+    assert_eq!(db::apply_retention(&conn, &policy, now).unwrap(), 1);
+    assert!(db::open_snapshot(&db_path, ids[0]).unwrap().is_none());
+
+    assert!(db::delete_scan(&conn, ids[1]).unwrap());
+    assert!(!db::delete_scan(&conn, ids[1]).unwrap());
+    let remaining = db::list_scans(&conn).unwrap();
+    assert_eq!(remaining.iter().map(|s| s.id).collect::<Vec<_>>(), vec![ids[2]]);
+    let leftover: i64 = conn
+        .query_row(
+            "SELECT COUNT(1) FROM snapshot_objects WHERE scan_id != ?1",
+            [ids[2]],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(leftover, 0);
+    assert!(db::has_disk_objects(&conn).unwrap());
+}
+
+#[test]
+fn size_series_merges_history_snapshots_and_current_scan() {
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(&root_dir).unwrap();
+    let db_path = dir.path().join("test.db");
+    let conn = db::open_db(&db_path).unwrap();
+    let root = root_dir.to_string_lossy().to_string();
+
+    // An old point whose snapshot has since been pruned.
+    db::write_size_history(&conn, 100, &[(root.clone(), 1)]).unwrap();
+    for (update_id, len) in [(200, 10usize), (300, 30)] {
+        std::fs::write(root_dir.join("f.bin"), vec![0u8; len]).unwrap();
+        let (files, folder_sizes) = index_directory(&root_dir);
+        db::write_scan(&conn, &files, &folder_sizes, update_id).unwrap();
+        db::write_size_history(&conn, update_id, &[(root.clone(), len as u64)]).unwrap();
+    }
+
+    let series = db::get_size_series(&conn, &root).unwrap();
+    let points: Vec<(i64, u64)> = series.iter().map(|p| (p.timestamp, p.size)).collect();
+    assert_eq!(points, vec![(100, 1), (200, 10), (300, 30)]);
+    assert!(db::get_size_series(&conn, "/nope").unwrap().is_empty());
+}
+
+#[test]
+fn partial_scans_are_kept_as_incomplete_snapshots() {
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(&root_dir).unwrap();
+    std::fs::write(root_dir.join("a.txt"), b"aaaa").unwrap();
+    let db_path = dir.path().join("test.db");
+    let conn = db::open_db(&db_path).unwrap();
+
+    let (files, folder_sizes) = index_directory(&root_dir);
+    let full = db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+    std::fs::write(root_dir.join("b.txt"), b"bb").unwrap();
+    let (files, folder_sizes) = index_directory(&root_dir);
+    let root = root_dir.to_string_lossy().to_string();
+    let partial = db::write_partial_scan(&conn, std::slice::from_ref(&root), &files, &folder_sizes, 2).unwrap();
+
+    let scans = db::list_scans(&conn).unwrap();
+    assert_eq!(scans.iter().map(|s| (s.id, s.complete)).collect::<Vec<_>>(), vec![(partial, false), (full, true)]);
+    assert_eq!(scans[0].roots, vec![root.clone()]);
+    assert_eq!((scans[0].files_count, scans[0].total_size), (2, 6));
+    let snapshot = db::open_snapshot(&db_path, partial).unwrap().unwrap();
+    assert_eq!(snapshot.size_of(&root).unwrap(), Some(6));
+    assert_eq!(snapshot.children(&root).unwrap().1.len(), 2);
+
+    // The current scan and the size series only come from complete scans.
+    assert_eq!(db::get_folder_size(&conn, &root).unwrap(), Some(4));
+    let series = db::get_size_series(&conn, &root).unwrap();
+    assert!(series.iter().all(|p| p.size == 4));
+}
+
+#[test]
+fn deletes_and_new_file_identities_also_edit_the_current_snapshot() {
+    use cutest_disk_tree::FileKey;
+
+    let dir = tempfile::tempdir().unwrap();
+    let root_dir = dir.path().join("data");
+    std::fs::create_dir_all(root_dir.join("sub")).unwrap();
+    std::fs::write(root_dir.join("keep.txt"), b"a").unwrap();
+    std::fs::write(root_dir.join("copy.txt"), b"bb").unwrap();
+    std::fs::write(root_dir.join("sub").join("x.txt"), b"xxxx").unwrap();
+    let db_path = dir.path().join("test.db");
+    let conn = db::open_db(&db_path).unwrap();
+
+    let (files, folder_sizes) = index_directory(&root_dir);
+    let older = db::write_scan(&conn, &files, &folder_sizes, 1).unwrap();
+    let current = db::write_scan(&conn, &files, &folder_sizes, 2).unwrap();
+    let root = root_dir.to_string_lossy().to_string();
+    let partial = db::write_partial_scan(&conn, std::slice::from_ref(&root), &files, &folder_sizes, 3).unwrap();
+
+    let sub = root_dir.join("sub").to_string_lossy().to_string();
+    let copy = root_dir.join("copy.txt").to_string_lossy().to_string();
+    assert_eq!(db::delete_subtree(&conn, &sub).unwrap(), 4);
+    assert!(db::update_file_identity(&conn, &copy, FileKey { dev: 7, ino: 8 }, Some(5)).unwrap());
+
+    let snapshot = db::open_snapshot(&db_path, current).unwrap().unwrap();
+    let snapshot_scan = snapshot.scan_result().unwrap();
+    let current_scan = db::get_scan_result(&conn).unwrap().unwrap();
+    let files = |scan: &cutest_disk_tree::ScanResult| {
+        let mut files: Vec<(String, u64, u64)> =
+            scan.files.iter().map(|f| (f.path.clone(), f.file_key.dev, f.file_key.ino)).collect();
+        files.sort();
+        files
+    };
+    assert_eq!(files(&snapshot_scan), files(&current_scan));
+    assert!(files(&snapshot_scan).contains(&(copy, 7, 8)));
+    assert_eq!(snapshot_scan.folder_sizes, current_scan.folder_sizes);
+    assert_eq!(snapshot.size_of(&root).unwrap(), Some(3));
+
+    // Older and partial snapshots keep what they recorded.
+    let scans = db::list_scans(&conn).unwrap();
+    let counts = |id: i64| {
+        let scan = scans.iter().find(|s| s.id == id).unwrap();
+        (scan.files_count, scan.folders_count, scan.total_size)
+    };
+    assert_eq!(counts(current), (2, 1, 3));
+    assert_eq!(counts(older), (3, 2, 7));
+    assert_eq!(counts(partial), (3, 2, 7));
+    assert_eq!(db::open_snapshot(&db_path, older).unwrap().unwrap().size_of(&sub).unwrap(), Some(4));
+}